@@ -0,0 +1,156 @@
+//! Benchmarks of key operations, comparing software keys with keys stored in
+//! the Secure Enclave (when available), to catch performance regressions in
+//! the FFI and Core Foundation conversion layers.
+//!
+//! These access the keychain, so like the interactive tests they require a
+//! signed executable, and are only built with the `benchmarks` feature:
+//!
+//! ```text
+//! cargo bench --features benchmarks
+//! ```
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use keychain_services::{
+    keychain::item::Query, AccessControl, AccessControlFlags, AccessOption, AttrAccessible,
+    AttrKeyClass, AttrKeyType, AttrTokenId, Key, KeyAlgorithm, KeyPair, KeyPairGenerateParams,
+};
+
+/// Sizes of messages signed and encrypted, in bytes
+const MESSAGE_SIZES: &[usize] = &[32, 1024, 65536];
+
+/// Application tag of the permanent key used for query benchmarks
+const QUERY_KEY_TAG: &str = "rs.keychain-services.bench.query";
+
+/// Generate the ephemeral key pairs to benchmark, labeled by where they're
+/// stored
+fn key_pairs() -> Vec<(&'static str, KeyPair)> {
+    let software = KeyPair::create(KeyPairGenerateParams::new(
+        AttrKeyType::EcSecPrimeRandom,
+        256,
+    ))
+    .unwrap();
+
+    let mut key_pairs = vec![("software", software)];
+
+    if KeyPair::is_secure_enclave_available() {
+        let mut flags = AccessControlFlags::new();
+        flags.add(AccessOption::PrivateKeyUsage);
+
+        let access_control =
+            AccessControl::create_with_flags(AttrAccessible::WhenUnlockedThisDeviceOnly, flags)
+                .unwrap();
+
+        let params = KeyPairGenerateParams::new(AttrKeyType::EcSecPrimeRandom, 256)
+            .token_id(AttrTokenId::SecureEnclave)
+            .access_control(&access_control);
+
+        key_pairs.push(("secure_enclave", KeyPair::create(params).unwrap()));
+    }
+
+    key_pairs
+}
+
+fn sign(c: &mut Criterion) {
+    let key_pairs = key_pairs();
+    let mut group = c.benchmark_group("sign");
+
+    for &size in MESSAGE_SIZES {
+        let message = vec![0u8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+
+        for (storage, key_pair) in &key_pairs {
+            group.bench_with_input(BenchmarkId::new(*storage, size), &message, |b, message| {
+                b.iter(|| {
+                    key_pair
+                        .private_key
+                        .sign(KeyAlgorithm::ECDSASignatureMessageX962SHA256, message)
+                        .unwrap()
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn verify(c: &mut Criterion) {
+    let key_pairs = key_pairs();
+    let mut group = c.benchmark_group("verify");
+
+    for &size in MESSAGE_SIZES {
+        let message = vec![0u8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+
+        for (storage, key_pair) in &key_pairs {
+            let signature = key_pair
+                .private_key
+                .sign(KeyAlgorithm::ECDSASignatureMessageX962SHA256, &message)
+                .unwrap();
+
+            group.bench_with_input(BenchmarkId::new(*storage, size), &message, |b, message| {
+                b.iter(|| {
+                    assert!(key_pair.public_key.verify(message, &signature).unwrap());
+                })
+            });
+        }
+    }
+
+    group.finish();
+}
+
+fn encrypt(c: &mut Criterion) {
+    let key_pairs = key_pairs();
+    let mut group = c.benchmark_group("encrypt");
+
+    for &size in MESSAGE_SIZES {
+        let plaintext = vec![0u8; size];
+        group.throughput(Throughput::Bytes(size as u64));
+
+        // Encryption only uses the public key, which is always in software,
+        // so benchmarking each key pair would be redundant
+        let (_, key_pair) = &key_pairs[0];
+
+        group.bench_with_input(
+            BenchmarkId::new("ecies", size),
+            &plaintext,
+            |b, plaintext| {
+                b.iter(|| {
+                    key_pair
+                        .public_key
+                        .encrypt(
+                            KeyAlgorithm::ECIESEncryptionStandardX963SHA256AESGCM,
+                            plaintext,
+                        )
+                        .unwrap()
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn query(c: &mut Criterion) {
+    let params = KeyPairGenerateParams::new(AttrKeyType::EcSecPrimeRandom, 256)
+        .application_tag(QUERY_KEY_TAG)
+        .permanent(true);
+
+    let key_pair = KeyPair::create(params).unwrap();
+    let mut group = c.benchmark_group("query");
+
+    group.bench_function("find_by_tag", |b| {
+        b.iter(|| {
+            let query = Query::new()
+                .key_class(AttrKeyClass::Private)
+                .application_tag(QUERY_KEY_TAG);
+
+            Key::find(query).unwrap().unwrap()
+        })
+    });
+
+    group.finish();
+    key_pair.private_key.delete().unwrap();
+}
+
+criterion_group!(benches, sign, verify, encrypt, query);
+criterion_main!(benches);
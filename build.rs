@@ -0,0 +1,80 @@
+//! Build script: compiles the Swift shim exposing CryptoKit to Rust when the
+//! `cryptokit` feature is enabled (CryptoKit has no C/Objective-C API).
+
+use std::{env, path::PathBuf, process::Command};
+
+/// Swift source for the CryptoKit bridge
+const BRIDGE_SOURCE: &str = "src/keychain/cryptokit/bridge.swift";
+
+/// Name of the static library the bridge is compiled into
+const BRIDGE_LIB: &str = "keychain_services_cryptokit";
+
+fn main() {
+    // CryptoKit is only available on Apple platforms
+    let target_vendor = env::var("CARGO_CFG_TARGET_VENDOR").unwrap_or_default();
+
+    if env::var_os("CARGO_FEATURE_CRYPTOKIT").is_none() || target_vendor != "apple" {
+        return;
+    }
+
+    println!("cargo:rerun-if-changed={}", BRIDGE_SOURCE);
+
+    let out_dir = match env::var_os("OUT_DIR") {
+        Some(out_dir) => PathBuf::from(out_dir),
+        None => panic!("OUT_DIR isn't set (build scripts must be run by cargo)"),
+    };
+    let lib_path = out_dir.join(format!("lib{}.a", BRIDGE_LIB));
+
+    let status = Command::new("xcrun")
+        .args([
+            "swiftc",
+            "-emit-library",
+            "-static",
+            "-parse-as-library",
+            "-O",
+        ])
+        .args(["-module-name", "KeychainServicesCryptoKit"])
+        .arg("-o")
+        .arg(&lib_path)
+        .arg(BRIDGE_SOURCE)
+        .status()
+        .unwrap_or_else(|e| {
+            panic!(
+                "error running `xcrun swiftc` (are the Xcode command line tools installed?): {}",
+                e
+            )
+        });
+
+    if !status.success() {
+        panic!(
+            "swiftc failed to compile the CryptoKit bridge ({}): a Swift toolchain with \
+             CryptoKit (Xcode 11 or newer) is required for the `cryptokit` feature",
+            status
+        );
+    }
+
+    let sdk_path = Command::new("xcrun")
+        .args(["--show-sdk-path"])
+        .output()
+        .unwrap_or_else(|e| {
+            panic!(
+                "error running `xcrun --show-sdk-path` (is the macOS SDK installed?): {}",
+                e
+            )
+        });
+
+    let sdk_path = match String::from_utf8(sdk_path.stdout) {
+        Ok(sdk_path) => sdk_path,
+        Err(_) => panic!("`xcrun --show-sdk-path` returned a path which isn't valid UTF-8"),
+    };
+
+    println!("cargo:rustc-link-search=native={}", out_dir.display());
+    println!("cargo:rustc-link-lib=static={}", BRIDGE_LIB);
+    println!(
+        "cargo:rustc-link-search=native={}/usr/lib/swift",
+        sdk_path.trim()
+    );
+    println!("cargo:rustc-link-search=native=/usr/lib/swift");
+    println!("cargo:rustc-link-lib=framework=CryptoKit");
+    println!("cargo:rustc-link-lib=framework=Foundation");
+}
@@ -0,0 +1,334 @@
+//! Minimal HTTP service which issues ES256 JSON Web Tokens signed by a
+//! keychain key (stored in the Secure Enclave when available).
+//!
+//! **This is demonstration code, not a secure token service.** It issues a
+//! token for any subject to any client presenting the shared API key, over
+//! plain HTTP. A real service must authenticate the subject itself (e.g.
+//! with a password or an upstream identity provider), only issue tokens for
+//! the subject which authenticated, and be served over TLS.
+//!
+//! Intended as a starting point for server-side use: the signing key is
+//! ensured at startup and looked up by its application tag for each request,
+//! and tokens are encoded in the JWS compact serialization. This crate has
+//! no async API, so each connection is handled on its own thread, as signing
+//! with a keychain key blocks.
+//!
+//! Usage:
+//!
+//! ```text
+//! $ export JWT_SERVICE_API_KEY="$(openssl rand -hex 32)"
+//! $ cargo run --example jwt-service -- 127.0.0.1:8080
+//! $ curl -H "Authorization: Bearer $JWT_SERVICE_API_KEY" \
+//!     'http://127.0.0.1:8080/token?sub=alice'
+//! $ curl http://127.0.0.1:8080/jwk
+//! ```
+//!
+//! `GET /token?sub=SUBJECT` issues a token valid for an hour to clients
+//! which send the API key as a bearer token, and `GET /jwk` returns the
+//! public key as a JSON Web Key for verifiers.
+
+use keychain_services::{
+    keychain::item::Query, AttrKeyClass, AttrKeyType, AttrTokenId, Error, ErrorKind, Key,
+    KeyAlgorithm, KeyManager, KeyPair, KeyPairGenerateParams,
+};
+use std::{
+    env,
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    process,
+    sync::Arc,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Environment variable holding the API key clients must present
+const API_KEY_VAR: &str = "JWT_SERVICE_API_KEY";
+
+/// Minimum length of the API key
+const MIN_API_KEY_LEN: usize = 32;
+
+/// Application tag of the token signing key
+const KEY_TAG: &str = "rs.keychain-services.example.jwt-service";
+
+/// Issuer of tokens
+const ISSUER: &str = "keychain-services-example";
+
+/// Lifetime of issued tokens in seconds
+const TOKEN_LIFETIME: u64 = 3600;
+
+/// Size of P-256 field elements (i.e. `r`, `s`, `x`, and `y`) in bytes
+const FIELD_SIZE: usize = 32;
+
+fn main() {
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8080".to_owned());
+
+    let api_key = match env::var(API_KEY_VAR) {
+        Ok(api_key) if api_key.len() >= MIN_API_KEY_LEN => Arc::new(api_key),
+        _ => {
+            eprintln!(
+                "error: set {} to a random secret of at least {} characters, which \
+                 clients must send as a bearer token",
+                API_KEY_VAR, MIN_API_KEY_LEN
+            );
+            process::exit(2);
+        }
+    };
+
+    let mut spec = KeyPairGenerateParams::new(AttrKeyType::EcSecPrimeRandom, 256);
+
+    if KeyPair::is_secure_enclave_available() {
+        spec = spec.token_id(AttrTokenId::SecureEnclave);
+    }
+
+    let ensured = KeyManager::ensure(KEY_TAG, spec).unwrap_or_else(|e| {
+        eprintln!("error: couldn't obtain signing key: {}", e);
+        process::exit(1);
+    });
+
+    let jwk = jwk(&ensured.key).unwrap_or_else(|e| {
+        eprintln!("error: couldn't export public key: {}", e);
+        process::exit(1);
+    });
+
+    let listener = TcpListener::bind(&addr).unwrap_or_else(|e| {
+        eprintln!("error: couldn't bind {}: {}", addr, e);
+        process::exit(1);
+    });
+
+    println!("listening on http://{}", addr);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let jwk = jwk.clone();
+                let api_key = api_key.clone();
+                thread::spawn(move || serve(stream, &jwk, &api_key));
+            }
+            Err(e) => eprintln!("accept error: {}", e),
+        }
+    }
+}
+
+/// Serve a single HTTP request
+fn serve(mut stream: TcpStream, jwk: &str, api_key: &str) {
+    let mut reader = BufReader::new(&stream);
+    let mut request_line = String::new();
+
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+
+    // Read the headers, keeping the credentials from `Authorization`
+    let mut authorization = None;
+
+    loop {
+        let mut header = String::new();
+
+        match reader.read_line(&mut header) {
+            Ok(0) | Err(_) => return,
+            Ok(_) if header.trim().is_empty() => break,
+            Ok(_) => {
+                let mut parts = header.splitn(2, ':');
+                let name = parts.next().unwrap_or("");
+
+                if name.trim().eq_ignore_ascii_case("authorization") {
+                    authorization = parts.next().map(|value| value.trim().to_owned());
+                }
+            }
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next(), parts.next().unwrap_or(""));
+    let mut target = target.splitn(2, '?');
+    let (path, query) = (target.next(), target.next().unwrap_or(""));
+
+    let (status, body) = match (method, path) {
+        (Some("GET"), Some("/jwk")) => ("200 OK", jwk.to_owned()),
+        (Some("GET"), Some("/token")) if !is_authorized(authorization.as_deref(), api_key) => {
+            ("401 Unauthorized", "invalid or missing API key".to_owned())
+        }
+        (Some("GET"), Some("/token")) => match query_param(query, "sub") {
+            Some(subject) => match issue_token(subject) {
+                Ok(token) => ("200 OK", token),
+                Err(e) => {
+                    eprintln!("signing failed: {}", e);
+                    ("500 Internal Server Error", "signing failed".to_owned())
+                }
+            },
+            None => ("400 Bad Request", "missing sub parameter".to_owned()),
+        },
+        _ => ("404 Not Found", "not found".to_owned()),
+    };
+
+    let _ = write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    );
+}
+
+/// Do the credentials in an `Authorization` header (if any) match the API
+/// key? Compared in constant time, so the key can't be guessed by timing.
+fn is_authorized(authorization: Option<&str>, api_key: &str) -> bool {
+    let token = match authorization.and_then(|value| value.get(..7).map(|s| (s, &value[7..]))) {
+        Some((scheme, token)) if scheme.eq_ignore_ascii_case("bearer ") => token.trim().as_bytes(),
+        _ => return false,
+    };
+
+    token.len() == api_key.len()
+        && token
+            .iter()
+            .zip(api_key.as_bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Issue a token for the given subject
+fn issue_token(subject: &str) -> Result<String, Error> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+
+    let header = r#"{"alg":"ES256","typ":"JWT"}"#;
+    let claims = format!(
+        r#"{{"iss":"{}","sub":"{}","iat":{},"exp":{}}}"#,
+        ISSUER,
+        json_escape(subject),
+        now,
+        now + TOKEN_LIFETIME
+    );
+
+    let signing_input = format!(
+        "{}.{}",
+        base64url(header.as_bytes()),
+        base64url(claims.as_bytes())
+    );
+
+    let query = Query::new()
+        .key_class(AttrKeyClass::Private)
+        .application_tag(KEY_TAG);
+
+    let key = Key::find(query)?
+        .ok_or_else(|| Error::new(ErrorKind::ItemNotFound, &"token signing key"))?;
+
+    let signature = key.sign(
+        KeyAlgorithm::ECDSASignatureMessageX962SHA256,
+        signing_input.as_bytes(),
+    )?;
+
+    // JWS requires the fixed-size `r || s` encoding rather than ASN.1 DER
+    let raw_signature = der_to_raw_signature(signature.as_bytes())
+        .ok_or_else(|| Error::new(ErrorKind::Decode, &"malformed ECDSA signature"))?;
+
+    Ok(format!("{}.{}", signing_input, base64url(&raw_signature)))
+}
+
+/// Encode the public key of the given private key as a JSON Web Key
+fn jwk(private_key: &Key) -> Result<String, Error> {
+    // For EC keys, this is the uncompressed point (i.e. `04 || X || Y`)
+    let point = private_key.public()?.to_external_representation()?;
+
+    if point.len() != 1 + 2 * FIELD_SIZE || point[0] != 0x04 {
+        return Err(Error::new(
+            ErrorKind::Decode,
+            &"unexpected public key format",
+        ));
+    }
+
+    Ok(format!(
+        r#"{{"kty":"EC","crv":"P-256","alg":"ES256","use":"sig","x":"{}","y":"{}"}}"#,
+        base64url(&point[1..1 + FIELD_SIZE]),
+        base64url(&point[1 + FIELD_SIZE..])
+    ))
+}
+
+/// Convert a DER-encoded ECDSA signature into the `r || s` encoding
+fn der_to_raw_signature(der: &[u8]) -> Option<Vec<u8>> {
+    let (sequence, _) = parse_der(der, 0x30)?;
+    let (r, rest) = parse_der(sequence, 0x02)?;
+    let (s, _) = parse_der(rest, 0x02)?;
+
+    let mut raw = vec![0u8; 2 * FIELD_SIZE];
+
+    for (int, out) in [r, s].iter().zip(raw.chunks_mut(FIELD_SIZE)) {
+        // Strip the sign byte DER adds to integers with the high bit set
+        let int = if int.len() > FIELD_SIZE && int[0] == 0 {
+            &int[1..]
+        } else {
+            int
+        };
+
+        if int.len() > FIELD_SIZE {
+            return None;
+        }
+
+        out[FIELD_SIZE - int.len()..].copy_from_slice(int);
+    }
+
+    Some(raw)
+}
+
+/// Parse a short-form DER element with the given tag, returning its
+/// contents and the remaining input
+fn parse_der(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    if input.len() < 2 || input[0] != tag || input[1] >= 0x80 {
+        return None;
+    }
+
+    let len = input[1] as usize;
+    let rest = &input[2..];
+
+    if rest.len() < len {
+        return None;
+    }
+
+    Some(rest.split_at(len))
+}
+
+/// Encode bytes as unpadded base64url
+fn base64url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut encoded = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &byte)| n | u32::from(byte) << (16 - 8 * i));
+
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+
+    encoded
+}
+
+/// Get the value of a parameter in a URL query string (without decoding)
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.split('&').find_map(|pair| {
+        let mut pair = pair.splitn(2, '=');
+
+        if pair.next() == Some(name) {
+            pair.next()
+        } else {
+            None
+        }
+    })
+}
+
+/// Escape a string for inclusion in a JSON string literal
+fn json_escape(s: &str) -> String {
+    s.chars()
+        .flat_map(|c| match c {
+            '"' | '\\' => vec!['\\', c],
+            c if c.is_control() => format!("\\u{:04x}", c as u32).chars().collect(),
+            c => vec![c],
+        })
+        .collect()
+}
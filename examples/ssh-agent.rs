@@ -0,0 +1,350 @@
+//! Minimal SSH agent backed by a keychain EC key (stored in the Secure
+//! Enclave when available).
+//!
+//! Demonstrates key management, signing, access control, and operation
+//! prompts end to end: the agent ensures a P-256 key exists (protected by
+//! `AccessConstraint::UserPresence`, so every signature requires Touch ID or
+//! the login password), serves it over the SSH agent protocol, and shows a
+//! prompt describing each signing request.
+//!
+//! Usage:
+//!
+//! ```text
+//! $ cargo run --example ssh-agent -- /tmp/keychain-agent.sock
+//! $ SSH_AUTH_SOCK=/tmp/keychain-agent.sock ssh-add -L >> ~/.ssh/authorized_keys
+//! $ SSH_AUTH_SOCK=/tmp/keychain-agent.sock ssh localhost
+//! ```
+//!
+//! Only the subset of the protocol needed to authenticate is implemented,
+//! i.e. listing identities and signing. See:
+//! <https://tools.ietf.org/html/draft-miller-ssh-agent>
+
+use keychain_services::{
+    keychain::item::Query, AccessConstraint, AccessControl, AccessControlFlags, AccessOption,
+    AttrAccessible, AttrKeyClass, AttrKeyType, AttrTokenId, Key, KeyAlgorithm, KeyManager, KeyPair,
+    KeyPairGenerateParams,
+};
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    process,
+};
+
+/// Application tag of the agent's key
+const KEY_TAG: &str = "rs.keychain-services.example.ssh-agent";
+
+/// Comment sent to clients along with the agent's public key
+const KEY_COMMENT: &str = "keychain-services ssh-agent example";
+
+/// SSH key type (and signature algorithm) of the agent's key
+const KEY_TYPE: &str = "ecdsa-sha2-nistp256";
+
+/// Name of the curve of the agent's key
+const CURVE_NAME: &str = "nistp256";
+
+/// Agent protocol message types
+const SSH_AGENT_FAILURE: u8 = 5;
+const SSH2_AGENTC_REQUEST_IDENTITIES: u8 = 11;
+const SSH2_AGENT_IDENTITIES_ANSWER: u8 = 12;
+const SSH2_AGENTC_SIGN_REQUEST: u8 = 13;
+const SSH2_AGENT_SIGN_RESPONSE: u8 = 14;
+
+fn main() {
+    let socket_path = env::args().nth(1).unwrap_or_else(|| {
+        eprintln!("usage: ssh-agent <socket path>");
+        process::exit(2);
+    });
+
+    let ensured = KeyManager::ensure(KEY_TAG, key_spec()).unwrap_or_else(|e| {
+        eprintln!("error: couldn't obtain agent key: {}", e);
+        process::exit(1);
+    });
+
+    if ensured.is_new() {
+        println!("generated a new key: add it to authorized_keys with `ssh-add -L`");
+    }
+
+    let public_key_blob = public_key_blob(&ensured.key).unwrap_or_else(|e| {
+        eprintln!("error: couldn't export public key: {}", e);
+        process::exit(1);
+    });
+
+    let _ = fs::remove_file(&socket_path);
+    let listener = UnixListener::bind(&socket_path).unwrap_or_else(|e| {
+        eprintln!("error: couldn't bind {}: {}", socket_path, e);
+        process::exit(1);
+    });
+
+    println!("listening on {}", socket_path);
+
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                if let Err(e) = serve(stream, &public_key_blob) {
+                    eprintln!("connection error: {}", e);
+                }
+            }
+            Err(e) => eprintln!("accept error: {}", e),
+        }
+    }
+}
+
+/// Specification of the agent's key: a P-256 key in the Secure Enclave (if
+/// available) which requires user presence for every use
+fn key_spec() -> KeyPairGenerateParams {
+    let mut flags = AccessControlFlags::new();
+    flags.add(AccessConstraint::UserPresence);
+
+    let mut spec =
+        KeyPairGenerateParams::new(AttrKeyType::EcSecPrimeRandom, 256).label(KEY_COMMENT);
+
+    if KeyPair::is_secure_enclave_available() {
+        flags.add(AccessOption::PrivateKeyUsage);
+        spec = spec.token_id(AttrTokenId::SecureEnclave);
+    }
+
+    let access_control =
+        AccessControl::create_with_flags(AttrAccessible::WhenUnlockedThisDeviceOnly, flags)
+            .expect("couldn't create access control");
+
+    spec.access_control(&access_control)
+}
+
+/// Serve agent requests on a connection until the client disconnects
+fn serve(mut stream: UnixStream, public_key_blob: &[u8]) -> io::Result<()> {
+    loop {
+        let mut len = [0u8; 4];
+
+        match stream.read_exact(&mut len) {
+            Ok(()) => (),
+            Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        }
+
+        let mut request = vec![0u8; u32::from_be_bytes(len) as usize];
+        stream.read_exact(&mut request)?;
+
+        let response = handle(&request, public_key_blob).unwrap_or_else(|| vec![SSH_AGENT_FAILURE]);
+
+        stream.write_all(&(response.len() as u32).to_be_bytes())?;
+        stream.write_all(&response)?;
+    }
+}
+
+/// Handle an agent request, returning the response (or `None` on failure)
+fn handle(request: &[u8], public_key_blob: &[u8]) -> Option<Vec<u8>> {
+    let (&message_type, mut body) = request.split_first()?;
+    let mut response = vec![];
+
+    match message_type {
+        SSH2_AGENTC_REQUEST_IDENTITIES => {
+            response.push(SSH2_AGENT_IDENTITIES_ANSWER);
+            response.extend_from_slice(&1u32.to_be_bytes());
+            put_string(&mut response, public_key_blob);
+            put_string(&mut response, KEY_COMMENT.as_bytes());
+        }
+        SSH2_AGENTC_SIGN_REQUEST => {
+            let key_blob = get_string(&mut body)?;
+            let data = get_string(&mut body)?;
+
+            if key_blob != public_key_blob {
+                return None;
+            }
+
+            let signature = sign(data)
+                .map_err(|e| eprintln!("signing failed: {}", e))
+                .ok()?;
+
+            let (r, s) = parse_der_signature(&signature)?;
+
+            let mut rs = vec![];
+            put_mpint(&mut rs, r);
+            put_mpint(&mut rs, s);
+
+            let mut signature_blob = vec![];
+            put_string(&mut signature_blob, KEY_TYPE.as_bytes());
+            put_string(&mut signature_blob, &rs);
+
+            response.push(SSH2_AGENT_SIGN_RESPONSE);
+            put_string(&mut response, &signature_blob);
+        }
+        _ => return None,
+    }
+
+    Some(response)
+}
+
+/// Sign data with the agent's key, returning an ASN.1 DER-encoded signature.
+///
+/// The key is looked up for each request so the user is shown a prompt
+/// explaining why their presence is required.
+fn sign(data: &[u8]) -> Result<Vec<u8>, keychain_services::Error> {
+    let query = Query::new()
+        .key_class(AttrKeyClass::Private)
+        .application_tag(KEY_TAG)
+        .use_operation_prompt("authenticate an SSH connection");
+
+    let key = Key::find(query)?.ok_or_else(|| {
+        keychain_services::Error::new(keychain_services::ErrorKind::ItemNotFound, "agent key")
+    })?;
+
+    let signature = key.sign(KeyAlgorithm::ECDSASignatureMessageX962SHA256, data)?;
+    Ok(signature.into_vec())
+}
+
+/// Encode the public key of the given private key as an SSH public key blob
+fn public_key_blob(private_key: &Key) -> Result<Vec<u8>, keychain_services::Error> {
+    // For EC keys, this is the uncompressed point (i.e. `04 || X || Y`)
+    let point = private_key.public()?.to_external_representation()?;
+
+    let mut blob = vec![];
+    put_string(&mut blob, KEY_TYPE.as_bytes());
+    put_string(&mut blob, CURVE_NAME.as_bytes());
+    put_string(&mut blob, &point);
+    Ok(blob)
+}
+
+/// Parse the `r` and `s` integers from a DER-encoded ECDSA signature
+fn parse_der_signature(der: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (sequence, _) = parse_der(der, 0x30)?;
+    let (r, rest) = parse_der(sequence, 0x02)?;
+    let (s, _) = parse_der(rest, 0x02)?;
+    Some((r, s))
+}
+
+/// Parse a short-form DER element with the given tag, returning its
+/// contents and the remaining input
+fn parse_der(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    if input.len() < 2 || input[0] != tag || input[1] >= 0x80 {
+        return None;
+    }
+
+    let len = input[1] as usize;
+    let rest = &input[2..];
+
+    if rest.len() < len {
+        return None;
+    }
+
+    Some(rest.split_at(len))
+}
+
+/// Read an SSH `string` (i.e. a length-prefixed byte string)
+fn get_string<'a>(input: &mut &'a [u8]) -> Option<&'a [u8]> {
+    if input.len() < 4 {
+        return None;
+    }
+
+    let len = u32::from_be_bytes([input[0], input[1], input[2], input[3]]) as usize;
+
+    if input.len() < 4 + len {
+        return None;
+    }
+
+    let string = &input[4..4 + len];
+    *input = &input[4 + len..];
+    Some(string)
+}
+
+/// Write an SSH `string` (i.e. a length-prefixed byte string)
+fn put_string(output: &mut Vec<u8>, string: &[u8]) {
+    output.extend_from_slice(&(string.len() as u32).to_be_bytes());
+    output.extend_from_slice(string);
+}
+
+/// Write an SSH `mpint` from a (positive) big endian DER integer
+fn put_mpint(output: &mut Vec<u8>, mut int: &[u8]) {
+    // DER integers are already minimal two's complement, except that SSH
+    // encodes zero as an empty string
+    while int.first() == Some(&0) && int.get(1).map_or(true, |byte| byte & 0x80 == 0) {
+        int = &int[1..];
+    }
+
+    put_string(output, int);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn string_round_trip() {
+        let mut encoded = vec![];
+        put_string(&mut encoded, b"ssh-ed25519");
+        put_string(&mut encoded, b"");
+        assert_eq!(&encoded[..4], &[0, 0, 0, 11]);
+
+        let mut input = &encoded[..];
+        assert_eq!(get_string(&mut input), Some(&b"ssh-ed25519"[..]));
+        assert_eq!(get_string(&mut input), Some(&b""[..]));
+        assert!(input.is_empty());
+        assert_eq!(get_string(&mut input), None);
+
+        // Length prefix longer than the remaining input
+        let mut truncated = &[0, 0, 0, 5, b'a', b'b'][..];
+        assert_eq!(get_string(&mut truncated), None);
+    }
+
+    #[test]
+    fn mpint_encoding() {
+        let mpint = |int: &[u8]| {
+            let mut output = vec![];
+            put_mpint(&mut output, int);
+            output
+        };
+
+        assert_eq!(mpint(&[0x01, 0x02]), [0, 0, 0, 2, 0x01, 0x02]);
+        // Leading zero needed to keep the value positive
+        assert_eq!(mpint(&[0x00, 0x80]), [0, 0, 0, 2, 0x00, 0x80]);
+        // Redundant leading zeroes are stripped
+        assert_eq!(mpint(&[0x00, 0x00, 0x7f]), [0, 0, 0, 1, 0x7f]);
+        // Zero is encoded as an empty string
+        assert_eq!(mpint(&[0x00]), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn der_signature_parsing() {
+        let der = [0x30, 0x07, 0x02, 0x02, 0x00, 0x80, 0x02, 0x01, 0x01];
+        assert_eq!(
+            parse_der_signature(&der),
+            Some((&[0x00, 0x80][..], &[0x01][..]))
+        );
+
+        // Wrong tag, truncated contents, and long-form lengths are rejected
+        assert_eq!(parse_der_signature(&[0x31, 0x00]), None);
+        assert_eq!(parse_der_signature(&der[..8]), None);
+        assert_eq!(parse_der_signature(&[0x30, 0x81, 0x00]), None);
+    }
+
+    #[test]
+    fn identities_answer() {
+        let public_key_blob = b"public key blob";
+        let response = handle(&[SSH2_AGENTC_REQUEST_IDENTITIES], public_key_blob).unwrap();
+
+        let (&message_type, mut body) = response.split_first().unwrap();
+        assert_eq!(message_type, SSH2_AGENT_IDENTITIES_ANSWER);
+        assert_eq!(&body[..4], &1u32.to_be_bytes());
+
+        body = &body[4..];
+        assert_eq!(get_string(&mut body), Some(&public_key_blob[..]));
+        assert_eq!(get_string(&mut body), Some(KEY_COMMENT.as_bytes()));
+        assert!(body.is_empty());
+    }
+
+    #[test]
+    fn rejected_requests() {
+        let public_key_blob = b"public key blob";
+
+        // Empty and unsupported requests
+        assert_eq!(handle(&[], public_key_blob), None);
+        assert_eq!(handle(&[SSH2_AGENT_SIGN_RESPONSE], public_key_blob), None);
+
+        // Sign requests for other keys, or which are malformed
+        let mut request = vec![SSH2_AGENTC_SIGN_REQUEST];
+        put_string(&mut request, b"other key blob");
+        put_string(&mut request, b"data");
+        assert_eq!(handle(&request, public_key_blob), None);
+        assert_eq!(handle(&request[..6], public_key_blob), None);
+    }
+}
@@ -3,8 +3,9 @@
 
 use crate::{attr::AttrAccessible, error::Error, ffi::*};
 use core_foundation::{
-    base::{kCFAllocatorDefault, CFOptionFlags, TCFType},
+    base::{kCFAllocatorDefault, CFCopyDescription, CFOptionFlags, TCFType},
     error::CFErrorRef,
+    string::CFString,
 };
 use std::{
     fmt::{self, Debug},
@@ -20,6 +21,7 @@ pub trait AccessControlFlag: Copy + Clone + Sized + Into<CFOptionFlags> {}
 /// `SecAccessControlCreateFlags` documentation at:
 /// <https://developer.apple.com/documentation/security/secaccesscontrolcreateflags>
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AccessConstraint {
     /// Require either passcode or biometric auth (TouchID/FaceID).
     ///
@@ -35,6 +37,10 @@ pub enum AccessConstraint {
 
     /// Require biometric auth (TouchID/FaceID) from the current user.
     ///
+    /// Items protected by this constraint are invalidated whenever the set of
+    /// enrolled biometrics changes (e.g. a fingerprint is added or removed).
+    /// Use `Key::is_still_valid` to detect this for keys.
+    ///
     /// Wrapper for `kSecAccessControlBiometryCurrentSet`. See:
     /// <https://developer.apple.com/documentation/security/secaccesscontrolcreateflags/ksecaccesscontrolbiometrycurrentset>
     BiometryCurrentSet,
@@ -65,6 +71,7 @@ impl From<AccessConstraint> for CFOptionFlags {
 /// `SecAccessControlCreateFlags` documentation at:
 /// <https://developer.apple.com/documentation/security/secaccesscontrolcreateflags>
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AccessConjunction {
     /// Require *all* constraints be satisfied.
     ///
@@ -96,6 +103,7 @@ impl From<AccessConjunction> for CFOptionFlags {
 /// `SecAccessControlCreateFlags` documentation at:
 /// <https://developer.apple.com/documentation/security/secaccesscontrolcreateflags>
 #[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AccessOption {
     /// Require private key be stored in the device's Secure Enclave.
     ///
@@ -129,6 +137,7 @@ impl From<AccessOption> for CFOptionFlags {
 /// Wrapper for the `SecAccessControlCreateFlags` type:
 /// <https://developer.apple.com/documentation/security/secaccesscontrolcreateflags>
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AccessControlFlags(CFOptionFlags);
 
 impl AccessControlFlags {
@@ -197,11 +206,152 @@ impl AccessControl {
             Err(error.into())
         }
     }
+
+    /// Create an `AccessControl` policy requiring biometric auth from the
+    /// currently enrolled set of biometrics (i.e. `BiometryCurrentSet`).
+    ///
+    /// If `private_key_usage` is set, `AccessOption::PrivateKeyUsage` is also
+    /// added, as required for keys generated in the Secure Enclave.
+    pub fn biometry_current_set(
+        protection: AttrAccessible,
+        private_key_usage: bool,
+    ) -> Result<Self, Error> {
+        let mut flags = AccessControlFlags::new();
+        flags.add(AccessConstraint::BiometryCurrentSet);
+
+        if private_key_usage {
+            flags.add(AccessOption::PrivateKeyUsage);
+        }
+
+        Self::create_with_flags(protection, flags)
+    }
+
+    /// Get the description of this policy reported by Keychain Services.
+    ///
+    /// The protection and constraints of an `AccessControl` can't otherwise
+    /// be inspected once it has been created, so this is mainly useful for
+    /// diagnostics and audits. The format is undocumented and may change.
+    ///
+    /// Wrapper for the `CFCopyDescription` function. See:
+    /// <https://developer.apple.com/documentation/corefoundation/1521252-cfcopydescription>
+    pub fn description(&self) -> String {
+        unsafe { CFString::wrap_under_create_rule(CFCopyDescription(self.as_CFTypeRef())) }
+            .to_string()
+    }
+
+    /// Inspect the protection class and constraints of this policy, e.g. to
+    /// confirm an existing item was created with the intended policy.
+    ///
+    /// See `AccessControlInfo::from_description` for caveats.
+    pub fn info(&self) -> AccessControlInfo {
+        AccessControlInfo::from_description(&self.description())
+    }
 }
 
 impl Debug for AccessControl {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // TODO: display more information about `AccessControl`s
-        write!(f, "SecAccessControl {{ ... }}")
+        let info = self.info();
+
+        f.debug_struct("SecAccessControl")
+            .field("protection", &info.protection)
+            .field("constraints", &info.constraints)
+            .field("options", &info.options)
+            .finish()
+    }
+}
+
+/// Protection class and flags of an `AccessControl` policy, as recovered
+/// from an existing policy (e.g. one obtained from a keychain item).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AccessControlInfo {
+    /// Protection class of the policy (if recognized)
+    pub protection: Option<AttrAccessible>,
+
+    /// Constraints required to access the item
+    pub constraints: Vec<AccessConstraint>,
+
+    /// Additional options set on the policy
+    pub options: Vec<AccessOption>,
+}
+
+impl AccessControlInfo {
+    /// Parse the description of an `AccessControl` policy (see
+    /// `AccessControl::description`).
+    ///
+    /// Keychain Services provides no API for inspecting a policy, so this
+    /// relies on the undocumented description format, which looks like:
+    ///
+    /// ```text
+    /// <SecAccessControlRef: ak;od(cpo(DeviceOwnerAuthentication));odel(true);oe(true)>
+    /// ```
+    ///
+    /// The protection class is the `kSecAttrAccessible` value (e.g. `ak` for
+    /// `WhenUnlocked`), and constraints are identified by the names of the
+    /// authentication requirements attached to each operation. Anything
+    /// unrecognized is ignored, so the result should be treated as a
+    /// best-effort report rather than a guarantee.
+    pub fn from_description(description: &str) -> Self {
+        let body = description
+            .trim()
+            .trim_start_matches('<')
+            .trim_end_matches('>');
+
+        let body = match body.find(':') {
+            Some(pos) => body[pos + 1..].trim(),
+            None => body,
+        };
+
+        let protection = body.split(';').next().and_then(|class| {
+            let class = class.trim();
+            let class = class
+                .strip_prefix("prot(")
+                .and_then(|class| class.strip_suffix(')'))
+                .unwrap_or(class);
+
+            AttrAccessible::ALL
+                .iter()
+                .find(|accessible| accessible.as_CFString().to_string() == class)
+                .cloned()
+        });
+
+        let mut constraints = vec![];
+
+        if body.contains("cpo(DeviceOwnerAuthentication)") {
+            constraints.push(AccessConstraint::UserPresence);
+        }
+
+        if body.contains("cbio(") {
+            if body.contains("pbioc(") {
+                constraints.push(AccessConstraint::BiometryCurrentSet);
+            } else {
+                constraints.push(AccessConstraint::BiometryAny);
+            }
+        }
+
+        if body.contains("cup(") {
+            constraints.push(AccessConstraint::DevicePasscode);
+        }
+
+        let mut options = vec![];
+
+        if body.contains("osgn(") || body.contains("ock(") {
+            options.push(AccessOption::PrivateKeyUsage);
+        }
+
+        if body.contains("pkofn(") {
+            options.push(AccessOption::ApplicationPassword);
+        }
+
+        AccessControlInfo {
+            protection,
+            constraints,
+            options,
+        }
+    }
+
+    /// Does this policy require the given constraint?
+    pub fn requires(&self, constraint: AccessConstraint) -> bool {
+        self.constraints.contains(&constraint)
     }
 }
@@ -1,17 +1,22 @@
 //! Keychain item attributes (i.e. `SecAttr*`)
 
-use crate::ffi::*;
+use crate::{error::*, ffi::*};
 use core_foundation::{
     base::{CFType, TCFType, ToVoid},
     data::CFData,
+    number::CFNumber,
     string::{CFString, CFStringRef},
 };
 use std::{
     ffi::c_void,
     fmt::{self, Debug, Display},
-    str::{self, Utf8Error},
+    net::{IpAddr, Ipv6Addr},
+    str::{self, FromStr, Utf8Error},
 };
 
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
 /// Trait implemented by all `Attr*` types to simplify adding them to
 /// attribute dictionaries.
 pub(crate) trait TAttr {
@@ -39,13 +44,29 @@ pub(crate) enum AttrKind {
     Account,
 
     /// Wrapper for the `kSecAttrApplicationLabel` attribute key. See:
-    /// <https://developer.apple.com/documentation/security/ksecattrlabel>
+    /// <https://developer.apple.com/documentation/security/ksecattrapplicationlabel>
     ApplicationLabel,
 
     /// Wrapper for the `kSecAttrApplicationTag` attribute key. See:
     /// <https://developer.apple.com/documentation/security/ksecattrapplicationtag>
     ApplicationTag,
 
+    /// Wrapper for the `kSecAttrAuthenticationType` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrauthenticationtype>
+    AuthenticationType,
+
+    /// Wrapper for the `kSecAttrComment` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcomment>
+    Comment,
+
+    /// Wrapper for the `kSecAttrCreationDate` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcreationdate>
+    CreationDate,
+
+    /// Wrapper for the `kSecAttrCreator` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcreator>
+    Creator,
+
     /// Wrapper for the `kSecKeyDerive` attribute key. See:
     /// <https://developer.apple.com/documentation/security/ksecattrcanderive>
     Derive,
@@ -54,6 +75,10 @@ pub(crate) enum AttrKind {
     /// <https://developer.apple.com/documentation/security/ksecattrcandecrypt>
     Decrypt,
 
+    /// Wrapper for the `kSecAttrDescription` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrdescription>
+    Description,
+
     /// Wrapper for the `kSecKeyEncrypt` attribute key. See:
     /// https://developer.apple.com/documentation/security/ksecattrcanencrypt>
     Encrypt,
@@ -62,6 +87,14 @@ pub(crate) enum AttrKind {
     /// <https://developer.apple.com/documentation/security/ksecattrisextractable>
     Extractable,
 
+    /// Wrapper for the `kSecAttrGeneric` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrgeneric>
+    Generic,
+
+    /// Wrapper for the `kSecAttrIsInvisible` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrisinvisible>
+    Invisible,
+
     /// Wrapper for the `kSecAttrKeyClass` attribute key. See:
     /// <https://developer.apple.com/documentation/security/ksecattrkeyclass>
     KeyClass,
@@ -74,18 +107,42 @@ pub(crate) enum AttrKind {
     /// <https://developer.apple.com/documentation/security/ksecattrkeytype>
     KeyType,
 
+    /// Wrapper for the `kSecAttrType` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrtype>
+    ItemType,
+
     /// Wrapper for the `kSecAttrLabel` attribute key. See:
     /// <https://developer.apple.com/documentation/security/ksecattrlabel>
     Label,
 
+    /// Wrapper for the `kSecAttrModificationDate` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrmodificationdate>
+    ModificationDate,
+
+    /// Wrapper for the `kSecAttrIsNegative` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrisnegative>
+    Negative,
+
+    /// Wrapper for the `kSecAttrPath` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrpath>
+    Path,
+
     /// Wrapper for the `kSecAttrIsPermanent` attribute key. See:
     /// <https://developer.apple.com/documentation/security/ksecattrispermanent>
     Permanent,
 
+    /// Wrapper for the `kSecAttrPort` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrport>
+    Port,
+
     /// Wrapper for the `kSecAttrProtocol` attribute key. See:
     /// <https://developer.apple.com/documentation/security/ksecattrprotocol>
     Protocol,
 
+    /// Wrapper for the `kSecAttrSecurityDomain` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrsecuritydomain>
+    SecurityDomain,
+
     /// Wrapper for `kSecKeySensitive` attribute key. See
     /// <https://developer.apple.com/documentation/security/ksecattrissensitive>
     Sensitive,
@@ -138,18 +195,38 @@ impl AttrKind {
                 AttrKind::ApplicationLabel
             } else if tag == SecKeychainAttrType::from(kSecAttrApplicationTag) {
                 AttrKind::ApplicationTag
+            } else if tag == SecKeychainAttrType::from(kSecAttrAuthenticationType) {
+                AttrKind::AuthenticationType
+            } else if tag == SecKeychainAttrType::from(kSecAttrComment) {
+                AttrKind::Comment
+            } else if tag == SecKeychainAttrType::from(kSecAttrCreationDate) {
+                AttrKind::CreationDate
+            } else if tag == SecKeychainAttrType::from(kSecAttrCreator) {
+                AttrKind::Creator
             } else if tag == SecKeychainAttrType::from(kSecAttrKeyClass) {
                 AttrKind::KeyClass
             } else if tag == SecKeychainAttrType::from(kSecAttrKeySizeInBits) {
                 AttrKind::KeySizeInBits
             } else if tag == SecKeychainAttrType::from(kSecAttrKeyType) {
                 AttrKind::KeyType
+            } else if tag == SecKeychainAttrType::from(kSecAttrType) {
+                AttrKind::ItemType
             } else if tag == SecKeychainAttrType::from(kSecAttrIsPermanent) {
                 AttrKind::Permanent
+            } else if tag == SecKeychainAttrType::from(kSecAttrPort) {
+                AttrKind::Port
             } else if tag == SecKeychainAttrType::from(kSecAttrLabel) {
                 AttrKind::Label
+            } else if tag == SecKeychainAttrType::from(kSecAttrModificationDate) {
+                AttrKind::ModificationDate
+            } else if tag == SecKeychainAttrType::from(kSecAttrIsNegative) {
+                AttrKind::Negative
+            } else if tag == SecKeychainAttrType::from(kSecAttrPath) {
+                AttrKind::Path
             } else if tag == SecKeychainAttrType::from(kSecAttrProtocol) {
                 AttrKind::Protocol
+            } else if tag == SecKeychainAttrType::from(kSecAttrSecurityDomain) {
+                AttrKind::SecurityDomain
             } else if tag == SecKeychainAttrType::from(kSecAttrServer) {
                 AttrKind::Server
             } else if tag == SecKeychainAttrType::from(kSecAttrService) {
@@ -162,6 +239,8 @@ impl AttrKind {
                 AttrKind::Derive
             } else if tag == SecKeychainAttrType::from(kSecAttrCanDecrypt) {
                 AttrKind::Decrypt
+            } else if tag == SecKeychainAttrType::from(kSecAttrDescription) {
+                AttrKind::Description
             } else if tag == SecKeychainAttrType::from(kSecAttrCanEncrypt) {
                 AttrKind::Encrypt
             } else if tag == SecKeychainAttrType::from(kSecAttrCanSign) {
@@ -174,6 +253,10 @@ impl AttrKind {
                 AttrKind::Unwrap
             } else if tag == SecKeychainAttrType::from(kSecAttrIsExtractable) {
                 AttrKind::Extractable
+            } else if tag == SecKeychainAttrType::from(kSecAttrGeneric) {
+                AttrKind::Generic
+            } else if tag == SecKeychainAttrType::from(kSecAttrIsInvisible) {
+                AttrKind::Invisible
             } else if tag == SecKeychainAttrType::from(kSecAttrIsSensitive) {
                 AttrKind::Sensitive
             } else {
@@ -200,21 +283,34 @@ impl From<AttrKind> for CFStringRef {
                 AttrKind::Account => kSecAttrAccount,
                 AttrKind::ApplicationLabel => kSecAttrApplicationLabel,
                 AttrKind::ApplicationTag => kSecAttrApplicationTag,
+                AttrKind::AuthenticationType => kSecAttrAuthenticationType,
+                AttrKind::Comment => kSecAttrComment,
+                AttrKind::CreationDate => kSecAttrCreationDate,
+                AttrKind::Creator => kSecAttrCreator,
                 AttrKind::Derive => kSecAttrCanDerive,
                 AttrKind::Decrypt => kSecAttrCanDecrypt,
+                AttrKind::Description => kSecAttrDescription,
                 AttrKind::Encrypt => kSecAttrCanEncrypt,
                 AttrKind::Extractable => kSecAttrIsExtractable,
+                AttrKind::Generic => kSecAttrGeneric,
+                AttrKind::Invisible => kSecAttrIsInvisible,
                 AttrKind::KeyClass => kSecAttrKeyClass,
                 AttrKind::KeySizeInBits => kSecAttrKeySizeInBits,
                 AttrKind::KeyType => kSecAttrKeyType,
+                AttrKind::ItemType => kSecAttrType,
                 AttrKind::Permanent => kSecAttrIsPermanent,
+                AttrKind::Port => kSecAttrPort,
                 AttrKind::Sensitive => kSecAttrIsSensitive,
                 AttrKind::Sign => kSecAttrCanSign,
                 AttrKind::Verify => kSecAttrCanVerify,
                 AttrKind::Wrap => kSecAttrCanWrap,
                 AttrKind::Unwrap => kSecAttrCanUnwrap,
                 AttrKind::Label => kSecAttrLabel,
+                AttrKind::ModificationDate => kSecAttrModificationDate,
+                AttrKind::Negative => kSecAttrIsNegative,
+                AttrKind::Path => kSecAttrPath,
                 AttrKind::Protocol => kSecAttrProtocol,
+                AttrKind::SecurityDomain => kSecAttrSecurityDomain,
                 AttrKind::Server => kSecAttrServer,
                 AttrKind::Service => kSecAttrService,
                 AttrKind::Synchronizable => kSecAttrSynchronizable,
@@ -239,6 +335,7 @@ unsafe impl ToVoid<CFType> for AttrKind {
 /// "Accessibility Values" section of "Item Attribute Keys and Values":
 /// <https://developer.apple.com/documentation/security/keychain_services/keychain_items/item_attribute_keys_and_values>
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AttrAccessible {
     /// Device is unlocked and a passcode has been set on the device.
     /// <https://developer.apple.com/documentation/security/ksecattraccessiblewhenpasscodesetthisdeviceonly>
@@ -271,6 +368,26 @@ pub enum AttrAccessible {
 }
 
 impl AttrAccessible {
+    /// All accessibility values
+    pub const ALL: &'static [AttrAccessible] = &[
+        AttrAccessible::WhenPasscodeSetThisDeviceOnly,
+        AttrAccessible::WhenUnlockedThisDeviceOnly,
+        AttrAccessible::WhenUnlocked,
+        AttrAccessible::AfterFirstUnlockThisDeviceOnly,
+        AttrAccessible::AfterFirstUnlock,
+        AttrAccessible::AlwaysThisDeviceOnly,
+        AttrAccessible::Always,
+    ];
+
+    /// Look up an accessibility value from a `kSecAttrAccessible` attribute
+    /// value (if it's a known one).
+    pub(crate) fn from_CFType(value: &CFType) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|accessible| accessible.as_CFString().as_CFType() == *value)
+            .cloned()
+    }
+
     /// Get pointer to an accessibility value to associate with the
     /// `kSecAttrAccessible` key for a keychain item
     pub fn as_CFString(self) -> CFString {
@@ -357,6 +474,20 @@ impl TAttr for AttrApplicationLabel {
     }
 }
 
+#[cfg(feature = "serde")]
+impl Serialize for AttrApplicationLabel {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_bytes().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for AttrApplicationLabel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::new(&Vec::<u8>::deserialize(deserializer)?))
+    }
+}
+
 /// Application-specific tags for keychain items.
 ///
 /// These should be unique for a specific item (i.e. named after its purpose
@@ -420,6 +551,160 @@ impl TAttr for AttrApplicationTag {
     }
 }
 
+#[cfg(feature = "serde")]
+impl Serialize for AttrApplicationTag {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_bytes().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for AttrApplicationTag {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::new(&Vec::<u8>::deserialize(deserializer)?))
+    }
+}
+
+/// Application-defined binary data attached to generic password items.
+///
+/// Many applications (including Apple's sample code) use this to identify
+/// their generic passwords, alongside or instead of the service and account.
+///
+/// Wrapper for the `kSecAttrGeneric` attribute key. See:
+/// <https://developer.apple.com/documentation/security/ksecattrgeneric>
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AttrGeneric(pub(crate) CFData);
+
+impl AttrGeneric {
+    /// Create new generic attribute data from a byte slice
+    pub fn new(bytes: &[u8]) -> Self {
+        AttrGeneric(CFData::from_buffer(bytes))
+    }
+
+    /// Borrow the generic attribute data as a byte slice
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for AttrGeneric {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl From<&[u8]> for AttrGeneric {
+    fn from(bytes: &[u8]) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl From<&str> for AttrGeneric {
+    fn from(string: &str) -> Self {
+        Self::new(string.as_bytes())
+    }
+}
+
+impl TAttr for AttrGeneric {
+    fn kind(&self) -> AttrKind {
+        AttrKind::Generic
+    }
+
+    fn as_CFType(&self) -> CFType {
+        self.0.as_CFType()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for AttrGeneric {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_bytes().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for AttrGeneric {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::new(&Vec::<u8>::deserialize(deserializer)?))
+    }
+}
+
+/// Four character code identifying the creator of a keychain item (e.g.
+/// `*b"aapl"`), as shown in the Keychain Access application.
+///
+/// Wrapper for the `kSecAttrCreator` attribute key. See:
+/// <https://developer.apple.com/documentation/security/ksecattrcreator>
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttrCreator(pub [u8; 4]);
+
+impl AttrCreator {
+    /// Borrow the creator code as bytes
+    pub fn as_bytes(&self) -> &[u8; 4] {
+        &self.0
+    }
+}
+
+impl Display for AttrCreator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+impl From<[u8; 4]> for AttrCreator {
+    fn from(code: [u8; 4]) -> Self {
+        AttrCreator(code)
+    }
+}
+
+impl TAttr for AttrCreator {
+    fn kind(&self) -> AttrKind {
+        AttrKind::Creator
+    }
+
+    fn as_CFType(&self) -> CFType {
+        CFNumber::from(u32::from_be_bytes(self.0) as i32).as_CFType()
+    }
+}
+
+/// Four character code identifying the type of a keychain item (e.g.
+/// `*b"note"` for secure notes created in the Keychain Access application).
+///
+/// Wrapper for the `kSecAttrType` attribute key. See:
+/// <https://developer.apple.com/documentation/security/ksecattrtype>
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttrItemType(pub [u8; 4]);
+
+impl AttrItemType {
+    /// Borrow the type code as bytes
+    pub fn as_bytes(&self) -> &[u8; 4] {
+        &self.0
+    }
+}
+
+impl Display for AttrItemType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+impl From<[u8; 4]> for AttrItemType {
+    fn from(code: [u8; 4]) -> Self {
+        AttrItemType(code)
+    }
+}
+
+impl TAttr for AttrItemType {
+    fn kind(&self) -> AttrKind {
+        AttrKind::ItemType
+    }
+
+    fn as_CFType(&self) -> CFType {
+        CFNumber::from(u32::from_be_bytes(self.0) as i32).as_CFType()
+    }
+}
+
 /// Human readable/meaningful labels for keychain items.
 ///
 /// Wrapper for the `kSecAttrLabel` attribute key. See:
@@ -456,12 +741,27 @@ impl TAttr for AttrLabel {
     }
 }
 
+#[cfg(feature = "serde")]
+impl Serialize for AttrLabel {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for AttrLabel {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::new(&String::deserialize(deserializer)?))
+    }
+}
+
 /// Classes of keys supported by Keychain Services (not to be confused with
 /// `SecClass`, `SecAttrClass` or `SecAttrKeyType`)
 ///
 /// Wrapper for the `kSecAttrKeyClass` attribute key. See:
 /// <https://developer.apple.com/documentation/security/ksecattrkeyclass>
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AttrKeyClass {
     /// Public keys.
     ///
@@ -541,6 +841,7 @@ impl<'a> From<&'a CFString> for AttrKeyClass {
 /// Wrapper for the `kSecAttrKeyType` attribute key. See:
 /// <https://developer.apple.com/documentation/security/ksecattrkeytype>
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AttrKeyType {
     /// AES algorithm.
     ///
@@ -623,12 +924,107 @@ impl<'a> From<&'a CFString> for AttrKeyType {
     }
 }
 
+/// Paths (e.g. of a URL) associated with `SecClass::InternetPassword`
+/// keychain items.
+///
+/// Wrapper for the `kSecAttrPath` attribute key. See:
+/// <https://developer.apple.com/documentation/security/ksecattrpath>
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AttrPath(pub(crate) CFString);
+
+impl AttrPath {
+    /// Create a new path from a `&str`
+    pub fn new(path: &str) -> Self {
+        AttrPath(CFString::new(path))
+    }
+}
+
+impl Display for AttrPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", &self.0)
+    }
+}
+
+impl From<&str> for AttrPath {
+    fn from(path: &str) -> Self {
+        Self::new(path)
+    }
+}
+
+impl TAttr for AttrPath {
+    fn kind(&self) -> AttrKind {
+        AttrKind::Path
+    }
+
+    fn as_CFType(&self) -> CFType {
+        self.0.as_CFType()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for AttrPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for AttrPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::new(&String::deserialize(deserializer)?))
+    }
+}
+
+/// Network ports associated with `SecClass::InternetPassword` keychain items.
+///
+/// Wrapper for the `kSecAttrPort` attribute key. See:
+/// <https://developer.apple.com/documentation/security/ksecattrport>
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AttrPort(pub u16);
+
+impl AttrPort {
+    /// Get the port number
+    pub fn number(self) -> u16 {
+        self.0
+    }
+}
+
+impl Display for AttrPort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl From<u16> for AttrPort {
+    fn from(port: u16) -> Self {
+        AttrPort(port)
+    }
+}
+
+impl From<AttrPort> for u16 {
+    fn from(port: AttrPort) -> u16 {
+        port.0
+    }
+}
+
+impl TAttr for AttrPort {
+    fn kind(&self) -> AttrKind {
+        AttrKind::Port
+    }
+
+    fn as_CFType(&self) -> CFType {
+        CFNumber::from(i32::from(self.0)).as_CFType()
+    }
+}
+
 /// Internet protocols optionally associated with `SecClass::InternetPassword`
 /// keychain items.
 ///
 /// Wrapper for the `kSecAttrProtocol` attribute key. See:
 /// <https://developer.apple.com/documentation/security/ksecattrprotocol>
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AttrProtocol {
     /// File Transfer Protocol
     FTP,
@@ -725,6 +1121,50 @@ pub enum AttrProtocol {
 }
 
 impl AttrProtocol {
+    /// All protocol values
+    pub const ALL: &'static [AttrProtocol] = &[
+        AttrProtocol::FTP,
+        AttrProtocol::FTPAccount,
+        AttrProtocol::HTTP,
+        AttrProtocol::IRC,
+        AttrProtocol::NNTP,
+        AttrProtocol::POP3,
+        AttrProtocol::SMTP,
+        AttrProtocol::SOCKS,
+        AttrProtocol::IMAP,
+        AttrProtocol::LDAP,
+        AttrProtocol::AppleTalk,
+        AttrProtocol::AFP,
+        AttrProtocol::Telnet,
+        AttrProtocol::SSH,
+        AttrProtocol::FTPS,
+        AttrProtocol::HTTPS,
+        AttrProtocol::HTTPProxy,
+        AttrProtocol::HTTPSProxy,
+        AttrProtocol::FTPProxy,
+        AttrProtocol::SMB,
+        AttrProtocol::RTSP,
+        AttrProtocol::RTSPProxy,
+        AttrProtocol::DAAP,
+        AttrProtocol::EPPC,
+        AttrProtocol::IPP,
+        AttrProtocol::NNTPS,
+        AttrProtocol::LDAPS,
+        AttrProtocol::TelnetS,
+        AttrProtocol::IMAPS,
+        AttrProtocol::IRCS,
+        AttrProtocol::POP3S,
+    ];
+
+    /// Look up a protocol from its four character code (e.g. `htps`), as
+    /// stored in the `ptcl` attribute of legacy keychain items.
+    pub fn from_code(code: &[u8; 4]) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|protocol| protocol.as_CFString().to_string().as_bytes() == code)
+            .cloned()
+    }
+
     /// Get `CFString` containing the `kSecAttrProtocol` dictionary value for
     /// this particular `SecAttrProtocol`.
     pub fn as_CFString(self) -> CFString {
@@ -766,6 +1206,42 @@ impl AttrProtocol {
     }
 }
 
+impl AttrProtocol {
+    /// Look up the protocol corresponding to a URL scheme (e.g. `https`).
+    pub fn from_scheme(scheme: &str) -> Option<Self> {
+        let protocol = match scheme.to_ascii_lowercase().as_str() {
+            "afp" => AttrProtocol::AFP,
+            "daap" => AttrProtocol::DAAP,
+            "eppc" => AttrProtocol::EPPC,
+            "ftp" => AttrProtocol::FTP,
+            "ftps" => AttrProtocol::FTPS,
+            "http" => AttrProtocol::HTTP,
+            "https" => AttrProtocol::HTTPS,
+            "imap" => AttrProtocol::IMAP,
+            "imaps" => AttrProtocol::IMAPS,
+            "ipp" => AttrProtocol::IPP,
+            "irc" => AttrProtocol::IRC,
+            "ircs" => AttrProtocol::IRCS,
+            "ldap" => AttrProtocol::LDAP,
+            "ldaps" => AttrProtocol::LDAPS,
+            "nntp" => AttrProtocol::NNTP,
+            "nntps" => AttrProtocol::NNTPS,
+            "pop3" => AttrProtocol::POP3,
+            "pop3s" => AttrProtocol::POP3S,
+            "rtsp" => AttrProtocol::RTSP,
+            "smb" => AttrProtocol::SMB,
+            "smtp" => AttrProtocol::SMTP,
+            "socks" => AttrProtocol::SOCKS,
+            "ssh" => AttrProtocol::SSH,
+            "telnet" => AttrProtocol::Telnet,
+            "telnets" => AttrProtocol::TelnetS,
+            _ => return None,
+        };
+
+        Some(protocol)
+    }
+}
+
 impl TAttr for AttrProtocol {
     fn kind(&self) -> AttrKind {
         AttrKind::Protocol
@@ -776,30 +1252,301 @@ impl TAttr for AttrProtocol {
     }
 }
 
-/// Identifiers for external storage tokens for cryptographic keys
-/// (i.e. Secure Enclave).
+/// Authentication schemes optionally associated with
+/// `SecClass::InternetPassword` keychain items.
+///
+/// Wrapper for the `kSecAttrAuthenticationType` attribute key. See:
+/// <https://developer.apple.com/documentation/security/ksecattrauthenticationtype>
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AttrAuthenticationType {
+    /// Windows NT LAN Manager authentication.
+    NTLM,
+
+    /// Microsoft Network default authentication.
+    MSN,
+
+    /// Distributed Password authentication.
+    DPA,
+
+    /// Remote Password authentication.
+    RPA,
+
+    /// HTTP Basic authentication.
+    HTTPBasic,
+
+    /// HTTP Digest Access authentication.
+    HTTPDigest,
+
+    /// HTML form based authentication.
+    HTMLForm,
+
+    /// Default authentication type.
+    Default,
+}
+
+impl AttrAuthenticationType {
+    /// All authentication type values
+    pub const ALL: &'static [AttrAuthenticationType] = &[
+        AttrAuthenticationType::NTLM,
+        AttrAuthenticationType::MSN,
+        AttrAuthenticationType::DPA,
+        AttrAuthenticationType::RPA,
+        AttrAuthenticationType::HTTPBasic,
+        AttrAuthenticationType::HTTPDigest,
+        AttrAuthenticationType::HTMLForm,
+        AttrAuthenticationType::Default,
+    ];
+
+    /// Look up an authentication type from its four character code (e.g.
+    /// `http` for HTTP Basic), as stored in the `atyp` attribute of legacy
+    /// keychain items.
+    pub fn from_code(code: &[u8; 4]) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .find(|auth_type| auth_type.as_CFString().to_string().as_bytes() == code)
+            .cloned()
+    }
+
+    /// Get `CFString` containing the `kSecAttrAuthenticationType` dictionary
+    /// value for this particular `SecAttrAuthenticationType`.
+    pub fn as_CFString(self) -> CFString {
+        unsafe {
+            CFString::wrap_under_get_rule(match self {
+                AttrAuthenticationType::NTLM => kSecAttrAuthenticationTypeNTLM,
+                AttrAuthenticationType::MSN => kSecAttrAuthenticationTypeMSN,
+                AttrAuthenticationType::DPA => kSecAttrAuthenticationTypeDPA,
+                AttrAuthenticationType::RPA => kSecAttrAuthenticationTypeRPA,
+                AttrAuthenticationType::HTTPBasic => kSecAttrAuthenticationTypeHTTPBasic,
+                AttrAuthenticationType::HTTPDigest => kSecAttrAuthenticationTypeHTTPDigest,
+                AttrAuthenticationType::HTMLForm => kSecAttrAuthenticationTypeHTMLForm,
+                AttrAuthenticationType::Default => kSecAttrAuthenticationTypeDefault,
+            })
+        }
+    }
+}
+
+impl TAttr for AttrAuthenticationType {
+    fn kind(&self) -> AttrKind {
+        AttrKind::AuthenticationType
+    }
+
+    fn as_CFType(&self) -> CFType {
+        self.as_CFString().as_CFType()
+    }
+}
+
+/// Security domains (e.g. HTTP authentication realms) associated with
+/// `SecClass::InternetPassword` keychain items.
+///
+/// Wrapper for the `kSecAttrSecurityDomain` attribute key. See:
+/// <https://developer.apple.com/documentation/security/ksecattrsecuritydomain>
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AttrSecurityDomain(pub(crate) CFString);
+
+impl AttrSecurityDomain {
+    /// Create a new security domain from a `&str`
+    pub fn new(domain: &str) -> Self {
+        AttrSecurityDomain(CFString::new(domain))
+    }
+}
+
+impl Display for AttrSecurityDomain {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", &self.0)
+    }
+}
+
+impl From<&str> for AttrSecurityDomain {
+    fn from(domain: &str) -> Self {
+        Self::new(domain)
+    }
+}
+
+impl TAttr for AttrSecurityDomain {
+    fn kind(&self) -> AttrKind {
+        AttrKind::SecurityDomain
+    }
+
+    fn as_CFType(&self) -> CFType {
+        self.0.as_CFType()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for AttrSecurityDomain {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for AttrSecurityDomain {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::new(&String::deserialize(deserializer)?))
+    }
+}
+
+/// Hostnames or IP addresses of servers associated with
+/// `SecClass::InternetPassword` keychain items.
+///
+/// Unlike other string attributes, servers are validated on construction:
+/// they must be a DNS hostname, an IPv4 address, or an IPv6 address
+/// (optionally enclosed in square brackets).
+///
+/// Wrapper for the `kSecAttrServer` attribute key. See:
+/// <https://developer.apple.com/documentation/security/ksecattrserver>
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AttrServer(pub(crate) CFString);
+
+impl AttrServer {
+    /// Create a new server attribute, returning an error with a kind of
+    /// `ErrorKind::Param` if `host` is not a valid hostname or IP address.
+    pub fn new(host: &str) -> Result<Self, Error> {
+        if is_valid_host(host) {
+            Ok(AttrServer(CFString::new(host)))
+        } else {
+            Err(Error::new(
+                ErrorKind::Param,
+                &format!("invalid server hostname: {:?}", host),
+            ))
+        }
+    }
+}
+
+impl Display for AttrServer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", &self.0)
+    }
+}
+
+impl FromStr for AttrServer {
+    type Err = Error;
+
+    fn from_str(host: &str) -> Result<Self, Error> {
+        Self::new(host)
+    }
+}
+
+impl TAttr for AttrServer {
+    fn kind(&self) -> AttrKind {
+        AttrKind::Server
+    }
+
+    fn as_CFType(&self) -> CFType {
+        self.0.as_CFType()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for AttrServer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for AttrServer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error as _;
+        Self::new(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+/// Is the given string a valid DNS hostname or IP address?
+fn is_valid_host(host: &str) -> bool {
+    if host.starts_with('[') && host.ends_with(']') {
+        return host[1..host.len() - 1].parse::<Ipv6Addr>().is_ok();
+    }
+
+    if host.parse::<IpAddr>().is_ok() {
+        return true;
+    }
+
+    let host = host.trim_end_matches('.');
+
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+        })
+}
+
+/// Identifiers for external storage tokens for cryptographic keys, i.e.
+/// the Secure Enclave or tokens provided by CryptoTokenKit extensions
+/// (e.g. smart cards or HSM bridges).
 ///
 /// Wrapper for the `kSecAttrTokenID` attribute key. See:
 /// <https://developer.apple.com/documentation/security/ksecattrtokenid>
-#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AttrTokenId {
     /// Secure Enclave Processor (SEP), e.g. T1/T2 chip.
     ///
     /// Wrapper for the `kSecAttrTokenIDSecureEnclave` attribute value. See:
     /// <https://developer.apple.com/documentation/security/ksecattrtokenidsecureenclave>
     SecureEnclave,
+
+    /// Token provided by a CryptoTokenKit extension, identified by its
+    /// token ID string (e.g. `com.apple.pivtoken:<instance ID>`).
+    ///
+    /// See the CryptoTokenKit documentation for more information:
+    /// <https://developer.apple.com/documentation/cryptotokenkit>
+    Token(String),
 }
 
 impl AttrTokenId {
+    /// Create a token ID from its string representation, mapping the Secure
+    /// Enclave's token ID to `AttrTokenId::SecureEnclave`.
+    pub fn new(token_id: &str) -> Self {
+        let secure_enclave = unsafe { CFString::wrap_under_get_rule(kSecAttrTokenIDSecureEnclave) };
+
+        if CFString::new(token_id) == secure_enclave {
+            AttrTokenId::SecureEnclave
+        } else {
+            AttrTokenId::Token(token_id.to_owned())
+        }
+    }
+
     /// Get `CFString` containing the `kSecAttrTokenID` dictionary value for
     /// this particular `SecAttrTokenId`.
-    pub fn as_CFString(self) -> CFString {
-        unsafe {
-            CFString::wrap_under_get_rule(match self {
-                AttrTokenId::SecureEnclave => kSecAttrTokenIDSecureEnclave,
-            })
+    pub fn as_CFString(&self) -> CFString {
+        match self {
+            AttrTokenId::SecureEnclave => unsafe {
+                CFString::wrap_under_get_rule(kSecAttrTokenIDSecureEnclave)
+            },
+            AttrTokenId::Token(token_id) => CFString::new(token_id),
         }
     }
+
+    /// Is this the Secure Enclave?
+    pub fn is_secure_enclave(&self) -> bool {
+        *self == AttrTokenId::SecureEnclave
+    }
+}
+
+impl Display for AttrTokenId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_CFString())
+    }
+}
+
+impl From<&str> for AttrTokenId {
+    fn from(token_id: &str) -> Self {
+        Self::new(token_id)
+    }
+}
+
+impl<'a> From<&'a CFString> for AttrTokenId {
+    fn from(token_id: &'a CFString) -> Self {
+        Self::new(&token_id.to_string())
+    }
 }
 
 impl TAttr for AttrTokenId {
@@ -816,6 +1563,7 @@ impl TAttr for AttrTokenId {
 ///
 /// <https://developer.apple.com/documentation/security/keychain_services/keychain_items/1495743-keychain_item_attribute_constant>
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum KeyAttr {
     /// Wrapper for the `kSecKeyAlwaysSensitive` attribute value see:
     /// <https://developer.apple.com/documentation/security/kseckeyalwayssensitive>
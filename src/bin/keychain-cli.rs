@@ -0,0 +1,326 @@
+//! `keychain-cli`: scriptable access to Keychain Services.
+//!
+//! Exposes this crate's item queries, key generation, signing, and key
+//! export in a form suitable for shell scripts, as a replacement for parsing
+//! the output of `security(1)`. Results are written to stdout (binary data
+//! as lowercase hex), errors to stderr, and the exit status is 0 on success,
+//! 1 if an operation failed, and 2 if the command line is invalid.
+//!
+//! Built only with the `cli` feature:
+//!
+//! ```text
+//! cargo install keychain-services --features cli
+//! ```
+
+#![deny(missing_docs, unused_import_braces, unused_qualifications)]
+
+use keychain_services::{
+    keychain::item::{export_inventory, Class, Query},
+    AccessControl, AccessControlFlags, AccessOption, AttrAccessible, AttrKeyClass, AttrKeyType,
+    AttrTokenId, Error, Key, KeyAlgorithm, KeyPair, KeyPairGenerateParams,
+};
+use std::{
+    env,
+    fmt::{self, Display},
+    io::{self, Read, Write},
+    process,
+};
+
+/// Usage information printed for invalid command lines
+const USAGE: &str = "\
+usage: keychain-cli <command> [options]
+
+commands:
+    list [--class CLASS]... [--label LABEL] [--format json|plist]
+        Print an inventory of item metadata (never secret data). CLASS is one
+        of generic_password, internet_password, certificate, key, identity
+        (default: all).
+
+    generate --tag TAG [--type ec|rsa] [--size BITS] [--label LABEL]
+             [--secure-enclave]
+        Generate a key pair stored in the keychain (default: 256-bit EC),
+        printing the hash of its public key.
+
+    sign --tag TAG [--algorithm ALG]
+        Sign the data read from stdin with the private key with the given
+        application tag, printing the signature. ALG is one of ecdsa-sha256
+        (default), ecdsa-sha384, ecdsa-sha512, rsa-pkcs1v15-sha256,
+        rsa-pkcs1v15-sha384, rsa-pkcs1v15-sha512.
+
+    export --tag TAG [--private]
+        Print the external representation of the public key (or, if
+        extractable, the private key) with the given application tag.
+";
+
+/// Classes of items listed when none are specified
+const ALL_CLASSES: &[Class] = &[
+    Class::GenericPassword,
+    Class::InternetPassword,
+    Class::Certificate,
+    Class::Key,
+    Class::Identity,
+];
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let result = match args.first().map(String::as_str) {
+        Some("list") => Options::parse(&args[1..], &[]).and_then(list),
+        Some("generate") => Options::parse(&args[1..], &["secure-enclave"]).and_then(generate),
+        Some("sign") => Options::parse(&args[1..], &[]).and_then(sign),
+        Some("export") => Options::parse(&args[1..], &["private"]).and_then(export),
+        Some(command) => Err(CliError::Usage(format!("unknown command: {}", command))),
+        None => Err(CliError::Usage("no command given".to_owned())),
+    };
+
+    match result {
+        Ok(()) => (),
+        Err(CliError::Usage(msg)) => {
+            eprintln!("error: {}\n\n{}", msg, USAGE);
+            process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// `list`: print an inventory of item metadata
+fn list(options: Options) -> Result<(), CliError> {
+    let mut classes = vec![];
+
+    for class in options.values("class") {
+        classes.push(parse_class(class)?);
+    }
+
+    if classes.is_empty() {
+        classes.extend_from_slice(ALL_CLASSES);
+    }
+
+    let mut query = Query::new();
+
+    if let Some(label) = options.value("label") {
+        query = query.label(label);
+    }
+
+    let inventory = export_inventory(&classes, query)?;
+
+    match options.value("format").unwrap_or("json") {
+        "json" => println!("{}", inventory.to_json()),
+        "plist" => io::stdout().write_all(&inventory.to_plist()?)?,
+        other => return Err(CliError::Usage(format!("unknown format: {}", other))),
+    }
+
+    Ok(())
+}
+
+/// `generate`: generate a key pair stored in the keychain
+fn generate(options: Options) -> Result<(), CliError> {
+    let tag = options.required("tag")?;
+
+    let key_type = match options.value("type").unwrap_or("ec") {
+        "ec" => AttrKeyType::EcSecPrimeRandom,
+        "rsa" => AttrKeyType::Rsa,
+        other => return Err(CliError::Usage(format!("unknown key type: {}", other))),
+    };
+
+    let key_size = match options.value("size") {
+        Some(size) => size
+            .parse()
+            .map_err(|_| CliError::Usage(format!("invalid key size: {}", size)))?,
+        None if key_type == AttrKeyType::Rsa => 2048,
+        None => 256,
+    };
+
+    let mut params = KeyPairGenerateParams::new(key_type, key_size)
+        .application_tag(tag)
+        .permanent(true);
+
+    if let Some(label) = options.value("label") {
+        params = params.label(label);
+    }
+
+    if options.flag("secure-enclave") {
+        let mut flags = AccessControlFlags::new();
+        flags.add(AccessOption::PrivateKeyUsage);
+
+        let access_control =
+            AccessControl::create_with_flags(AttrAccessible::WhenUnlockedThisDeviceOnly, flags)?;
+
+        params = params
+            .token_id(AttrTokenId::SecureEnclave)
+            .access_control(&access_control);
+    }
+
+    let key_pair = KeyPair::create(params)?;
+    let public_key_hash = key_pair
+        .private_key
+        .application_label()
+        .map(|label| hex(label.as_bytes()))
+        .unwrap_or_default();
+
+    println!("{}", public_key_hash);
+    Ok(())
+}
+
+/// `sign`: sign data from stdin with a private key
+fn sign(options: Options) -> Result<(), CliError> {
+    let key = find_key(options.required("tag")?, AttrKeyClass::Private)?;
+
+    let alg = match options.value("algorithm").unwrap_or("ecdsa-sha256") {
+        "ecdsa-sha256" => KeyAlgorithm::ECDSASignatureMessageX962SHA256,
+        "ecdsa-sha384" => KeyAlgorithm::ECDSASignatureMessageX962SHA384,
+        "ecdsa-sha512" => KeyAlgorithm::ECDSASignatureMessageX962SHA512,
+        "rsa-pkcs1v15-sha256" => KeyAlgorithm::RSASignatureMessagePKCS1v15SHA256,
+        "rsa-pkcs1v15-sha384" => KeyAlgorithm::RSASignatureMessagePKCS1v15SHA384,
+        "rsa-pkcs1v15-sha512" => KeyAlgorithm::RSASignatureMessagePKCS1v15SHA512,
+        other => return Err(CliError::Usage(format!("unknown algorithm: {}", other))),
+    };
+
+    let mut data = vec![];
+    io::stdin().read_to_end(&mut data)?;
+
+    let signature = key.sign(alg, &data)?;
+    println!("{}", hex(signature.as_bytes()));
+    Ok(())
+}
+
+/// `export`: print the external representation of a key
+fn export(options: Options) -> Result<(), CliError> {
+    let private_key = find_key(options.required("tag")?, AttrKeyClass::Private)?;
+
+    let key = if options.flag("private") {
+        private_key
+    } else {
+        private_key.public()?
+    };
+
+    println!("{}", hex(&key.to_external_representation()?));
+    Ok(())
+}
+
+/// Find the key of the given class with the given application tag
+fn find_key(tag: &str, key_class: AttrKeyClass) -> Result<Key, CliError> {
+    let query = Query::new().application_tag(tag).key_class(key_class);
+
+    Key::find(query)?
+        .ok_or_else(|| CliError::NotFound(format!("no key with application tag: {}", tag)))
+}
+
+/// Parse the name of an item class, as used in inventories
+fn parse_class(name: &str) -> Result<Class, CliError> {
+    match name {
+        "generic_password" => Ok(Class::GenericPassword),
+        "internet_password" => Ok(Class::InternetPassword),
+        "certificate" => Ok(Class::Certificate),
+        "key" => Ok(Class::Key),
+        "identity" => Ok(Class::Identity),
+        other => Err(CliError::Usage(format!("unknown item class: {}", other))),
+    }
+}
+
+/// Encode bytes as lowercase hex
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Options given to a command, i.e. `--name value` pairs and `--name` flags
+struct Options {
+    values: Vec<(String, String)>,
+    flags: Vec<String>,
+}
+
+impl Options {
+    /// Parse the given arguments, treating the given names as flags (i.e.
+    /// options without values)
+    fn parse(args: &[String], flag_names: &[&str]) -> Result<Self, CliError> {
+        let mut options = Options {
+            values: vec![],
+            flags: vec![],
+        };
+
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            let name = match arg.strip_prefix("--") {
+                Some(name) => name,
+                None => return Err(CliError::Usage(format!("unexpected argument: {}", arg))),
+            };
+
+            if flag_names.contains(&name) {
+                options.flags.push(name.to_owned());
+            } else {
+                let value = args
+                    .next()
+                    .ok_or_else(|| CliError::Usage(format!("missing value for --{}", name)))?;
+
+                options.values.push((name.to_owned(), value.clone()));
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Get all values given for an option
+    fn values(&self, name: &str) -> Vec<&str> {
+        self.values
+            .iter()
+            .filter(|(n, _)| n == name)
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    /// Get the last value given for an option (if any)
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values(name).pop()
+    }
+
+    /// Get the value given for an option, which must be present
+    fn required(&self, name: &str) -> Result<&str, CliError> {
+        self.value(name)
+            .ok_or_else(|| CliError::Usage(format!("missing required option --{}", name)))
+    }
+
+    /// Was the given flag set?
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|flag| flag == name)
+    }
+}
+
+/// Errors which cause the CLI to exit unsuccessfully
+enum CliError {
+    /// Invalid command line
+    Usage(String),
+
+    /// A requested item doesn't exist
+    NotFound(String),
+
+    /// Keychain Services operation failed
+    Keychain(Error),
+
+    /// Reading input or writing output failed
+    Io(io::Error),
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(msg) | CliError::NotFound(msg) => f.write_str(msg),
+            CliError::Keychain(e) => write!(f, "{}", e),
+            CliError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<Error> for CliError {
+    fn from(e: Error) -> CliError {
+        CliError::Keychain(e)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(e: io::Error) -> CliError {
+        CliError::Io(e)
+    }
+}
@@ -0,0 +1,202 @@
+//! Dates associated with keychain items, i.e. the `kSecAttrCreationDate`
+//! and `kSecAttrModificationDate` attributes.
+//!
+//! Keychain Services represents these as `CFDate` values (seconds relative
+//! to 2001-01-01 00:00:00 UTC), or in the legacy `SecKeychainItem` API as
+//! `YYYYMMDDhhmmssZ` timestamp strings. This module converts both to
+//! `std::time::SystemTime`.
+
+use core_foundation::date::{CFAbsoluteTime, CFDate};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Number of seconds between the Unix epoch (1970-01-01) and the
+/// Core Foundation reference date (2001-01-01).
+///
+/// Equivalent to `kCFAbsoluteTimeIntervalSince1970`. See:
+/// <https://developer.apple.com/documentation/corefoundation/kcfabsolutetimeintervalsince1970>
+pub(crate) const ABSOLUTE_TIME_INTERVAL_SINCE_1970: f64 = 978_307_200.0;
+
+/// Range of dates used to select keychain items by their creation or
+/// modification date.
+///
+/// Both bounds are optional and inclusive.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DateRange {
+    start: Option<SystemTime>,
+    end: Option<SystemTime>,
+}
+
+impl DateRange {
+    /// Create a new `DateRange` which matches all dates
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a `DateRange` matching dates between `start` and `end`
+    /// (inclusive).
+    pub fn between<S, E>(start: S, end: E) -> Self
+    where
+        S: Into<SystemTime>,
+        E: Into<SystemTime>,
+    {
+        Self::new().after(start).before(end)
+    }
+
+    /// Only match dates on or after the given time.
+    pub fn after<T: Into<SystemTime>>(mut self, time: T) -> Self {
+        self.start = Some(time.into());
+        self
+    }
+
+    /// Only match dates on or before the given time.
+    pub fn before<T: Into<SystemTime>>(mut self, time: T) -> Self {
+        self.end = Some(time.into());
+        self
+    }
+
+    /// Only match dates which are at least `age` old, e.g. to locate items
+    /// which are due for rotation.
+    pub fn older_than(self, age: Duration) -> Self {
+        self.before(SystemTime::now() - age)
+    }
+
+    /// Only match dates which are at most `age` old.
+    pub fn newer_than(self, age: Duration) -> Self {
+        self.after(SystemTime::now() - age)
+    }
+
+    /// Get the lower bound of this range (if any)
+    pub fn start(&self) -> Option<SystemTime> {
+        self.start
+    }
+
+    /// Get the upper bound of this range (if any)
+    pub fn end(&self) -> Option<SystemTime> {
+        self.end
+    }
+
+    /// Does this range contain the given time?
+    pub fn contains(&self, time: SystemTime) -> bool {
+        self.start.map(|start| time >= start).unwrap_or(true)
+            && self.end.map(|end| time <= end).unwrap_or(true)
+    }
+}
+
+/// Convert a `CFDate` into a `SystemTime`
+pub(crate) fn system_time_from_CFDate(date: &CFDate) -> SystemTime {
+    let unix_time = date.abs_time() + ABSOLUTE_TIME_INTERVAL_SINCE_1970;
+
+    if unix_time >= 0.0 {
+        UNIX_EPOCH + Duration::from_secs_f64(unix_time)
+    } else {
+        UNIX_EPOCH - Duration::from_secs_f64(-unix_time)
+    }
+}
+
+/// Convert a `SystemTime` into a `CFDate`
+pub(crate) fn CFDate_from_system_time(time: SystemTime) -> CFDate {
+    let unix_time = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs_f64(),
+        Err(e) => -e.duration().as_secs_f64(),
+    };
+
+    CFDate::new((unix_time - ABSOLUTE_TIME_INTERVAL_SINCE_1970) as CFAbsoluteTime)
+}
+
+/// Parse a legacy `SecKeychainItem` timestamp (i.e. the `cdat`/`mdat`
+/// attributes) of the form `YYYYMMDDhhmmssZ`, optionally NUL-terminated.
+pub(crate) fn parse_timestamp(bytes: &[u8]) -> Option<SystemTime> {
+    if bytes.len() < 14 || !bytes[..14].iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+
+    let field = |range: std::ops::Range<usize>| {
+        bytes[range]
+            .iter()
+            .fold(0i64, |acc, digit| acc * 10 + i64::from(digit - b'0'))
+    };
+
+    let (year, month, day) = (field(0..4), field(4..6), field(6..8));
+    let (hour, minute, second) = (field(8..10), field(10..12), field(12..14));
+
+    if !(1..=12).contains(&month)
+        || !(1..=31).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    let secs = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+
+    if secs >= 0 {
+        Some(UNIX_EPOCH + Duration::from_secs(secs as u64))
+    } else {
+        Some(UNIX_EPOCH - Duration::from_secs(-secs as u64))
+    }
+}
+
+/// Format a `SystemTime` as a legacy `SecKeychainItem` timestamp, i.e. the
+/// NUL-terminated `YYYYMMDDhhmmssZ` form parsed by `parse_timestamp`.
+/// Fractional seconds are rounded down.
+pub(crate) fn format_timestamp(time: SystemTime) -> Vec<u8> {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs_f64().ceil() as i64),
+    };
+
+    let (days, secs_of_day) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+    let (year, month, day) = civil_from_days(days);
+
+    let mut timestamp = format!(
+        "{:04}{:02}{:02}{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+    .into_bytes();
+
+    timestamp.push(0);
+    timestamp
+}
+
+/// Number of days since 1970-01-01 for the given proleptic Gregorian date.
+///
+/// Based on Howard Hinnant's `days_from_civil` algorithm:
+/// <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = (if year >= 0 { year } else { year - 399 }) / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Proleptic Gregorian date for the given number of days since 1970-01-01,
+/// i.e. the inverse of `days_from_civil`.
+///
+/// Based on Howard Hinnant's `civil_from_days` algorithm:
+/// <https://howardhinnant.github.io/date_algorithms.html#civil_from_days>
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = (if days >= 0 { days } else { days - 146_096 }) / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
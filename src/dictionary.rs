@@ -1,6 +1,12 @@
 //! Builder for constructing a `CFDictionary` from attribute pairs.
 
-use crate::{attr::TAttr, ffi::kSecClass, keychain::item};
+#[cfg(feature = "serde")]
+use crate::date::ABSOLUTE_TIME_INTERVAL_SINCE_1970;
+use crate::{
+    attr::TAttr,
+    ffi::{kSecClass, kSecUseOperationPrompt, kSecValueData},
+    keychain::item,
+};
 use core_foundation::{
     self,
     base::{CFType, TCFType},
@@ -8,14 +14,23 @@ use core_foundation::{
     number::CFNumber,
     string::{CFString, CFStringRef},
 };
+#[cfg(feature = "serde")]
+use core_foundation::{base::CFTypeRef, data::CFData, date::CFDate};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "serde")]
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
 
 /// All CFDictionary types we use follow this signature
 pub(crate) type Dictionary = core_foundation::dictionary::CFDictionary<CFType, CFType>;
 
 /// Builder for attribute/parameter dictionaries we pass as arguments.
-// TODO: ensure there are no duplicate items, e.g. with `HashMap`/`BTreeMap`
-// storage and checking if the same key is added twice.
-#[derive(Clone, Default, Debug)]
+///
+/// Each key appears at most once: `CFDictionaryCreate` keeps the first of
+/// several values for the same key, so adding a key again replaces its
+/// value rather than appending a duplicate which would be ignored.
+#[derive(Clone, Default)]
 pub(crate) struct DictionaryBuilder(Vec<(CFType, CFType)>);
 
 impl DictionaryBuilder {
@@ -24,16 +39,43 @@ impl DictionaryBuilder {
         DictionaryBuilder(vec![])
     }
 
-    /// Add a key/value pair to the dictionary
+    /// Add a key/value pair to the dictionary, replacing any value it
+    /// already has for the key
     pub(crate) fn add<K, V>(&mut self, key: K, value: &V)
     where
         K: Into<CFStringRef>,
         V: TCFType,
     {
-        self.0.push((
-            unsafe { CFString::wrap_under_get_rule(key.into()) }.as_CFType(),
-            value.as_CFType(),
-        ))
+        let key = unsafe { CFString::wrap_under_get_rule(key.into()) }.as_CFType();
+        self.0.retain(|(k, _)| *k != key);
+        self.0.push((key, value.as_CFType()))
+    }
+
+    /// Get the value for the given key (if present)
+    pub(crate) fn get<K>(&self, key: K) -> Option<&CFType>
+    where
+        K: Into<CFStringRef>,
+    {
+        let key = unsafe { CFString::wrap_under_get_rule(key.into()) }.as_CFType();
+        self.0.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Remove all values for the given key
+    pub(crate) fn remove<K>(&mut self, key: K)
+    where
+        K: Into<CFStringRef>,
+    {
+        let key = unsafe { CFString::wrap_under_get_rule(key.into()) }.as_CFType();
+        self.0.retain(|(k, _)| *k != key);
+    }
+
+    /// Add all key/value pairs of the given dictionary, replacing any values
+    /// this one already has for the same keys
+    pub(crate) fn merge(&mut self, other: &DictionaryBuilder) {
+        for (key, value) in &other.0 {
+            self.0.retain(|(k, _)| k != key);
+            self.0.push((key.clone(), value.clone()));
+        }
     }
 
     /// Add an attribute (i.e. `TSecAttr`) to the dictionary
@@ -54,6 +96,22 @@ impl DictionaryBuilder {
         self.add(unsafe { kSecClass }, &class.as_CFString());
     }
 
+    /// Get the `keychain::item::Class` value of the dictionary (if present)
+    pub(crate) fn class(&self) -> Option<item::Class> {
+        let value = self.get(unsafe { kSecClass })?;
+
+        [
+            item::Class::GenericPassword,
+            item::Class::InternetPassword,
+            item::Class::Certificate,
+            item::Class::Key,
+            item::Class::Identity,
+        ]
+        .iter()
+        .find(|class| class.as_CFString().as_CFType() == *value)
+        .cloned()
+    }
+
     /// Add a key/value pair with an `i64` value to the dictionary
     pub(crate) fn add_number<K>(&mut self, key: K, value: i64)
     where
@@ -72,8 +130,172 @@ impl DictionaryBuilder {
     }
 }
 
+/// Lists the attributes and parameters, but never the secret data or the
+/// operation prompt (which may describe what the secret is for), so queries
+/// and changes can be logged safely.
+impl Debug for DictionaryBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let redacted_keys = [unsafe { kSecValueData }, unsafe { kSecUseOperationPrompt }]
+            .iter()
+            .map(|&key| unsafe { CFString::wrap_under_get_rule(key) }.as_CFType())
+            .collect::<Vec<_>>();
+
+        let mut map = f.debug_map();
+
+        for (key, value) in &self.0 {
+            if redacted_keys.contains(key) {
+                map.entry(key, &Redacted);
+            } else {
+                map.entry(key, value);
+            }
+        }
+
+        map.finish()
+    }
+}
+
+/// Placeholder for a redacted value in `Debug` output
+struct Redacted;
+
+impl Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "[REDACTED]")
+    }
+}
+
 impl From<DictionaryBuilder> for Dictionary {
     fn from(builder: DictionaryBuilder) -> Dictionary {
         Dictionary::from_CFType_pairs(&builder.0)
     }
 }
+
+/// Serialized representation of dictionary values.
+///
+/// Only the property list types we place in attribute dictionaries are
+/// supported: booleans, numbers (whole-valued floating point numbers are
+/// deserialized as integers), strings, data, dates, and nested
+/// dictionaries. Other objects (e.g. an `AccessControl` or a `Keychain`)
+/// and arrays (e.g. `Query::keychains`) cannot be serialized, and attempting
+/// to do so returns an error naming the attribute.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum Value {
+    Boolean(bool),
+    Number(i64),
+    Float(f64),
+    String(String),
+    Data(Vec<u8>),
+    Date(DateValue),
+    Dictionary(DictionaryBuilder),
+}
+
+/// Serialized representation of a date: a map with a single `$date` key
+/// whose value is the number of seconds since the Unix epoch, which can't
+/// be confused with an attribute dictionary.
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DateValue {
+    #[serde(rename = "$date")]
+    unix_time: f64,
+}
+
+#[cfg(feature = "serde")]
+impl Value {
+    /// Convert a `CFType` into a serializable value (if supported)
+    fn from_CFType(value: &CFType) -> Option<Self> {
+        if let Some(boolean) = value.downcast::<CFBoolean>() {
+            Some(Value::Boolean(boolean.into()))
+        } else if let Some(number) = value.downcast::<CFNumber>() {
+            number
+                .to_i64()
+                .map(Value::Number)
+                .or_else(|| number.to_f64().map(Value::Float))
+        } else if let Some(string) = value.downcast::<CFString>() {
+            Some(Value::String(string.to_string()))
+        } else if let Some(data) = value.downcast::<CFData>() {
+            Some(Value::Data(data.to_vec()))
+        } else if let Some(date) = value.downcast::<CFDate>() {
+            Some(Value::Date(DateValue {
+                unix_time: date.abs_time() + ABSOLUTE_TIME_INTERVAL_SINCE_1970,
+            }))
+        } else if let Some(dictionary) =
+            value.downcast::<core_foundation::dictionary::CFDictionary>()
+        {
+            let (keys, values) = dictionary.get_keys_and_values();
+            let pairs = keys
+                .into_iter()
+                .zip(values)
+                .map(|(k, v)| unsafe {
+                    (
+                        CFType::wrap_under_get_rule(k as CFTypeRef),
+                        CFType::wrap_under_get_rule(v as CFTypeRef),
+                    )
+                })
+                .collect();
+
+            Some(Value::Dictionary(DictionaryBuilder(pairs)))
+        } else {
+            None
+        }
+    }
+
+    /// Convert this value into a `CFType`
+    fn into_CFType(self) -> CFType {
+        match self {
+            Value::Boolean(boolean) => CFBoolean::from(boolean).as_CFType(),
+            Value::Number(number) => CFNumber::from(number).as_CFType(),
+            Value::Float(number) => CFNumber::from(number).as_CFType(),
+            Value::String(string) => CFString::new(&string).as_CFType(),
+            Value::Data(data) => CFData::from_buffer(&data).as_CFType(),
+            Value::Date(date) => {
+                CFDate::new(date.unix_time - ABSOLUTE_TIME_INTERVAL_SINCE_1970).as_CFType()
+            }
+            Value::Dictionary(builder) => Dictionary::from(builder).as_CFType(),
+        }
+    }
+}
+
+/// Serializes attribute dictionaries as maps keyed by the underlying
+/// Keychain Services attribute names.
+#[cfg(feature = "serde")]
+impl Serialize for DictionaryBuilder {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeMap};
+
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+
+        for (key, value) in &self.0 {
+            let key = key
+                .downcast::<CFString>()
+                .ok_or_else(|| S::Error::custom("dictionary key is not a string"))?
+                .to_string();
+
+            let value = Value::from_CFType(value).ok_or_else(|| {
+                S::Error::custom(format!(
+                    "attribute {:?} has a value which can't be serialized (only booleans, \
+                     numbers, strings, data, dates, and dictionaries are supported)",
+                    key
+                ))
+            })?;
+
+            map.serialize_entry(&key, &value)?;
+        }
+
+        map.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for DictionaryBuilder {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let map = BTreeMap::<String, Value>::deserialize(deserializer)?;
+
+        Ok(DictionaryBuilder(
+            map.into_iter()
+                .map(|(key, value)| (CFString::new(&key).as_CFType(), value.into_CFType()))
+                .collect(),
+        ))
+    }
+}
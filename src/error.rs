@@ -1,9 +1,12 @@
 //! Error types
 
-use crate::ffi::*;
+use crate::{
+    ffi::*,
+    keychain::{entitlements::EntitlementRequirement, localization},
+};
 use core_foundation::{
     base::{CFRelease, CFTypeRef, OSStatus, TCFType},
-    error::{CFErrorCopyDescription, CFErrorGetCode, CFErrorGetDomain, CFErrorRef},
+    error::{CFError, CFErrorCopyDescription, CFErrorRef},
     string::CFString,
 };
 use failure::{Backtrace, Fail};
@@ -40,6 +43,10 @@ const errSecDataNotAvailable: OSStatus = -25316;
 /// <https://developer.apple.com/documentation/security/errsecdatanotmodifiable>
 const errSecDataNotModifiable: OSStatus = -25317;
 
+/// Unable to decode the provided data.
+/// <https://developer.apple.com/documentation/security/errsecdecode>
+const errSecDecode: OSStatus = -26275;
+
 /// Callback with the same name already exists.
 /// <https://developer.apple.com/documentation/security/errsecduplicatecallback>
 const errSecDuplicateCallback: OSStatus = -25297;
@@ -134,6 +141,10 @@ const errSecNoSuchKeychain: OSStatus = -25294;
 /// <https://developer.apple.com/documentation/security/errsecnotavailable>
 const errSecNotAvailable: OSStatus = -25291;
 
+/// One or more parameters passed to a function were not valid.
+/// <https://developer.apple.com/documentation/security/errsecparam>
+const errSecParam: OSStatus = -50;
+
 /// Can't perform given action on read-only item.
 /// <https://developer.apple.com/documentation/security/errsecreadonly>
 const errSecReadOnly: OSStatus = -25292;
@@ -142,6 +153,10 @@ const errSecReadOnly: OSStatus = -25292;
 /// <https://developer.apple.com/documentation/security/errsecreadonlyattr>
 const errSecReadOnlyAttr: OSStatus = -25309;
 
+/// User canceled the operation (e.g. dismissed a password or PIN prompt).
+/// <https://developer.apple.com/documentation/security/errsecusercanceled>
+const errSecUserCanceled: OSStatus = -128;
+
 /// Invalid version.
 /// <https://developer.apple.com/documentation/security/errsecwrongversion>
 const errSecWrongSecVersion: OSStatus = -25310;
@@ -190,9 +205,24 @@ impl Error {
     }
 }
 
+/// Displays the description and kind of the error, as localized by the
+/// installed `Localizer` (if any).
 impl Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} ({})", &self.description, &self.kind)
+        match localization::localizer() {
+            Some(localizer) => {
+                let description = localizer
+                    .error_description(&self.kind, &self.description)
+                    .unwrap_or_else(|| self.description.clone());
+
+                let kind = localizer
+                    .error_kind(&self.kind)
+                    .unwrap_or_else(|| self.kind.to_string());
+
+                write!(f, "{} ({})", description, kind)
+            }
+            None => write!(f, "{} ({})", &self.description, &self.kind),
+        }
     }
 }
 
@@ -211,7 +241,7 @@ impl From<CFErrorRef> for Error {
     ///
     /// Calls `CFRelease` on the provided `CFErrorRef`.
     fn from(error_ref: CFErrorRef) -> Error {
-        let kind = ErrorKind::from(error_ref);
+        let kind = ErrorKind::from_cf_error(&unsafe { CFError::wrap_under_get_rule(error_ref) });
         let backtrace = Backtrace::new();
         let description =
             unsafe { CFString::wrap_under_create_rule(CFErrorCopyDescription(error_ref)) }
@@ -233,6 +263,14 @@ impl From<CFErrorRef> for Error {
 /// Kinds of errors.
 #[derive(Clone, Debug, Fail)]
 pub enum ErrorKind {
+    /// Access control policy can't be satisfied on this device, e.g. it
+    /// requires a passcode or biometrics but none are configured.
+    ///
+    /// There is no corresponding `OSStatus` code: this is inferred from the
+    /// errors returned when generating keys with an `AccessControl` policy.
+    #[fail(display = "access control policy unusable on this device")]
+    AccessControlUnusable,
+
     /// Authentication and/or authorization failed.
     ///
     /// Wrapper for the `errSecAuthFailed` status code. See:
@@ -247,6 +285,14 @@ pub enum ErrorKind {
     #[fail(display = "buffer too small")]
     BufferTooSmall,
 
+    /// Item was modified by someone else since it was read, so an update
+    /// guarded by its modification date was not applied.
+    ///
+    /// There is no corresponding `OSStatus` code: see
+    /// `item::update_if_unchanged`.
+    #[fail(display = "item was modified concurrently")]
+    Conflict,
+
     /// Certificate chain creation attempt failed.
     ///
     /// Wrapper for the `errSecCreateChainFailed` status code. See:
@@ -275,6 +321,13 @@ pub enum ErrorKind {
     #[fail(display = "data not modifiable")]
     DataNotModifiable,
 
+    /// Unable to decode the provided data.
+    ///
+    /// Wrapper for the `errSecDecode` status code. See:
+    /// <https://developer.apple.com/documentation/security/errsecdecode>
+    #[fail(display = "unable to decode data")]
+    Decode,
+
     /// Callback with the same name already exists.
     ///
     /// Wrapper for the `errSecDuplicateCallback` status code. See:
@@ -359,6 +412,16 @@ pub enum ErrorKind {
     #[fail(display = "item not found")]
     ItemNotFound,
 
+    /// Key has been invalidated and can no longer be used, e.g. because it
+    /// was protected by `AccessConstraint::BiometryCurrentSet` and the set of
+    /// enrolled biometrics has since changed, or it was not restored along
+    /// with the rest of the keychain on a new device.
+    ///
+    /// Applications should respond by generating (and re-enrolling) a new key.
+    /// There is no corresponding `OSStatus` code: see `Key::ensure_valid`.
+    #[fail(display = "key has been invalidated")]
+    KeyInvalidated,
+
     /// Invalid key size.
     ///
     /// Wrapper for the `errSecKeySizeNotAllowed` status code. See:
@@ -375,6 +438,17 @@ pub enum ErrorKind {
     #[fail(display = "missing application entitlement (errSecMissingEntitlement)")]
     MissingEntitlement,
 
+    /// Required entitlement for accessing the keychain is missing, and the
+    /// entitlements of the running process identify which one.
+    ///
+    /// Refinement of `MissingEntitlement` made by comparing the request
+    /// against `Entitlements::current()`.
+    #[fail(display = "missing keychain entitlement: {}", requirement)]
+    MissingKeychainEntitlement {
+        /// Access group or data protection requirement which isn't met
+        requirement: EntitlementRequirement,
+    },
+
     /// Certificate module unavailable.
     ///
     /// Wrapper for the `errSecNoCertificateModule` status code. See:
@@ -431,6 +505,19 @@ pub enum ErrorKind {
     #[fail(display = "not available")]
     NotAvailable,
 
+    /// One or more parameters passed to a function were not valid.
+    ///
+    /// Wrapper for the `errSecParam` status code. See:
+    /// <https://developer.apple.com/documentation/security/errsecparam>
+    #[fail(display = "invalid parameter")]
+    Param,
+
+    /// Operation violates the `Policy` installed by the application.
+    ///
+    /// There is no corresponding `OSStatus` code: see `policy::set_policy`.
+    #[fail(display = "policy violation")]
+    PolicyViolation,
+
     /// Can't perform given action on read-only item.
     ///
     /// Wrapper for the `errSecReadOnly` status code. See:
@@ -445,6 +532,22 @@ pub enum ErrorKind {
     #[fail(display = "read-only attr")]
     ReadOnlyAttr,
 
+    /// Secure Enclave is not available on this device (e.g. a Mac without a
+    /// T1/T2 chip or Apple silicon).
+    ///
+    /// There is no corresponding `OSStatus` code: see
+    /// `KeyPair::is_secure_enclave_available`.
+    #[fail(display = "Secure Enclave unavailable")]
+    SecureEnclaveUnavailable,
+
+    /// User canceled the operation, e.g. by dismissing a password, PIN or
+    /// biometric prompt.
+    ///
+    /// Wrapper for the `errSecUserCanceled` status code. See:
+    /// <https://developer.apple.com/documentation/security/errsecusercanceled>
+    #[fail(display = "user canceled the operation")]
+    UserCanceled,
+
     /// Invalid version.
     ///
     /// Wrapper for the `errSecWrongSecVersion` status code. See:
@@ -498,13 +601,23 @@ pub enum ErrorKind {
     },
 }
 
-impl From<CFErrorRef> for ErrorKind {
-    fn from(error_ref: CFErrorRef) -> ErrorKind {
-        ErrorKind::CFError {
-            code: unsafe { CFErrorGetCode(error_ref) } as i64,
-            domain: unsafe { CFString::wrap_under_get_rule(CFErrorGetDomain(error_ref)) }
-                .to_string(),
+impl ErrorKind {
+    /// Decode the kind of error from the code and domain of a `CFError`
+    pub(crate) fn from_cf_error(error: &CFError) -> ErrorKind {
+        let code = error.code() as i64;
+        let domain = error.domain().to_string();
+
+        // Errors in the `NSOSStatusErrorDomain` (e.g. those returned by
+        // `SecKeyCreateRandomKey`) carry an `OSStatus` code, which we decode
+        // if it's one we know about
+        if domain == "NSOSStatusErrorDomain" {
+            match ErrorKind::from(code as OSStatus) {
+                ErrorKind::OSError { .. } => (),
+                kind => return kind,
+            }
         }
+
+        ErrorKind::CFError { code, domain }
     }
 }
 
@@ -517,6 +630,7 @@ impl From<OSStatus> for ErrorKind {
             errSecDataTooLarge => ErrorKind::DataTooLarge,
             errSecDataNotAvailable => ErrorKind::DataNotAvailable,
             errSecDataNotModifiable => ErrorKind::DataNotModifiable,
+            errSecDecode => ErrorKind::Decode,
             errSecDuplicateCallback => ErrorKind::DuplicateCallback,
             errSecDuplicateItem => ErrorKind::DuplicateItem,
             errSecDuplicateKeychain => ErrorKind::DuplicateKeychain,
@@ -539,8 +653,10 @@ impl From<OSStatus> for ErrorKind {
             errSecNoSuchClass => ErrorKind::NoSuchClass,
             errSecNoSuchKeychain => ErrorKind::NoSuchKeychain,
             errSecNotAvailable => ErrorKind::NotAvailable,
+            errSecParam => ErrorKind::Param,
             errSecReadOnly => ErrorKind::ReadOnly,
             errSecReadOnlyAttr => ErrorKind::ReadOnlyAttr,
+            errSecUserCanceled => ErrorKind::UserCanceled,
             errSecWrongSecVersion => ErrorKind::WrongSecVersion,
             errSecErrnoBase..=errSecErrnoLimit => match (status - errSecErrnoBase) as u8 {
                 1 => ErrorKind::Io {
@@ -5,11 +5,13 @@ use core_foundation::{
     error::CFErrorRef,
     string::{CFString, CFStringRef},
 };
+#[cfg(feature = "legacy-keychain")]
+use std::slice;
 use std::{
     borrow::Cow,
     fmt::{self, Debug},
     os::raw::{c_char, c_void},
-    ptr, slice, str,
+    ptr, str,
 };
 
 /// Four character codes used as identifiers. See:
@@ -19,7 +21,7 @@ use std::{
 pub(crate) struct FourCharacterCode(u32);
 
 impl FourCharacterCode {
-    fn as_bytes(&self) -> &[u8; 4] {
+    pub(crate) fn as_bytes(&self) -> &[u8; 4] {
         unsafe { &*(self as *const FourCharacterCode as *const [u8; 4]) }
     }
 
@@ -87,6 +89,18 @@ impl<'a> From<&'a CFString> for FourCharacterCode {
 /// <https://developer.apple.com/documentation/security/secaccesscontrolref>
 pub(crate) type AccessControlRef = CFTypeRef;
 
+/// Reference to a `Certificate`
+///
+/// See `SecCertificateRef` documentation:
+/// <https://developer.apple.com/documentation/security/seccertificateref>
+pub(crate) type CertificateRef = CFTypeRef;
+
+/// Reference to an `Identity`
+///
+/// See `SecIdentityRef` documentation:
+/// <https://developer.apple.com/documentation/security/secidentityref>
+pub(crate) type IdentityRef = CFTypeRef;
+
 /// Reference to a `Key`
 ///
 /// See `SecKeyRef` documentation:
@@ -115,6 +129,7 @@ pub(crate) type SecKeychainAttrType = FourCharacterCode;
 ///
 /// Wrapper for the `SecKeychainAttribute` struct. See:
 /// <https://developer.apple.com/documentation/security/seckeychainattribute>
+#[cfg(feature = "legacy-keychain")]
 #[repr(C)]
 pub(super) struct SecKeychainAttribute {
     tag: SecKeychainAttrType,
@@ -122,7 +137,18 @@ pub(super) struct SecKeychainAttribute {
     data: *mut u8,
 }
 
+#[cfg(feature = "legacy-keychain")]
 impl SecKeychainAttribute {
+    /// Create an attribute with the given tag pointing to the given data,
+    /// which must outlive it
+    pub(crate) fn new(tag: SecKeychainAttrType, data: &[u8]) -> Self {
+        SecKeychainAttribute {
+            tag,
+            length: data.len() as u32,
+            data: data.as_ptr() as *mut u8,
+        }
+    }
+
     /// Get the `FourCharacterCode` tag identifying this attribute's type
     pub(crate) fn tag(&self) -> SecKeychainAttrType {
         self.tag
@@ -145,13 +171,23 @@ impl SecKeychainAttribute {
 ///
 /// Wrapper for the `SecKeychainAttributeList` struct. See:
 /// <https://developer.apple.com/documentation/security/seckeychainattributelist>
+#[cfg(feature = "legacy-keychain")]
 #[repr(C)]
 pub(super) struct SecKeychainAttributeList {
     count: u32,
     attr: *mut SecKeychainAttribute,
 }
 
+#[cfg(feature = "legacy-keychain")]
 impl SecKeychainAttributeList {
+    /// Create a list pointing to the given attributes, which must outlive it
+    pub(crate) fn new(attrs: &mut [SecKeychainAttribute]) -> Self {
+        SecKeychainAttributeList {
+            count: attrs.len() as u32,
+            attr: attrs.as_mut_ptr(),
+        }
+    }
+
     /// Get an iterator over this attribute list.
     pub(crate) fn iter(&self) -> slice::Iter<SecKeychainAttribute> {
         self.as_slice().iter()
@@ -163,9 +199,69 @@ impl SecKeychainAttributeList {
     }
 }
 
+/// Tags and formats of the attributes of a class of items.
+///
+/// NOTE: This type does not implement `Drop`. The caller must free it with
+/// `SecKeychainFreeAttributeInfo`!
+///
+/// Wrapper for the `SecKeychainAttributeInfo` struct. See:
+/// <https://developer.apple.com/documentation/security/seckeychainattributeinfo>
+#[cfg(feature = "legacy-keychain")]
+#[repr(C)]
+pub(super) struct SecKeychainAttributeInfo {
+    count: u32,
+    tag: *mut u32,
+    format: *mut u32,
+}
+
+#[cfg(feature = "legacy-keychain")]
+impl SecKeychainAttributeInfo {
+    /// Get the tag and `CSSM_DB_ATTRIBUTE_FORMAT` of each attribute
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (SecKeychainAttrType, u32)> + '_ {
+        let (tags, formats): (&[u32], &[u32]) = if self.count == 0 {
+            (&[], &[])
+        } else {
+            unsafe {
+                (
+                    slice::from_raw_parts(self.tag, self.count as usize),
+                    slice::from_raw_parts(self.format, self.count as usize),
+                )
+            }
+        };
+
+        tags.iter()
+            .zip(formats)
+            .map(|(&tag, &format)| (FourCharacterCode::from(tag), format))
+    }
+}
+
+#[cfg(feature = "legacy-keychain")]
+pub(crate) type SecKeychainEvent = u32;
+
+#[cfg(feature = "legacy-keychain")]
+pub(crate) type SecKeychainEventMask = u32;
+
+#[cfg(feature = "legacy-keychain")]
+#[repr(C)]
+pub(crate) struct SecKeychainCallbackInfo {
+    pub(crate) version: u32,
+    pub(crate) item: ItemRef,
+    pub(crate) keychain: KeychainRef,
+    pub(crate) pid: i32,
+}
+
+#[cfg(feature = "legacy-keychain")]
+pub(crate) type SecKeychainCallback = extern "C" fn(
+    keychain_event: SecKeychainEvent,
+    info: *mut SecKeychainCallbackInfo,
+    context: *mut c_void,
+) -> OSStatus;
+
 #[link(name = "Security", kind = "framework")]
 extern "C" {
     pub(crate) static kSecAttrAccessControl: CFStringRef;
+    pub(crate) static kSecAttrAccessGroup: CFStringRef;
+    pub(crate) static kSecAttrAccessGroupToken: CFStringRef;
     pub(crate) static kSecAttrAccessible: CFStringRef;
     pub(crate) static kSecAttrAccessibleWhenPasscodeSetThisDeviceOnly: CFStringRef;
     pub(crate) static kSecAttrAccessibleWhenUnlockedThisDeviceOnly: CFStringRef;
@@ -177,6 +273,15 @@ extern "C" {
     pub(crate) static kSecAttrAccount: CFStringRef;
     pub(crate) static kSecAttrApplicationLabel: CFStringRef;
     pub(crate) static kSecAttrApplicationTag: CFStringRef;
+    pub(crate) static kSecAttrAuthenticationType: CFStringRef;
+    pub(crate) static kSecAttrAuthenticationTypeNTLM: CFStringRef;
+    pub(crate) static kSecAttrAuthenticationTypeMSN: CFStringRef;
+    pub(crate) static kSecAttrAuthenticationTypeDPA: CFStringRef;
+    pub(crate) static kSecAttrAuthenticationTypeRPA: CFStringRef;
+    pub(crate) static kSecAttrAuthenticationTypeHTTPBasic: CFStringRef;
+    pub(crate) static kSecAttrAuthenticationTypeHTTPDigest: CFStringRef;
+    pub(crate) static kSecAttrAuthenticationTypeHTMLForm: CFStringRef;
+    pub(crate) static kSecAttrAuthenticationTypeDefault: CFStringRef;
     pub(crate) static kSecAttrCanEncrypt: CFStringRef;
     pub(crate) static kSecAttrCanDecrypt: CFStringRef;
     pub(crate) static kSecAttrCanDerive: CFStringRef;
@@ -184,7 +289,14 @@ extern "C" {
     pub(crate) static kSecAttrCanVerify: CFStringRef;
     pub(crate) static kSecAttrCanWrap: CFStringRef;
     pub(crate) static kSecAttrCanUnwrap: CFStringRef;
+    pub(crate) static kSecAttrComment: CFStringRef;
+    pub(crate) static kSecAttrCreationDate: CFStringRef;
+    pub(crate) static kSecAttrCreator: CFStringRef;
+    pub(crate) static kSecAttrDescription: CFStringRef;
+    pub(crate) static kSecAttrGeneric: CFStringRef;
     pub(crate) static kSecAttrIsExtractable: CFStringRef;
+    pub(crate) static kSecAttrIsInvisible: CFStringRef;
+    pub(crate) static kSecAttrIsNegative: CFStringRef;
     pub(crate) static kSecAttrIsPermanent: CFStringRef;
     pub(crate) static kSecAttrIsSensitive: CFStringRef;
     pub(crate) static kSecAttrKeyClass: CFStringRef;
@@ -198,6 +310,9 @@ extern "C" {
     pub(crate) static kSecAttrKeyTypeECSECPrimeRandom: CFStringRef;
     pub(crate) static kSecAttrKeySizeInBits: CFStringRef;
     pub(crate) static kSecAttrLabel: CFStringRef;
+    pub(crate) static kSecAttrPath: CFStringRef;
+    pub(crate) static kSecAttrPort: CFStringRef;
+    pub(crate) static kSecAttrModificationDate: CFStringRef;
     pub(crate) static kSecAttrProtocol: CFStringRef;
     pub(crate) static kSecAttrProtocolFTP: CFStringRef;
     pub(crate) static kSecAttrProtocolFTPAccount: CFStringRef;
@@ -230,10 +345,13 @@ extern "C" {
     pub(crate) static kSecAttrProtocolIMAPS: CFStringRef;
     pub(crate) static kSecAttrProtocolIRCS: CFStringRef;
     pub(crate) static kSecAttrProtocolPOP3S: CFStringRef;
+    pub(crate) static kSecAttrSecurityDomain: CFStringRef;
     pub(crate) static kSecAttrServer: CFStringRef;
     pub(crate) static kSecAttrService: CFStringRef;
     pub(crate) static kSecAttrSynchronizable: CFStringRef;
+    pub(crate) static kSecAttrSynchronizableAny: CFStringRef;
     pub(crate) static kSecAttrTokenID: CFStringRef;
+    pub(crate) static kSecAttrType: CFStringRef;
     pub(crate) static kSecAttrTokenIDSecureEnclave: CFStringRef;
     pub(crate) static kSecClass: CFStringRef;
     pub(crate) static kSecClassGenericPassword: CFStringRef;
@@ -291,6 +409,8 @@ extern "C" {
     pub(crate) static kSecKeyAlgorithmECDHKeyExchangeStandardX963SHA256: CFStringRef;
     pub(crate) static kSecKeyAlgorithmECDHKeyExchangeStandardX963SHA384: CFStringRef;
     pub(crate) static kSecKeyAlgorithmECDHKeyExchangeStandardX963SHA512: CFStringRef;
+    pub(crate) static kSecKeyKeyExchangeParameterRequestedSize: CFStringRef;
+    pub(crate) static kSecKeyKeyExchangeParameterSharedInfo: CFStringRef;
     pub(crate) static kSecKeyAlgorithmRSAEncryptionRaw: CFStringRef;
     pub(crate) static kSecKeyAlgorithmRSAEncryptionPKCS1: CFStringRef;
     pub(crate) static kSecKeyAlgorithmRSAEncryptionOAEPSHA1: CFStringRef;
@@ -344,14 +464,32 @@ extern "C" {
     pub(crate) static kSecKeyUnwrap: CFStringRef;
     pub(crate) static kSecKeyVerify: CFStringRef;
     pub(crate) static kSecKeyWrap: CFStringRef;
+    pub(crate) static kSecMatchCaseInsensitive: CFStringRef;
+    pub(crate) static kSecMatchDiacriticInsensitive: CFStringRef;
+    pub(crate) static kSecMatchEmailAddressIfPresent: CFStringRef;
+    pub(crate) static kSecMatchIssuers: CFStringRef;
+    pub(crate) static kSecMatchItemList: CFStringRef;
     pub(crate) static kSecMatchLimit: CFStringRef;
     pub(crate) static kSecMatchLimitOne: CFStringRef;
+    pub(crate) static kSecMatchSearchList: CFStringRef;
+    pub(crate) static kSecMatchSubjectContains: CFStringRef;
+    pub(crate) static kSecMatchValidOnDate: CFStringRef;
+    pub(crate) static kSecMatchWidthInsensitive: CFStringRef;
     pub(crate) static kSecMatchLimitAll: CFStringRef;
     pub(crate) static kSecPrivateKeyAttrs: CFStringRef;
+    pub(crate) static kSecReturnAttributes: CFStringRef;
+    pub(crate) static kSecReturnData: CFStringRef;
     pub(crate) static kSecReturnRef: CFStringRef;
+    pub(crate) static kSecReturnPersistentRef: CFStringRef;
+    pub(crate) static kSecUseAuthenticationUI: CFStringRef;
+    pub(crate) static kSecUseAuthenticationUIFail: CFStringRef;
+    pub(crate) static kSecUseDataProtectionKeychain: CFStringRef;
+    pub(crate) static kSecUseItemList: CFStringRef;
     pub(crate) static kSecUseKeychain: CFStringRef;
     pub(crate) static kSecUseOperationPrompt: CFStringRef;
     pub(crate) static kSecValueData: CFStringRef;
+    pub(crate) static kSecValueRef: CFStringRef;
+    pub(crate) static kSecValuePersistentRef: CFStringRef;
 
     pub(crate) fn SecAccessControlCreateWithFlags(
         allocator: CFAllocatorRef,
@@ -360,12 +498,32 @@ extern "C" {
         error: *mut CFErrorRef,
     ) -> CFTypeRef;
     pub(crate) fn SecAccessControlGetTypeID() -> CFTypeID;
+    pub(crate) fn SecCertificateCopyData(certificate: CertificateRef) -> CFDataRef;
+    pub(crate) fn SecCertificateCopyNormalizedIssuerSequence(
+        certificate: CertificateRef,
+    ) -> CFDataRef;
+    pub(crate) fn SecCertificateCopySubjectSummary(certificate: CertificateRef) -> CFStringRef;
+    pub(crate) fn SecCertificateCreateWithData(
+        allocator: CFAllocatorRef,
+        data: CFDataRef,
+    ) -> CertificateRef;
+    pub(crate) fn SecCertificateGetTypeID() -> CFTypeID;
     pub(crate) fn SecCopyErrorMessageString(
         status: OSStatus,
         reserved: *const c_void,
     ) -> CFStringRef;
+    pub(crate) fn SecIdentityCopyCertificate(
+        identity: IdentityRef,
+        certificate: *mut CertificateRef,
+    ) -> OSStatus;
+    pub(crate) fn SecIdentityCopyPrivateKey(
+        identity: IdentityRef,
+        privateKey: *mut KeyRef,
+    ) -> OSStatus;
+    pub(crate) fn SecIdentityGetTypeID() -> CFTypeID;
     pub(crate) fn SecItemAdd(attributes: CFDictionaryRef, result: *mut CFTypeRef) -> OSStatus;
     pub(crate) fn SecItemDelete(attributes: CFDictionaryRef) -> OSStatus;
+    pub(crate) fn SecItemUpdate(query: CFDictionaryRef, attributes: CFDictionaryRef) -> OSStatus;
     pub(crate) fn SecItemCopyMatching(query: CFDictionaryRef, result: *mut CFTypeRef) -> OSStatus;
     pub(crate) fn SecKeyCopyAttributes(key: KeyRef) -> CFDictionaryRef;
     pub(crate) fn SecKeyCreateWithData(
@@ -402,6 +560,13 @@ extern "C" {
         ciphertext: CFDataRef,
         error: *mut CFErrorRef,
     ) -> CFDataRef;
+    pub(crate) fn SecKeyCopyKeyExchangeResult(
+        private_key: KeyRef,
+        algorithm: CFTypeRef,
+        public_key: KeyRef,
+        parameters: CFDictionaryRef,
+        error: *mut CFErrorRef,
+    ) -> CFDataRef;
     pub(crate) fn SecKeyGeneratePair(
         parameters: CFDictionaryRef,
         publicKey: *mut KeyRef,
@@ -411,6 +576,11 @@ extern "C" {
         parameters: CFDictionaryRef,
         error: *mut CFErrorRef,
     ) -> KeyRef;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeyGenerateSymmetric(
+        parameters: CFDictionaryRef,
+        error: *mut CFErrorRef,
+    ) -> KeyRef;
     pub(crate) fn SecKeyIsAlgorithmSupported(
         key: KeyRef,
         operationType: CFIndex,
@@ -419,6 +589,7 @@ extern "C" {
     pub(crate) fn SecKeyCopyPublicKey(privatekey: KeyRef) -> KeyRef;
     pub(crate) fn SecKeyGetTypeID() -> CFTypeID;
     pub(crate) fn SecKeychainCopyDefault(keychain: *mut KeychainRef) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
     pub(crate) fn SecKeychainCreate(
         path_name: *const c_char,
         password_length: u32,
@@ -427,9 +598,39 @@ extern "C" {
         initial_access: CFTypeRef,
         keychain: *mut KeychainRef,
     ) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainAddCallback(
+        callback_function: SecKeychainCallback,
+        event_mask: SecKeychainEventMask,
+        user_context: *mut c_void,
+    ) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
     pub(crate) fn SecKeychainDelete(keychain_or_array: KeychainRef) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainRemoveCallback(callback_function: SecKeychainCallback) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainOpen(path_name: *const c_char, keychain: *mut KeychainRef)
+        -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainGetPath(
+        keychain: KeychainRef,
+        io_path_length: *mut u32,
+        path_name: *mut c_char,
+    ) -> OSStatus;
     pub(crate) fn SecKeychainGetTypeID() -> CFTypeID;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainLock(keychain: KeychainRef) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainUnlock(
+        keychain: KeychainRef,
+        password_length: u32,
+        password: *const c_void,
+        use_password: bool,
+    ) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainGetStatus(keychain: KeychainRef, status: *mut u32) -> OSStatus;
     pub(crate) fn SecKeychainItemGetTypeID() -> CFTypeID;
+    #[cfg(feature = "legacy-keychain")]
     pub(crate) fn SecKeychainItemCopyContent(
         item_ref: ItemRef,
         itemClass: *mut FourCharacterCode,
@@ -437,8 +638,151 @@ extern "C" {
         data_length: *mut u32,
         data_out: *mut *mut c_void,
     ) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainItemFreeAttributesAndData(
+        attr_list: *mut SecKeychainAttributeList,
+        data: *mut c_void,
+    ) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainItemModifyAttributesAndData(
+        item_ref: ItemRef,
+        attr_list: *const SecKeychainAttributeList,
+        length: u32,
+        data: *const c_void,
+    ) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainAttributeInfoForItemID(
+        keychain: KeychainRef,
+        item_id: FourCharacterCode,
+        info: *mut *mut SecKeychainAttributeInfo,
+    ) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainFreeAttributeInfo(info: *mut SecKeychainAttributeInfo) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainFindGenericPassword(
+        keychain_or_array: CFTypeRef,
+        service_name_length: u32,
+        service_name: *const c_char,
+        account_name_length: u32,
+        account_name: *const c_char,
+        password_length: *mut u32,
+        password_data: *mut *mut c_void,
+        item_ref: *mut ItemRef,
+    ) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainFindInternetPassword(
+        keychain_or_array: CFTypeRef,
+        server_name_length: u32,
+        server_name: *const c_char,
+        security_domain_length: u32,
+        security_domain: *const c_char,
+        account_name_length: u32,
+        account_name: *const c_char,
+        path_length: u32,
+        path: *const c_char,
+        port: u16,
+        protocol: FourCharacterCode,
+        authentication_type: FourCharacterCode,
+        password_length: *mut u32,
+        password_data: *mut *mut c_void,
+        item_ref: *mut ItemRef,
+    ) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainItemCreateFromContent(
+        item_class: FourCharacterCode,
+        attr_list: *mut SecKeychainAttributeList,
+        length: u32,
+        data: *const c_void,
+        keychain_ref: KeychainRef,
+        initial_access: CFTypeRef,
+        item_ref: *mut ItemRef,
+    ) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainItemCopyAttributesAndData(
+        item_ref: ItemRef,
+        info: *mut SecKeychainAttributeInfo,
+        item_class: *mut FourCharacterCode,
+        attr_list: *mut *mut SecKeychainAttributeList,
+        length: *mut u32,
+        data: *mut *mut c_void,
+    ) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainItemCopyKeychain(
+        item_ref: ItemRef,
+        keychain_ref: *mut KeychainRef,
+    ) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn SecKeychainItemDelete(item_ref: ItemRef) -> OSStatus;
+    #[cfg(feature = "legacy-keychain")]
     pub(crate) fn SecKeychainItemFreeContent(
         attr_list: *mut SecKeychainAttributeList,
         data: *mut c_void,
     ) -> OSStatus;
+    pub(crate) fn SecRandomCopyBytes(rnd: CFTypeRef, count: usize, bytes: *mut c_void) -> i32;
+    pub(crate) fn SecTaskCreateFromSelf(allocator: CFAllocatorRef) -> CFTypeRef;
+    pub(crate) fn SecTaskCopyValueForEntitlement(
+        task: CFTypeRef,
+        entitlement: CFStringRef,
+        error: *mut CFErrorRef,
+    ) -> CFTypeRef;
+}
+
+/// `CCOperation` for encrypting with `CCCrypt`
+pub(crate) const kCCEncrypt: u32 = 0;
+
+/// `CCOperation` for decrypting with `CCCrypt`
+pub(crate) const kCCDecrypt: u32 = 1;
+
+/// `CCAlgorithm` for AES (with a key size determined by the key length)
+pub(crate) const kCCAlgorithmAES: u32 = 0;
+
+/// `CCOptions` flag for PKCS#7 padding (in CBC mode, the default)
+pub(crate) const kCCOptionPKCS7Padding: u32 = 1;
+
+/// `CCCryptorStatus` of successful operations
+pub(crate) const kCCSuccess: i32 = 0;
+
+/// `CCHmacAlgorithm` for HMAC-SHA-256
+pub(crate) const kCCHmacAlgSHA256: u32 = 2;
+
+/// `CCPBKDFAlgorithm` for PBKDF2
+pub(crate) const kCCPBKDF2: u32 = 2;
+
+/// `CCPseudoRandomAlgorithm` for PBKDF2 with HMAC-SHA-256
+pub(crate) const kCCPRFHmacAlgSHA256: u32 = 3;
+
+// CommonCrypto, which is part of libSystem
+extern "C" {
+    pub(crate) fn CCCrypt(
+        op: u32,
+        alg: u32,
+        options: u32,
+        key: *const c_void,
+        key_length: usize,
+        iv: *const c_void,
+        data_in: *const c_void,
+        data_in_length: usize,
+        data_out: *mut c_void,
+        data_out_available: usize,
+        data_out_moved: *mut usize,
+    ) -> i32;
+    pub(crate) fn CCHmac(
+        algorithm: u32,
+        key: *const c_void,
+        key_length: usize,
+        data: *const c_void,
+        data_length: usize,
+        mac_out: *mut c_void,
+    );
+    pub(crate) fn CCKeyDerivationPBKDF(
+        algorithm: u32,
+        password: *const c_char,
+        password_len: usize,
+        salt: *const u8,
+        salt_len: usize,
+        prf: u32,
+        rounds: u32,
+        derived_key: *mut u8,
+        derived_key_len: usize,
+    ) -> i32;
 }
@@ -0,0 +1,227 @@
+//! Compliance audit reports of the protection levels of the keys and other
+//! items visible to this application.
+//!
+//! Reports include each item's identifying metadata (as in an `Inventory`)
+//! along with how it's protected, and never include any secret data. They
+//! can be exported as JSON, with each entry having the inventory fields plus
+//! the following (all optional except `synchronizable`):
+//!
+//! - `accessible`: accessibility, e.g. `when_unlocked_this_device_only`
+//! - `access_control`: description of the access control policy
+//! - `access_constraints`: constraints of the access control policy, e.g.
+//!   `biometry_current_set` or `private_key_usage`
+//! - `token_id`: `secure_enclave` or the ID of the token the item is stored in
+//! - `extractable`: whether the item (i.e. key) can be exported
+//! - `synchronizable`: whether the item is synchronized via iCloud Keychain
+//!
+//! The schema is versioned by `AUDIT_SCHEMA_VERSION`.
+
+use super::item::{unix_seconds, write_json_string, Class, InventoryEntry, ItemAttributes, Query};
+use crate::{
+    access::{AccessConstraint, AccessControl, AccessControlInfo, AccessOption},
+    attr::{AttrAccessible, AttrTokenId},
+    error::Error,
+};
+use std::{fmt::Write, time::SystemTime};
+
+/// Version of the audit report schema, incremented on incompatible changes.
+pub const AUDIT_SCHEMA_VERSION: u32 = 1;
+
+/// Classes of items included in audit reports
+const AUDITED_CLASSES: &[Class] = &[
+    Class::Key,
+    Class::Identity,
+    Class::Certificate,
+    Class::GenericPassword,
+    Class::InternetPassword,
+];
+
+/// Audit report of the protection levels of keychain items.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuditReport {
+    /// Version of the schema this report conforms to
+    pub schema_version: u32,
+
+    /// When this report was generated, in seconds since the Unix epoch
+    pub generated_at: i64,
+
+    /// Entries for the items in this report
+    pub items: Vec<AuditEntry>,
+}
+
+/// Protection level of an individual keychain item in an `AuditReport`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AuditEntry {
+    /// Identifying metadata of the item
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub item: InventoryEntry,
+
+    /// Accessibility of the item, e.g. `when_unlocked_this_device_only`
+    /// (for items protected by an access control policy, its protection
+    /// class as reported by `AccessControl::info`)
+    pub accessible: Option<String>,
+
+    /// Description of the access control policy protecting the item (see
+    /// `AccessControl::description`)
+    pub access_control: Option<String>,
+
+    /// Constraints and options of the access control policy protecting the
+    /// item, e.g. `biometry_current_set` (see `AccessControl::info`)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub access_constraints: Vec<String>,
+
+    /// Token the item is stored in, i.e. `secure_enclave` or a token ID
+    pub token_id: Option<String>,
+
+    /// Can the item (i.e. key) be exported from the keychain?
+    pub extractable: Option<bool>,
+
+    /// Is the item synchronized via iCloud Keychain?
+    pub synchronizable: bool,
+}
+
+/// Generate an `AuditReport` of all keys, identities, certificates, and
+/// passwords visible to this application (including synchronizable ones).
+pub fn report() -> Result<AuditReport, Error> {
+    let mut items = vec![];
+
+    for class in AUDITED_CLASSES {
+        for item in ItemAttributes::find_all(*class, Query::new().synchronizable_any())? {
+            items.push(AuditEntry::from(&item));
+        }
+    }
+
+    Ok(AuditReport {
+        schema_version: AUDIT_SCHEMA_VERSION,
+        generated_at: unix_seconds(SystemTime::now()),
+        items,
+    })
+}
+
+impl AuditReport {
+    /// Serialize this report as JSON
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        write!(
+            json,
+            "{{\"schema_version\":{},\"generated_at\":{},\"items\":[",
+            self.schema_version, self.generated_at
+        )
+        .unwrap();
+
+        for (i, entry) in self.items.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+
+            write_entry(&mut json, entry);
+        }
+
+        json.push_str("]}");
+        json
+    }
+}
+
+impl<'a> From<&'a ItemAttributes> for AuditEntry {
+    fn from(item: &'a ItemAttributes) -> AuditEntry {
+        let access_control = item.access_control();
+        let info = access_control.as_ref().map(AccessControl::info);
+
+        let protection = item
+            .accessible()
+            .or_else(|| info.as_ref().and_then(|info| info.protection));
+
+        let accessible = protection.map(|accessible| {
+            match accessible {
+                AttrAccessible::WhenPasscodeSetThisDeviceOnly => {
+                    "when_passcode_set_this_device_only"
+                }
+                AttrAccessible::WhenUnlockedThisDeviceOnly => "when_unlocked_this_device_only",
+                AttrAccessible::WhenUnlocked => "when_unlocked",
+                AttrAccessible::AfterFirstUnlockThisDeviceOnly => {
+                    "after_first_unlock_this_device_only"
+                }
+                AttrAccessible::AfterFirstUnlock => "after_first_unlock",
+                AttrAccessible::AlwaysThisDeviceOnly => "always_this_device_only",
+                AttrAccessible::Always => "always",
+            }
+            .to_owned()
+        });
+
+        let token_id = item.token_id().map(|token_id| match token_id {
+            AttrTokenId::SecureEnclave => "secure_enclave".to_owned(),
+            AttrTokenId::Token(id) => id,
+        });
+
+        AuditEntry {
+            item: InventoryEntry::from(item),
+            accessible,
+            access_control: access_control.map(|access_control| access_control.description()),
+            access_constraints: info.map(constraint_names).unwrap_or_default(),
+            token_id,
+            extractable: item.is_extractable(),
+            synchronizable: item.is_synchronizable(),
+        }
+    }
+}
+
+/// Write an entry to the given JSON output as an object
+fn write_entry(json: &mut String, entry: &AuditEntry) {
+    json.push('{');
+    entry.item.write_json_fields(json);
+
+    let strings = [
+        ("accessible", &entry.accessible),
+        ("access_control", &entry.access_control),
+        ("token_id", &entry.token_id),
+    ];
+
+    for (name, value) in strings.iter() {
+        if let Some(value) = value {
+            write!(json, ",\"{}\":", name).unwrap();
+            write_json_string(json, value);
+        }
+    }
+
+    if !entry.access_constraints.is_empty() {
+        json.push_str(",\"access_constraints\":[");
+
+        for (i, constraint) in entry.access_constraints.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+
+            write_json_string(json, constraint);
+        }
+
+        json.push(']');
+    }
+
+    if let Some(extractable) = entry.extractable {
+        write!(json, ",\"extractable\":{}", extractable).unwrap();
+    }
+
+    write!(json, ",\"synchronizable\":{}}}", entry.synchronizable).unwrap();
+}
+
+/// Get the names of the constraints and options of an access control policy
+fn constraint_names(info: AccessControlInfo) -> Vec<String> {
+    let constraints = info
+        .constraints
+        .into_iter()
+        .map(|constraint| match constraint {
+            AccessConstraint::UserPresence => "user_presence",
+            AccessConstraint::BiometryAny => "biometry_any",
+            AccessConstraint::BiometryCurrentSet => "biometry_current_set",
+            AccessConstraint::DevicePasscode => "device_passcode",
+        });
+
+    let options = info.options.into_iter().map(|option| match option {
+        AccessOption::PrivateKeyUsage => "private_key_usage",
+        AccessOption::ApplicationPassword => "application_password",
+    });
+
+    constraints.chain(options).map(str::to_owned).collect()
+}
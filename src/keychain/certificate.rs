@@ -0,0 +1,97 @@
+//! X.509 certificates stored in the keychain (or on a token)
+
+use crate::{
+    error::{Error, ErrorKind},
+    ffi::*,
+};
+use core_foundation::{base::TCFType, data::CFData, string::CFString};
+use std::fmt::{self, Debug};
+use std::ptr;
+
+declare_TCFType! {
+    /// X.509 certificates.
+    ///
+    /// Wrapper for the `SecCertificate`/`SecCertificateRef` types:
+    /// <https://developer.apple.com/documentation/security/seccertificateref>
+    Certificate, CertificateRef
+}
+
+impl_TCFType!(Certificate, CertificateRef, SecCertificateGetTypeID);
+
+impl Certificate {
+    /// Parse a DER-encoded X.509 certificate.
+    ///
+    /// Returns an error with a kind of `ErrorKind::Decode` if the data isn't
+    /// a valid certificate.
+    ///
+    /// Wrapper for the `SecCertificateCreateWithData` function. See:
+    /// <https://developer.apple.com/documentation/security/seccertificatecreatewithdata(_:_:)>
+    pub fn from_der(der: &[u8]) -> Result<Self, Error> {
+        let data = CFData::from_buffer(der);
+        let cert_ref =
+            unsafe { SecCertificateCreateWithData(ptr::null(), data.as_concrete_TypeRef()) };
+
+        if cert_ref.is_null() {
+            Err(Error::new(
+                ErrorKind::Decode,
+                "data is not a DER-encoded X.509 certificate",
+            ))
+        } else {
+            Ok(unsafe { Certificate::wrap_under_create_rule(cert_ref) })
+        }
+    }
+
+    /// Get a human-readable summary of this certificate's subject, e.g.
+    /// its common name.
+    ///
+    /// Wrapper for the `SecCertificateCopySubjectSummary` function. See:
+    /// <https://developer.apple.com/documentation/security/1394814-seccertificatecopysubjectsummary>
+    pub fn subject_summary(&self) -> Option<String> {
+        let summary = unsafe { SecCertificateCopySubjectSummary(self.as_concrete_TypeRef()) };
+
+        if summary.is_null() {
+            None
+        } else {
+            Some(unsafe { CFString::wrap_under_create_rule(summary) }.to_string())
+        }
+    }
+
+    /// Get the DER encoding of this certificate's issuer's distinguished
+    /// name in normalized form, e.g. for matching certificates issued by
+    /// the same authority with `Query::issuers`.
+    ///
+    /// Wrapper for the `SecCertificateCopyNormalizedIssuerSequence`
+    /// function. See:
+    /// <https://developer.apple.com/documentation/security/1396032-seccertificatecopynormalizedissu>
+    pub fn normalized_issuer(&self) -> Option<Vec<u8>> {
+        let issuer =
+            unsafe { SecCertificateCopyNormalizedIssuerSequence(self.as_concrete_TypeRef()) };
+
+        if issuer.is_null() {
+            None
+        } else {
+            Some(unsafe { CFData::wrap_under_create_rule(issuer) }.to_vec())
+        }
+    }
+
+    /// Get the DER encoding of this certificate.
+    ///
+    /// Wrapper for the `SecCertificateCopyData` function. See:
+    /// <https://developer.apple.com/documentation/security/1396080-seccertificatecopydata>
+    pub fn to_der(&self) -> Vec<u8> {
+        unsafe {
+            CFData::wrap_under_create_rule(SecCertificateCopyData(self.as_concrete_TypeRef()))
+        }
+        .to_vec()
+    }
+}
+
+impl Debug for Certificate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SecCertificate {{ subject_summary: {:?} }}",
+            self.subject_summary()
+        )
+    }
+}
@@ -0,0 +1,109 @@
+// C ABI shim exposing the parts of CryptoKit used by `keychain::cryptokit`.
+//
+// Compiled into a static library by `build.rs` when the `cryptokit` cargo
+// feature is enabled. All keys are passed as raw 32-byte representations and
+// all functions return 0 on success or -1 if CryptoKit rejected the input.
+
+import CryptoKit
+import Foundation
+
+@_cdecl("kcs_ed25519_generate")
+public func kcs_ed25519_generate(_ privateKey: UnsafeMutablePointer<UInt8>) {
+    Curve25519.Signing.PrivateKey().rawRepresentation.copyBytes(to: privateKey, count: 32)
+}
+
+@_cdecl("kcs_ed25519_public_key")
+public func kcs_ed25519_public_key(
+    _ privateKey: UnsafePointer<UInt8>,
+    _ publicKey: UnsafeMutablePointer<UInt8>
+) -> Int32 {
+    guard let key = try? Curve25519.Signing.PrivateKey(
+        rawRepresentation: Data(bytes: privateKey, count: 32)
+    ) else {
+        return -1
+    }
+
+    key.publicKey.rawRepresentation.copyBytes(to: publicKey, count: 32)
+    return 0
+}
+
+@_cdecl("kcs_ed25519_sign")
+public func kcs_ed25519_sign(
+    _ privateKey: UnsafePointer<UInt8>,
+    _ message: UnsafePointer<UInt8>,
+    _ messageLength: Int,
+    _ signature: UnsafeMutablePointer<UInt8>
+) -> Int32 {
+    guard let key = try? Curve25519.Signing.PrivateKey(
+        rawRepresentation: Data(bytes: privateKey, count: 32)
+    ), let result = try? key.signature(for: Data(bytes: message, count: messageLength)) else {
+        return -1
+    }
+
+    result.copyBytes(to: signature, count: 64)
+    return 0
+}
+
+/// Returns 1 if the signature is valid, 0 if it is not, and -1 if the
+/// public key is malformed.
+@_cdecl("kcs_ed25519_verify")
+public func kcs_ed25519_verify(
+    _ publicKey: UnsafePointer<UInt8>,
+    _ message: UnsafePointer<UInt8>,
+    _ messageLength: Int,
+    _ signature: UnsafePointer<UInt8>
+) -> Int32 {
+    guard let key = try? Curve25519.Signing.PublicKey(
+        rawRepresentation: Data(bytes: publicKey, count: 32)
+    ) else {
+        return -1
+    }
+
+    let valid = key.isValidSignature(
+        Data(bytes: signature, count: 64),
+        for: Data(bytes: message, count: messageLength)
+    )
+
+    return valid ? 1 : 0
+}
+
+@_cdecl("kcs_x25519_generate")
+public func kcs_x25519_generate(_ privateKey: UnsafeMutablePointer<UInt8>) {
+    Curve25519.KeyAgreement.PrivateKey().rawRepresentation.copyBytes(to: privateKey, count: 32)
+}
+
+@_cdecl("kcs_x25519_public_key")
+public func kcs_x25519_public_key(
+    _ privateKey: UnsafePointer<UInt8>,
+    _ publicKey: UnsafeMutablePointer<UInt8>
+) -> Int32 {
+    guard let key = try? Curve25519.KeyAgreement.PrivateKey(
+        rawRepresentation: Data(bytes: privateKey, count: 32)
+    ) else {
+        return -1
+    }
+
+    key.publicKey.rawRepresentation.copyBytes(to: publicKey, count: 32)
+    return 0
+}
+
+@_cdecl("kcs_x25519_shared_secret")
+public func kcs_x25519_shared_secret(
+    _ privateKey: UnsafePointer<UInt8>,
+    _ peerPublicKey: UnsafePointer<UInt8>,
+    _ sharedSecret: UnsafeMutablePointer<UInt8>
+) -> Int32 {
+    guard let key = try? Curve25519.KeyAgreement.PrivateKey(
+        rawRepresentation: Data(bytes: privateKey, count: 32)
+    ), let peerKey = try? Curve25519.KeyAgreement.PublicKey(
+        rawRepresentation: Data(bytes: peerPublicKey, count: 32)
+    ), let result = try? key.sharedSecretFromKeyAgreement(with: peerKey) else {
+        return -1
+    }
+
+    result.withUnsafeBytes { bytes in
+        sharedSecret.initialize(from: bytes.bindMemory(to: UInt8.self).baseAddress!, count: 32)
+    }
+
+    return 0
+}
@@ -0,0 +1,177 @@
+//! Ed25519 digital signatures (CryptoKit's `Curve25519.Signing`)
+
+use super::*;
+use std::fmt::{self, Debug};
+use zeroize::Zeroize;
+
+/// Size of an Ed25519 signature
+const SIGNATURE_SIZE: usize = 64;
+
+/// Ed25519 private (signing) key.
+///
+/// Wrapper for CryptoKit's `Curve25519.Signing.PrivateKey`. See:
+/// <https://developer.apple.com/documentation/cryptokit/curve25519/signing/privatekey>
+pub struct Ed25519PrivateKey([u8; KEY_SIZE]);
+
+impl Ed25519PrivateKey {
+    /// Generate a new random Ed25519 private key.
+    pub fn generate() -> Self {
+        let mut secret = [0u8; KEY_SIZE];
+        unsafe { kcs_ed25519_generate(secret.as_mut_ptr()) };
+        Ed25519PrivateKey(secret)
+    }
+
+    /// Find an Ed25519 private key previously stored in the given keychain
+    /// with `Ed25519PrivateKey::store`, returning `Ok(None)` if no such key
+    /// exists.
+    pub fn find(keychain: &Keychain, service: &str, account: &str) -> Result<Option<Self>, Error> {
+        Ok(load_secret(keychain, service, account)?.map(|secret| {
+            let mut bytes = [0u8; KEY_SIZE];
+            bytes.copy_from_slice(secret.as_bytes());
+            Ed25519PrivateKey(bytes)
+        }))
+    }
+
+    /// Store this private key in the given keychain as a generic password
+    /// item with the given service and account.
+    pub fn store(&self, keychain: &Keychain, service: &str, account: &str) -> Result<(), Error> {
+        store_secret(keychain, service, account, &self.0)
+    }
+
+    /// Get the public key which corresponds to this private key.
+    pub fn public_key(&self) -> Ed25519PublicKey {
+        let mut public_key = [0u8; KEY_SIZE];
+        let status = unsafe { kcs_ed25519_public_key(self.0.as_ptr(), public_key.as_mut_ptr()) };
+
+        // CryptoKit accepts any 32-byte seed as an Ed25519 private key
+        assert_eq!(status, 0, "CryptoKit rejected Ed25519 private key");
+        Ed25519PublicKey(public_key)
+    }
+
+    /// Create an Ed25519 signature of the given data using this key.
+    pub fn sign(&self, data: &[u8]) -> Result<Ed25519Signature, Error> {
+        let mut signature = [0u8; SIGNATURE_SIZE];
+        let status = unsafe {
+            kcs_ed25519_sign(
+                self.0.as_ptr(),
+                data.as_ptr(),
+                data.len(),
+                signature.as_mut_ptr(),
+            )
+        };
+
+        if status == 0 {
+            Ok(Ed25519Signature(signature))
+        } else {
+            Err(Error::new(
+                ErrorKind::Param,
+                "CryptoKit failed to create Ed25519 signature",
+            ))
+        }
+    }
+}
+
+impl Debug for Ed25519PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Ed25519PrivateKey {{ public_key: {:?} }}",
+            self.public_key()
+        )
+    }
+}
+
+impl Drop for Ed25519PrivateKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Ed25519 public (verification) key.
+///
+/// Wrapper for CryptoKit's `Curve25519.Signing.PublicKey`. See:
+/// <https://developer.apple.com/documentation/cryptokit/curve25519/signing/publickey>
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Ed25519PublicKey([u8; KEY_SIZE]);
+
+impl Ed25519PublicKey {
+    /// Parse an Ed25519 public key from its 32-byte encoding.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != KEY_SIZE {
+            return Err(Error::new(
+                ErrorKind::Param,
+                &format!("expected {}-byte Ed25519 public key", KEY_SIZE),
+            ));
+        }
+
+        let mut public_key = [0u8; KEY_SIZE];
+        public_key.copy_from_slice(bytes);
+        Ok(Ed25519PublicKey(public_key))
+    }
+
+    /// Borrow this public key as bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Verify an Ed25519 signature of the given data using this key.
+    pub fn verify(&self, data: &[u8], signature: &Ed25519Signature) -> Result<bool, Error> {
+        let result = unsafe {
+            kcs_ed25519_verify(
+                self.0.as_ptr(),
+                data.as_ptr(),
+                data.len(),
+                signature.0.as_ptr(),
+            )
+        };
+
+        match result {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::new(ErrorKind::Param, "malformed Ed25519 public key")),
+        }
+    }
+}
+
+impl AsRef<[u8]> for Ed25519PublicKey {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+/// Ed25519 signatures
+#[derive(Copy, Clone)]
+pub struct Ed25519Signature([u8; SIGNATURE_SIZE]);
+
+impl Ed25519Signature {
+    /// Parse an Ed25519 signature from its 64-byte encoding.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != SIGNATURE_SIZE {
+            return Err(Error::new(
+                ErrorKind::Param,
+                &format!("expected {}-byte Ed25519 signature", SIGNATURE_SIZE),
+            ));
+        }
+
+        let mut signature = [0u8; SIGNATURE_SIZE];
+        signature.copy_from_slice(bytes);
+        Ok(Ed25519Signature(signature))
+    }
+
+    /// Borrow the signature data as bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for Ed25519Signature {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Debug for Ed25519Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Ed25519Signature({:?})", &self.0[..])
+    }
+}
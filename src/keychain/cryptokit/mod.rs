@@ -0,0 +1,99 @@
+//! Curve25519 keys implemented using Apple's CryptoKit framework, which
+//! (unlike `SecKey`) supports Ed25519 signatures and X25519 key agreement.
+//!
+//! CryptoKit keys are not `SecKey`s, so they can't be stored as keychain
+//! keys. Instead, following Apple's guidance, their raw representation is
+//! stored as a generic password item. See:
+//! <https://developer.apple.com/documentation/cryptokit/storing_cryptokit_keys_in_the_keychain>
+//!
+//! Requires the `cryptokit` cargo feature and macOS 10.15+.
+
+mod ed25519;
+mod x25519;
+
+pub use self::{ed25519::*, x25519::*};
+use crate::{
+    attr::AttrKind,
+    dictionary::DictionaryBuilder,
+    error::{Error, ErrorKind},
+    ffi::kSecValueData,
+    keychain::{
+        item::{self, GenericPassword, PasswordData},
+        Keychain,
+    },
+};
+use core_foundation::data::CFData;
+
+/// Size of a raw Curve25519 key (public or private)
+pub(crate) const KEY_SIZE: usize = 32;
+
+extern "C" {
+    fn kcs_ed25519_generate(private_key: *mut u8);
+    fn kcs_ed25519_public_key(private_key: *const u8, public_key: *mut u8) -> i32;
+    fn kcs_ed25519_sign(
+        private_key: *const u8,
+        message: *const u8,
+        message_len: usize,
+        signature: *mut u8,
+    ) -> i32;
+    fn kcs_ed25519_verify(
+        public_key: *const u8,
+        message: *const u8,
+        message_len: usize,
+        signature: *const u8,
+    ) -> i32;
+    fn kcs_x25519_generate(private_key: *mut u8);
+    fn kcs_x25519_public_key(private_key: *const u8, public_key: *mut u8) -> i32;
+    fn kcs_x25519_shared_secret(
+        private_key: *const u8,
+        peer_public_key: *const u8,
+        shared_secret: *mut u8,
+    ) -> i32;
+}
+
+/// Store the raw representation of a CryptoKit private key as a generic
+/// password item.
+fn store_secret(
+    keychain: &Keychain,
+    service: &str,
+    account: &str,
+    secret: &[u8],
+) -> Result<(), Error> {
+    let mut attrs = DictionaryBuilder::new();
+    attrs.add_class(item::Class::GenericPassword);
+    attrs.add_string(AttrKind::Service, service);
+    attrs.add_string(AttrKind::Account, account);
+    attrs.add(unsafe { kSecValueData }, &CFData::from_buffer(secret));
+
+    keychain.add_item(attrs).map(|_| ())
+}
+
+/// Load the raw representation of a CryptoKit private key stored with
+/// `store_secret`, checking it's the expected size.
+fn load_secret(
+    keychain: &Keychain,
+    service: &str,
+    account: &str,
+) -> Result<Option<PasswordData>, Error> {
+    let item = match GenericPassword::find(keychain, service, account)? {
+        Some(item) => item,
+        None => return Ok(None),
+    };
+
+    let secret = item.password()?;
+
+    if secret.as_bytes().len() != KEY_SIZE {
+        return Err(Error::new(
+            ErrorKind::Decode,
+            &format!(
+                "expected {}-byte key in {}/{}, found {} bytes",
+                KEY_SIZE,
+                service,
+                account,
+                secret.as_bytes().len()
+            ),
+        ));
+    }
+
+    Ok(Some(secret))
+}
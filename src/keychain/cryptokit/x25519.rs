@@ -0,0 +1,165 @@
+//! X25519 Diffie-Hellman key agreement (CryptoKit's `Curve25519.KeyAgreement`)
+
+use super::*;
+use std::fmt::{self, Debug};
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// X25519 private key.
+///
+/// Wrapper for CryptoKit's `Curve25519.KeyAgreement.PrivateKey`. See:
+/// <https://developer.apple.com/documentation/cryptokit/curve25519/keyagreement/privatekey>
+pub struct X25519PrivateKey([u8; KEY_SIZE]);
+
+impl X25519PrivateKey {
+    /// Generate a new random X25519 private key.
+    pub fn generate() -> Self {
+        let mut secret = [0u8; KEY_SIZE];
+        unsafe { kcs_x25519_generate(secret.as_mut_ptr()) };
+        X25519PrivateKey(secret)
+    }
+
+    /// Find an X25519 private key previously stored in the given keychain
+    /// with `X25519PrivateKey::store`, returning `Ok(None)` if no such key
+    /// exists.
+    pub fn find(keychain: &Keychain, service: &str, account: &str) -> Result<Option<Self>, Error> {
+        Ok(load_secret(keychain, service, account)?.map(|secret| {
+            let mut bytes = [0u8; KEY_SIZE];
+            bytes.copy_from_slice(secret.as_bytes());
+            X25519PrivateKey(bytes)
+        }))
+    }
+
+    /// Store this private key in the given keychain as a generic password
+    /// item with the given service and account.
+    pub fn store(&self, keychain: &Keychain, service: &str, account: &str) -> Result<(), Error> {
+        store_secret(keychain, service, account, &self.0)
+    }
+
+    /// Get the public key which corresponds to this private key.
+    pub fn public_key(&self) -> X25519PublicKey {
+        let mut public_key = [0u8; KEY_SIZE];
+        let status = unsafe { kcs_x25519_public_key(self.0.as_ptr(), public_key.as_mut_ptr()) };
+
+        // CryptoKit accepts any 32-byte string as an X25519 private key
+        assert_eq!(status, 0, "CryptoKit rejected X25519 private key");
+        X25519PublicKey(public_key)
+    }
+
+    /// Compute the shared secret between this private key and a peer's
+    /// public key.
+    ///
+    /// The result is the raw X25519 output, which should be passed through
+    /// a key derivation function (e.g. HKDF) before being used as a key.
+    pub fn diffie_hellman(
+        &self,
+        peer_public_key: &X25519PublicKey,
+    ) -> Result<X25519SharedSecret, Error> {
+        let mut shared_secret = [0u8; KEY_SIZE];
+        let status = unsafe {
+            kcs_x25519_shared_secret(
+                self.0.as_ptr(),
+                peer_public_key.0.as_ptr(),
+                shared_secret.as_mut_ptr(),
+            )
+        };
+
+        if status == 0 {
+            Ok(X25519SharedSecret(shared_secret))
+        } else {
+            Err(Error::new(
+                ErrorKind::Param,
+                "CryptoKit rejected X25519 key agreement (e.g. low order public key)",
+            ))
+        }
+    }
+}
+
+impl Debug for X25519PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "X25519PrivateKey {{ public_key: {:?} }}",
+            self.public_key()
+        )
+    }
+}
+
+impl Drop for X25519PrivateKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// X25519 public key.
+///
+/// Wrapper for CryptoKit's `Curve25519.KeyAgreement.PublicKey`. See:
+/// <https://developer.apple.com/documentation/cryptokit/curve25519/keyagreement/publickey>
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct X25519PublicKey([u8; KEY_SIZE]);
+
+impl X25519PublicKey {
+    /// Parse an X25519 public key from its 32-byte encoding.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != KEY_SIZE {
+            return Err(Error::new(
+                ErrorKind::Param,
+                &format!("expected {}-byte X25519 public key", KEY_SIZE),
+            ));
+        }
+
+        let mut public_key = [0u8; KEY_SIZE];
+        public_key.copy_from_slice(bytes);
+        Ok(X25519PublicKey(public_key))
+    }
+
+    /// Borrow this public key as bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for X25519PublicKey {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+/// Shared secret resulting from X25519 key agreement, which is cleared from
+/// memory when dropped.
+///
+/// Wrapper for CryptoKit's `SharedSecret`. See:
+/// <https://developer.apple.com/documentation/cryptokit/sharedsecret>
+pub struct X25519SharedSecret([u8; KEY_SIZE]);
+
+impl X25519SharedSecret {
+    /// Borrow the shared secret as bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for X25519SharedSecret {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Debug for X25519SharedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "X25519SharedSecret([REDACTED])")
+    }
+}
+
+impl Zeroize for X25519SharedSecret {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for X25519SharedSecret {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for X25519SharedSecret {}
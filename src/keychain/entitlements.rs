@@ -0,0 +1,247 @@
+//! Code signing entitlements of the running process.
+//!
+//! Most Keychain Services functionality beyond the legacy file-based
+//! keychains (the data protection keychain, access groups, the Secure
+//! Enclave) is gated on entitlements, and requests the process isn't entitled
+//! to make fail with `errSecMissingEntitlement`. The entitlements read here
+//! are used to work out which one is missing. See:
+//! <https://developer.apple.com/documentation/security/keychain_services/keychain_items/sharing_access_to_keychain_items_among_a_collection_of_apps>
+
+use crate::{
+    dictionary::DictionaryBuilder,
+    error::{Error, ErrorKind},
+    ffi::*,
+};
+use core_foundation::{
+    array::CFArray,
+    base::{kCFAllocatorDefault, CFType, CFTypeRef, TCFType},
+    boolean::CFBoolean,
+    string::CFString,
+};
+use std::{
+    fmt::{self, Display},
+    os::raw::c_void,
+    ptr,
+};
+
+/// `application-identifier` entitlement (macOS)
+const APPLICATION_IDENTIFIER: &str = "com.apple.application-identifier";
+
+/// `application-identifier` entitlement (iOS and older macOS releases)
+const APPLICATION_IDENTIFIER_LEGACY: &str = "application-identifier";
+
+/// `keychain-access-groups` entitlement
+const KEYCHAIN_ACCESS_GROUPS: &str = "keychain-access-groups";
+
+/// App Groups entitlement, whose groups are also usable as access groups
+const APPLICATION_GROUPS: &str = "com.apple.security.application-groups";
+
+/// Keychain-related entitlements the running process is signed with.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entitlements {
+    /// Application identifier, i.e. `<team ID>.<bundle ID>`, which is also
+    /// the default access group of the process.
+    pub application_identifier: Option<String>,
+
+    /// Groups listed in the `keychain-access-groups` entitlement.
+    pub keychain_access_groups: Vec<String>,
+
+    /// Groups listed in the `com.apple.security.application-groups`
+    /// entitlement.
+    pub application_groups: Vec<String>,
+}
+
+impl Entitlements {
+    /// Read the entitlements of the running process from its code signature.
+    /// An unsigned process has no entitlements.
+    ///
+    /// Wrapper for the `SecTaskCopyValueForEntitlement` function. See:
+    /// <https://developer.apple.com/documentation/security/1393461-sectaskcopyvalueforentitlement>
+    pub fn current() -> Result<Self, Error> {
+        let task_ref = unsafe { SecTaskCreateFromSelf(kCFAllocatorDefault) };
+
+        if task_ref.is_null() {
+            return Err(Error::new(
+                ErrorKind::NotAvailable,
+                "couldn't create a SecTask for the current process",
+            ));
+        }
+
+        let task = unsafe { CFType::wrap_under_create_rule(task_ref) };
+
+        let application_identifier = copy_entitlement(&task, APPLICATION_IDENTIFIER)
+            .or_else(|| copy_entitlement(&task, APPLICATION_IDENTIFIER_LEGACY))
+            .and_then(|value| value.downcast::<CFString>())
+            .map(|id| id.to_string());
+
+        Ok(Self {
+            application_identifier,
+            keychain_access_groups: string_array(copy_entitlement(&task, KEYCHAIN_ACCESS_GROUPS)),
+            application_groups: string_array(copy_entitlement(&task, APPLICATION_GROUPS)),
+        })
+    }
+
+    /// Does the process have no keychain-related entitlements at all?
+    pub fn is_empty(&self) -> bool {
+        self.application_identifier.is_none()
+            && self.keychain_access_groups.is_empty()
+            && self.application_groups.is_empty()
+    }
+
+    /// Is the process entitled to store items in the given access group?
+    ///
+    /// Entries of `keychain-access-groups` ending in `*` match any group
+    /// with the preceding prefix.
+    pub fn allows_access_group(&self, access_group: &str) -> bool {
+        let matches = |pattern: &String| {
+            if pattern.ends_with('*') {
+                access_group.starts_with(&pattern[..pattern.len() - 1])
+            } else {
+                pattern == access_group
+            }
+        };
+
+        self.application_identifier.iter().any(matches)
+            || self.keychain_access_groups.iter().any(matches)
+            || self.application_groups.iter().any(matches)
+    }
+
+    /// Is the process entitled to use the data protection keychain, i.e. does
+    /// it have an access group to put its items in?
+    pub fn allows_data_protection_keychain(&self) -> bool {
+        self.application_identifier.is_some() || !self.keychain_access_groups.is_empty()
+    }
+
+    /// Determine which entitlement appears to be missing for a request which
+    /// targets the given access group (if any) and/or the data protection
+    /// keychain, or `None` if these entitlements allow the request.
+    pub fn missing_requirement(
+        &self,
+        access_group: Option<&str>,
+        data_protection: bool,
+    ) -> Option<EntitlementRequirement> {
+        if self.is_empty() {
+            return Some(EntitlementRequirement::CodeSignature);
+        }
+
+        if let Some(group) = access_group {
+            if !self.allows_access_group(group) {
+                return Some(EntitlementRequirement::AccessGroup(group.to_owned()));
+            }
+        }
+
+        if (data_protection || access_group.is_some()) && !self.allows_data_protection_keychain() {
+            return Some(EntitlementRequirement::DataProtectionKeychain);
+        }
+
+        None
+    }
+}
+
+/// Entitlement a keychain request appears to require but the process lacks.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EntitlementRequirement {
+    /// The process has no keychain entitlements at all, typically because it
+    /// is unsigned or signed without an entitlements file.
+    CodeSignature,
+
+    /// The access group isn't listed in the `keychain-access-groups` or
+    /// App Groups entitlements, and isn't the application identifier.
+    AccessGroup(String),
+
+    /// The data protection keychain requires an application identifier or
+    /// `keychain-access-groups` entitlement.
+    DataProtectionKeychain,
+}
+
+impl Display for EntitlementRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EntitlementRequirement::CodeSignature => {
+                write!(f, "process is not signed with any keychain entitlements")
+            }
+            EntitlementRequirement::AccessGroup(group) => write!(
+                f,
+                "access group `{}` is not in the `{}` entitlement",
+                group, KEYCHAIN_ACCESS_GROUPS
+            ),
+            EntitlementRequirement::DataProtectionKeychain => write!(
+                f,
+                "data protection keychain requires an `{}` or `{}` entitlement",
+                APPLICATION_IDENTIFIER, KEYCHAIN_ACCESS_GROUPS
+            ),
+        }
+    }
+}
+
+/// Refine an `ErrorKind::MissingEntitlement` error returned for a request
+/// with the given attributes into `ErrorKind::MissingKeychainEntitlement`,
+/// naming the entitlement the request appears to need. Other errors, and
+/// ones whose cause can't be determined, are returned unchanged.
+pub(crate) fn diagnose(error: Error, attrs: &DictionaryBuilder) -> Error {
+    match error.kind() {
+        ErrorKind::MissingEntitlement => (),
+        _ => return error,
+    }
+
+    let entitlements = match Entitlements::current() {
+        Ok(entitlements) => entitlements,
+        Err(_) => return error,
+    };
+
+    let access_group = attrs
+        .get(unsafe { kSecAttrAccessGroup })
+        .and_then(|value| value.downcast::<CFString>())
+        .map(|group| group.to_string());
+
+    let data_protection = attrs
+        .get(unsafe { kSecUseDataProtectionKeychain })
+        .and_then(|value| value.downcast::<CFBoolean>())
+        .is_some_and(bool::from);
+
+    match entitlements.missing_requirement(access_group.as_deref(), data_protection) {
+        Some(requirement) => Error::new(
+            ErrorKind::MissingKeychainEntitlement { requirement },
+            &format!(
+                "keychain request denied by code signing entitlements ({})",
+                error
+            ),
+        ),
+        None => error,
+    }
+}
+
+/// Copy the value of the given entitlement of the given `SecTask`
+fn copy_entitlement(task: &CFType, entitlement: &str) -> Option<CFType> {
+    let value = unsafe {
+        SecTaskCopyValueForEntitlement(
+            task.as_CFTypeRef(),
+            CFString::new(entitlement).as_concrete_TypeRef(),
+            ptr::null_mut(),
+        )
+    };
+
+    if value.is_null() {
+        None
+    } else {
+        Some(unsafe { CFType::wrap_under_create_rule(value) })
+    }
+}
+
+/// Collect the strings in an entitlement whose value is an array of strings
+fn string_array(value: Option<CFType>) -> Vec<String> {
+    value
+        .and_then(|value| value.downcast::<CFArray<*const c_void>>())
+        .map(|array| {
+            array
+                .iter()
+                .filter_map(|item| {
+                    unsafe { CFType::wrap_under_get_rule(*item as CFTypeRef) }
+                        .downcast::<CFString>()
+                })
+                .map(|string| string.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,274 @@
+//! Notifications of keychain events (e.g. a keychain being unlocked or an
+//! item being added) via the legacy (i.e. file-based keychain) callback API.
+//!
+//! Callbacks are delivered on the run loop of the thread which made the
+//! first subscription, so that thread must be running its run loop (as the
+//! main thread of an application does) for them to be received.
+
+use super::{Item, Keychain};
+use crate::{error::Error, ffi::*};
+use core_foundation::base::{OSStatus, TCFType};
+use std::{
+    os::raw::c_void,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Kinds of keychain events.
+///
+/// Wrapper for the `SecKeychainEvent` type. See:
+/// <https://developer.apple.com/documentation/security/seckeychainevent>
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+pub enum KeychainEventKind {
+    /// A keychain was locked.
+    ///
+    /// Wrapper for `kSecLockEvent`.
+    Lock,
+
+    /// A keychain was unlocked.
+    ///
+    /// Wrapper for `kSecUnlockEvent`.
+    Unlock,
+
+    /// An item was added to a keychain.
+    ///
+    /// Wrapper for `kSecAddEvent`.
+    Add,
+
+    /// An item was deleted from a keychain.
+    ///
+    /// Wrapper for `kSecDeleteEvent`.
+    Delete,
+
+    /// An item was updated.
+    ///
+    /// Wrapper for `kSecUpdateEvent`.
+    Update,
+
+    /// The password of a keychain was changed.
+    ///
+    /// Wrapper for `kSecPasswordChangedEvent`.
+    PasswordChanged,
+
+    /// The default keychain was changed.
+    ///
+    /// Wrapper for `kSecDefaultChangedEvent`.
+    DefaultChanged,
+
+    /// The data of an item was accessed.
+    ///
+    /// Wrapper for `kSecDataAccessEvent`.
+    DataAccess,
+
+    /// The list of keychains was changed.
+    ///
+    /// Wrapper for `kSecKeychainListChangedEvent`.
+    KeychainListChanged,
+
+    /// Trust settings were changed.
+    ///
+    /// Wrapper for `kSecTrustSettingsChangedEvent`.
+    TrustSettingsChanged,
+}
+
+impl KeychainEventKind {
+    /// All kinds of keychain events
+    pub const ALL: &'static [KeychainEventKind] = &[
+        KeychainEventKind::Lock,
+        KeychainEventKind::Unlock,
+        KeychainEventKind::Add,
+        KeychainEventKind::Delete,
+        KeychainEventKind::Update,
+        KeychainEventKind::PasswordChanged,
+        KeychainEventKind::DefaultChanged,
+        KeychainEventKind::DataAccess,
+        KeychainEventKind::KeychainListChanged,
+        KeychainEventKind::TrustSettingsChanged,
+    ];
+
+    /// Get the `SecKeychainEventMask` bit for this kind of event
+    fn mask(self) -> SecKeychainEventMask {
+        1 << SecKeychainEvent::from(self)
+    }
+}
+
+impl From<KeychainEventKind> for SecKeychainEvent {
+    fn from(kind: KeychainEventKind) -> SecKeychainEvent {
+        match kind {
+            KeychainEventKind::Lock => 1,
+            KeychainEventKind::Unlock => 2,
+            KeychainEventKind::Add => 3,
+            KeychainEventKind::Delete => 4,
+            KeychainEventKind::Update => 5,
+            KeychainEventKind::PasswordChanged => 6,
+            KeychainEventKind::DefaultChanged => 9,
+            KeychainEventKind::DataAccess => 10,
+            KeychainEventKind::KeychainListChanged => 11,
+            KeychainEventKind::TrustSettingsChanged => 12,
+        }
+    }
+}
+
+/// Keychain event along with the information about it provided by the
+/// callback API.
+///
+/// Wrapper for the `SecKeychainCallbackInfo` type. See:
+/// <https://developer.apple.com/documentation/security/seckeychaincallbackinfo>
+#[derive(Debug)]
+pub struct KeychainEvent {
+    /// Kind of event
+    pub kind: KeychainEventKind,
+
+    /// Keychain the event occurred in (if any)
+    pub keychain: Option<Keychain>,
+
+    /// Item the event concerns (if any). Items which were deleted can no
+    /// longer be accessed.
+    pub item: Option<Item>,
+
+    /// ID of the process which caused the event (if known)
+    pub pid: Option<u32>,
+}
+
+impl KeychainEvent {
+    /// Decode an event passed to a `SecKeychainCallback`, returning `None`
+    /// if it's of an unknown kind.
+    unsafe fn from_callback(
+        event: SecKeychainEvent,
+        info: *const SecKeychainCallbackInfo,
+    ) -> Option<Self> {
+        let kind = *KeychainEventKind::ALL
+            .iter()
+            .find(|&&kind| SecKeychainEvent::from(kind) == event)?;
+
+        let mut result = KeychainEvent {
+            kind,
+            keychain: None,
+            item: None,
+            pid: None,
+        };
+
+        if let Some(info) = info.as_ref() {
+            if !info.keychain.is_null() {
+                result.keychain = Some(Keychain::wrap_under_get_rule(info.keychain));
+            }
+
+            if !info.item.is_null() {
+                result.item = Some(Item::wrap_under_get_rule(info.item));
+            }
+
+            if info.pid > 0 {
+                result.pid = Some(info.pid as u32);
+            }
+        }
+
+        Some(result)
+    }
+}
+
+/// Subscription to keychain events created by `subscribe`, which
+/// unsubscribes when dropped.
+#[derive(Debug)]
+pub struct EventSubscription {
+    id: u64,
+}
+
+impl Drop for EventSubscription {
+    fn drop(&mut self) {
+        let mut subscribers = SUBSCRIBERS.lock().unwrap();
+        subscribers.retain(|subscriber| subscriber.id != self.id);
+
+        if subscribers.is_empty() {
+            unsafe { SecKeychainRemoveCallback(keychain_callback) };
+        }
+    }
+}
+
+/// Invoke the given callback whenever a keychain event of one of the given
+/// kinds occurs, until the returned `EventSubscription` is dropped.
+///
+/// Wrapper for the `SecKeychainAddCallback` function. See:
+/// <https://developer.apple.com/documentation/security/1397947-seckeychainaddcallback>
+pub fn subscribe<F>(kinds: &[KeychainEventKind], callback: F) -> Result<EventSubscription, Error>
+where
+    F: FnMut(&KeychainEvent) + Send + 'static,
+{
+    let mut subscribers = SUBSCRIBERS.lock().unwrap();
+
+    // A single callback receiving all events is registered on behalf of
+    // every subscriber, as the API only allows a function to be added once
+    if subscribers.is_empty() {
+        let mask = KeychainEventKind::ALL
+            .iter()
+            .fold(0, |mask, kind| mask | kind.mask());
+
+        let status = unsafe { SecKeychainAddCallback(keychain_callback, mask, ptr::null_mut()) };
+
+        if let Some(e) = Error::maybe_from_OSStatus(status) {
+            return Err(e);
+        }
+    }
+
+    let id = NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed);
+
+    subscribers.push(Subscriber {
+        id,
+        mask: kinds.iter().fold(0, |mask, kind| mask | kind.mask()),
+        callback: Arc::new(Mutex::new(Box::new(callback))),
+    });
+
+    Ok(EventSubscription { id })
+}
+
+/// Callback invoked by a subscription
+type SubscriberCallback = Arc<Mutex<Box<dyn FnMut(&KeychainEvent) + Send>>>;
+
+/// Registered subscription
+struct Subscriber {
+    id: u64,
+    mask: SecKeychainEventMask,
+    callback: SubscriberCallback,
+}
+
+/// Currently registered subscriptions
+static SUBSCRIBERS: Mutex<Vec<Subscriber>> = Mutex::new(Vec::new());
+
+/// ID of the next subscription
+static NEXT_SUBSCRIPTION_ID: AtomicU64 = AtomicU64::new(0);
+
+/// `SecKeychainCallback` which dispatches events to subscribers
+extern "C" fn keychain_callback(
+    event: SecKeychainEvent,
+    info: *mut SecKeychainCallbackInfo,
+    _context: *mut c_void,
+) -> OSStatus {
+    let event = match unsafe { KeychainEvent::from_callback(event, info) } {
+        Some(event) => event,
+        None => return 0,
+    };
+
+    // Release the lock before invoking callbacks so they can (un)subscribe
+    let callbacks: Vec<SubscriberCallback> = match SUBSCRIBERS.lock() {
+        Ok(subscribers) => subscribers
+            .iter()
+            .filter(|subscriber| subscriber.mask & event.kind.mask() != 0)
+            .map(|subscriber| subscriber.callback.clone())
+            .collect(),
+        Err(_) => return 0,
+    };
+
+    for callback in callbacks {
+        // Panics must not unwind into Keychain Services
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+            if let Ok(mut callback) = callback.lock() {
+                callback(&event);
+            }
+        }));
+    }
+
+    0
+}
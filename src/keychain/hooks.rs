@@ -0,0 +1,150 @@
+//! Middleware invoked around keychain operations, for cross-cutting
+//! concerns such as metrics, rate limiting, and policy enforcement.
+//!
+//! Registered `Middleware` is invoked in an "onion" fashion: `before`
+//! hooks run in registration order and can reject an operation, after
+//! which `after` hooks run in reverse order (only for the middleware whose
+//! `before` hook ran successfully).
+
+use super::item::Class;
+use crate::error::Error;
+use std::{
+    fmt::{self, Debug},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+
+/// Kinds of keychain operations observed by `Middleware`
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum OperationKind {
+    /// Adding an item to the keychain (i.e. `SecItemAdd`)
+    Add,
+
+    /// Finding items in the keychain (i.e. `SecItemCopyMatching`)
+    Find,
+
+    /// Updating an item in the keychain (i.e. `SecItemUpdate`)
+    Update,
+
+    /// Deleting an item from the keychain (i.e. `SecItemDelete`)
+    Delete,
+
+    /// Generating a key pair
+    GenerateKey,
+
+    /// Signing data with a key
+    Sign,
+
+    /// Verifying a signature with a key
+    Verify,
+
+    /// Encrypting data with a key
+    Encrypt,
+
+    /// Decrypting data with a key
+    Decrypt,
+
+    /// Deriving a shared secret from a private key and a peer's public key
+    KeyExchange,
+}
+
+/// Description of a keychain operation passed to `Middleware`. Never
+/// includes any secret data.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct OperationDescriptor {
+    /// Kind of operation
+    pub kind: OperationKind,
+
+    /// Class of the items the operation concerns (if known)
+    pub class: Option<Class>,
+}
+
+/// Hooks invoked around keychain operations.
+pub trait Middleware: Send + Sync {
+    /// Invoked before an operation is performed. Returning an error
+    /// rejects the operation, and the error is returned to the caller.
+    fn before(&self, _descriptor: &OperationDescriptor) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Invoked after an operation is performed (or rejected by subsequently
+    /// registered middleware), along with the error it failed with (if any).
+    fn after(&self, _descriptor: &OperationDescriptor, _error: Option<&Error>) {}
+}
+
+/// Registration of `Middleware` created by `register_middleware`, which
+/// unregisters it when dropped.
+pub struct MiddlewareRegistration {
+    id: u64,
+}
+
+impl Debug for MiddlewareRegistration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "MiddlewareRegistration({})", self.id)
+    }
+}
+
+impl Drop for MiddlewareRegistration {
+    fn drop(&mut self) {
+        MIDDLEWARE.write().unwrap().retain(|(id, _)| *id != self.id);
+    }
+}
+
+/// Register `Middleware` to be invoked around all subsequent keychain
+/// operations (in all threads), until the returned `MiddlewareRegistration`
+/// is dropped.
+pub fn register_middleware<M>(middleware: M) -> MiddlewareRegistration
+where
+    M: Middleware + 'static,
+{
+    let id = NEXT_MIDDLEWARE_ID.fetch_add(1, Ordering::Relaxed);
+    MIDDLEWARE.write().unwrap().push((id, Arc::new(middleware)));
+    MiddlewareRegistration { id }
+}
+
+/// Perform a keychain operation, invoking any registered middleware
+/// around it.
+pub(crate) fn run<T, F>(kind: OperationKind, class: Option<Class>, operation: F) -> Result<T, Error>
+where
+    F: FnOnce() -> Result<T, Error>,
+{
+    // Snapshot the middleware so hooks can (un)register middleware
+    let middleware: Vec<Arc<dyn Middleware>> = MIDDLEWARE
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(_, middleware)| middleware.clone())
+        .collect();
+
+    if middleware.is_empty() {
+        return operation();
+    }
+
+    let descriptor = OperationDescriptor { kind, class };
+
+    for (i, m) in middleware.iter().enumerate() {
+        if let Err(e) = m.before(&descriptor) {
+            for m in middleware[..i].iter().rev() {
+                m.after(&descriptor, Some(&e));
+            }
+
+            return Err(e);
+        }
+    }
+
+    let result = operation();
+
+    for m in middleware.iter().rev() {
+        m.after(&descriptor, result.as_ref().err());
+    }
+
+    result
+}
+
+/// Currently registered middleware
+static MIDDLEWARE: RwLock<Vec<(u64, Arc<dyn Middleware>)>> = RwLock::new(Vec::new());
+
+/// ID of the next middleware registration
+static NEXT_MIDDLEWARE_ID: AtomicU64 = AtomicU64::new(0);
@@ -0,0 +1,60 @@
+//! Identities: a certificate along with its associated private key
+
+use crate::{error::Error, ffi::*, key::Key, keychain::certificate::Certificate};
+use core_foundation::base::TCFType;
+use std::{
+    fmt::{self, Debug},
+    ptr,
+};
+
+declare_TCFType! {
+    /// Identities, i.e. a certificate together with its private key.
+    ///
+    /// Wrapper for the `SecIdentity`/`SecIdentityRef` types:
+    /// <https://developer.apple.com/documentation/security/secidentityref>
+    Identity, IdentityRef
+}
+
+impl_TCFType!(Identity, IdentityRef, SecIdentityGetTypeID);
+
+impl Identity {
+    /// Get the certificate for this identity.
+    ///
+    /// Wrapper for the `SecIdentityCopyCertificate` function. See:
+    /// <https://developer.apple.com/documentation/security/1401305-secidentitycopycertificate>
+    pub fn certificate(&self) -> Result<Certificate, Error> {
+        let mut result: CertificateRef = ptr::null_mut();
+        let status = unsafe { SecIdentityCopyCertificate(self.as_concrete_TypeRef(), &mut result) };
+
+        if let Some(e) = Error::maybe_from_OSStatus(status) {
+            Err(e)
+        } else {
+            Ok(unsafe { Certificate::wrap_under_create_rule(result) })
+        }
+    }
+
+    /// Get the private key for this identity.
+    ///
+    /// Wrapper for the `SecIdentityCopyPrivateKey` function. See:
+    /// <https://developer.apple.com/documentation/security/1396023-secidentitycopyprivatekey>
+    pub fn private_key(&self) -> Result<Key, Error> {
+        let mut result: KeyRef = ptr::null_mut();
+        let status = unsafe { SecIdentityCopyPrivateKey(self.as_concrete_TypeRef(), &mut result) };
+
+        if let Some(e) = Error::maybe_from_OSStatus(status) {
+            Err(e)
+        } else {
+            Ok(unsafe { Key::wrap_under_create_rule(result) })
+        }
+    }
+}
+
+impl Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "SecIdentity {{ certificate: {:?} }}",
+            self.certificate().ok()
+        )
+    }
+}
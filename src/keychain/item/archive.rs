@@ -0,0 +1,417 @@
+//! Backing up passwords to passphrase-encrypted archives and restoring them,
+//! e.g. to move an application's credentials to another machine.
+//!
+//! An archive holds the attributes `Item::copy_to` carries over along with
+//! the secret data of each item, as a binary property list. It's encrypted
+//! with AES-256-CBC and authenticated with HMAC-SHA-256, using keys derived
+//! from the passphrase with PBKDF2-HMAC-SHA-256:
+//!
+//! ```text
+//! magic ("KSARCHIV") | format version (1) | PBKDF2 rounds (32-bit, big endian)
+//! | salt (16 bytes) | IV (16 bytes) | ciphertext | HMAC (32 bytes)
+//! ```
+//!
+//! Access control policies (see `AccessControl`) can't be archived, so
+//! restored items are only protected according to their accessibility.
+
+use super::{
+    copy::{add_to_access_group, copy_attrs, find_with_data},
+    Class, ItemAttributes, ItemDestination, Query,
+};
+use crate::{
+    attr::AttrKind,
+    dictionary::{Dictionary, DictionaryBuilder},
+    error::{Error, ErrorKind},
+    ffi::*,
+};
+use core_foundation::{
+    array::CFArray,
+    base::{CFType, CFTypeRef, TCFType},
+    data::CFData,
+    dictionary::CFDictionary,
+    propertylist::{
+        self, kCFPropertyListBinaryFormat_v1_0, kCFPropertyListImmutable, CFPropertyList,
+    },
+    string::CFString,
+};
+use std::{
+    borrow::Borrow,
+    os::raw::{c_char, c_void},
+    ptr,
+};
+use zeroize::Zeroizing;
+
+/// Identifies archives written by `export_archive`
+const MAGIC: &[u8; 8] = b"KSARCHIV";
+
+/// Version of the archive format written by `export_archive`
+const FORMAT_VERSION: u8 = 1;
+
+/// Number of PBKDF2 rounds used to derive the keys of new archives
+const PBKDF2_ROUNDS: u32 = 600_000;
+
+/// Fewest PBKDF2 rounds accepted when opening archives. The rounds are read
+/// from the header before it's authenticated, so they're bounded to keep
+/// tampered archives from weakening or stalling key derivation.
+const MIN_PBKDF2_ROUNDS: u32 = 100_000;
+
+/// Most PBKDF2 rounds accepted when opening archives
+const MAX_PBKDF2_ROUNDS: u32 = 10_000_000;
+
+/// Size of the random PBKDF2 salt
+const SALT_SIZE: usize = 16;
+
+/// Size of the random IV (i.e. the AES block size)
+const IV_SIZE: usize = 16;
+
+/// Size of each of the encryption and MAC keys
+const KEY_SIZE: usize = 32;
+
+/// Size of an HMAC-SHA-256 tag
+const MAC_SIZE: usize = 32;
+
+/// Size of the header preceding the ciphertext
+const HEADER_SIZE: usize = 8 + 1 + 4 + SALT_SIZE + IV_SIZE;
+
+/// Classes of items which can be archived
+const ARCHIVED_CLASSES: &[Class] = &[Class::GenericPassword, Class::InternetPassword];
+
+/// Items added to the keychain by `import_archive`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct ArchiveImport {
+    imported: usize,
+    skipped: usize,
+}
+
+impl ArchiveImport {
+    /// Get the number of items which were added to the destination
+    pub fn imported(&self) -> usize {
+        self.imported
+    }
+
+    /// Get the number of items which were skipped because the destination
+    /// already has them
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+}
+
+/// Export all items of the given classes (or if none are given, all
+/// passwords) matching the given `Query` to an archive encrypted with the
+/// given passphrase.
+///
+/// Reading the secret data of each item may prompt the user to allow
+/// access to it (see `Query::use_operation_prompt`). Only passwords can be
+/// archived: other classes return an error with a kind of
+/// `ErrorKind::Param`.
+pub fn export_archive<Q: Borrow<Query>>(
+    classes: &[Class],
+    query: Q,
+    passphrase: &str,
+) -> Result<Vec<u8>, Error> {
+    let classes = if classes.is_empty() {
+        ARCHIVED_CLASSES
+    } else {
+        classes
+    };
+
+    let prompt = DictionaryBuilder::from(query.borrow())
+        .get(unsafe { kSecUseOperationPrompt })
+        .cloned();
+    let mut entries = vec![];
+
+    for &class in classes {
+        if !ARCHIVED_CLASSES.contains(&class) {
+            return Err(Error::new(
+                ErrorKind::Param,
+                &format!("can't archive items of class {}", class),
+            ));
+        }
+
+        for item in ItemAttributes::find(class, query.borrow(), true)? {
+            let item_ref = item
+                .value_ref()
+                .ok_or_else(|| Error::new(ErrorKind::Param, "no reference to item"))?;
+
+            let mut item_query = DictionaryBuilder::new();
+            item_query.add(unsafe { kSecValueRef }, &item_ref);
+
+            if let Some(prompt) = &prompt {
+                item_query.add(unsafe { kSecUseOperationPrompt }, prompt);
+            }
+
+            let mut attrs = copy_attrs(class, &find_with_data(class, item_query)?);
+            attrs.remove(AttrKind::AccessControl);
+            entries.push(Dictionary::from(attrs));
+        }
+    }
+
+    let plist = propertylist::create_data(
+        CFArray::from_CFTypes(&entries).as_CFTypeRef(),
+        kCFPropertyListBinaryFormat_v1_0,
+    )
+    .map_err(|e| Error::new(ErrorKind::from_cf_error(&e), &e.description()))?;
+
+    let plist = WipeOnDrop(plist);
+    seal(&Zeroizing::new(plist.0.bytes().to_vec()), passphrase)
+}
+
+/// Import the items in an archive written by `export_archive` into the
+/// given destination.
+///
+/// Items the destination already has are left alone. Returns an error with
+/// a kind of `ErrorKind::AuthFailed` if the passphrase is wrong (or the
+/// archive has been tampered with), in which case nothing is imported.
+pub fn import_archive(
+    archive: &[u8],
+    passphrase: &str,
+    destination: ItemDestination,
+) -> Result<ArchiveImport, Error> {
+    let plaintext = WipeOnDrop(CFData::from_buffer(&open(archive, passphrase)?));
+
+    let (plist, _) = propertylist::create_with_data(plaintext.0.clone(), kCFPropertyListImmutable)
+        .map_err(|e| Error::new(ErrorKind::from_cf_error(&e), &e.description()))?;
+
+    let entries = unsafe { CFPropertyList::wrap_under_create_rule(plist) }
+        .as_CFType()
+        .downcast::<CFArray>()
+        .ok_or_else(|| Error::new(ErrorKind::Decode, "malformed archive contents"))?;
+    let entries = unsafe { CFArray::<CFType>::wrap_under_get_rule(entries.as_concrete_TypeRef()) };
+
+    let mut result = ArchiveImport::default();
+
+    for entry in entries.iter() {
+        let entry = entry
+            .downcast::<CFDictionary>()
+            .map(|dict| unsafe { Dictionary::wrap_under_get_rule(dict.as_concrete_TypeRef()) })
+            .ok_or_else(|| Error::new(ErrorKind::Decode, "malformed archive entry"))?;
+
+        let class = entry
+            .find(unsafe { kSecClass } as CFTypeRef)
+            .and_then(|class| class.downcast::<CFString>())
+            .and_then(|class| Class::from_tag(FourCharacterCode::from(&class)))
+            .filter(|class| ARCHIVED_CLASSES.contains(class))
+            .ok_or_else(|| Error::new(ErrorKind::Decode, "malformed archive entry"))?;
+
+        let attrs = copy_attrs(class, &entry);
+
+        let added = match destination {
+            ItemDestination::Keychain(keychain) => keychain.add_item(attrs).map(|_| ()),
+            ItemDestination::AccessGroup(access_group) => {
+                add_to_access_group(attrs, access_group).map(|_| ())
+            }
+        };
+
+        match added {
+            Ok(()) => result.imported += 1,
+            Err(e) => match e.kind() {
+                ErrorKind::DuplicateItem => result.skipped += 1,
+                _ => return Err(e),
+            },
+        }
+    }
+
+    Ok(result)
+}
+
+/// Encrypt and authenticate the given plaintext with keys derived from the
+/// given passphrase, returning the archive
+fn seal(plaintext: &[u8], passphrase: &str) -> Result<Vec<u8>, Error> {
+    let mut salt = [0u8; SALT_SIZE];
+    let mut iv = [0u8; IV_SIZE];
+    random_bytes(&mut salt)?;
+    random_bytes(&mut iv)?;
+
+    let keys = derive_keys(passphrase, &salt, PBKDF2_ROUNDS)?;
+
+    let mut archive = Vec::with_capacity(HEADER_SIZE + plaintext.len() + IV_SIZE + MAC_SIZE);
+    archive.extend_from_slice(MAGIC);
+    archive.push(FORMAT_VERSION);
+    archive.extend_from_slice(&PBKDF2_ROUNDS.to_be_bytes());
+    archive.extend_from_slice(&salt);
+    archive.extend_from_slice(&iv);
+    archive.extend_from_slice(&aes_cbc(kCCEncrypt, &keys[..KEY_SIZE], &iv, plaintext)?);
+
+    let mac = hmac_sha256(&keys[KEY_SIZE..], &archive);
+    archive.extend_from_slice(&mac);
+    Ok(archive)
+}
+
+/// Authenticate and decrypt the given archive with keys derived from the
+/// given passphrase, returning the plaintext
+fn open(archive: &[u8], passphrase: &str) -> Result<Zeroizing<Vec<u8>>, Error> {
+    if archive.len() < HEADER_SIZE + MAC_SIZE || &archive[..MAGIC.len()] != MAGIC {
+        return Err(Error::new(ErrorKind::Decode, "not a keychain archive"));
+    }
+
+    let version = archive[MAGIC.len()];
+
+    if version != FORMAT_VERSION {
+        return Err(Error::new(
+            ErrorKind::Decode,
+            &format!("unsupported archive format version: {}", version),
+        ));
+    }
+
+    let mut rounds = [0u8; 4];
+    rounds.copy_from_slice(&archive[9..13]);
+    let rounds = u32::from_be_bytes(rounds);
+
+    if !(MIN_PBKDF2_ROUNDS..=MAX_PBKDF2_ROUNDS).contains(&rounds) {
+        return Err(Error::new(
+            ErrorKind::Decode,
+            &format!("unsupported number of PBKDF2 rounds: {}", rounds),
+        ));
+    }
+
+    let salt = &archive[13..13 + SALT_SIZE];
+    let iv = &archive[13 + SALT_SIZE..HEADER_SIZE];
+    let (body, mac) = archive.split_at(archive.len() - MAC_SIZE);
+
+    let keys = derive_keys(passphrase, salt, rounds)?;
+
+    if !constant_time_eq(&hmac_sha256(&keys[KEY_SIZE..], body), mac) {
+        return Err(Error::new(
+            ErrorKind::AuthFailed,
+            "wrong passphrase or corrupted archive",
+        ));
+    }
+
+    aes_cbc(kCCDecrypt, &keys[..KEY_SIZE], iv, &body[HEADER_SIZE..])
+}
+
+/// Derive the encryption key followed by the MAC key from the given
+/// passphrase.
+///
+/// Wrapper for the `CCKeyDerivationPBKDF` function.
+fn derive_keys(passphrase: &str, salt: &[u8], rounds: u32) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let mut keys = Zeroizing::new(vec![0u8; KEY_SIZE * 2]);
+
+    let status = unsafe {
+        CCKeyDerivationPBKDF(
+            kCCPBKDF2,
+            passphrase.as_ptr() as *const c_char,
+            passphrase.len(),
+            salt.as_ptr(),
+            salt.len(),
+            kCCPRFHmacAlgSHA256,
+            rounds,
+            keys.as_mut_ptr(),
+            keys.len(),
+        )
+    };
+
+    if status != kCCSuccess {
+        return Err(Error::new(
+            ErrorKind::Param,
+            &format!("key derivation failed (status {})", status),
+        ));
+    }
+
+    Ok(keys)
+}
+
+/// Encrypt or decrypt the given data with AES-CBC and PKCS#7 padding.
+///
+/// Wrapper for the `CCCrypt` function.
+fn aes_cbc(
+    operation: u32,
+    key: &[u8],
+    iv: &[u8],
+    input: &[u8],
+) -> Result<Zeroizing<Vec<u8>>, Error> {
+    let mut output = Zeroizing::new(vec![0u8; input.len() + IV_SIZE]);
+    let mut output_length = 0;
+
+    let status = unsafe {
+        CCCrypt(
+            operation,
+            kCCAlgorithmAES,
+            kCCOptionPKCS7Padding,
+            key.as_ptr() as *const c_void,
+            key.len(),
+            iv.as_ptr() as *const c_void,
+            input.as_ptr() as *const c_void,
+            input.len(),
+            output.as_mut_ptr() as *mut c_void,
+            output.len(),
+            &mut output_length,
+        )
+    };
+
+    if status != kCCSuccess {
+        // Decryption fails on malformed input, whereas encryption can only
+        // fail in CommonCrypto itself
+        let kind = if operation == kCCDecrypt {
+            ErrorKind::Decode
+        } else {
+            ErrorKind::OSError {
+                code: i64::from(status),
+            }
+        };
+
+        return Err(Error::new(
+            kind,
+            &format!("AES operation failed (status {})", status),
+        ));
+    }
+
+    output.truncate(output_length);
+    Ok(output)
+}
+
+/// Serialized archive contents, which are overwritten when dropped. The
+/// `CFData` must have been created by us and not be shared beyond the
+/// current operation.
+struct WipeOnDrop(CFData);
+
+impl Drop for WipeOnDrop {
+    fn drop(&mut self) {
+        let bytes = self.0.bytes();
+        unsafe { ptr::write_bytes(bytes.as_ptr() as *mut u8, 0, bytes.len()) };
+    }
+}
+
+/// Compute the HMAC-SHA-256 tag of the given data.
+///
+/// Wrapper for the `CCHmac` function.
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; MAC_SIZE] {
+    let mut mac = [0u8; MAC_SIZE];
+
+    unsafe {
+        CCHmac(
+            kCCHmacAlgSHA256,
+            key.as_ptr() as *const c_void,
+            key.len(),
+            data.as_ptr() as *const c_void,
+            data.len(),
+            mac.as_mut_ptr() as *mut c_void,
+        )
+    };
+
+    mac
+}
+
+/// Fill the given buffer with cryptographically secure random bytes.
+///
+/// Wrapper for the `SecRandomCopyBytes` function. See:
+/// <https://developer.apple.com/documentation/security/secrandomcopybytes(_:_:_:)>
+fn random_bytes(buffer: &mut [u8]) -> Result<(), Error> {
+    let status = unsafe {
+        SecRandomCopyBytes(
+            ptr::null(),
+            buffer.len(),
+            buffer.as_mut_ptr() as *mut c_void,
+        )
+    };
+
+    match Error::maybe_from_OSStatus(status) {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Compare two MACs without leaking where they differ through timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
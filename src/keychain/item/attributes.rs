@@ -0,0 +1,585 @@
+//! Attributes of keychain items, fetched without their secret data
+
+use super::{
+    copy, deletion_mode, tombstone, Class, DeletionMode, ItemTags, MatchLimit, PasswordData,
+    PersistentItemRef, Query,
+};
+use crate::{
+    access::AccessControl,
+    attr::{
+        AttrAccessible, AttrApplicationTag, AttrCreator, AttrGeneric, AttrItemType, AttrKeyClass,
+        AttrKind, AttrTokenId,
+    },
+    date::{self, DateRange},
+    dictionary::{Dictionary, DictionaryBuilder},
+    error::{Error, ErrorKind},
+    ffi::*,
+    keychain::{
+        entitlements,
+        hooks::{self, OperationKind},
+    },
+};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
+use core_foundation::{
+    array::{CFArray, CFArrayRef},
+    base::{CFType, CFTypeRef, TCFType},
+    boolean::CFBoolean,
+    data::{CFData, CFDataRef},
+    date::CFDate,
+    dictionary::CFDictionaryRef,
+    number::CFNumber,
+    string::{CFString, CFStringRef},
+};
+use std::{
+    borrow::Borrow,
+    fmt::{self, Debug},
+    ptr,
+    time::SystemTime,
+};
+
+/// Attributes of a keychain item (e.g. its account, label, and dates)
+/// returned by a query. Never includes the item's secret data, and fetching
+/// them does not prompt the user to allow access to it.
+pub struct ItemAttributes {
+    class: Class,
+    attrs: Dictionary,
+}
+
+impl ItemAttributes {
+    /// Find the attributes of all items of the given class matching the
+    /// given `Query`, returning an empty `Vec` if nothing matches.
+    ///
+    /// This is considerably cheaper than fetching the items themselves, so
+    /// it's the preferred way to scan the keychain for items of interest.
+    ///
+    /// Wrapper for `SecItemCopyMatching` with `kSecReturnAttributes`. See:
+    /// <https://developer.apple.com/documentation/security/ksecreturnattributes>
+    pub fn find_all<Q: Borrow<Query>>(class: Class, query: Q) -> Result<Vec<Self>, Error> {
+        Self::find(class, query.borrow(), false)
+    }
+
+    /// Find the attributes of all items of the given class matching the
+    /// given `Query` along with their persistent references (see
+    /// `ItemAttributes::persistent_ref`), which can be stored and later
+    /// resolved with `Keychain::item_from_persistent_ref`.
+    ///
+    /// Wrapper for `SecItemCopyMatching` with `kSecReturnPersistentRef`. See:
+    /// <https://developer.apple.com/documentation/security/ksecreturnpersistentref>
+    pub fn find_with_persistent_refs<Q: Borrow<Query>>(
+        class: Class,
+        query: Q,
+    ) -> Result<Vec<Self>, Error> {
+        Self::find_returning(class, query.borrow(), &[unsafe { kSecReturnPersistentRef }])
+    }
+
+    /// Find the attributes of all items of the given class matching the
+    /// given `Query` which were created within the given `DateRange`.
+    ///
+    /// Keychain Services can't filter on date ranges, so results are
+    /// filtered after fetching their attributes.
+    pub fn find_created_within<Q: Borrow<Query>>(
+        class: Class,
+        query: Q,
+        range: &DateRange,
+    ) -> Result<Vec<Self>, Error> {
+        Ok(Self::find_all(class, query)?
+            .into_iter()
+            .filter(|item| item.creation_date().is_some_and(|d| range.contains(d)))
+            .collect())
+    }
+
+    /// Find the attributes of all items of the given class matching the
+    /// given `Query` which were last modified within the given `DateRange`,
+    /// e.g. `DateRange::new().older_than(..)` to find items due for rotation.
+    pub fn find_modified_within<Q: Borrow<Query>>(
+        class: Class,
+        query: Q,
+        range: &DateRange,
+    ) -> Result<Vec<Self>, Error> {
+        Ok(Self::find_all(class, query)?
+            .into_iter()
+            .filter(|item| item.modification_date().is_some_and(|d| range.contains(d)))
+            .collect())
+    }
+
+    /// Find the attributes of all items of the given class matching the
+    /// given `Query` which have the given user-defined tag (see `ItemTags`).
+    ///
+    /// Keychain Services can only match the comment the tags are stored in
+    /// exactly, so results are filtered after fetching their attributes.
+    pub fn find_by_tag<Q: Borrow<Query>>(
+        class: Class,
+        query: Q,
+        tag: &str,
+    ) -> Result<Vec<Self>, Error> {
+        Ok(Self::find_all(class, query)?
+            .into_iter()
+            .filter(|item| item.tags().contains(tag))
+            .collect())
+    }
+
+    /// Find the single item of the given class matching the given `Query`,
+    /// returning its attributes along with its secret data (e.g. a password)
+    /// from the same lookup, or `Ok(None)` if nothing matches.
+    ///
+    /// Unlike `GenericPassword::password` and friends, this doesn't rely on
+    /// `SecKeychainItemCopyContent`, so it also works for items stored in the
+    /// data protection keychain. Reading the data may prompt the user to
+    /// allow access to it.
+    ///
+    /// Wrapper for `SecItemCopyMatching` with `kSecReturnData`. See:
+    /// <https://developer.apple.com/documentation/security/ksecreturndata>
+    pub fn find_with_data<Q: Borrow<Query>>(
+        class: Class,
+        query: Q,
+    ) -> Result<Option<(Self, PasswordData)>, Error> {
+        let result = match copy::find_with_data(class, DictionaryBuilder::from(query.borrow())) {
+            Ok(result) => result,
+            Err(e) => {
+                return match e.kind() {
+                    ErrorKind::ItemNotFound => Ok(None),
+                    _ => Err(e),
+                }
+            }
+        };
+
+        let data = result
+            .find(unsafe { kSecValueData } as CFTypeRef)
+            .and_then(|value| value.downcast::<CFData>())
+            .map(|data| PasswordData::new(data.bytes().to_vec()))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::MissingEntitlement,
+                    "SecItemCopyMatching refused to return data",
+                )
+            })?;
+
+        // Keep the secret data out of the returned attributes
+        let mut attrs = DictionaryBuilder::new();
+        let (keys, values) = result.get_keys_and_values();
+
+        for (key, value) in keys.into_iter().zip(values) {
+            if key as CFStringRef != unsafe { kSecValueData } {
+                attrs.add(key as CFStringRef, &unsafe {
+                    CFType::wrap_under_get_rule(value as CFTypeRef)
+                });
+            }
+        }
+
+        let attributes = ItemAttributes {
+            class,
+            attrs: Dictionary::from(attrs),
+        };
+
+        Ok(Some((attributes, data)))
+    }
+
+    /// Get the class of this item
+    pub fn class(&self) -> Class {
+        self.class
+    }
+
+    /// Get the account this item is associated with (if any).
+    pub fn account(&self) -> Option<String> {
+        self.string_attribute(AttrKind::Account)
+    }
+
+    /// Get the service this item is associated with (if any).
+    pub fn service(&self) -> Option<String> {
+        self.string_attribute(AttrKind::Service)
+    }
+
+    /// Get the server this item is associated with (if any).
+    pub fn server(&self) -> Option<String> {
+        self.string_attribute(AttrKind::Server)
+    }
+
+    /// Get the label of this item (if any).
+    pub fn label(&self) -> Option<String> {
+        self.string_attribute(AttrKind::Label)
+    }
+
+    /// Get the comment associated with this item (if any).
+    pub fn comment(&self) -> Option<String> {
+        self.string_attribute(AttrKind::Comment)
+    }
+
+    /// Get the description of this item's kind (if any).
+    pub fn description(&self) -> Option<String> {
+        self.string_attribute(AttrKind::Description)
+    }
+
+    /// Get the creator code of this item (if any).
+    pub fn creator(&self) -> Option<AttrCreator> {
+        self.four_character_code_attribute(AttrKind::Creator)
+            .map(AttrCreator)
+    }
+
+    /// Get the type code of this item (if any).
+    pub fn item_type(&self) -> Option<AttrItemType> {
+        self.four_character_code_attribute(AttrKind::ItemType)
+            .map(AttrItemType)
+    }
+
+    /// Get the user-defined tags attached to this item (see `ItemTags`).
+    pub fn tags(&self) -> ItemTags {
+        self.comment()
+            .map(|comment| ItemTags::from_comment(&comment))
+            .unwrap_or_default()
+    }
+
+    /// Get when this item expires (if ever), as recorded in its tags. See
+    /// `ItemTags::set_expiry`.
+    pub fn expiry(&self) -> Option<SystemTime> {
+        self.tags().expiry()
+    }
+
+    /// Has this item expired, i.e. is its expiry in the past?
+    pub fn is_expired(&self) -> bool {
+        self.expiry()
+            .is_some_and(|expiry| expiry <= SystemTime::now())
+    }
+
+    /// Has this item been soft-deleted? See `Tombstone`.
+    pub fn is_tombstone(&self) -> bool {
+        tombstone::deletion_time(self).is_some()
+    }
+
+    /// Get the `AttrKeyClass` of this item (if it is a key).
+    pub fn key_class(&self) -> Option<AttrKeyClass> {
+        self.attrs
+            .find(AttrKind::KeyClass)
+            .map(|class| AttrKeyClass::from(class.as_CFTypeRef() as CFStringRef))
+    }
+
+    /// Get the `AttrApplicationTag` of this item (if any).
+    pub fn application_tag(&self) -> Option<AttrApplicationTag> {
+        self.attrs.find(AttrKind::ApplicationTag).map(|tag| {
+            AttrApplicationTag(unsafe {
+                CFData::wrap_under_get_rule(tag.as_CFTypeRef() as CFDataRef)
+            })
+        })
+    }
+
+    /// Get the `AttrGeneric` data of this item (if any).
+    pub fn generic(&self) -> Option<AttrGeneric> {
+        self.attrs.find(AttrKind::Generic).map(|generic| {
+            AttrGeneric(unsafe { CFData::wrap_under_get_rule(generic.as_CFTypeRef() as CFDataRef) })
+        })
+    }
+
+    /// Is this item synchronized via iCloud Keychain (as opposed to being
+    /// device-local)? Missing attributes are treated as device-local.
+    ///
+    /// Wrapper for the `kSecAttrSynchronizable` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrsynchronizable>
+    pub fn is_synchronizable(&self) -> bool {
+        self.boolean_attribute(AttrKind::Synchronizable)
+            .unwrap_or(false)
+    }
+
+    /// Get when this item is accessible (if known). Items protected by an
+    /// `AccessControl` have their accessibility embedded in it instead.
+    ///
+    /// Wrapper for the `kSecAttrAccessible` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccessible>
+    pub fn accessible(&self) -> Option<AttrAccessible> {
+        self.attrs
+            .find(AttrKind::Accessible)
+            .and_then(|value| AttrAccessible::from_CFType(&value))
+    }
+
+    /// Get the access control policy protecting this item (if any).
+    ///
+    /// Wrapper for the `kSecAttrAccessControl` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccesscontrol>
+    pub fn access_control(&self) -> Option<AccessControl> {
+        self.attrs
+            .find(AttrKind::AccessControl)
+            .and_then(|value| value.downcast::<AccessControl>())
+    }
+
+    /// Get the token this item is stored in (e.g. the Secure Enclave), if
+    /// it's token-backed.
+    ///
+    /// Wrapper for the `kSecAttrTokenID` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrtokenid>
+    pub fn token_id(&self) -> Option<AttrTokenId> {
+        self.attrs
+            .find(AttrKind::TokenId)
+            .and_then(|value| value.downcast::<CFString>())
+            .map(|token_id| AttrTokenId::from(&token_id))
+    }
+
+    /// Get the access group this item belongs to (if any), which determines
+    /// which applications can share it.
+    ///
+    /// Wrapper for the `kSecAttrAccessGroup` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccessgroup>
+    pub fn access_group(&self) -> Option<String> {
+        self.attrs
+            .find(unsafe { kSecAttrAccessGroup } as CFTypeRef)
+            .and_then(|value| value.downcast::<CFString>())
+            .map(|group| group.to_string())
+    }
+
+    /// Can this item (i.e. key) be exported from the keychain (if known)?
+    ///
+    /// Wrapper for the `kSecAttrIsExtractable` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrisextractable>
+    pub fn is_extractable(&self) -> Option<bool> {
+        self.boolean_attribute(AttrKind::Extractable)
+    }
+
+    /// Get the persistent reference to this item, i.e. an opaque identifier
+    /// which remains valid across process launches (if it was requested,
+    /// e.g. with `ItemAttributes::find_with_persistent_refs`).
+    pub fn persistent_ref(&self) -> Option<PersistentItemRef> {
+        self.attrs
+            .find(unsafe { kSecValuePersistentRef } as CFTypeRef)
+            .and_then(|value| value.downcast::<CFData>())
+            .map(|data| PersistentItemRef::from_bytes(data.bytes()))
+    }
+
+    /// Get the date this item was created (if available).
+    ///
+    /// Wrapper for the `kSecAttrCreationDate` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcreationdate>
+    pub fn creation_date(&self) -> Option<SystemTime> {
+        self.date_attribute(AttrKind::CreationDate)
+    }
+
+    /// Get the date this item was last modified (if available).
+    ///
+    /// Wrapper for the `kSecAttrModificationDate` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrmodificationdate>
+    pub fn modification_date(&self) -> Option<SystemTime> {
+        self.date_attribute(AttrKind::ModificationDate)
+    }
+
+    /// Get the date this item was created as a `chrono::DateTime`.
+    #[cfg(feature = "chrono")]
+    pub fn creation_datetime(&self) -> Option<DateTime<Utc>> {
+        self.creation_date().map(DateTime::from)
+    }
+
+    /// Get the date this item was last modified as a `chrono::DateTime`.
+    #[cfg(feature = "chrono")]
+    pub fn modification_datetime(&self) -> Option<DateTime<Utc>> {
+        self.modification_date().map(DateTime::from)
+    }
+
+    /// Find the attributes of all items of the given class matching the
+    /// given `Query`, optionally along with references to the items
+    /// themselves (so they can be e.g. deleted).
+    pub(crate) fn find(class: Class, query: &Query, return_refs: bool) -> Result<Vec<Self>, Error> {
+        if return_refs {
+            Self::find_returning(class, query, &[unsafe { kSecReturnRef }])
+        } else {
+            Self::find_returning(class, query, &[])
+        }
+    }
+
+    /// Find the attributes of all items of the given class matching the
+    /// given `Query`, along with the additional results selected by the
+    /// given `kSecReturn*` keys.
+    pub(crate) fn find_returning(
+        class: Class,
+        query: &Query,
+        return_keys: &[CFStringRef],
+    ) -> Result<Vec<Self>, Error> {
+        hooks::run(OperationKind::Find, Some(class), || {
+            let mut params = DictionaryBuilder::from(query);
+            params.add_class(class);
+            params.add_boolean(unsafe { kSecReturnAttributes }, true);
+
+            if params.get(unsafe { kSecMatchLimit }).is_none() {
+                params.add(unsafe { kSecMatchLimit }, &MatchLimit::All.as_CFType());
+            }
+
+            for &key in return_keys {
+                params.add_boolean(key, true);
+            }
+
+            let mut result: CFTypeRef = ptr::null_mut();
+            let status = unsafe {
+                SecItemCopyMatching(
+                    Dictionary::from(params.clone()).as_concrete_TypeRef(),
+                    &mut result,
+                )
+            };
+
+            if let Some(e) = Error::maybe_from_OSStatus(status) {
+                return match e.kind() {
+                    ErrorKind::ItemNotFound => Ok(vec![]),
+                    _ => Err(entitlements::diagnose(e, &params)),
+                };
+            }
+
+            let result = unsafe { CFType::wrap_under_create_rule(result) };
+
+            // A `MatchLimit::One` query returns a single dictionary
+            if !result.instance_of::<CFArray>() {
+                let attrs = unsafe {
+                    Dictionary::wrap_under_get_rule(result.as_CFTypeRef() as CFDictionaryRef)
+                };
+                return Ok(vec![ItemAttributes { class, attrs }]);
+            }
+
+            let items = unsafe {
+                CFArray::<Dictionary>::wrap_under_get_rule(result.as_CFTypeRef() as CFArrayRef)
+            };
+            Ok(items
+                .iter()
+                .map(|attrs| ItemAttributes {
+                    class,
+                    attrs: unsafe { Dictionary::wrap_under_get_rule(attrs.as_concrete_TypeRef()) },
+                })
+                .collect())
+        })
+    }
+
+    /// Delete the item these attributes belong to, which must have been
+    /// found with `return_refs` set.
+    ///
+    /// If the `DeletionMode` is `Tombstone`, the item is tombstoned instead
+    /// unless it already has been, in which case it's destroyed.
+    pub(crate) fn delete(&self) -> Result<(), Error> {
+        if deletion_mode() == DeletionMode::Tombstone && !self.is_tombstone() {
+            hooks::run(OperationKind::Delete, Some(self.class()), || {
+                tombstone::bury(self)
+            })
+        } else {
+            self.destroy()
+        }
+    }
+
+    /// Permanently destroy the item these attributes belong to, which must
+    /// have been found with `return_refs` set.
+    ///
+    /// Wrapper for the `SecItemDelete` function. See:
+    /// <https://developer.apple.com/documentation/security/1395547-secitemdelete>
+    pub(crate) fn destroy(&self) -> Result<(), Error> {
+        hooks::run(OperationKind::Delete, Some(self.class()), || {
+            let status = unsafe { SecItemDelete(self.item_query()?.as_concrete_TypeRef()) };
+
+            match Error::maybe_from_OSStatus(status) {
+                Some(e) => Err(e),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Update the attributes of the item these attributes belong to, which
+    /// must have been found with `return_refs` set.
+    ///
+    /// Wrapper for the `SecItemUpdate` function. See:
+    /// <https://developer.apple.com/documentation/security/1393617-secitemupdate>
+    pub(crate) fn update(&self, attrs: DictionaryBuilder) -> Result<(), Error> {
+        hooks::run(OperationKind::Update, Some(self.class()), || {
+            super::validate_update(&attrs, self.is_synchronizable())?;
+
+            let status = unsafe {
+                SecItemUpdate(
+                    self.item_query()?.as_concrete_TypeRef(),
+                    Dictionary::from(attrs).as_concrete_TypeRef(),
+                )
+            };
+
+            match Error::maybe_from_OSStatus(status) {
+                Some(e) => Err(e),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Get the reference to the item these attributes belong to (if it was
+    /// requested with `kSecReturnRef`)
+    pub(crate) fn value_ref(&self) -> Option<CFType> {
+        self.attrs
+            .find(unsafe { kSecValueRef } as CFTypeRef)
+            .map(|item_ref| (*item_ref).clone())
+    }
+
+    /// Build a query matching exactly the item these attributes belong to
+    fn item_query(&self) -> Result<Dictionary, Error> {
+        let item_ref = self
+            .attrs
+            .find(unsafe { kSecValueRef } as CFTypeRef)
+            .ok_or_else(|| Error::new(ErrorKind::Param, "no reference to item"))?;
+
+        let mut query = DictionaryBuilder::new();
+        query.add_class(self.class);
+        query.add(unsafe { kSecValueRef }, &*item_ref);
+        Ok(Dictionary::from(query))
+    }
+
+    /// Get a `CFString` attribute of this item as a `String`.
+    fn string_attribute(&self, attr_kind: AttrKind) -> Option<String> {
+        self.attrs.find(attr_kind).map(|value| {
+            unsafe { CFString::wrap_under_get_rule(value.as_CFTypeRef() as CFStringRef) }
+                .to_string()
+        })
+    }
+
+    /// Get a boolean attribute of this item, which may be represented as
+    /// either a `CFBoolean` or a `CFNumber`.
+    fn boolean_attribute(&self, attr_kind: AttrKind) -> Option<bool> {
+        self.attrs.find(attr_kind).and_then(|value| {
+            if let Some(boolean) = value.downcast::<CFBoolean>() {
+                Some(bool::from(boolean))
+            } else if let Some(number) = value.downcast::<CFNumber>() {
+                number.to_i64().map(|n| n != 0)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Get a four character code attribute of this item, which may be
+    /// represented as either a `CFNumber` or a `CFString`.
+    fn four_character_code_attribute(&self, attr_kind: AttrKind) -> Option<[u8; 4]> {
+        self.attrs.find(attr_kind).and_then(|value| {
+            if let Some(number) = value.downcast::<CFNumber>() {
+                number.to_i64().map(|n| (n as u32).to_be_bytes())
+            } else if let Some(string) = value.downcast::<CFString>() {
+                let string = string.to_string();
+
+                if string.len() == 4 {
+                    let mut code = [0u8; 4];
+                    code.copy_from_slice(string.as_bytes());
+                    Some(code)
+                } else {
+                    None
+                }
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Get a `CFDate` attribute of this item as a `SystemTime`.
+    fn date_attribute(&self, attr_kind: AttrKind) -> Option<SystemTime> {
+        self.attrs
+            .find(attr_kind)
+            .and_then(|value| value.downcast::<CFDate>())
+            .map(|date| date::system_time_from_CFDate(&date))
+    }
+}
+
+impl Debug for ItemAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ItemAttributes")
+            .field("class", &self.class)
+            .field("account", &self.account())
+            .field("service", &self.service())
+            .field("server", &self.server())
+            .field("label", &self.label())
+            .field("access_group", &self.access_group())
+            .field("synchronizable", &self.is_synchronizable())
+            .field("creation_date", &self.creation_date())
+            .field("modification_date", &self.modification_date())
+            .finish()
+    }
+}
@@ -0,0 +1,205 @@
+//! Counting, updating, and deleting all keychain items matching a query at
+//! once.
+//!
+//! Combined with `Query::item_list`, these operate on an explicit set of
+//! previously enumerated items rather than whatever currently matches the
+//! query's attributes.
+
+use super::{
+    deletion_mode, pages::find_persistent_refs, validate_attrs, Class, DeletionMode,
+    ItemAttributes, ItemChanges, Query,
+};
+use crate::{
+    dictionary::{Dictionary, DictionaryBuilder},
+    error::{Error, ErrorKind},
+    ffi::*,
+    keychain::{
+        entitlements,
+        hooks::{self, OperationKind},
+    },
+};
+use core_foundation::{array::CFArray, base::TCFType};
+use std::{borrow::Borrow, os::raw::c_void};
+
+/// Count the items of the given class matching the given `Query`, without
+/// fetching their attributes or secret data.
+///
+/// If the query has a `MatchLimit`, at most that many items are counted.
+pub fn count_matching<Q: Borrow<Query>>(class: Class, query: Q) -> Result<usize, Error> {
+    if has_empty_item_list(&DictionaryBuilder::from(query.borrow())) {
+        return Ok(0);
+    }
+
+    Ok(find_persistent_refs(class, query.borrow())?.len())
+}
+
+/// Apply the given changes to all items of the given class matching the
+/// given `Query`.
+///
+/// Returns an error with a kind of `ErrorKind::ItemNotFound` if no items
+/// match the query.
+///
+/// Wrapper for the `SecItemUpdate` function. See:
+/// <https://developer.apple.com/documentation/security/1393617-secitemupdate>
+pub fn update_matching<Q: Borrow<Query>>(
+    class: Class,
+    query: Q,
+    changes: &ItemChanges,
+) -> Result<(), Error> {
+    hooks::run(OperationKind::Update, Some(class), || {
+        let attrs = changes.attrs();
+        validate_attrs(attrs)?;
+
+        let params = matching_query(class, query.borrow())?;
+
+        let status = unsafe {
+            SecItemUpdate(
+                Dictionary::from(params).as_concrete_TypeRef(),
+                Dictionary::from(attrs.clone()).as_concrete_TypeRef(),
+            )
+        };
+
+        match Error::maybe_from_OSStatus(status) {
+            Some(e) => Err(entitlements::diagnose(e, attrs)),
+            None => Ok(()),
+        }
+    })
+}
+
+/// Delete all items of the given class matching the given `Query`.
+///
+/// If the `DeletionMode` is `Tombstone`, each matching item is tombstoned
+/// individually instead.
+///
+/// Returns an error with a kind of `ErrorKind::ItemNotFound` if no items
+/// match the query.
+///
+/// Wrapper for the `SecItemDelete` function. See:
+/// <https://developer.apple.com/documentation/security/1395547-secitemdelete>
+pub fn delete_matching<Q: Borrow<Query>>(class: Class, query: Q) -> Result<(), Error> {
+    let params = matching_query(class, query.borrow())?;
+
+    if deletion_mode() == DeletionMode::Tombstone {
+        let items = ItemAttributes::find(class, query.borrow(), true)?;
+
+        if items.is_empty() {
+            return Err(Error::new(ErrorKind::ItemNotFound, "no items match query"));
+        }
+
+        return items.iter().try_for_each(ItemAttributes::delete);
+    }
+
+    hooks::run(OperationKind::Delete, Some(class), || {
+        let status =
+            unsafe { SecItemDelete(Dictionary::from(params.clone()).as_concrete_TypeRef()) };
+
+        match Error::maybe_from_OSStatus(status) {
+            Some(e) => Err(entitlements::diagnose(e, &params)),
+            None => Ok(()),
+        }
+    })
+}
+
+/// Delete all items of the given class matching the given `Query`, whether
+/// they're device-local or synchronized via iCloud Keychain.
+///
+/// Queries only match device-local items unless told otherwise (see
+/// `Query::synchronizable_any`), so `delete_matching` leaves synchronized
+/// copies of items behind. Deleting a synchronized item removes it from
+/// all of the user's devices.
+///
+/// Returns an error with a kind of `ErrorKind::ItemNotFound` if no items
+/// match the query.
+pub fn delete_matching_everywhere<Q: Borrow<Query>>(class: Class, query: Q) -> Result<(), Error> {
+    delete_matching(class, query.borrow().clone().synchronizable_any())
+}
+
+/// Items removed by `delete_matching_with_report`.
+#[derive(Debug, Default)]
+pub struct DeletionReport {
+    deleted: Vec<ItemAttributes>,
+    failed: Vec<(ItemAttributes, Error)>,
+}
+
+impl DeletionReport {
+    /// Get the number of items which matched the query, i.e. which were
+    /// either deleted or failed to be deleted.
+    pub fn matched(&self) -> usize {
+        self.deleted.len() + self.failed.len()
+    }
+
+    /// Get the attributes of the items which were deleted (or tombstoned).
+    pub fn deleted(&self) -> &[ItemAttributes] {
+        &self.deleted
+    }
+
+    /// Get the attributes of the items which couldn't be deleted, along
+    /// with the error deleting each of them failed with.
+    pub fn failed(&self) -> &[(ItemAttributes, Error)] {
+        &self.failed
+    }
+
+    /// Were all matching items deleted?
+    pub fn is_complete(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Delete all items of the given class matching the given `Query`,
+/// reporting exactly which items were removed.
+///
+/// Unlike `delete_matching`, the matching items are enumerated (attributes
+/// only) first and then deleted one at a time, so a query which matches
+/// nothing results in an empty report rather than an error, and a failure
+/// to delete one item doesn't prevent the others from being deleted.
+///
+/// Errors are only returned if the matching items can't be enumerated.
+pub fn delete_matching_with_report<Q: Borrow<Query>>(
+    class: Class,
+    query: Q,
+) -> Result<DeletionReport, Error> {
+    let mut report = DeletionReport::default();
+
+    if has_empty_item_list(&DictionaryBuilder::from(query.borrow())) {
+        return Ok(report);
+    }
+
+    for item in ItemAttributes::find(class, query.borrow(), true)? {
+        match item.delete() {
+            Ok(()) => report.deleted.push(item),
+            Err(e) => report.failed.push((item, e)),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Build the query for an update or delete of the matching items.
+///
+/// An empty `kSecUseItemList` may be disregarded by Keychain Services,
+/// widening the operation to every item of the class, so it's rejected here
+/// as matching nothing.
+fn matching_query(class: Class, query: &Query) -> Result<DictionaryBuilder, Error> {
+    let mut query = DictionaryBuilder::from(query);
+    query.add_class(class);
+
+    // Match limits only apply to lookups
+    query.remove(unsafe { kSecMatchLimit });
+
+    if has_empty_item_list(&query) {
+        return Err(Error::new(
+            ErrorKind::ItemNotFound,
+            "empty item list matches no items",
+        ));
+    }
+
+    Ok(query)
+}
+
+/// Does the given query have a `kSecUseItemList` with no items in it?
+fn has_empty_item_list(query: &DictionaryBuilder) -> bool {
+    query
+        .get(unsafe { kSecUseItemList })
+        .and_then(|value| value.downcast::<CFArray<*const c_void>>())
+        .is_some_and(|items| items.len() == 0)
+}
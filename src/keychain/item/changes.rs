@@ -0,0 +1,111 @@
+//! Polling-based detection of changes to keychain items.
+//!
+//! Keychain Services has no API for being notified when items in the
+//! data protection keychain change, so `ChangeDetector` periodically
+//! queries the keychain and diffs the persistent references and
+//! modification dates of the matching items against the previous results.
+//! This allows applications to react to changes made by other processes
+//! sharing the same access group.
+
+use super::{Class, ItemAttributes, PersistentItemRef, Query};
+use crate::error::Error;
+use std::{
+    collections::BTreeMap,
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// Change to a keychain item detected by a `ChangeDetector`.
+#[derive(Debug)]
+pub enum ItemChange {
+    /// Item was added
+    Added(ItemAttributes),
+
+    /// Item was modified (i.e. its modification date changed)
+    Updated(ItemAttributes),
+
+    /// Item with the given persistent reference was removed
+    Removed(PersistentItemRef),
+}
+
+/// Detects items being added, updated, or removed by polling the results
+/// of a `Query`.
+#[derive(Debug)]
+pub struct ChangeDetector {
+    class: Class,
+    query: Query,
+    snapshot: BTreeMap<PersistentItemRef, Option<SystemTime>>,
+}
+
+impl ChangeDetector {
+    /// Create a detector for changes to items of the given class matching
+    /// the given `Query`, taking an initial snapshot of them (i.e. items
+    /// which already exist won't be reported as added).
+    pub fn new(class: Class, query: Query) -> Result<Self, Error> {
+        let mut detector = ChangeDetector {
+            class,
+            query,
+            snapshot: BTreeMap::new(),
+        };
+
+        detector.poll()?;
+        Ok(detector)
+    }
+
+    /// Query the keychain, returning the changes since the last poll (or
+    /// since the detector was created).
+    ///
+    /// Changes which happen within the same second may go unnoticed as
+    /// keychain modification dates have a resolution of one second.
+    pub fn poll(&mut self) -> Result<Vec<ItemChange>, Error> {
+        let mut snapshot = BTreeMap::new();
+        let mut changes = vec![];
+
+        for item in ItemAttributes::find_with_persistent_refs(self.class, &self.query)? {
+            // Items are only tracked by persistent reference
+            let persistent_ref = match item.persistent_ref() {
+                Some(persistent_ref) => persistent_ref,
+                None => continue,
+            };
+
+            let modification_date = item.modification_date();
+
+            match self.snapshot.remove(&persistent_ref) {
+                None => changes.push(ItemChange::Added(item)),
+                Some(previous) if previous != modification_date => {
+                    changes.push(ItemChange::Updated(item))
+                }
+                Some(_) => (),
+            }
+
+            snapshot.insert(persistent_ref, modification_date);
+        }
+
+        // Anything remaining in the old snapshot is no longer present
+        for persistent_ref in self.snapshot.keys() {
+            changes.push(ItemChange::Removed(persistent_ref.clone()));
+        }
+
+        self.snapshot = snapshot;
+        Ok(changes)
+    }
+
+    /// Poll the keychain at the given interval, passing the changes found
+    /// by each poll (which may be none) to `on_changes` until it returns
+    /// `false` or an error occurs.
+    ///
+    /// This blocks the calling thread, so it is typically run in a thread
+    /// dedicated to it.
+    pub fn watch<F>(&mut self, interval: Duration, mut on_changes: F) -> Result<(), Error>
+    where
+        F: FnMut(Vec<ItemChange>) -> bool,
+    {
+        loop {
+            thread::sleep(interval);
+
+            if !on_changes(self.poll()?) {
+                return Ok(());
+            }
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::ffi::*;
 use core_foundation::{base::TCFType, string::CFString};
+use std::fmt::{self, Display};
 
 /// Classes of keychain items supported by Keychain Services
 /// (not to be confused with `SecAttrClass` or `SecType`)
@@ -7,6 +8,7 @@ use core_foundation::{base::TCFType, string::CFString};
 /// Wrapper for the `kSecClass` attribute key. See:
 /// <https://developer.apple.com/documentation/security/ksecclass>
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Class {
     /// Generic password items.
     ///
@@ -61,6 +63,14 @@ impl Class {
 
         Some(result)
     }
+
+    /// Get the `FourCharacterCode` identifying this class in the legacy
+    /// `SecKeychainItem` API (i.e. its `SecItemClass`)
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn as_tag(self) -> FourCharacterCode {
+        FourCharacterCode::from(&self.as_CFString())
+    }
+
     /// Get `CFString` containing the `kSecClass` dictionary value for
     /// this particular `SecClass`.
     pub fn as_CFString(self) -> CFString {
@@ -81,3 +91,15 @@ impl From<FourCharacterCode> for Class {
         Self::from_tag(tag).unwrap_or_else(|| panic!("invalid SecItemClass tag: {:?}", tag))
     }
 }
+
+impl Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Class::GenericPassword => "generic password",
+            Class::InternetPassword => "Internet password",
+            Class::Certificate => "certificate",
+            Class::Key => "key",
+            Class::Identity => "identity",
+        })
+    }
+}
@@ -0,0 +1,254 @@
+//! The classic `SecKeychainFindGenericPassword` and
+//! `SecKeychainFindInternetPassword` functions for file-based keychains.
+//!
+//! These search the same way as the `security find-generic-password` and
+//! `security find-internet-password` commands: attributes which aren't
+//! given match any value, and if no keychains are given, the user's
+//! keychain search list (e.g. the login keychain) is searched.
+
+use super::{Class, GenericPassword, InternetPassword, Item, PasswordData};
+use crate::{
+    attr::{AttrAuthenticationType, AttrPort, AttrProtocol},
+    error::{Error, ErrorKind},
+    ffi::*,
+    keychain::{
+        hooks::{self, OperationKind},
+        Keychain,
+    },
+};
+use core_foundation::{
+    array::CFArray,
+    base::{OSStatus, TCFType},
+};
+use std::{
+    os::raw::{c_char, c_void},
+    ptr, slice,
+};
+
+/// Attributes to search for with `InternetPassword::find_legacy`. Any which
+/// aren't set match any value.
+#[derive(Clone, Debug, Default)]
+pub struct LegacyInternetPasswordQuery {
+    server: Option<String>,
+    security_domain: Option<String>,
+    account: Option<String>,
+    path: Option<String>,
+    port: Option<AttrPort>,
+    protocol: Option<AttrProtocol>,
+    authentication_type: Option<AttrAuthenticationType>,
+}
+
+impl LegacyInternetPasswordQuery {
+    /// Create a new query matching any Internet password
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match passwords for the given server
+    pub fn server(mut self, server: &str) -> Self {
+        self.server = Some(server.to_owned());
+        self
+    }
+
+    /// Match passwords for the given security domain (i.e. realm)
+    pub fn security_domain(mut self, security_domain: &str) -> Self {
+        self.security_domain = Some(security_domain.to_owned());
+        self
+    }
+
+    /// Match passwords for the given account
+    pub fn account(mut self, account: &str) -> Self {
+        self.account = Some(account.to_owned());
+        self
+    }
+
+    /// Match passwords for the given path
+    pub fn path(mut self, path: &str) -> Self {
+        self.path = Some(path.to_owned());
+        self
+    }
+
+    /// Match passwords for the given port
+    pub fn port<P: Into<AttrPort>>(mut self, port: P) -> Self {
+        self.port = Some(port.into());
+        self
+    }
+
+    /// Match passwords for the given protocol
+    pub fn protocol(mut self, protocol: AttrProtocol) -> Self {
+        self.protocol = Some(protocol);
+        self
+    }
+
+    /// Match passwords using the given authentication type
+    pub fn authentication_type(mut self, authentication_type: AttrAuthenticationType) -> Self {
+        self.authentication_type = Some(authentication_type);
+        self
+    }
+}
+
+impl GenericPassword {
+    /// Find a generic password with the given service and/or account (or if
+    /// neither is given, any generic password) in the given keychains,
+    /// returning it along with its secret data, or `Ok(None)` if no such
+    /// password exists.
+    ///
+    /// If no keychains are given, the user's keychain search list is
+    /// searched.
+    ///
+    /// Wrapper for the `SecKeychainFindGenericPassword` function. See:
+    /// <https://developer.apple.com/documentation/security/seckeychainfindgenericpassword(_:_:_:_:_:_:_:_:)>
+    pub fn find_legacy(
+        keychains: &[Keychain],
+        service: Option<&str>,
+        account: Option<&str>,
+    ) -> Result<Option<(Self, PasswordData)>, Error> {
+        hooks::run(OperationKind::Find, Some(Class::GenericPassword), || {
+            let search_list = search_list(keychains);
+            let (service_length, service) = string_arg(service);
+            let (account_length, account) = string_arg(account);
+
+            let mut length = 0;
+            let mut data: *mut c_void = ptr::null_mut();
+            let mut item: ItemRef = ptr::null_mut();
+
+            let status = unsafe {
+                SecKeychainFindGenericPassword(
+                    search_list
+                        .as_ref()
+                        .map_or(ptr::null(), TCFType::as_CFTypeRef),
+                    service_length,
+                    service,
+                    account_length,
+                    account,
+                    &mut length,
+                    &mut data,
+                    &mut item,
+                )
+            };
+
+            found_password(status, item, length, data)
+                .map(|found| found.map(|(item, data)| (GenericPassword(item), data)))
+        })
+    }
+}
+
+impl InternetPassword {
+    /// Find an Internet password matching the given query in the given
+    /// keychains, returning it along with its secret data, or `Ok(None)` if
+    /// no such password exists.
+    ///
+    /// If no keychains are given, the user's keychain search list is
+    /// searched.
+    ///
+    /// Wrapper for the `SecKeychainFindInternetPassword` function. See:
+    /// <https://developer.apple.com/documentation/security/seckeychainfindinternetpassword(_:_:_:_:_:_:_:_:_:_:_:_:_:_:_:)>
+    pub fn find_legacy(
+        keychains: &[Keychain],
+        query: &LegacyInternetPasswordQuery,
+    ) -> Result<Option<(Self, PasswordData)>, Error> {
+        hooks::run(OperationKind::Find, Some(Class::InternetPassword), || {
+            let search_list = search_list(keychains);
+            let (server_length, server) = string_arg(query.server.as_deref());
+            let (domain_length, domain) = string_arg(query.security_domain.as_deref());
+            let (account_length, account) = string_arg(query.account.as_deref());
+            let (path_length, path) = string_arg(query.path.as_deref());
+
+            // Zero matches any protocol or authentication type
+            let protocol = query
+                .protocol
+                .map_or(FourCharacterCode::from(0), |protocol| {
+                    FourCharacterCode::from(&protocol.as_CFString())
+                });
+            let authentication_type = query
+                .authentication_type
+                .map_or(FourCharacterCode::from(0), |auth_type| {
+                    FourCharacterCode::from(&auth_type.as_CFString())
+                });
+
+            let mut length = 0;
+            let mut data: *mut c_void = ptr::null_mut();
+            let mut item: ItemRef = ptr::null_mut();
+
+            let status = unsafe {
+                SecKeychainFindInternetPassword(
+                    search_list
+                        .as_ref()
+                        .map_or(ptr::null(), TCFType::as_CFTypeRef),
+                    server_length,
+                    server,
+                    domain_length,
+                    domain,
+                    account_length,
+                    account,
+                    path_length,
+                    path,
+                    query.port.map_or(0, |port| port.0),
+                    protocol,
+                    authentication_type,
+                    &mut length,
+                    &mut data,
+                    &mut item,
+                )
+            };
+
+            found_password(status, item, length, data)
+                .map(|found| found.map(|(item, data)| (InternetPassword(item), data)))
+        })
+    }
+}
+
+/// Build the `keychainOrArray` argument for the given keychains, which is
+/// `NULL` (i.e. the search list) if there are none
+fn search_list(keychains: &[Keychain]) -> Option<CFArray<Keychain>> {
+    if keychains.is_empty() {
+        None
+    } else {
+        Some(CFArray::from_CFTypes(keychains))
+    }
+}
+
+/// Build a length and pointer argument for the given string, which are zero
+/// and `NULL` (i.e. matching anything) if there is none
+fn string_arg(string: Option<&str>) -> (u32, *const c_char) {
+    match string {
+        Some(string) => (string.len() as u32, string.as_ptr() as *const c_char),
+        None => (0, ptr::null()),
+    }
+}
+
+/// Interpret the result of a `SecKeychainFind*Password` call, taking
+/// ownership of the returned item and copying (then freeing) its data
+fn found_password(
+    status: OSStatus,
+    item: ItemRef,
+    length: u32,
+    data: *mut c_void,
+) -> Result<Option<(Item, PasswordData)>, Error> {
+    if let Some(e) = Error::maybe_from_OSStatus(status) {
+        return match e.kind() {
+            ErrorKind::ItemNotFound => Ok(None),
+            _ => Err(e),
+        };
+    }
+
+    let item = unsafe { Item::wrap_under_create_rule(item) };
+
+    if data.is_null() {
+        return Err(Error::new(
+            ErrorKind::MissingEntitlement,
+            "SecKeychainFind*Password refused to return data",
+        ));
+    }
+
+    let password = PasswordData::new(
+        unsafe { slice::from_raw_parts(data as *const u8, length as usize) }.to_vec(),
+    );
+
+    let status = unsafe { SecKeychainItemFreeContent(ptr::null_mut(), data) };
+
+    match Error::maybe_from_OSStatus(status) {
+        Some(e) => Err(e),
+        None => Ok(Some((item, password))),
+    }
+}
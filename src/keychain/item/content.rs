@@ -0,0 +1,208 @@
+//! Reading the contents of keychain items via `SecItemCopyMatching`.
+//!
+//! These replace the `SecKeychainItemCopyContent`-based accessors when the
+//! `legacy-keychain` feature is disabled.
+
+use super::{Class, Item, MatchLimit};
+use crate::{
+    attr::AttrKind,
+    date,
+    dictionary::{Dictionary, DictionaryBuilder},
+    error::{Error, ErrorKind},
+    ffi::*,
+};
+use core_foundation::{
+    base::{CFType, CFTypeRef, TCFType},
+    boolean::CFBoolean,
+    data::CFData,
+    date::CFDate,
+    dictionary::{CFDictionary, CFDictionaryRef},
+    number::CFNumber,
+    string::{CFString, CFStringRef},
+};
+use std::{ptr, time::SystemTime};
+
+/// Classes an item may belong to, in the order they're tried
+const CLASSES: &[Class] = &[
+    Class::GenericPassword,
+    Class::InternetPassword,
+    Class::Key,
+    Class::Certificate,
+    Class::Identity,
+];
+
+impl Item {
+    /// Get the class of this item
+    pub fn class(&self) -> Class {
+        self.try_class()
+            .expect("keychain item doesn't belong to any known class")
+    }
+
+    /// Get the class of this item, failing if it's no longer valid (e.g.
+    /// because it has been deleted)
+    pub(crate) fn try_class(&self) -> Result<Class, Error> {
+        for &class in CLASSES {
+            match self.copy_matching(class, None) {
+                Ok(_) => return Ok(class),
+                Err(e) => match e.kind() {
+                    ErrorKind::ItemNotFound => continue,
+                    _ => return Err(e),
+                },
+            }
+        }
+
+        Err(Error::new(
+            ErrorKind::ItemNotFound,
+            "keychain item doesn't belong to any known class",
+        ))
+    }
+
+    /// Get the raw data associated with this keychain item
+    pub(crate) fn data(&self) -> Result<Vec<u8>, Error> {
+        let result = self.copy_matching(self.try_class()?, Some(unsafe { kSecReturnData }))?;
+
+        result
+            .downcast::<CFData>()
+            .map(|data| data.bytes().to_vec())
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::MissingEntitlement,
+                    "SecItemCopyMatching refused to return data",
+                )
+            })
+    }
+
+    /// Get a date attribute of this item as a `SystemTime`.
+    pub(super) fn date_attribute(&self, attr_kind: AttrKind) -> Result<SystemTime, Error> {
+        self.attribute_value(attr_kind)?
+            .downcast::<CFDate>()
+            .map(|date| date::system_time_from_CFDate(&date))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::Decode,
+                    &format!("malformed date in attribute {:?}", attr_kind),
+                )
+            })
+    }
+
+    /// Get the raw data of an attribute of this item, encoded the same way
+    /// `SecKeychainItemCopyContent` would return it.
+    pub(crate) fn attribute_data(&self, attr_kind: AttrKind) -> Result<Vec<u8>, Error> {
+        let value = self.attribute_value(attr_kind)?;
+
+        if let Some(string) = value.downcast::<CFString>() {
+            Ok(string.to_string().into_bytes())
+        } else if let Some(data) = value.downcast::<CFData>() {
+            Ok(data.bytes().to_vec())
+        } else if let Some(boolean) = value.downcast::<CFBoolean>() {
+            Ok(vec![bool::from(boolean) as u8])
+        } else if let Some(number) = value.downcast::<CFNumber>() {
+            number
+                .to_i64()
+                .map(|n| (n as u32).to_ne_bytes().to_vec())
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::Decode,
+                        &format!("malformed number in attribute {:?}", attr_kind),
+                    )
+                })
+        } else {
+            Err(Error::new(
+                ErrorKind::Decode,
+                &format!("unsupported value type for attribute {:?}", attr_kind),
+            ))
+        }
+    }
+
+    /// Get a four character code attribute of this item. Depending on the
+    /// attribute, these are returned either as numbers (e.g. creator codes)
+    /// or as strings (e.g. protocols).
+    pub(super) fn four_character_code_attribute(
+        &self,
+        attr_kind: AttrKind,
+    ) -> Result<[u8; 4], Error> {
+        let value = self.attribute_value(attr_kind)?;
+
+        let code = if let Some(string) = value.downcast::<CFString>() {
+            let bytes = string.to_string().into_bytes();
+
+            if bytes.len() == 4 {
+                let mut code = [0u8; 4];
+                code.copy_from_slice(&bytes);
+                Some(code)
+            } else {
+                None
+            }
+        } else if let Some(number) = value.downcast::<CFNumber>() {
+            number.to_i64().map(|n| (n as u32).to_be_bytes())
+        } else {
+            None
+        };
+
+        code.ok_or_else(|| {
+            Error::new(
+                ErrorKind::Decode,
+                &format!("malformed {:?} attribute", attr_kind),
+            )
+        })
+    }
+
+    /// Get the value of an attribute of this item.
+    fn attribute_value(&self, attr_kind: AttrKind) -> Result<CFType, Error> {
+        let class = self.try_class()?;
+        let result = self.copy_matching(class, Some(unsafe { kSecReturnAttributes }))?;
+
+        if !result.instance_of::<CFDictionary>() {
+            return Err(Error::new(
+                ErrorKind::Decode,
+                "SecItemCopyMatching returned malformed attributes",
+            ));
+        }
+
+        let attrs =
+            unsafe { Dictionary::wrap_under_get_rule(result.as_CFTypeRef() as CFDictionaryRef) };
+
+        attrs
+            .find(attr_kind)
+            .map(|value| (*value).clone())
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NoSuchAttr,
+                    &format!("missing attribute {:?}", attr_kind),
+                )
+            })
+    }
+
+    /// Look this item up by reference as an item of the given class,
+    /// returning the result selected by the given `kSecReturn*` key (or the
+    /// item itself if none is given).
+    fn copy_matching(
+        &self,
+        class: Class,
+        return_key: Option<CFStringRef>,
+    ) -> Result<CFType, Error> {
+        let mut query = DictionaryBuilder::new();
+        query.add_class(class);
+        query.add(unsafe { kSecValueRef }, self);
+        query.add(unsafe { kSecMatchLimit }, &MatchLimit::One.as_CFType());
+        query.add_boolean(return_key.unwrap_or(unsafe { kSecReturnRef }), true);
+
+        let mut result: CFTypeRef = ptr::null_mut();
+        let status = unsafe {
+            SecItemCopyMatching(Dictionary::from(query).as_concrete_TypeRef(), &mut result)
+        };
+
+        if let Some(e) = Error::maybe_from_OSStatus(status) {
+            return Err(e);
+        }
+
+        if result.is_null() {
+            return Err(Error::new(
+                ErrorKind::ItemNotFound,
+                "SecItemCopyMatching returned no result",
+            ));
+        }
+
+        Ok(unsafe { CFType::wrap_under_create_rule(result) })
+    }
+}
@@ -0,0 +1,174 @@
+//! Copying items into another keychain or access group, e.g. to migrate
+//! credentials from a legacy (i.e. file-based) keychain into the data
+//! protection keychain.
+
+use super::{validate_attrs, Class, Item, MatchLimit};
+use crate::{
+    attr::AttrKind,
+    dictionary::{Dictionary, DictionaryBuilder},
+    error::{Error, ErrorKind},
+    ffi::*,
+    keychain::{
+        entitlements,
+        hooks::{self, OperationKind},
+        policy::{self, Subject},
+        Keychain,
+    },
+};
+use core_foundation::{
+    base::{CFTypeRef, TCFType},
+    data::{CFData, CFDataRef},
+    dictionary::CFDictionaryRef,
+};
+use std::ptr;
+
+/// Attributes carried over to copies of items. Others (e.g. the creation
+/// date) are assigned anew by the destination.
+const COPIED_ATTRS: &[AttrKind] = &[
+    AttrKind::Account,
+    AttrKind::Service,
+    AttrKind::Server,
+    AttrKind::Protocol,
+    AttrKind::Port,
+    AttrKind::Path,
+    AttrKind::SecurityDomain,
+    AttrKind::Label,
+    AttrKind::Comment,
+    AttrKind::Description,
+    AttrKind::Creator,
+    AttrKind::ItemType,
+    AttrKind::Invisible,
+    AttrKind::Negative,
+    AttrKind::Synchronizable,
+    AttrKind::Accessible,
+    AttrKind::AccessControl,
+];
+
+/// Where to copy an item with `Item::copy_to`.
+#[derive(Copy, Clone, Debug)]
+pub enum ItemDestination<'a> {
+    /// A (legacy, file-based) keychain
+    Keychain(&'a Keychain),
+
+    /// An access group of the data protection keychain, which the
+    /// application must be entitled to use
+    AccessGroup(&'a str),
+}
+
+impl Item {
+    /// Copy this item (i.e. its attributes and secret data) into the given
+    /// destination, leaving the original in place.
+    ///
+    /// Reading the secret data prompts the user to allow access to it if
+    /// the item is protected. Only passwords can be copied: other items
+    /// return an error with a kind of `ErrorKind::Param`.
+    ///
+    /// Wrapper for the `SecItemCopyMatching` and `SecItemAdd` functions. See:
+    /// <https://developer.apple.com/documentation/security/1398306-secitemcopymatching>
+    /// <https://developer.apple.com/documentation/security/1401659-secitemadd>
+    pub fn copy_to(&self, destination: ItemDestination) -> Result<(), Error> {
+        let class = self.class();
+
+        match class {
+            Class::GenericPassword | Class::InternetPassword => (),
+            other => {
+                return Err(Error::new(
+                    ErrorKind::Param,
+                    &format!("can't copy items of class {}", other),
+                ))
+            }
+        }
+
+        let mut query = DictionaryBuilder::new();
+        query.add(unsafe { kSecValueRef }, self);
+
+        let attrs = copy_attrs(class, &find_with_data(class, query)?);
+
+        match destination {
+            ItemDestination::Keychain(keychain) => keychain.add_item(attrs).map(|_| ()),
+            ItemDestination::AccessGroup(access_group) => {
+                add_to_access_group(attrs, access_group).map(|_| ())
+            }
+        }
+    }
+}
+
+/// Fetch the attributes of the single item of the given class matched by
+/// the given query along with its secret data.
+pub(crate) fn find_with_data(
+    class: Class,
+    mut query: DictionaryBuilder,
+) -> Result<Dictionary, Error> {
+    hooks::run(OperationKind::Find, Some(class), || {
+        query.add_class(class);
+        // Exactly one dictionary is expected back, whatever the query's limit
+        query.remove(unsafe { kSecMatchLimit });
+        query.add(unsafe { kSecMatchLimit }, &MatchLimit::One.as_CFType());
+        query.add_boolean(unsafe { kSecReturnAttributes }, true);
+        query.add_boolean(unsafe { kSecReturnData }, true);
+
+        let mut result: CFTypeRef = ptr::null_mut();
+        let status = unsafe {
+            SecItemCopyMatching(
+                Dictionary::from(query.clone()).as_concrete_TypeRef(),
+                &mut result,
+            )
+        };
+
+        match Error::maybe_from_OSStatus(status) {
+            Some(e) => Err(entitlements::diagnose(e, &query)),
+            None => Ok(unsafe { Dictionary::wrap_under_create_rule(result as CFDictionaryRef) }),
+        }
+    })
+}
+
+/// Build the attributes for adding a copy of an item from its attributes
+/// and secret data (as fetched by `find_with_data`).
+pub(crate) fn copy_attrs(class: Class, source: &Dictionary) -> DictionaryBuilder {
+    let mut attrs = DictionaryBuilder::new();
+    attrs.add_class(class);
+
+    for &kind in COPIED_ATTRS {
+        if let Some(value) = source.find(kind) {
+            attrs.add(kind, &*value);
+        }
+    }
+
+    if let Some(data) = source.find(unsafe { kSecValueData } as CFTypeRef) {
+        attrs.add(unsafe { kSecValueData }, &*data);
+    }
+
+    attrs
+}
+
+/// Add an item with the given attributes to the given access group of the
+/// data protection keychain, returning its persistent reference.
+///
+/// Wrapper for the `SecItemAdd` function with `kSecUseDataProtectionKeychain`.
+/// See: <https://developer.apple.com/documentation/security/ksecusedataprotectionkeychain>
+pub(crate) fn add_to_access_group(
+    mut attrs: DictionaryBuilder,
+    access_group: &str,
+) -> Result<CFData, Error> {
+    hooks::run(OperationKind::Add, attrs.class(), || {
+        policy::enforce(&Subject::Item(attrs.class()), &mut attrs)?;
+        validate_attrs(&attrs)?;
+
+        attrs.add_string(unsafe { kSecAttrAccessGroup }, access_group);
+        attrs.add_boolean(unsafe { kSecUseDataProtectionKeychain }, true);
+        attrs.add_boolean(unsafe { kSecReturnPersistentRef }, true);
+
+        let mut result: CFTypeRef = ptr::null_mut();
+        let status = unsafe {
+            SecItemAdd(
+                Dictionary::from(attrs.clone()).as_concrete_TypeRef(),
+                &mut result,
+            )
+        };
+
+        match Error::maybe_from_OSStatus(status) {
+            Some(e) => Err(entitlements::diagnose(e, &attrs)),
+            None => Ok(unsafe { CFData::wrap_under_create_rule(result as CFDataRef) }),
+        }
+    })
+}
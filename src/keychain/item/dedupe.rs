@@ -0,0 +1,125 @@
+//! Detection and cleanup of duplicate keychain items, i.e. several items
+//! with the same identifying attributes (which e.g. buggy app reinstalls
+//! can leave behind).
+
+use super::{Class, ItemAttributes, Query};
+use crate::{attr::AttrKeyClass, error::Error};
+use std::{borrow::Borrow, collections::BTreeMap, time::SystemTime};
+
+/// What `dedupe` should do with the duplicates it finds
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DedupeAction {
+    /// Only report duplicates, leaving the keychain unmodified
+    Report,
+
+    /// Delete all but the newest (i.e. most recently created) item in each
+    /// group of duplicates
+    DeleteOlder,
+}
+
+/// Group of items which share the same identifying attributes.
+#[derive(Debug)]
+pub struct DuplicateGroup {
+    identity: Vec<(&'static str, String)>,
+    items: Vec<ItemAttributes>,
+}
+
+impl DuplicateGroup {
+    /// Get the identifying attributes shared by the items in this group as
+    /// name/value pairs, e.g. `[("service", ..), ("account", ..)]`.
+    pub fn identity(&self) -> &[(&'static str, String)] {
+        &self.identity
+    }
+
+    /// Get the newest (i.e. most recently created) item in this group,
+    /// which is the one kept by `DedupeAction::DeleteOlder`.
+    pub fn newest(&self) -> &ItemAttributes {
+        &self.items[0]
+    }
+
+    /// Get the older items in this group, i.e. the duplicates of the newest
+    /// one, ordered from newest to oldest.
+    pub fn duplicates(&self) -> &[ItemAttributes] {
+        &self.items[1..]
+    }
+
+    /// Get all items in this group, ordered from newest to oldest.
+    pub fn items(&self) -> &[ItemAttributes] {
+        &self.items
+    }
+}
+
+/// Find items of the given class matching the given `Query` which have
+/// the same identifying attributes, and optionally delete all but the
+/// newest of them.
+///
+/// Items are identified by:
+///
+/// - Generic passwords: service and account
+/// - Internet passwords: server and account
+/// - Keys: key class and application tag (untagged keys are skipped)
+/// - Certificates and identities: label
+///
+/// Returns the groups of duplicates found (with the deleted items still
+/// included in the report if `DedupeAction::DeleteOlder` was given).
+pub fn dedupe<Q: Borrow<Query>>(
+    class: Class,
+    query: Q,
+    action: DedupeAction,
+) -> Result<Vec<DuplicateGroup>, Error> {
+    let mut groups: BTreeMap<Vec<(&'static str, String)>, Vec<ItemAttributes>> = BTreeMap::new();
+
+    for item in ItemAttributes::find(class, query.borrow(), action == DedupeAction::DeleteOlder)? {
+        if let Some(identity) = identity(&item) {
+            groups.entry(identity).or_default().push(item);
+        }
+    }
+
+    let mut result = vec![];
+
+    for (identity, mut items) in groups {
+        if items.len() < 2 {
+            continue;
+        }
+
+        // Newest first, treating items without a creation date as oldest
+        items.sort_by_key(|item| item.creation_date().unwrap_or(SystemTime::UNIX_EPOCH));
+        items.reverse();
+
+        if action == DedupeAction::DeleteOlder {
+            for item in &items[1..] {
+                item.delete()?;
+            }
+        }
+
+        result.push(DuplicateGroup { identity, items });
+    }
+
+    Ok(result)
+}
+
+/// Get the identifying attributes of an item, or `None` if it lacks them.
+fn identity(item: &ItemAttributes) -> Option<Vec<(&'static str, String)>> {
+    let identity = match item.class() {
+        Class::GenericPassword => vec![("service", item.service()?), ("account", item.account()?)],
+        Class::InternetPassword => vec![("server", item.server()?), ("account", item.account()?)],
+        Class::Key => {
+            let key_class = match item.key_class()? {
+                AttrKeyClass::Public => "public",
+                AttrKeyClass::Private => "private",
+                AttrKeyClass::Symmetric => "symmetric",
+            };
+
+            let tag = item.application_tag()?;
+            let tag = match tag.as_str() {
+                Ok(tag) => tag.to_owned(),
+                Err(_) => format!("{:?}", tag.as_bytes()),
+            };
+
+            vec![("key_class", key_class.to_owned()), ("tag", tag)]
+        }
+        Class::Certificate | Class::Identity => vec![("label", item.label()?)],
+    };
+
+    Some(identity)
+}
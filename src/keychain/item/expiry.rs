@@ -0,0 +1,92 @@
+//! Expiring secrets, i.e. items tagged with an expiry time (see
+//! `ItemTags::set_expiry`), which are deleted once it has passed so
+//! temporary credentials don't accumulate in the keychain forever.
+//!
+//! Keychain Services doesn't expire items itself, so expired items must be
+//! purged by the application, either periodically with `purge_expired` or
+//! in the background with an `ExpiryPurger`. Items which have already been
+//! tombstoned (see `Tombstone`) are left for `purge_tombstones` to purge.
+
+use super::{Class, ItemAttributes, Query};
+use crate::error::Error;
+use std::{
+    borrow::Borrow,
+    fmt::{self, Debug},
+    sync::mpsc::{self, RecvTimeoutError, Sender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// Delete all expired items of the given classes which match the given
+/// `Query`, returning the attributes of the items which were deleted.
+///
+/// The query is applied to each class in turn, so it should only contain
+/// attributes which are applicable to all of them (e.g. a label).
+pub fn purge_expired<Q: Borrow<Query>>(
+    classes: &[Class],
+    query: Q,
+) -> Result<Vec<ItemAttributes>, Error> {
+    let mut purged = vec![];
+
+    for class in classes {
+        for item in ItemAttributes::find(*class, query.borrow(), true)? {
+            if item.is_expired() && !item.is_tombstone() {
+                item.delete()?;
+                purged.push(item);
+            }
+        }
+    }
+
+    Ok(purged)
+}
+
+/// Background thread which periodically purges all expired items of the
+/// given classes, until it is dropped.
+pub struct ExpiryPurger {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ExpiryPurger {
+    /// Spawn a thread which calls `purge_expired` for items of the given
+    /// classes at the given interval, passing the result of each purge to
+    /// `on_purge` (e.g. to log errors).
+    pub fn spawn<F>(classes: &[Class], interval: Duration, mut on_purge: F) -> Self
+    where
+        F: FnMut(Result<Vec<ItemAttributes>, Error>) + Send + 'static,
+    {
+        let classes = classes.to_vec();
+        let (stop, stopped) = mpsc::channel();
+
+        let thread = thread::spawn(move || loop {
+            on_purge(purge_expired(&classes, Query::new()));
+
+            match stopped.recv_timeout(interval) {
+                Err(RecvTimeoutError::Timeout) => (),
+                _ => return,
+            }
+        });
+
+        ExpiryPurger {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Debug for ExpiryPurger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ExpiryPurger { ... }")
+    }
+}
+
+impl Drop for ExpiryPurger {
+    fn drop(&mut self) {
+        // Dropping the sender wakes the thread, which then exits
+        self.stop.take();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
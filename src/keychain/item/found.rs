@@ -0,0 +1,100 @@
+//! Looking up items of several classes at once, as typed items.
+//!
+//! Keychain Services can only search one class of items at a time, so each
+//! class is searched in turn and the results are combined.
+
+use super::{
+    pages::STORED_CLASSES, Class, GenericPassword, InternetPassword, Item, ItemAttributes, Query,
+};
+use crate::{
+    error::{Error, ErrorKind},
+    keychain::{Certificate, Identity, Key},
+};
+use core_foundation::base::CFType;
+use std::borrow::Borrow;
+
+/// Keychain item of any class, as found by `find_items`.
+#[derive(Debug)]
+pub enum FoundItem {
+    /// Generic password
+    GenericPassword(GenericPassword),
+
+    /// Internet password
+    InternetPassword(InternetPassword),
+
+    /// Certificate
+    Certificate(Certificate),
+
+    /// Cryptographic key
+    Key(Key),
+
+    /// Identity, i.e. a certificate together with its private key
+    Identity(Identity),
+}
+
+impl FoundItem {
+    /// Get the class of this item
+    pub fn class(&self) -> Class {
+        match self {
+            FoundItem::GenericPassword(_) => Class::GenericPassword,
+            FoundItem::InternetPassword(_) => Class::InternetPassword,
+            FoundItem::Certificate(_) => Class::Certificate,
+            FoundItem::Key(_) => Class::Key,
+            FoundItem::Identity(_) => Class::Identity,
+        }
+    }
+
+    /// Wrap the reference to an item of the given class as returned by
+    /// `kSecReturnRef`, if it's of the expected type
+    fn from_ref(class: Class, item_ref: &CFType) -> Option<Self> {
+        match class {
+            Class::GenericPassword => item_ref
+                .downcast::<Item>()
+                .map(|item| FoundItem::GenericPassword(GenericPassword(item))),
+            Class::InternetPassword => item_ref
+                .downcast::<Item>()
+                .map(|item| FoundItem::InternetPassword(InternetPassword(item))),
+            Class::Certificate => item_ref
+                .downcast::<Certificate>()
+                .map(FoundItem::Certificate),
+            Class::Key => item_ref.downcast::<Key>().map(FoundItem::Key),
+            Class::Identity => item_ref.downcast::<Identity>().map(FoundItem::Identity),
+        }
+    }
+}
+
+/// Find all items of the given classes matching the given `Query`, as typed
+/// items.
+///
+/// If no classes are given, passwords, certificates, and keys are searched.
+/// Identities are only found if `Class::Identity` is given explicitly, since
+/// each of them duplicates a certificate and a key.
+///
+/// If the query has a `MatchLimit`, it applies to each class separately.
+pub fn find_items<Q: Borrow<Query>>(classes: &[Class], query: Q) -> Result<Vec<FoundItem>, Error> {
+    let classes = if classes.is_empty() {
+        STORED_CLASSES
+    } else {
+        classes
+    };
+
+    let mut items = vec![];
+
+    for &class in classes {
+        for attrs in ItemAttributes::find(class, query.borrow(), true)? {
+            let item = attrs
+                .value_ref()
+                .and_then(|item_ref| FoundItem::from_ref(class, &item_ref))
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::Decode,
+                        &format!("malformed reference to {} item", class),
+                    )
+                })?;
+
+            items.push(item);
+        }
+    }
+
+    Ok(items)
+}
@@ -0,0 +1,298 @@
+//! Machine-readable inventory of keychain items (i.e. their metadata, never
+//! their secret data) for compliance audits and MDM reporting.
+//!
+//! Inventories can be exported as JSON or as an XML property list, and
+//! (with the `serde` feature) serialized in any other format. Each entry
+//! has the following fields, all of which except `class` are optional:
+//!
+//! - `class`: `generic_password`, `internet_password`, `certificate`, `key`,
+//!   or `identity`
+//! - `label`, `account`, `service`, `server`, `comment`: strings
+//! - `key_class`: `public`, `private`, or `symmetric`
+//! - `application_tag`: lowercase hex
+//! - `created_at`, `modified_at`: seconds since the Unix epoch
+//!
+//! The schema is versioned by `INVENTORY_SCHEMA_VERSION`.
+
+use super::{Class, ItemAttributes, Query};
+use crate::{
+    attr::AttrKeyClass,
+    dictionary::Dictionary,
+    error::{Error, ErrorKind},
+};
+use core_foundation::{
+    array::CFArray,
+    base::{CFType, TCFType},
+    number::CFNumber,
+    propertylist::{self, kCFPropertyListXMLFormat_v1_0},
+    string::CFString,
+};
+use std::{
+    borrow::Borrow,
+    fmt::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Version of the inventory schema, incremented on incompatible changes.
+pub const INVENTORY_SCHEMA_VERSION: u32 = 1;
+
+/// Inventory of the metadata of keychain items.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Inventory {
+    /// Version of the schema this inventory conforms to
+    pub schema_version: u32,
+
+    /// Entries for the items in this inventory
+    pub items: Vec<InventoryEntry>,
+}
+
+/// Metadata of an individual keychain item in an `Inventory`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InventoryEntry {
+    /// Class of the item, e.g. `generic_password`
+    pub class: String,
+
+    /// Label of the item
+    pub label: Option<String>,
+
+    /// Account the item is associated with
+    pub account: Option<String>,
+
+    /// Service the item is associated with
+    pub service: Option<String>,
+
+    /// Server the item is associated with
+    pub server: Option<String>,
+
+    /// Comment on the item
+    pub comment: Option<String>,
+
+    /// Key class, i.e. `public`, `private`, or `symmetric`
+    pub key_class: Option<String>,
+
+    /// Application tag of the item, as lowercase hex
+    pub application_tag: Option<String>,
+
+    /// Creation date of the item, in seconds since the Unix epoch
+    pub created_at: Option<i64>,
+
+    /// Modification date of the item, in seconds since the Unix epoch
+    pub modified_at: Option<i64>,
+}
+
+/// Export an `Inventory` of the items of the given classes which match the
+/// given `Query`.
+///
+/// The query is applied to each class in turn, so it should only contain
+/// attributes which are applicable to all of them (e.g. a label).
+pub fn export_inventory<Q: Borrow<Query>>(classes: &[Class], query: Q) -> Result<Inventory, Error> {
+    let mut items = vec![];
+
+    for class in classes {
+        for item in ItemAttributes::find_all(*class, query.borrow())? {
+            items.push(InventoryEntry::from(&item));
+        }
+    }
+
+    Ok(Inventory {
+        schema_version: INVENTORY_SCHEMA_VERSION,
+        items,
+    })
+}
+
+impl Inventory {
+    /// Serialize this inventory as JSON
+    pub fn to_json(&self) -> String {
+        let mut json = String::new();
+        write!(
+            json,
+            "{{\"schema_version\":{},\"items\":[",
+            self.schema_version
+        )
+        .unwrap();
+
+        for (i, entry) in self.items.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+
+            json.push('{');
+            entry.write_json_fields(&mut json);
+            json.push('}');
+        }
+
+        json.push_str("]}");
+        json
+    }
+
+    /// Serialize this inventory as an XML property list.
+    ///
+    /// Wrapper for the `CFPropertyListCreateData` function. See:
+    /// <https://developer.apple.com/documentation/corefoundation/1429213-cfpropertylistcreatedata>
+    pub fn to_plist(&self) -> Result<Vec<u8>, Error> {
+        let items: Vec<CFType> = self
+            .items
+            .iter()
+            .map(|entry| {
+                let pairs: Vec<(CFType, CFType)> = entry
+                    .fields()
+                    .into_iter()
+                    .map(|(name, value)| {
+                        let value = match value {
+                            Field::String(s) => CFString::new(s).as_CFType(),
+                            Field::Number(n) => CFNumber::from(n).as_CFType(),
+                        };
+
+                        (CFString::from_static_string(name).as_CFType(), value)
+                    })
+                    .collect();
+
+                Dictionary::from_CFType_pairs(&pairs).as_CFType()
+            })
+            .collect();
+
+        let inventory = Dictionary::from_CFType_pairs(&[
+            (
+                CFString::from_static_string("schema_version").as_CFType(),
+                CFNumber::from(i64::from(self.schema_version)).as_CFType(),
+            ),
+            (
+                CFString::from_static_string("items").as_CFType(),
+                CFArray::from_CFTypes(&items).as_CFType(),
+            ),
+        ]);
+
+        propertylist::create_data(inventory.as_CFTypeRef(), kCFPropertyListXMLFormat_v1_0)
+            .map(|data| data.to_vec())
+            .map_err(|e| Error::new(ErrorKind::from_cf_error(&e), &e.description()))
+    }
+}
+
+impl InventoryEntry {
+    /// Write the fields of this entry which are present to the given JSON
+    /// output (without the enclosing braces)
+    pub(crate) fn write_json_fields(&self, json: &mut String) {
+        for (i, (name, value)) in self.fields().iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+
+            write!(json, "\"{}\":", name).unwrap();
+
+            match value {
+                Field::String(s) => write_json_string(json, s),
+                Field::Number(n) => write!(json, "{}", n).unwrap(),
+            }
+        }
+    }
+
+    /// Get the fields of this entry which are present, in schema order
+    fn fields(&self) -> Vec<(&'static str, Field<'_>)> {
+        let mut fields = vec![("class", Field::String(&self.class))];
+
+        let strings = [
+            ("label", &self.label),
+            ("account", &self.account),
+            ("service", &self.service),
+            ("server", &self.server),
+            ("comment", &self.comment),
+            ("key_class", &self.key_class),
+            ("application_tag", &self.application_tag),
+        ];
+
+        for (name, value) in strings.iter() {
+            if let Some(value) = value {
+                fields.push((name, Field::String(value)));
+            }
+        }
+
+        let numbers = [
+            ("created_at", self.created_at),
+            ("modified_at", self.modified_at),
+        ];
+
+        for (name, value) in numbers.iter() {
+            if let Some(value) = value {
+                fields.push((name, Field::Number(*value)));
+            }
+        }
+
+        fields
+    }
+}
+
+impl<'a> From<&'a ItemAttributes> for InventoryEntry {
+    fn from(item: &'a ItemAttributes) -> InventoryEntry {
+        let class = match item.class() {
+            Class::GenericPassword => "generic_password",
+            Class::InternetPassword => "internet_password",
+            Class::Certificate => "certificate",
+            Class::Key => "key",
+            Class::Identity => "identity",
+        };
+
+        let key_class = item.key_class().map(|key_class| {
+            match key_class {
+                AttrKeyClass::Public => "public",
+                AttrKeyClass::Private => "private",
+                AttrKeyClass::Symmetric => "symmetric",
+            }
+            .to_owned()
+        });
+
+        let application_tag = item.application_tag().map(|tag| {
+            tag.as_bytes()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect()
+        });
+
+        InventoryEntry {
+            class: class.to_owned(),
+            label: item.label(),
+            account: item.account(),
+            service: item.service(),
+            server: item.server(),
+            comment: item.comment(),
+            key_class,
+            application_tag,
+            created_at: item.creation_date().map(unix_seconds),
+            modified_at: item.modification_date().map(unix_seconds),
+        }
+    }
+}
+
+/// Values of inventory entry fields
+enum Field<'a> {
+    String(&'a str),
+    Number(i64),
+}
+
+/// Convert a `SystemTime` into seconds since the Unix epoch
+pub(crate) fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
+}
+
+/// Write a string to the given JSON output as a quoted, escaped literal
+pub(crate) fn write_json_string(json: &mut String, s: &str) {
+    json.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+}
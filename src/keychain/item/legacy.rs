@@ -0,0 +1,705 @@
+//! Typed decoding of the raw data of legacy `SecKeychainItem` attributes.
+//!
+//! Attributes of legacy (i.e. file-based) keychain items are identified by
+//! `FourCharacterCode` tags (e.g. `acct` for the account) and stored in
+//! Apple's binary formats: four character codes and port numbers as native
+//! endian integers, dates as `YYYYMMDDhhmmssZ` strings, and so on.
+//! `AttrValue::decode` interprets the data of well-known attributes so it
+//! can be used without knowing these formats, and `AttrValue::encode`
+//! converts values back to them. `AttrTag` names the well-known tags.
+
+#[cfg(feature = "legacy-keychain")]
+use super::{Class, Item};
+use crate::{
+    attr::{AttrAuthenticationType, AttrKind, AttrPort, AttrProtocol},
+    date,
+    ffi::*,
+};
+#[cfg(feature = "legacy-keychain")]
+use crate::{
+    error::{Error, ErrorKind},
+    keychain::{
+        hooks::{self, OperationKind},
+        Keychain,
+    },
+};
+#[cfg(feature = "legacy-keychain")]
+use core_foundation::base::TCFType;
+use std::{
+    fmt::{self, Display},
+    time::SystemTime,
+};
+#[cfg(feature = "legacy-keychain")]
+use std::{os::raw::c_void, ptr};
+
+/// Typed value of a legacy keychain item attribute.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AttrValue {
+    /// Text, e.g. an account (`acct`), service (`svce`), or label (`labl`)
+    String(String),
+
+    /// Date, i.e. the creation (`cdat`) or modification (`mdat`) date
+    Date(SystemTime),
+
+    /// Four character code, e.g. a creator (`crtr`) or type (`type`) code,
+    /// or an unrecognized protocol or authentication type
+    Code([u8; 4]),
+
+    /// Internet protocol (`ptcl`)
+    Protocol(AttrProtocol),
+
+    /// Authentication type (`atyp`)
+    AuthenticationType(AttrAuthenticationType),
+
+    /// Port number (`port`)
+    Port(AttrPort),
+
+    /// Other integer, e.g. a script code (`scrp`) or certificate type
+    /// (`ctyp`)
+    Number(u32),
+
+    /// Flag, e.g. whether the item is invisible (`invi`) or negative (`nega`)
+    Boolean(bool),
+
+    /// Raw data of an attribute which isn't well-known, or which couldn't be
+    /// decoded as the expected type
+    Data(Vec<u8>),
+}
+
+impl AttrValue {
+    /// Decode the raw data of the attribute with the given tag (e.g.
+    /// `b"acct"`).
+    ///
+    /// Data of attributes which aren't well-known, or which doesn't match
+    /// the expected format for the attribute, is returned as
+    /// `AttrValue::Data` rather than failing.
+    pub fn decode(tag: &[u8; 4], data: &[u8]) -> Self {
+        let value = match AttrKind::from_tag(FourCharacterCode::from(tag)) {
+            Some(AttrKind::Account)
+            | Some(AttrKind::Comment)
+            | Some(AttrKind::Description)
+            | Some(AttrKind::Label)
+            | Some(AttrKind::Path)
+            | Some(AttrKind::SecurityDomain)
+            | Some(AttrKind::Server)
+            | Some(AttrKind::Service) => {
+                String::from_utf8(data.to_vec()).ok().map(AttrValue::String)
+            }
+            Some(AttrKind::CreationDate) | Some(AttrKind::ModificationDate) => {
+                date::parse_timestamp(data).map(AttrValue::Date)
+            }
+            Some(AttrKind::Creator) | Some(AttrKind::ItemType) => {
+                decode_code(data).map(AttrValue::Code)
+            }
+            Some(AttrKind::Protocol) => decode_code(data).map(|code| {
+                AttrProtocol::from_code(&code).map_or(AttrValue::Code(code), AttrValue::Protocol)
+            }),
+            Some(AttrKind::AuthenticationType) => decode_code(data).map(|code| {
+                AttrAuthenticationType::from_code(&code)
+                    .map_or(AttrValue::Code(code), AttrValue::AuthenticationType)
+            }),
+            Some(AttrKind::Port) => decode_u32(data)
+                .filter(|&port| port <= u32::from(u16::MAX))
+                .map(|port| AttrValue::Port(AttrPort(port as u16))),
+            Some(AttrKind::Invisible) | Some(AttrKind::Negative) => {
+                Some(AttrValue::Boolean(data.iter().any(|&byte| byte != 0)))
+            }
+            Some(_) => None,
+            None => decode_untyped(tag, data),
+        };
+
+        value.unwrap_or_else(|| AttrValue::Data(data.to_vec()))
+    }
+
+    /// Encode this value as the raw data of a legacy attribute, i.e. the
+    /// inverse of `AttrValue::decode`.
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            AttrValue::String(string) => string.as_bytes().to_vec(),
+            AttrValue::Date(time) => date::format_timestamp(*time),
+            AttrValue::Code(code) => encode_code(code),
+            AttrValue::Protocol(protocol) => encode_code_str(&protocol.as_CFString().to_string()),
+            AttrValue::AuthenticationType(auth_type) => {
+                encode_code_str(&auth_type.as_CFString().to_string())
+            }
+            AttrValue::Port(port) => u32::from(port.0).to_ne_bytes().to_vec(),
+            AttrValue::Number(number) => number.to_ne_bytes().to_vec(),
+            AttrValue::Boolean(flag) => u32::from(*flag).to_ne_bytes().to_vec(),
+            AttrValue::Data(data) => data.clone(),
+        }
+    }
+}
+
+/// Tag identifying a legacy keychain item attribute, i.e. one of the
+/// well-known `FourCharacterCode`s (e.g. `acct` for the account) or any
+/// other tag.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum AttrTag {
+    /// Account name (`acct`)
+    Account,
+
+    /// Service name (generic passwords) (`svce`)
+    Service,
+
+    /// Server name (Internet passwords) (`srvr`)
+    Server,
+
+    /// Security domain, i.e. realm (Internet passwords) (`sdmn`)
+    SecurityDomain,
+
+    /// Protocol (Internet passwords) (`ptcl`)
+    Protocol,
+
+    /// Authentication type (Internet passwords) (`atyp`)
+    AuthenticationType,
+
+    /// Port number (Internet passwords) (`port`)
+    Port,
+
+    /// Path (Internet passwords) (`path`)
+    Path,
+
+    /// User-visible label (`labl`)
+    Label,
+
+    /// Description of the item's kind (`desc`)
+    Description,
+
+    /// User-editable comment (`icmt`)
+    Comment,
+
+    /// Creation date (`cdat`)
+    CreationDate,
+
+    /// Modification date (`mdat`)
+    ModificationDate,
+
+    /// Creator code (`crtr`)
+    Creator,
+
+    /// Type code (`type`)
+    ItemType,
+
+    /// Script code of the item's text attributes (`scrp`)
+    ScriptCode,
+
+    /// Application-defined data (generic passwords) (`gena`)
+    Generic,
+
+    /// Whether the item is hidden from Keychain Access (`invi`)
+    Invisible,
+
+    /// Whether the item is a negative entry, i.e. has no valid password (`nega`)
+    Negative,
+
+    /// Whether the item has a custom icon (`cusi`)
+    CustomIcon,
+
+    /// Alias (AppleShare passwords) (`alis`)
+    Alias,
+
+    /// Volume name (AppleShare passwords) (`vlme`)
+    Volume,
+
+    /// Server address (AppleShare passwords) (`addr`)
+    Address,
+
+    /// Server signature block (AppleShare passwords) (`ssig`)
+    Signature,
+
+    /// Certificate type (certificates) (`ctyp`)
+    CertificateType,
+
+    /// Certificate encoding (certificates) (`cenc`)
+    CertificateEncoding,
+
+    /// CRL type (CRLs) (`crtp`)
+    CrlType,
+
+    /// CRL encoding (CRLs) (`crnc`)
+    CrlEncoding,
+
+    /// Tag which isn't well-known
+    Other([u8; 4]),
+}
+
+impl AttrTag {
+    /// All well-known tags
+    pub const ALL: &'static [AttrTag] = &[
+        AttrTag::Account,
+        AttrTag::Service,
+        AttrTag::Server,
+        AttrTag::SecurityDomain,
+        AttrTag::Protocol,
+        AttrTag::AuthenticationType,
+        AttrTag::Port,
+        AttrTag::Path,
+        AttrTag::Label,
+        AttrTag::Description,
+        AttrTag::Comment,
+        AttrTag::CreationDate,
+        AttrTag::ModificationDate,
+        AttrTag::Creator,
+        AttrTag::ItemType,
+        AttrTag::ScriptCode,
+        AttrTag::Generic,
+        AttrTag::Invisible,
+        AttrTag::Negative,
+        AttrTag::CustomIcon,
+        AttrTag::Alias,
+        AttrTag::Volume,
+        AttrTag::Address,
+        AttrTag::Signature,
+        AttrTag::CertificateType,
+        AttrTag::CertificateEncoding,
+        AttrTag::CrlType,
+        AttrTag::CrlEncoding,
+    ];
+
+    /// Look up the tag for the given four character code, falling back to
+    /// `AttrTag::Other` if it isn't well-known.
+    pub fn from_code(code: &[u8; 4]) -> Self {
+        Self::ALL
+            .iter()
+            .find(|tag| &tag.code() == code)
+            .cloned()
+            .unwrap_or(AttrTag::Other(*code))
+    }
+
+    /// Get the four character code of this tag
+    pub fn code(self) -> [u8; 4] {
+        match self {
+            AttrTag::Account => *b"acct",
+            AttrTag::Service => *b"svce",
+            AttrTag::Server => *b"srvr",
+            AttrTag::SecurityDomain => *b"sdmn",
+            AttrTag::Protocol => *b"ptcl",
+            AttrTag::AuthenticationType => *b"atyp",
+            AttrTag::Port => *b"port",
+            AttrTag::Path => *b"path",
+            AttrTag::Label => *b"labl",
+            AttrTag::Description => *b"desc",
+            AttrTag::Comment => *b"icmt",
+            AttrTag::CreationDate => *b"cdat",
+            AttrTag::ModificationDate => *b"mdat",
+            AttrTag::Creator => *b"crtr",
+            AttrTag::ItemType => *b"type",
+            AttrTag::ScriptCode => *b"scrp",
+            AttrTag::Generic => *b"gena",
+            AttrTag::Invisible => *b"invi",
+            AttrTag::Negative => *b"nega",
+            AttrTag::CustomIcon => *b"cusi",
+            AttrTag::Alias => *b"alis",
+            AttrTag::Volume => *b"vlme",
+            AttrTag::Address => *b"addr",
+            AttrTag::Signature => *b"ssig",
+            AttrTag::CertificateType => *b"ctyp",
+            AttrTag::CertificateEncoding => *b"cenc",
+            AttrTag::CrlType => *b"crtp",
+            AttrTag::CrlEncoding => *b"crnc",
+            AttrTag::Other(code) => code,
+        }
+    }
+}
+
+impl From<[u8; 4]> for AttrTag {
+    fn from(code: [u8; 4]) -> Self {
+        Self::from_code(&code)
+    }
+}
+
+impl From<FourCharacterCode> for AttrTag {
+    fn from(code: FourCharacterCode) -> Self {
+        Self::from_code(code.as_bytes())
+    }
+}
+
+impl From<AttrTag> for [u8; 4] {
+    fn from(tag: AttrTag) -> Self {
+        tag.code()
+    }
+}
+
+impl Display for AttrTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.code()))
+    }
+}
+
+/// Attribute of a legacy keychain item along with its decoded value.
+#[cfg(feature = "legacy-keychain")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacyAttribute {
+    /// Tag identifying the attribute, e.g. `b"acct"` for the account
+    pub tag: [u8; 4],
+
+    /// Decoded value of the attribute
+    pub value: AttrValue,
+}
+
+#[cfg(feature = "legacy-keychain")]
+impl LegacyAttribute {
+    /// Get the tag identifying this attribute as an `AttrTag`
+    pub fn attr_tag(&self) -> AttrTag {
+        AttrTag::from_code(&self.tag)
+    }
+}
+
+/// Format of the data of a legacy keychain item attribute.
+///
+/// Wrapper for the `CSSM_DB_ATTRIBUTE_FORMAT` type.
+#[cfg(feature = "legacy-keychain")]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LegacyAttributeFormat {
+    /// Text
+    String,
+
+    /// Signed 32-bit integer
+    SInt32,
+
+    /// Unsigned 32-bit integer
+    UInt32,
+
+    /// Arbitrary precision integer
+    BigNum,
+
+    /// Floating point number
+    Real,
+
+    /// Timestamp of the form `YYYYMMDDhhmmssZ`
+    TimeDate,
+
+    /// Raw data
+    Blob,
+
+    /// List of unsigned 32-bit integers
+    MultiUInt32,
+
+    /// Structured data
+    Complex,
+
+    /// Format code this crate doesn't know about
+    Unknown(u32),
+}
+
+#[cfg(feature = "legacy-keychain")]
+impl LegacyAttributeFormat {
+    /// Look up a format from its `CSSM_DB_ATTRIBUTE_FORMAT` code
+    fn from_u32(format: u32) -> Self {
+        match format {
+            0 => LegacyAttributeFormat::String,
+            1 => LegacyAttributeFormat::SInt32,
+            2 => LegacyAttributeFormat::UInt32,
+            3 => LegacyAttributeFormat::BigNum,
+            4 => LegacyAttributeFormat::Real,
+            5 => LegacyAttributeFormat::TimeDate,
+            6 => LegacyAttributeFormat::Blob,
+            7 => LegacyAttributeFormat::MultiUInt32,
+            8 => LegacyAttributeFormat::Complex,
+            other => LegacyAttributeFormat::Unknown(other),
+        }
+    }
+}
+
+/// Attribute which items of a class can carry in a legacy keychain.
+#[cfg(feature = "legacy-keychain")]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct LegacyAttributeInfo {
+    /// Tag identifying the attribute, e.g. `b"acct"` for the account
+    pub tag: [u8; 4],
+
+    /// Format of the attribute's data
+    pub format: LegacyAttributeFormat,
+}
+
+#[cfg(feature = "legacy-keychain")]
+impl LegacyAttributeInfo {
+    /// Get the tag identifying this attribute as an `AttrTag`
+    pub fn attr_tag(&self) -> AttrTag {
+        AttrTag::from_code(&self.tag)
+    }
+}
+
+#[cfg(feature = "legacy-keychain")]
+impl Item {
+    /// Get all attributes of this item along with their decoded values.
+    ///
+    /// Every attribute items of this item's class can carry (see
+    /// `Item::legacy_attribute_info`) is looked up, so this includes
+    /// attributes which have no `kSecAttr*` equivalent.
+    ///
+    /// Wrapper for the `SecKeychainItemCopyAttributesAndData` function. See:
+    /// <https://developer.apple.com/documentation/security/seckeychainitemcopyattributesanddata(_:_:_:_:_:_:)>
+    pub fn legacy_attributes(&self) -> Result<Vec<LegacyAttribute>, Error> {
+        let info = self.copy_attribute_info()?;
+        let mut attrs: *mut SecKeychainAttributeList = ptr::null_mut();
+
+        let status = unsafe {
+            SecKeychainItemCopyAttributesAndData(
+                self.as_concrete_TypeRef(),
+                info,
+                ptr::null_mut(),
+                &mut attrs,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+
+        let free_status = unsafe { SecKeychainFreeAttributeInfo(info) };
+
+        if let Some(e) = Error::maybe_from_OSStatus(status) {
+            return Err(e);
+        }
+
+        if let Some(e) = Error::maybe_from_OSStatus(free_status) {
+            return Err(e);
+        }
+
+        let result = unsafe { &*attrs }
+            .iter()
+            .filter_map(|attr| {
+                let tag = *attr.tag().as_bytes();
+
+                // Attributes the item doesn't carry are returned without data
+                attr.data().map(|data| LegacyAttribute {
+                    tag,
+                    value: AttrValue::decode(&tag, data),
+                })
+            })
+            .collect();
+
+        let status = unsafe { SecKeychainItemFreeAttributesAndData(attrs, ptr::null_mut()) };
+
+        match Error::maybe_from_OSStatus(status) {
+            Some(e) => Err(e),
+            None => Ok(result),
+        }
+    }
+
+    /// Get the tag and format of every attribute which items of this item's
+    /// class can carry in its keychain.
+    ///
+    /// Wrapper for the `SecKeychainAttributeInfoForItemID` function. See:
+    /// <https://developer.apple.com/documentation/security/seckeychainattributeinfoforitemid(_:_:_:)>
+    pub fn legacy_attribute_info(&self) -> Result<Vec<LegacyAttributeInfo>, Error> {
+        let info = self.copy_attribute_info()?;
+
+        let result = unsafe { &*info }
+            .iter()
+            .map(|(tag, format)| LegacyAttributeInfo {
+                tag: *tag.as_bytes(),
+                format: LegacyAttributeFormat::from_u32(format),
+            })
+            .collect();
+
+        let status = unsafe { SecKeychainFreeAttributeInfo(info) };
+
+        match Error::maybe_from_OSStatus(status) {
+            Some(e) => Err(e),
+            None => Ok(result),
+        }
+    }
+
+    /// Create a new item of the given class in the given keychain from a
+    /// list of attributes and secret data, e.g. to write items which older
+    /// applications expect to find under specific attribute tags. Values are
+    /// encoded with `AttrValue::encode`.
+    ///
+    /// Only passwords and certificates can be created this way.
+    ///
+    /// Wrapper for the `SecKeychainItemCreateFromContent` function. See:
+    /// <https://developer.apple.com/documentation/security/seckeychainitemcreatefromcontent(_:_:_:_:_:_:_:)>
+    pub fn create_from_legacy_content(
+        keychain: &Keychain,
+        class: Class,
+        attrs: &[LegacyAttribute],
+        data: &[u8],
+    ) -> Result<Item, Error> {
+        hooks::run(OperationKind::Add, Some(class), || {
+            let mut result: ItemRef = ptr::null_mut();
+
+            let status = with_attribute_list(attrs, |attr_list| unsafe {
+                SecKeychainItemCreateFromContent(
+                    class.as_tag(),
+                    attr_list,
+                    data.len() as u32,
+                    data.as_ptr() as *const c_void,
+                    keychain.as_concrete_TypeRef(),
+                    ptr::null(),
+                    &mut result,
+                )
+            });
+
+            match Error::maybe_from_OSStatus(status) {
+                Some(e) => Err(e),
+                None => Ok(unsafe { Item::wrap_under_create_rule(result) }),
+            }
+        })
+    }
+
+    /// Change the given attributes and/or the secret data of this item in
+    /// place, e.g. to edit an item created by another tool in a file-based
+    /// keychain. Values are encoded with `AttrValue::encode`.
+    ///
+    /// Attributes which aren't given are left unchanged, as is the secret
+    /// data if `data` is `None`.
+    ///
+    /// Wrapper for the `SecKeychainItemModifyAttributesAndData` function. See:
+    /// <https://developer.apple.com/documentation/security/seckeychainitemmodifyattributesanddata(_:_:_:_:)>
+    pub fn set_legacy_content(
+        &self,
+        attrs: &[LegacyAttribute],
+        data: Option<&[u8]>,
+    ) -> Result<(), Error> {
+        hooks::run(OperationKind::Update, Some(self.class()), || {
+            let (length, data) = match data {
+                Some(data) => (data.len() as u32, data.as_ptr() as *const c_void),
+                None => (0, ptr::null()),
+            };
+
+            let status = with_attribute_list(attrs, |attr_list| unsafe {
+                SecKeychainItemModifyAttributesAndData(
+                    self.as_concrete_TypeRef(),
+                    attr_list,
+                    length,
+                    data,
+                )
+            });
+
+            match Error::maybe_from_OSStatus(status) {
+                Some(e) => Err(e),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Get the keychain this item is stored in, e.g. to report where an
+    /// item found by searching several keychains came from.
+    ///
+    /// Wrapper for the `SecKeychainItemCopyKeychain` function. See:
+    /// <https://developer.apple.com/documentation/security/seckeychainitemcopykeychain(_:_:)>
+    pub fn keychain(&self) -> Result<Keychain, Error> {
+        let mut result: KeychainRef = ptr::null_mut();
+        let status =
+            unsafe { SecKeychainItemCopyKeychain(self.as_concrete_TypeRef(), &mut result) };
+
+        match Error::maybe_from_OSStatus(status) {
+            Some(e) => Err(already_deleted(e)),
+            None => Ok(unsafe { Keychain::wrap_under_create_rule(result) }),
+        }
+    }
+
+    /// Delete this item from its keychain.
+    ///
+    /// Returns an error with a kind of `ErrorKind::ItemNotFound` if the item
+    /// has already been deleted (e.g. by another process), which Keychain
+    /// Services reports as an invalid item reference.
+    ///
+    /// Wrapper for the `SecKeychainItemDelete` function. See:
+    /// <https://developer.apple.com/documentation/security/seckeychainitemdelete(_:)>
+    pub fn delete(self) -> Result<(), Error> {
+        let class = self.try_class().map_err(already_deleted)?;
+
+        hooks::run(OperationKind::Delete, Some(class), || {
+            let status = unsafe { SecKeychainItemDelete(self.as_concrete_TypeRef()) };
+
+            match Error::maybe_from_OSStatus(status) {
+                Some(e) => Err(already_deleted(e)),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Get the attribute info for this item's class in its keychain. Note
+    /// that the caller must free it with `SecKeychainFreeAttributeInfo`.
+    fn copy_attribute_info(&self) -> Result<*mut SecKeychainAttributeInfo, Error> {
+        let keychain = self.keychain()?;
+        let mut info: *mut SecKeychainAttributeInfo = ptr::null_mut();
+
+        let status = unsafe {
+            SecKeychainAttributeInfoForItemID(
+                keychain.as_concrete_TypeRef(),
+                self.try_class()?.as_tag(),
+                &mut info,
+            )
+        };
+
+        match Error::maybe_from_OSStatus(status) {
+            Some(e) => Err(e),
+            None => Ok(info),
+        }
+    }
+}
+
+/// Encode the given attributes as a `SecKeychainAttributeList` for the
+/// duration of a call to the given function
+#[cfg(feature = "legacy-keychain")]
+fn with_attribute_list<F, T>(attrs: &[LegacyAttribute], f: F) -> T
+where
+    F: FnOnce(&mut SecKeychainAttributeList) -> T,
+{
+    let encoded: Vec<Vec<u8>> = attrs.iter().map(|attr| attr.value.encode()).collect();
+
+    let mut raw_attrs: Vec<SecKeychainAttribute> = attrs
+        .iter()
+        .zip(&encoded)
+        .map(|(attr, value)| SecKeychainAttribute::new(attr.tag.into(), value))
+        .collect();
+
+    f(&mut SecKeychainAttributeList::new(&mut raw_attrs))
+}
+
+/// Report an invalid reference to an item as the item not being found, as
+/// references become invalid when the item they refer to is deleted
+#[cfg(feature = "legacy-keychain")]
+fn already_deleted(e: Error) -> Error {
+    match e.kind() {
+        ErrorKind::InvalidItemRef => Error::new(ErrorKind::ItemNotFound, "item has been deleted"),
+        _ => e,
+    }
+}
+
+/// Decode the data of a well-known legacy attribute which has no
+/// `kSecAttr*` equivalent
+fn decode_untyped(tag: &[u8; 4], data: &[u8]) -> Option<AttrValue> {
+    match tag {
+        // Script code, certificate type and encoding, CRL type and encoding
+        b"scrp" | b"ctyp" | b"cenc" | b"crtp" | b"crnc" => decode_u32(data).map(AttrValue::Number),
+        // Custom icon flag
+        b"cusi" => Some(AttrValue::Boolean(data.iter().any(|&byte| byte != 0))),
+        _ => None,
+    }
+}
+
+/// Decode a four character code stored as a native endian integer
+fn decode_code(data: &[u8]) -> Option<[u8; 4]> {
+    decode_u32(data).map(u32::to_be_bytes)
+}
+
+/// Encode a four character code as a native endian integer
+fn encode_code(code: &[u8; 4]) -> Vec<u8> {
+    u32::from_be_bytes(*code).to_ne_bytes().to_vec()
+}
+
+/// Encode a four character code given as a string (e.g. the
+/// `kSecAttrProtocol` value of a protocol), padded with spaces
+fn encode_code_str(code: &str) -> Vec<u8> {
+    let mut bytes = [b' '; 4];
+
+    for (byte, &c) in bytes.iter_mut().zip(code.as_bytes()) {
+        *byte = c;
+    }
+
+    encode_code(&bytes)
+}
+
+/// Decode a native endian `u32`
+fn decode_u32(data: &[u8]) -> Option<u32> {
+    if data.len() != 4 {
+        return None;
+    }
+
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(data);
+    Some(u32::from_ne_bytes(bytes))
+}
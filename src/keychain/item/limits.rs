@@ -0,0 +1,155 @@
+//! Limits on the size of the secret data stored in keychain items.
+//!
+//! Keychain Services doesn't document any limits of its own, but every
+//! query has to load the items it matches, and iCloud Keychain silently
+//! fails to sync large items. Data beyond these limits (e.g. documents or
+//! databases) belongs in a file encrypted with a key which is stored in the
+//! keychain (e.g. with `Key::encrypt`), rather than in the keychain itself.
+//!
+//! The default limits are conservative guidelines chosen by this crate, not
+//! limits imposed by Keychain Services, so applications with other needs can
+//! change (or disable) them with `set_data_size_limits`.
+
+use crate::{
+    attr::AttrKind,
+    dictionary::DictionaryBuilder,
+    error::{Error, ErrorKind},
+    ffi::*,
+};
+use core_foundation::{boolean::CFBoolean, data::CFData, string::CFString};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Default maximum size of the secret data of a keychain item in bytes.
+pub const DEFAULT_MAX_DATA_SIZE: usize = 1024 * 1024;
+
+/// Default maximum size of the secret data of a synchronizable (i.e. iCloud
+/// Keychain) item in bytes.
+pub const DEFAULT_MAX_SYNCHRONIZABLE_DATA_SIZE: usize = 64 * 1024;
+
+/// Limits on the size of the secret data of keychain items, enforced when
+/// items are added or updated. `None` disables a limit.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct DataSizeLimits {
+    /// Maximum size of the data of any item in bytes
+    pub max_data_size: Option<usize>,
+
+    /// Maximum size of the data of synchronizable items in bytes
+    pub max_synchronizable_data_size: Option<usize>,
+}
+
+impl DataSizeLimits {
+    /// Don't limit the size of item data
+    pub fn unlimited() -> Self {
+        Self {
+            max_data_size: None,
+            max_synchronizable_data_size: None,
+        }
+    }
+}
+
+impl Default for DataSizeLimits {
+    fn default() -> Self {
+        Self {
+            max_data_size: Some(DEFAULT_MAX_DATA_SIZE),
+            max_synchronizable_data_size: Some(DEFAULT_MAX_SYNCHRONIZABLE_DATA_SIZE),
+        }
+    }
+}
+
+/// Set the `DataSizeLimits` enforced process-wide.
+pub fn set_data_size_limits(limits: DataSizeLimits) {
+    MAX_DATA_SIZE.store(limits.max_data_size.unwrap_or(usize::MAX), Ordering::SeqCst);
+    MAX_SYNCHRONIZABLE_DATA_SIZE.store(
+        limits.max_synchronizable_data_size.unwrap_or(usize::MAX),
+        Ordering::SeqCst,
+    );
+}
+
+/// Get the current `DataSizeLimits`.
+pub fn data_size_limits() -> DataSizeLimits {
+    let limit = |value: &AtomicUsize| match value.load(Ordering::SeqCst) {
+        usize::MAX => None,
+        size => Some(size),
+    };
+
+    DataSizeLimits {
+        max_data_size: limit(&MAX_DATA_SIZE),
+        max_synchronizable_data_size: limit(&MAX_SYNCHRONIZABLE_DATA_SIZE),
+    }
+}
+
+/// Maximum size of item data (`usize::MAX` if unlimited)
+static MAX_DATA_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_DATA_SIZE);
+
+/// Maximum size of synchronizable item data (`usize::MAX` if unlimited)
+static MAX_SYNCHRONIZABLE_DATA_SIZE: AtomicUsize =
+    AtomicUsize::new(DEFAULT_MAX_SYNCHRONIZABLE_DATA_SIZE);
+
+/// Check that the given secret data is within the current size limit for
+/// keychain items (or synchronizable items, if `synchronizable` is set).
+///
+/// Returns an error with a kind of `ErrorKind::DataTooLarge` if not.
+pub fn validate_data(data: &[u8], synchronizable: bool) -> Result<(), Error> {
+    validate_size(data.len(), synchronizable)
+}
+
+/// Check the secret data (i.e. `kSecValueData`) in the given attributes
+/// (if any) is within the size limit, taking `kSecAttrSynchronizable` into
+/// account.
+pub(crate) fn validate_attrs(attrs: &DictionaryBuilder) -> Result<(), Error> {
+    validate_update(attrs, false)
+}
+
+/// Check the secret data in the given attributes (if any) is within the
+/// size limit when updating an existing item, which is synchronizable if
+/// `synchronizable` is set (unless the attributes change it).
+pub(crate) fn validate_update(
+    attrs: &DictionaryBuilder,
+    synchronizable: bool,
+) -> Result<(), Error> {
+    let value = match attrs.get(unsafe { kSecValueData }) {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+
+    let size = if let Some(data) = value.downcast::<CFData>() {
+        data.len() as usize
+    } else if let Some(string) = value.downcast::<CFString>() {
+        string.to_string().len()
+    } else {
+        return Err(Error::new(
+            ErrorKind::Param,
+            "item data must be a byte or UTF-8 string",
+        ));
+    };
+
+    let synchronizable = match attrs.get(AttrKind::Synchronizable) {
+        Some(value) => value.downcast::<CFBoolean>().map(bool::from) == Some(true),
+        None => synchronizable,
+    };
+
+    validate_size(size, synchronizable)
+}
+
+/// Check the given data size is within the applicable limit
+fn validate_size(size: usize, synchronizable: bool) -> Result<(), Error> {
+    let limits = data_size_limits();
+
+    let (limit, kind) = if synchronizable {
+        (limits.max_synchronizable_data_size, "synchronizable item")
+    } else {
+        (limits.max_data_size, "keychain item")
+    };
+
+    match limit {
+        Some(limit) if size > limit => Err(Error::new(
+            ErrorKind::DataTooLarge,
+            &format!(
+                "{}-byte data exceeds {}-byte limit for a {}: store it in a file encrypted \
+                 with a key kept in the keychain instead",
+                size, limit, kind
+            ),
+        )),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,128 @@
+//! Moving items between access groups of the data protection keychain, e.g.
+//! when the shared keychain access group of an application changes.
+//!
+//! Keychain Services has no transactions, so moves are carried out in
+//! phases, each of which is undone if a later one fails:
+//!
+//! 1. The attributes and secret data of all matching items are read (which
+//!    may prompt the user), without modifying anything
+//! 2. A copy of each item is added to the new access group
+//! 3. The originals are deleted
+//!
+//! If adding a copy fails, the copies added so far are deleted. If deleting
+//! an original fails, the originals deleted so far are re-added and all of
+//! the copies are deleted. Rollback is best-effort: the error which caused
+//! it is returned even if undoing a step fails too.
+
+use super::{
+    copy::{add_to_access_group, copy_attrs, find_with_data},
+    Class, ItemAttributes, Query,
+};
+use crate::{
+    dictionary::{Dictionary, DictionaryBuilder},
+    error::{Error, ErrorKind},
+    ffi::*,
+    keychain::hooks::{self, OperationKind},
+};
+use core_foundation::{base::TCFType, data::CFData};
+use std::borrow::Borrow;
+
+/// Move all items of the given class matching the given `Query` from one
+/// access group to another, returning the number of items moved.
+///
+/// Only passwords can be moved: other classes return an error with a kind
+/// of `ErrorKind::Param`. See the module documentation for how failures are
+/// rolled back.
+pub fn move_to_access_group<Q: Borrow<Query>>(
+    class: Class,
+    query: Q,
+    from: &str,
+    to: &str,
+) -> Result<usize, Error> {
+    match class {
+        Class::GenericPassword | Class::InternetPassword => (),
+        other => {
+            return Err(Error::new(
+                ErrorKind::Param,
+                &format!("can't move items of class {}", other),
+            ))
+        }
+    }
+
+    let query = query.borrow().clone().access_group(from);
+    let mut originals = vec![];
+
+    for item in ItemAttributes::find_with_persistent_refs(class, &query)? {
+        let persistent_ref = item
+            .persistent_ref()
+            .map(|persistent_ref| persistent_ref.as_CFData())
+            .ok_or_else(|| Error::new(ErrorKind::Param, "no reference to item"))?;
+
+        let source = find_with_data(class, persistent_ref_query(&persistent_ref))?;
+        originals.push((persistent_ref, source));
+    }
+
+    let mut copies = vec![];
+
+    for (_, source) in &originals {
+        match add_to_access_group(copy_attrs(class, source), to) {
+            Ok(copy) => copies.push(copy),
+            Err(e) => {
+                delete_all(class, &copies);
+                return Err(e);
+            }
+        }
+    }
+
+    for (i, (persistent_ref, _)) in originals.iter().enumerate() {
+        if let Err(e) = delete(class, persistent_ref) {
+            restore_all(class, &originals[..i], from);
+            delete_all(class, &copies);
+            return Err(e);
+        }
+    }
+
+    Ok(originals.len())
+}
+
+/// Build a query matching exactly the item with the given persistent
+/// reference in the data protection keychain
+fn persistent_ref_query(persistent_ref: &CFData) -> DictionaryBuilder {
+    let mut query = DictionaryBuilder::new();
+    query.add(unsafe { kSecValuePersistentRef }, persistent_ref);
+    query.add_boolean(unsafe { kSecUseDataProtectionKeychain }, true);
+    query
+}
+
+/// Delete the item with the given persistent reference.
+///
+/// Wrapper for the `SecItemDelete` function. See:
+/// <https://developer.apple.com/documentation/security/1395547-secitemdelete>
+fn delete(class: Class, persistent_ref: &CFData) -> Result<(), Error> {
+    hooks::run(OperationKind::Delete, Some(class), || {
+        let mut query = persistent_ref_query(persistent_ref);
+        query.add_class(class);
+
+        let status = unsafe { SecItemDelete(Dictionary::from(query).as_concrete_TypeRef()) };
+
+        match Error::maybe_from_OSStatus(status) {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    })
+}
+
+/// Roll back adding the given copies, ignoring failures
+fn delete_all(class: Class, copies: &[CFData]) {
+    for copy in copies {
+        let _ = delete(class, copy);
+    }
+}
+
+/// Roll back deleting the given originals by re-adding them to their
+/// original access group, ignoring failures
+fn restore_all(class: Class, originals: &[(CFData, Dictionary)], access_group: &str) {
+    for (_, source) in originals {
+        let _ = add_to_access_group(copy_attrs(class, source), access_group);
+    }
+}
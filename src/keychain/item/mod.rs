@@ -1,12 +1,56 @@
 //! Items stored in a keychain (e.g. certificates, keys, passwords)
 
+mod archive;
+mod attributes;
+mod bulk;
+mod changes;
 mod class;
+#[cfg(feature = "legacy-keychain")]
+mod classic;
+#[cfg(not(feature = "legacy-keychain"))]
+mod content;
+mod copy;
+mod dedupe;
+mod expiry;
+mod found;
+mod inventory;
+mod legacy;
+mod limits;
+mod migrate;
+mod pages;
 mod password;
+mod persistent;
 mod query;
+mod tags;
+mod tombstone;
+mod update;
 
-pub use self::{class::*, password::*, query::*};
-use crate::{attr::AttrKind, error::*, ffi::*};
+#[cfg(feature = "legacy-keychain")]
+pub use self::classic::*;
+pub use self::{
+    archive::*, attributes::*, bulk::*, changes::*, class::*, copy::*, dedupe::*, expiry::*,
+    found::*, inventory::*, legacy::*, limits::*, migrate::*, pages::*, password::*, persistent::*,
+    query::*, tags::*, tombstone::*, update::*,
+};
+use crate::{
+    attr::{AttrAccessible, AttrCreator, AttrItemType, AttrKind, AttrLabel},
+    date,
+    dictionary::{Dictionary, DictionaryBuilder},
+    error::*,
+    ffi::*,
+    keychain::{
+        entitlements,
+        hooks::{self, OperationKind},
+    },
+};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
 use core_foundation::base::TCFType;
+use std::{
+    fmt::{self, Debug, Display},
+    time::SystemTime,
+};
+#[cfg(feature = "legacy-keychain")]
 use std::{mem, os::raw::c_void, ptr, slice};
 
 declare_TCFType! {
@@ -21,10 +65,18 @@ impl_TCFType!(Item, ItemRef, SecKeychainItemGetTypeID);
 
 impl Item {
     /// Get the class of this item
+    #[cfg(feature = "legacy-keychain")]
     pub fn class(&self) -> Class {
+        self.try_class().unwrap()
+    }
+
+    /// Get the class of this item, failing if it's no longer valid (e.g.
+    /// because it has been deleted)
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn try_class(&self) -> Result<Class, Error> {
         let mut result = FourCharacterCode::from(b"NULL");
 
-        Error::maybe_from_OSStatus(unsafe {
+        match Error::maybe_from_OSStatus(unsafe {
             SecKeychainItemCopyContent(
                 self.as_concrete_TypeRef(),
                 &mut result,
@@ -32,13 +84,14 @@ impl Item {
                 ptr::null_mut(),
                 ptr::null_mut(),
             )
-        })
-        .unwrap();
-
-        result.into()
+        }) {
+            Some(e) => Err(e),
+            None => Ok(result.into()),
+        }
     }
 
     /// Get the raw data associated with this keychain item
+    #[cfg(feature = "legacy-keychain")]
     pub(crate) fn data(&self) -> Result<Vec<u8>, Error> {
         let result_ptr: *mut u8 = ptr::null_mut();
         let mut length = 0;
@@ -74,9 +127,262 @@ impl Item {
         }
     }
 
+    /// Get the date this item was created.
+    ///
+    /// Wrapper for the `kSecAttrCreationDate` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcreationdate>
+    pub fn creation_date(&self) -> Result<SystemTime, Error> {
+        self.date_attribute(AttrKind::CreationDate)
+    }
+
+    /// Get the date this item was last modified.
+    ///
+    /// Wrapper for the `kSecAttrModificationDate` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrmodificationdate>
+    pub fn modification_date(&self) -> Result<SystemTime, Error> {
+        self.date_attribute(AttrKind::ModificationDate)
+    }
+
+    /// Get the user-visible label of this item.
+    ///
+    /// Wrapper for the `kSecAttrLabel` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrlabel>
+    pub fn label(&self) -> Result<String, Error> {
+        self.attribute(AttrKind::Label)
+    }
+
+    /// Change the label of this item in place.
+    ///
+    /// Unlike deleting and recreating the item, this preserves its access
+    /// control settings (including any "Always Allow" approvals).
+    ///
+    /// Wrapper for the `SecItemUpdate` function. See:
+    /// <https://developer.apple.com/documentation/security/1393617-secitemupdate>
+    pub fn set_label<L: Into<AttrLabel>>(&self, label: L) -> Result<(), Error> {
+        let mut attrs = DictionaryBuilder::new();
+        attrs.add_attr(&label.into());
+        update(self.try_class()?, self, attrs)
+    }
+
+    /// Bump the modification date of this item to the current time without
+    /// changing its data or any of its other attributes.
+    ///
+    /// This gives freshness-based policies (e.g. rotating stale credentials
+    /// or evicting the least recently used items) a reliable signal that an
+    /// item is still in use even when its contents haven't changed.
+    ///
+    /// Wrapper for the `SecItemUpdate` function. See:
+    /// <https://developer.apple.com/documentation/security/1393617-secitemupdate>
+    pub fn touch(&self) -> Result<(), Error> {
+        update(self.try_class()?, self, touch_attrs())
+    }
+
+    /// Apply the given changes to the attributes and/or secret data of this
+    /// item in place.
+    ///
+    /// Unlike deleting and recreating the item, this preserves its access
+    /// control settings, so other applications (and "Always Allow"
+    /// approvals) retain access to the updated item.
+    ///
+    /// Wrapper for the `SecItemUpdate` function. See:
+    /// <https://developer.apple.com/documentation/security/1393617-secitemupdate>
+    pub fn update(&self, changes: &ItemChanges) -> Result<(), Error> {
+        update(self.try_class()?, self, changes.attrs().clone())
+    }
+
+    /// Change the protection class of this item in place, e.g. to stop an
+    /// item being readable while the device is locked as part of a security
+    /// hardening migration.
+    ///
+    /// If Keychain Services refuses to change the protection class alone
+    /// (with an error of kind `ErrorKind::Param`), the item's secret data is
+    /// read and written back along with it, which may prompt the user to
+    /// allow access. Items protected by an `AccessControl` policy can't be
+    /// changed this way, as the policy has a protection class of its own.
+    ///
+    /// Wrapper for the `kSecAttrAccessible` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccessible>
+    pub fn set_accessible(&self, accessible: AttrAccessible) -> Result<(), Error> {
+        let changes = ItemChanges::new().accessible(accessible);
+
+        self.update(&changes).or_else(|e| match e.kind() {
+            ErrorKind::Param => {
+                let data = PasswordData::new(self.data()?);
+                self.update(&changes.data(data.as_bytes()))
+            }
+            _ => Err(e),
+        })
+    }
+
+    /// Get the user-visible comment associated with this item.
+    ///
+    /// Wrapper for the `kSecAttrComment` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcomment>
+    pub fn comment(&self) -> Result<String, Error> {
+        self.attribute(AttrKind::Comment)
+    }
+
+    /// Get the user-defined tags attached to this item (see `ItemTags`),
+    /// which are empty if its comment doesn't contain encoded tags.
+    pub fn tags(&self) -> Result<ItemTags, Error> {
+        match self.comment() {
+            Ok(comment) => Ok(ItemTags::from_comment(&comment)),
+            Err(e) => match e.kind() {
+                ErrorKind::NoSuchAttr => Ok(ItemTags::new()),
+                _ => Err(e),
+            },
+        }
+    }
+
+    /// Replace the user-defined tags attached to this item in place.
+    ///
+    /// Tags are stored in the item's comment, overwriting any comment
+    /// which was previously present.
+    pub fn set_tags(&self, tags: &ItemTags) -> Result<(), Error> {
+        let mut attrs = DictionaryBuilder::new();
+        attrs.add_string(AttrKind::Comment, tags.to_string());
+        update(self.try_class()?, self, attrs)
+    }
+
+    /// Get the user-visible description of this item's kind (e.g.
+    /// "application password").
+    ///
+    /// Wrapper for the `kSecAttrDescription` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrdescription>
+    pub fn description(&self) -> Result<String, Error> {
+        self.attribute(AttrKind::Description)
+    }
+
+    /// Get the creator code of this item.
+    ///
+    /// Wrapper for the `kSecAttrCreator` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcreator>
+    pub fn creator(&self) -> Result<AttrCreator, Error> {
+        self.four_character_code_attribute(AttrKind::Creator)
+            .map(AttrCreator)
+    }
+
+    /// Get the type code of this item.
+    ///
+    /// Wrapper for the `kSecAttrType` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrtype>
+    pub fn item_type(&self) -> Result<AttrItemType, Error> {
+        self.four_character_code_attribute(AttrKind::ItemType)
+            .map(AttrItemType)
+    }
+
+    /// Is this item hidden from the Keychain Access application?
+    ///
+    /// Wrapper for the `kSecAttrIsInvisible` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrisinvisible>
+    pub fn is_invisible(&self) -> Result<bool, Error> {
+        self.boolean_attribute(AttrKind::Invisible)
+    }
+
+    /// Is this a "negative" item, i.e. a placeholder recording that the
+    /// user declined to store a password (e.g. "never for this website")?
+    ///
+    /// Wrapper for the `kSecAttrIsNegative` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrisnegative>
+    pub fn is_negative(&self) -> Result<bool, Error> {
+        self.boolean_attribute(AttrKind::Negative)
+    }
+
+    /// Get the date this item was created as a `chrono::DateTime`.
+    #[cfg(feature = "chrono")]
+    pub fn creation_datetime(&self) -> Result<DateTime<Utc>, Error> {
+        self.creation_date().map(DateTime::from)
+    }
+
+    /// Get the date this item was last modified as a `chrono::DateTime`.
+    #[cfg(feature = "chrono")]
+    pub fn modification_datetime(&self) -> Result<DateTime<Utc>, Error> {
+        self.modification_date().map(DateTime::from)
+    }
+
     /// Get an attribute of this item as a `String`.
-    // TODO: handle attribute types other than `String`?
+    ///
+    /// Returns an error with a kind of `ErrorKind::Decode` if the attribute
+    /// isn't valid UTF-8, which legacy keychain items don't guarantee.
     pub(crate) fn attribute(&self, attr_kind: AttrKind) -> Result<String, Error> {
+        String::from_utf8(self.attribute_data(attr_kind)?).map_err(|_| {
+            Error::new(
+                ErrorKind::Decode,
+                &format!("{:?} attribute isn't valid UTF-8", attr_kind),
+            )
+        })
+    }
+
+    /// Get the attributes which identify this item (e.g. service and
+    /// account for generic passwords) as name/value pairs, omitting any
+    /// which are missing. Never includes the item's secret data.
+    fn identifying_attributes(&self, class: Class) -> Vec<(&'static str, String)> {
+        let kinds: &[(&'static str, AttrKind)] = match class {
+            Class::GenericPassword => &[
+                ("service", AttrKind::Service),
+                ("account", AttrKind::Account),
+            ],
+            Class::InternetPassword => {
+                &[("server", AttrKind::Server), ("account", AttrKind::Account)]
+            }
+            _ => &[("label", AttrKind::Label)],
+        };
+
+        kinds
+            .iter()
+            .filter_map(|&(name, kind)| {
+                self.attribute_data(kind)
+                    .ok()
+                    .map(|data| (name, String::from_utf8_lossy(&data).into_owned()))
+            })
+            .collect()
+    }
+
+    /// Get a date attribute of this item as a `SystemTime`.
+    #[cfg(feature = "legacy-keychain")]
+    fn date_attribute(&self, attr_kind: AttrKind) -> Result<SystemTime, Error> {
+        let data = self.attribute_data(attr_kind)?;
+
+        date::parse_timestamp(&data).ok_or_else(|| {
+            Error::new(
+                ErrorKind::Decode,
+                &format!("malformed timestamp in attribute {:?}", attr_kind),
+            )
+        })
+    }
+
+    /// Get a four character code attribute of this item.
+    #[cfg(feature = "legacy-keychain")]
+    fn four_character_code_attribute(&self, attr_kind: AttrKind) -> Result<[u8; 4], Error> {
+        let data = self.attribute_data(attr_kind)?;
+
+        if data.len() != 4 {
+            return Err(Error::new(
+                ErrorKind::Decode,
+                &format!("malformed {:?} attribute ({} bytes)", attr_kind, data.len()),
+            ));
+        }
+
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&data);
+        Ok(u32::from_ne_bytes(bytes).to_be_bytes())
+    }
+
+    /// Get a boolean attribute of this item, treating a missing attribute
+    /// as `false`.
+    fn boolean_attribute(&self, attr_kind: AttrKind) -> Result<bool, Error> {
+        match self.attribute_data(attr_kind) {
+            Ok(data) => Ok(data.iter().any(|&byte| byte != 0)),
+            Err(e) => match e.kind() {
+                ErrorKind::NoSuchAttr => Ok(false),
+                _ => Err(e),
+            },
+        }
+    }
+
+    /// Get the raw data of an attribute of this item.
+    #[cfg(feature = "legacy-keychain")]
+    pub(crate) fn attribute_data(&self, attr_kind: AttrKind) -> Result<Vec<u8>, Error> {
         let mut attrs = unsafe { self.attributes() }?;
 
         let result = attrs
@@ -88,7 +394,7 @@ impl Item {
                     false
                 }
             })
-            .map(|attr| String::from_utf8(attr.data().unwrap().into()).unwrap());
+            .map(|attr| Vec::from(attr.data().unwrap_or_default()));
 
         Error::maybe_from_OSStatus(unsafe {
             SecKeychainItemFreeContent(&mut attrs, ptr::null_mut())
@@ -105,6 +411,7 @@ impl Item {
 
     /// Get the attributes of a keychain item. Note that this does not handle
     /// deallocating the attribute list so the caller must take care to do so.
+    #[cfg(feature = "legacy-keychain")]
     unsafe fn attributes(&self) -> Result<SecKeychainAttributeList, Error> {
         let mut result: SecKeychainAttributeList = mem::zeroed();
 
@@ -123,3 +430,89 @@ impl Item {
         }
     }
 }
+
+/// Attributes for a no-op update which bumps an item's modification date.
+/// The date is set explicitly, as an update which changes nothing may leave
+/// it untouched.
+pub(crate) fn touch_attrs() -> DictionaryBuilder {
+    let mut attrs = DictionaryBuilder::new();
+    attrs.add(
+        AttrKind::ModificationDate,
+        &date::CFDate_from_system_time(SystemTime::now()),
+    );
+    attrs
+}
+
+/// Update the attributes of the given item (i.e. an `Item`, `Key`, or other
+/// keychain object) of the given class in place.
+///
+/// Wrapper for the `SecItemUpdate` function. See:
+/// <https://developer.apple.com/documentation/security/1393617-secitemupdate>
+pub(crate) fn update<T: TCFType>(
+    class: Class,
+    item: &T,
+    attrs: DictionaryBuilder,
+) -> Result<(), Error> {
+    hooks::run(OperationKind::Update, Some(class), || {
+        validate_attrs(&attrs)?;
+
+        let mut query = DictionaryBuilder::new();
+        query.add_class(class);
+        query.add(unsafe { kSecValueRef }, item);
+
+        let status = unsafe {
+            SecItemUpdate(
+                Dictionary::from(query).as_concrete_TypeRef(),
+                Dictionary::from(attrs.clone()).as_concrete_TypeRef(),
+            )
+        };
+
+        match Error::maybe_from_OSStatus(status) {
+            Some(e) => Err(entitlements::diagnose(e, &attrs)),
+            None => Ok(()),
+        }
+    })
+}
+
+impl Debug for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let class = match self.try_class() {
+            Ok(class) => class,
+            Err(_) => return write!(f, "SecKeychainItem {{ <invalid> }}"),
+        };
+
+        write!(f, "SecKeychainItem {{ class: {:?}", class)?;
+
+        for (name, value) in self.identifying_attributes(class) {
+            write!(f, ", {}: {:?}", name, value)?;
+        }
+
+        write!(f, " }}")
+    }
+}
+
+/// Human-readable description of an item which never includes its secret
+/// data, e.g. `generic password (service: "example.com", account: "alice")`.
+impl Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let class = match self.try_class() {
+            Ok(class) => class,
+            Err(_) => return write!(f, "invalid item"),
+        };
+
+        write!(f, "{}", class)?;
+
+        let attrs = self.identifying_attributes(class);
+
+        if !attrs.is_empty() {
+            let attrs: Vec<_> = attrs
+                .iter()
+                .map(|(name, value)| format!("{}: {:?}", name, value))
+                .collect();
+
+            write!(f, " ({})", attrs.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,212 @@
+//! Paging through large result sets a batch of items at a time.
+//!
+//! Keychain Services has no cursors, so the persistent references of all
+//! matching items (which are small) are fetched up front, and each page then
+//! looks up the attributes of the next batch of them with
+//! `kSecMatchItemList`. Items removed between pages are skipped, and items
+//! added after the first lookup aren't included.
+//!
+//! `KeychainItems` builds on this to enumerate every item in a keychain.
+
+use super::{Class, ItemAttributes, MatchLimit, Query};
+use crate::{
+    dictionary::{Dictionary, DictionaryBuilder},
+    error::{Error, ErrorKind},
+    ffi::*,
+    keychain::{
+        entitlements,
+        hooks::{self, OperationKind},
+    },
+};
+use core_foundation::{
+    array::{CFArray, CFArrayRef},
+    base::{CFTypeRef, TCFType},
+    data::CFData,
+};
+use std::{borrow::Borrow, ptr, vec};
+
+/// Number of items fetched at a time by `KeychainItems`
+const KEYCHAIN_ITEMS_PAGE_SIZE: usize = 100;
+
+/// Classes of items enumerated by `KeychainItems` (and found by
+/// `find_items`) when none are specified. Identities aren't stored as items
+/// of their own (they're a certificate paired with its private key), so
+/// they're excluded.
+pub(super) const STORED_CLASSES: &[Class] = &[
+    Class::GenericPassword,
+    Class::InternetPassword,
+    Class::Certificate,
+    Class::Key,
+];
+
+/// Iterator over the attributes of the items matching a query, yielding
+/// them a page (i.e. a `Vec` of at most the page size) at a time.
+///
+/// The attributes include references to the items, so they can be acted on
+/// (e.g. deleted) as each page is processed.
+#[derive(Debug)]
+pub struct ItemPages {
+    class: Class,
+    query: Query,
+    page_size: usize,
+    persistent_refs: vec::IntoIter<CFData>,
+}
+
+impl ItemPages {
+    /// Get the number of matching items which haven't been paged through yet
+    pub fn remaining(&self) -> usize {
+        self.persistent_refs.len()
+    }
+}
+
+impl Iterator for ItemPages {
+    type Item = Result<Vec<ItemAttributes>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let page: Vec<CFData> = self.persistent_refs.by_ref().take(self.page_size).collect();
+
+        if page.is_empty() {
+            return None;
+        }
+
+        let query = self
+            .query
+            .clone()
+            .match_persistent_refs(&page)
+            .match_limit(MatchLimit::Number(page.len()));
+
+        Some(ItemAttributes::find_returning(
+            self.class,
+            &query,
+            &[unsafe { kSecReturnRef }, unsafe { kSecReturnPersistentRef }],
+        ))
+    }
+}
+
+/// Iterator over the attributes of all items in a keychain (see
+/// `Keychain::items`), one class at a time.
+#[derive(Debug)]
+pub struct KeychainItems {
+    query: Query,
+    classes: vec::IntoIter<Class>,
+    pages: Option<ItemPages>,
+    page: vec::IntoIter<ItemAttributes>,
+}
+
+impl KeychainItems {
+    /// Enumerate the items of the given classes (or if none are given, all
+    /// items) matching the given `Query`.
+    pub(crate) fn new(classes: &[Class], query: Query) -> Self {
+        let classes = if classes.is_empty() {
+            STORED_CLASSES
+        } else {
+            classes
+        };
+
+        KeychainItems {
+            query,
+            classes: Vec::from(classes).into_iter(),
+            pages: None,
+            page: vec![].into_iter(),
+        }
+    }
+}
+
+impl Iterator for KeychainItems {
+    type Item = Result<ItemAttributes, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(item) = self.page.next() {
+                return Some(Ok(item));
+            }
+
+            if let Some(pages) = self.pages.as_mut() {
+                match pages.next() {
+                    Some(Ok(page)) => {
+                        self.page = page.into_iter();
+                        continue;
+                    }
+                    Some(Err(e)) => return Some(Err(e)),
+                    None => self.pages = None,
+                }
+            }
+
+            let class = self.classes.next()?;
+
+            match ItemAttributes::pages(class, &self.query, KEYCHAIN_ITEMS_PAGE_SIZE) {
+                Ok(pages) => self.pages = Some(pages),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+impl ItemAttributes {
+    /// Page through the attributes of all items of the given class matching
+    /// the given `Query`, at most `page_size` items at a time, rather than
+    /// fetching them all at once.
+    ///
+    /// If the query has a `MatchLimit`, at most that many items are paged
+    /// through in total.
+    pub fn pages<Q: Borrow<Query>>(
+        class: Class,
+        query: Q,
+        page_size: usize,
+    ) -> Result<ItemPages, Error> {
+        if page_size == 0 {
+            return Err(Error::new(ErrorKind::Param, "page size must be non-zero"));
+        }
+
+        let persistent_refs = find_persistent_refs(class, query.borrow())?;
+
+        Ok(ItemPages {
+            class,
+            query: query.borrow().clone(),
+            page_size,
+            persistent_refs: persistent_refs.into_iter(),
+        })
+    }
+}
+
+/// Find the persistent references of all items of the given class matching
+/// the given `Query` (without their attributes).
+pub(super) fn find_persistent_refs(class: Class, query: &Query) -> Result<Vec<CFData>, Error> {
+    hooks::run(OperationKind::Find, Some(class), || {
+        let mut params = DictionaryBuilder::from(query);
+        params.add_class(class);
+        params.add_boolean(unsafe { kSecReturnPersistentRef }, true);
+
+        // Persistent references must be returned as an array, which
+        // `MatchLimit::One` doesn't
+        let limit = match params.get(unsafe { kSecMatchLimit }) {
+            None => MatchLimit::All.as_CFType(),
+            Some(limit) if *limit == MatchLimit::One.as_CFType() => {
+                MatchLimit::Number(1).as_CFType()
+            }
+            Some(limit) => limit.clone(),
+        };
+
+        params.remove(unsafe { kSecMatchLimit });
+        params.add(unsafe { kSecMatchLimit }, &limit);
+
+        let mut result: CFTypeRef = ptr::null_mut();
+        let status = unsafe {
+            SecItemCopyMatching(
+                Dictionary::from(params.clone()).as_concrete_TypeRef(),
+                &mut result,
+            )
+        };
+
+        if let Some(e) = Error::maybe_from_OSStatus(status) {
+            return match e.kind() {
+                ErrorKind::ItemNotFound => Ok(vec![]),
+                _ => Err(entitlements::diagnose(e, &params)),
+            };
+        }
+
+        let persistent_refs =
+            unsafe { CFArray::<CFData>::wrap_under_create_rule(result as CFArrayRef) };
+        Ok(persistent_refs.iter().map(|data| (*data).clone()).collect())
+    })
+}
@@ -1,9 +1,31 @@
-use crate::{attr::*, dictionary::DictionaryBuilder, error::Error, ffi::*, keychain::*};
-use std::str;
+use super::{ItemChanges, ItemTags};
+use crate::{
+    access::AccessControl, attr::*, dictionary::DictionaryBuilder, error::*, ffi::*, keychain::*,
+};
+#[cfg(feature = "legacy-keychain")]
+use core_foundation::array::CFArray;
+use core_foundation::{
+    base::{CFType, TCFType},
+    string::CFString,
+};
+#[cfg(feature = "legacy-keychain")]
+use std::slice;
+use std::{
+    fmt::{self, Debug, Display},
+    str,
+    time::SystemTime,
+};
+#[cfg(feature = "url")]
+use url::Url;
 use zeroize::Zeroize;
 
+/// Service of the generic passwords macOS stores Wi-Fi (a.k.a. AirPort)
+/// network passwords as, with the network's SSID as the account.
+pub const AIRPORT_SERVICE: &str = "AirPort";
+
 /// Generic passwords
-pub struct GenericPassword(Item);
+#[derive(Debug)]
+pub struct GenericPassword(pub(super) Item);
 
 impl GenericPassword {
     /// Create a new generic password item in the given keychain.
@@ -22,14 +44,86 @@ impl GenericPassword {
         Ok(GenericPassword(keychain.add_item(attrs)?))
     }
 
-    /// Find a generic password in the given keychain.
-    pub fn find(keychain: &Keychain, service: &str, account: &str) -> Result<Self, Error> {
+    /// Create a new generic password item in the given keychain using the
+    /// given `GenericPasswordParams`.
+    pub fn create_with_params(
+        keychain: &Keychain,
+        params: &GenericPasswordParams,
+        password: &str,
+    ) -> Result<Self, Error> {
+        let mut attrs = DictionaryBuilder::from(params);
+        attrs.add_string(unsafe { kSecValueData }, password);
+
+        Ok(GenericPassword(keychain.add_item(attrs)?))
+    }
+
+    /// Find a generic password in the given keychain, returning `Ok(None)`
+    /// if no such password exists.
+    pub fn find(keychain: &Keychain, service: &str, account: &str) -> Result<Option<Self>, Error> {
         let mut attrs = DictionaryBuilder::new();
         attrs.add_class(item::Class::GenericPassword);
         attrs.add_string(AttrKind::Service, service);
         attrs.add_string(AttrKind::Account, account);
 
-        Ok(GenericPassword(keychain.find_item(attrs)?))
+        Ok(keychain.find_item(attrs)?.map(GenericPassword))
+    }
+
+    /// Find the saved Wi-Fi password for the network with the given SSID,
+    /// returning `Ok(None)` if no password is saved for it.
+    ///
+    /// macOS stores these as generic passwords with a service of
+    /// `AIRPORT_SERVICE` and the SSID as the account, in the System keychain
+    /// (or for networks synced via iCloud, the user's keychains), which are
+    /// searched in that order.
+    #[cfg(feature = "legacy-keychain")]
+    pub fn find_wifi(ssid: &str) -> Result<Option<Self>, Error> {
+        let mut attrs = DictionaryBuilder::new();
+        attrs.add_class(item::Class::GenericPassword);
+        attrs.add_string(AttrKind::Service, AIRPORT_SERVICE);
+        attrs.add_string(AttrKind::Account, ssid);
+
+        let system = Keychain::system()?;
+        let mut system_attrs = attrs.clone();
+        system_attrs.add(
+            unsafe { kSecMatchSearchList },
+            &CFArray::from_CFTypes(slice::from_ref(&system)),
+        );
+
+        match system.find_item(system_attrs)? {
+            Some(item) => Ok(Some(GenericPassword(item))),
+            None => Ok(Keychain::find_default()?
+                .find_item(attrs)?
+                .map(GenericPassword)),
+        }
+    }
+
+    /// Get the saved Wi-Fi password for the network with the given SSID
+    /// (see `GenericPassword::find_wifi`), returning `Ok(None)` if no
+    /// password is saved for it.
+    ///
+    /// Reading passwords from the System keychain requires an administrator
+    /// to authorize access, so this prompts for their credentials. If the
+    /// prompt is dismissed an error of kind `ErrorKind::UserCanceled` is
+    /// returned, and if it can't be shown (e.g. in an SSH session) an error
+    /// of kind `ErrorKind::InteractionNotAllowed`.
+    #[cfg(feature = "legacy-keychain")]
+    pub fn wifi_password(ssid: &str) -> Result<Option<PasswordData>, Error> {
+        let password = match Self::find_wifi(ssid)? {
+            Some(password) => password,
+            None => return Ok(None),
+        };
+
+        password.password().map(Some).map_err(|e| match e.kind() {
+            ErrorKind::InteractionNotAllowed => Error::new(
+                ErrorKind::InteractionNotAllowed,
+                &format!(
+                    "can't prompt for authorization to read Wi-Fi password for {:?} \
+                     (not running in an interactive session?)",
+                    ssid
+                ),
+            ),
+            _ => e,
+        })
     }
 
     /// Get the account this password is associated with
@@ -42,14 +136,126 @@ impl GenericPassword {
         self.0.attribute(AttrKind::Service)
     }
 
+    /// Get the application-defined generic data attached to this password
+    pub fn generic(&self) -> Result<AttrGeneric, Error> {
+        Ok(AttrGeneric::new(&self.0.attribute_data(AttrKind::Generic)?))
+    }
+
+    /// Get the date this password was created
+    pub fn creation_date(&self) -> Result<SystemTime, Error> {
+        self.0.creation_date()
+    }
+
+    /// Get the date this password was last modified
+    pub fn modification_date(&self) -> Result<SystemTime, Error> {
+        self.0.modification_date()
+    }
+
+    /// Get the label of this password
+    pub fn label(&self) -> Result<String, Error> {
+        self.0.label()
+    }
+
+    /// Change the label of this password in place (see `Item::set_label`)
+    pub fn set_label<L: Into<AttrLabel>>(&self, label: L) -> Result<(), Error> {
+        self.0.set_label(label)
+    }
+
+    /// Bump the modification date of this password (see `Item::touch`)
+    pub fn touch(&self) -> Result<(), Error> {
+        self.0.touch()
+    }
+
+    /// Apply the given changes to this password in place (see
+    /// `Item::update`)
+    pub fn update(&self, changes: &ItemChanges) -> Result<(), Error> {
+        self.0.update(changes)
+    }
+
+    /// Change the protection class of this password in place (see
+    /// `Item::set_accessible`)
+    pub fn set_accessible(&self, accessible: AttrAccessible) -> Result<(), Error> {
+        self.0.set_accessible(accessible)
+    }
+
+    /// Get the keychain this password is stored in (see `Item::keychain`)
+    #[cfg(feature = "legacy-keychain")]
+    pub fn keychain(&self) -> Result<Keychain, Error> {
+        self.0.keychain()
+    }
+
+    /// Delete this password from its keychain (see `Item::delete`)
+    #[cfg(feature = "legacy-keychain")]
+    pub fn delete(self) -> Result<(), Error> {
+        self.0.delete()
+    }
+
+    /// Get the comment associated with this password
+    pub fn comment(&self) -> Result<String, Error> {
+        self.0.comment()
+    }
+
+    /// Get the user-defined tags attached to this password
+    pub fn tags(&self) -> Result<ItemTags, Error> {
+        self.0.tags()
+    }
+
+    /// Replace the user-defined tags attached to this password
+    pub fn set_tags(&self, tags: &ItemTags) -> Result<(), Error> {
+        self.0.set_tags(tags)
+    }
+
+    /// Get the description of this password's kind
+    pub fn description(&self) -> Result<String, Error> {
+        self.0.description()
+    }
+
+    /// Get the creator code of this password
+    pub fn creator(&self) -> Result<AttrCreator, Error> {
+        self.0.creator()
+    }
+
+    /// Get the type code of this password
+    pub fn item_type(&self) -> Result<AttrItemType, Error> {
+        self.0.item_type()
+    }
+
+    /// Is this password hidden from the Keychain Access application?
+    pub fn is_invisible(&self) -> Result<bool, Error> {
+        self.0.is_invisible()
+    }
+
+    /// Is this a negative entry (i.e. the user declined to store a password)?
+    pub fn is_negative(&self) -> Result<bool, Error> {
+        self.0.is_negative()
+    }
+
     /// Get the raw password value
     pub fn password(&self) -> Result<PasswordData, Error> {
         Ok(PasswordData(self.0.data()?))
     }
+
+    /// Change the password value in place, preserving the item's other
+    /// attributes and access control settings.
+    ///
+    /// Wrapper for the `SecItemUpdate` function. See:
+    /// <https://developer.apple.com/documentation/security/1393617-secitemupdate>
+    pub fn set_password(&self, password: &str) -> Result<(), Error> {
+        let mut attrs = DictionaryBuilder::new();
+        attrs.add_string(unsafe { kSecValueData }, password);
+        item::update(self.0.class(), &self.0, attrs)
+    }
+
+    /// Copy this password into another keychain or access group (see
+    /// `Item::copy_to`).
+    pub fn copy_to(&self, destination: item::ItemDestination) -> Result<(), Error> {
+        self.0.copy_to(destination)
+    }
 }
 
 /// Internet passwords
-pub struct InternetPassword(Item);
+#[derive(Debug)]
+pub struct InternetPassword(pub(super) Item);
 
 impl InternetPassword {
     /// Create a new Internet password item in the given keychain.
@@ -68,13 +274,39 @@ impl InternetPassword {
         Ok(InternetPassword(keychain.add_item(attrs)?))
     }
 
-    /// Find an Internet password in the given keychain.
+    /// Create a new Internet password item in the given keychain using the
+    /// given `InternetPasswordParams`.
+    pub fn create_with_params(
+        keychain: &Keychain,
+        params: &InternetPasswordParams,
+        password: &str,
+    ) -> Result<Self, Error> {
+        let mut attrs = DictionaryBuilder::from(params);
+        attrs.add_string(unsafe { kSecValueData }, password);
+
+        Ok(InternetPassword(keychain.add_item(attrs)?))
+    }
+
+    /// Find an Internet password in the given keychain matching the given
+    /// `InternetPasswordParams`, returning `Ok(None)` if no such password
+    /// exists.
+    pub fn find_with_params(
+        keychain: &Keychain,
+        params: &InternetPasswordParams,
+    ) -> Result<Option<Self>, Error> {
+        Ok(keychain
+            .find_item(DictionaryBuilder::from(params))?
+            .map(InternetPassword))
+    }
+
+    /// Find an Internet password in the given keychain, returning `Ok(None)`
+    /// if no such password exists.
     pub fn find(
         keychain: &Keychain,
         server: &str,
         account: &str,
         protocol: Option<AttrProtocol>,
-    ) -> Result<Self, Error> {
+    ) -> Result<Option<Self>, Error> {
         let mut attrs = DictionaryBuilder::new();
         attrs.add_class(item::Class::InternetPassword);
         attrs.add_string(AttrKind::Server, server);
@@ -84,7 +316,7 @@ impl InternetPassword {
             attrs.add_attr(&proto);
         }
 
-        Ok(InternetPassword(keychain.find_item(attrs)?))
+        Ok(keychain.find_item(attrs)?.map(InternetPassword))
     }
 
     /// Get the account this password is associated with
@@ -97,10 +329,624 @@ impl InternetPassword {
         self.0.attribute(AttrKind::Server)
     }
 
+    /// Get the date this password was created
+    pub fn creation_date(&self) -> Result<SystemTime, Error> {
+        self.0.creation_date()
+    }
+
+    /// Get the date this password was last modified
+    pub fn modification_date(&self) -> Result<SystemTime, Error> {
+        self.0.modification_date()
+    }
+
+    /// Get the label of this password
+    pub fn label(&self) -> Result<String, Error> {
+        self.0.label()
+    }
+
+    /// Change the label of this password in place (see `Item::set_label`)
+    pub fn set_label<L: Into<AttrLabel>>(&self, label: L) -> Result<(), Error> {
+        self.0.set_label(label)
+    }
+
+    /// Bump the modification date of this password (see `Item::touch`)
+    pub fn touch(&self) -> Result<(), Error> {
+        self.0.touch()
+    }
+
+    /// Apply the given changes to this password in place (see
+    /// `Item::update`)
+    pub fn update(&self, changes: &ItemChanges) -> Result<(), Error> {
+        self.0.update(changes)
+    }
+
+    /// Change the protection class of this password in place (see
+    /// `Item::set_accessible`)
+    pub fn set_accessible(&self, accessible: AttrAccessible) -> Result<(), Error> {
+        self.0.set_accessible(accessible)
+    }
+
+    /// Get the keychain this password is stored in (see `Item::keychain`)
+    #[cfg(feature = "legacy-keychain")]
+    pub fn keychain(&self) -> Result<Keychain, Error> {
+        self.0.keychain()
+    }
+
+    /// Delete this password from its keychain (see `Item::delete`)
+    #[cfg(feature = "legacy-keychain")]
+    pub fn delete(self) -> Result<(), Error> {
+        self.0.delete()
+    }
+
+    /// Get the comment associated with this password
+    pub fn comment(&self) -> Result<String, Error> {
+        self.0.comment()
+    }
+
+    /// Get the user-defined tags attached to this password
+    pub fn tags(&self) -> Result<ItemTags, Error> {
+        self.0.tags()
+    }
+
+    /// Replace the user-defined tags attached to this password
+    pub fn set_tags(&self, tags: &ItemTags) -> Result<(), Error> {
+        self.0.set_tags(tags)
+    }
+
+    /// Get the description of this password's kind
+    pub fn description(&self) -> Result<String, Error> {
+        self.0.description()
+    }
+
+    /// Get the creator code of this password
+    pub fn creator(&self) -> Result<AttrCreator, Error> {
+        self.0.creator()
+    }
+
+    /// Get the type code of this password
+    pub fn item_type(&self) -> Result<AttrItemType, Error> {
+        self.0.item_type()
+    }
+
+    /// Is this password hidden from the Keychain Access application?
+    pub fn is_invisible(&self) -> Result<bool, Error> {
+        self.0.is_invisible()
+    }
+
+    /// Is this a negative entry (i.e. the user declined to store a password)?
+    pub fn is_negative(&self) -> Result<bool, Error> {
+        self.0.is_negative()
+    }
+
+    /// Get the port this password is associated with
+    pub fn port(&self) -> Result<AttrPort, Error> {
+        let data = self.0.attribute_data(AttrKind::Port)?;
+
+        if data.len() != 4 {
+            return Err(Error::new(
+                ErrorKind::Decode,
+                &format!("malformed port attribute ({} bytes)", data.len()),
+            ));
+        }
+
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&data);
+        let port = u32::from_ne_bytes(bytes);
+
+        if port > u32::from(u16::MAX) {
+            return Err(Error::new(
+                ErrorKind::Decode,
+                &format!("port attribute out of range: {}", port),
+            ));
+        }
+
+        Ok(AttrPort(port as u16))
+    }
+
+    /// Get the path this password is associated with
+    pub fn path(&self) -> Result<String, Error> {
+        self.0.attribute(AttrKind::Path)
+    }
+
+    /// Get the security domain this password is associated with
+    pub fn security_domain(&self) -> Result<String, Error> {
+        self.0.attribute(AttrKind::SecurityDomain)
+    }
+
+    /// Get the authentication scheme this password is used with
+    pub fn authentication_type(&self) -> Result<AttrAuthenticationType, Error> {
+        let code = self
+            .0
+            .four_character_code_attribute(AttrKind::AuthenticationType)?;
+
+        AttrAuthenticationType::from_code(&code).ok_or_else(|| {
+            Error::new(
+                ErrorKind::Decode,
+                &format!(
+                    "unknown authentication type: {}",
+                    String::from_utf8_lossy(&code)
+                ),
+            )
+        })
+    }
+
     /// Get the raw password value
     pub fn password(&self) -> Result<PasswordData, Error> {
         Ok(PasswordData(self.0.data()?))
     }
+
+    /// Change the password value in place, preserving the item's other
+    /// attributes and access control settings.
+    ///
+    /// Wrapper for the `SecItemUpdate` function. See:
+    /// <https://developer.apple.com/documentation/security/1393617-secitemupdate>
+    pub fn set_password(&self, password: &str) -> Result<(), Error> {
+        let mut attrs = DictionaryBuilder::new();
+        attrs.add_string(unsafe { kSecValueData }, password);
+        item::update(self.0.class(), &self.0, attrs)
+    }
+
+    /// Copy this password into another keychain or access group (see
+    /// `Item::copy_to`).
+    pub fn copy_to(&self, destination: item::ItemDestination) -> Result<(), Error> {
+        self.0.copy_to(destination)
+    }
+}
+
+impl Display for GenericPassword {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl Display for InternetPassword {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+/// Builder for the attributes of a generic password, i.e. service and
+/// account along with optional metadata (comment, description, etc).
+///
+/// These are passed to `GenericPassword::create_with_params`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GenericPasswordParams(DictionaryBuilder);
+
+impl GenericPasswordParams {
+    /// Create new generic password parameters for the given service and
+    /// account.
+    pub fn new(service: &str, account: &str) -> Self {
+        let mut attrs = DictionaryBuilder::new();
+        attrs.add_class(item::Class::GenericPassword);
+        attrs.add_string(AttrKind::Service, service);
+        attrs.add_string(AttrKind::Account, account);
+        GenericPasswordParams(attrs)
+    }
+
+    /// Attach application-defined generic data to this password.
+    ///
+    /// Wrapper for the `kSecAttrGeneric` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrgeneric>
+    pub fn generic<G: Into<AttrGeneric>>(mut self, generic: G) -> Self {
+        self.0.add_attr(&generic.into());
+        self
+    }
+
+    /// Set a user-visible label on this password.
+    ///
+    /// Wrapper for the `kSecAttrLabel` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrlabel>
+    pub fn label<L: Into<AttrLabel>>(mut self, label: L) -> Self {
+        self.0.add_attr(&label.into());
+        self
+    }
+
+    /// Set a user-visible comment on this password.
+    ///
+    /// Wrapper for the `kSecAttrComment` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcomment>
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.0.remove(AttrKind::Comment);
+        self.0.add_string(AttrKind::Comment, comment);
+        self
+    }
+
+    /// Attach the given user-defined tags to this password (see
+    /// `ItemTags`). These are stored in the comment attribute, so this
+    /// replaces any comment set with `comment()`.
+    pub fn tags(mut self, tags: &ItemTags) -> Self {
+        self.0.remove(AttrKind::Comment);
+        self.0.add_string(AttrKind::Comment, tags.to_string());
+        self
+    }
+
+    /// Set the creator code of this password.
+    ///
+    /// Wrapper for the `kSecAttrCreator` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcreator>
+    pub fn creator<C: Into<AttrCreator>>(mut self, creator: C) -> Self {
+        self.0.add_attr(&creator.into());
+        self
+    }
+
+    /// Set a user-visible description of this password's kind (e.g.
+    /// "application password").
+    ///
+    /// Wrapper for the `kSecAttrDescription` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrdescription>
+    pub fn description(mut self, description: &str) -> Self {
+        self.0.add_string(AttrKind::Description, description);
+        self
+    }
+
+    /// Set whether this password is hidden from the Keychain Access
+    /// application.
+    ///
+    /// Wrapper for the `kSecAttrIsInvisible` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrisinvisible>
+    pub fn invisible(mut self, value: bool) -> Self {
+        self.0.add_boolean(AttrKind::Invisible, value);
+        self
+    }
+
+    /// Set the type code of this password.
+    ///
+    /// Wrapper for the `kSecAttrType` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrtype>
+    pub fn item_type<T: Into<AttrItemType>>(mut self, item_type: T) -> Self {
+        self.0.add_attr(&item_type.into());
+        self
+    }
+
+    /// Set whether this is a negative entry, i.e. one which records that
+    /// the user declined to store a password.
+    ///
+    /// Wrapper for the `kSecAttrIsNegative` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrisnegative>
+    pub fn negative(mut self, value: bool) -> Self {
+        self.0.add_boolean(AttrKind::Negative, value);
+        self
+    }
+
+    /// Set when this password is accessible (e.g. only while the device is
+    /// unlocked).
+    ///
+    /// Wrapper for the `kSecAttrAccessible` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccessible>
+    pub fn accessible(mut self, accessible: AttrAccessible) -> Self {
+        self.0.add_attr(&accessible);
+        self
+    }
+
+    /// Store this password in the given access group of the data protection
+    /// keychain, to share it with other applications and extensions from
+    /// the same team. The application must be entitled to use the access
+    /// group.
+    ///
+    /// Wrapper for the `kSecAttrAccessGroup` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccessgroup>
+    pub fn access_group(mut self, access_group: &str) -> Self {
+        self.0
+            .add_string(unsafe { kSecAttrAccessGroup }, access_group);
+        self.data_protection_keychain(true)
+    }
+
+    /// Store (or look up) this password in the data protection keychain
+    /// rather than a legacy file-based keychain (see
+    /// `Query::data_protection_keychain`).
+    ///
+    /// Wrapper for the `kSecUseDataProtectionKeychain` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecusedataprotectionkeychain>
+    pub fn data_protection_keychain(mut self, value: bool) -> Self {
+        self.0.remove(unsafe { kSecUseDataProtectionKeychain });
+        self.0
+            .add_boolean(unsafe { kSecUseDataProtectionKeychain }, value);
+        self
+    }
+
+    /// Set whether this password is synchronized with other devices via
+    /// iCloud Keychain (which limits its size, see `DataSizeLimits`).
+    ///
+    /// Wrapper for the `kSecAttrSynchronizable` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrsynchronizable>
+    pub fn synchronizable(mut self, value: bool) -> Self {
+        self.0.add_boolean(AttrKind::Synchronizable, value);
+        self
+    }
+
+    /// Set the access control policy (a.k.a. ACL) for this password, which
+    /// takes precedence over any `accessible()` setting.
+    ///
+    /// Wrapper for the `kSecAttrAccessControl` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccesscontrol>
+    pub fn access_control(mut self, access_control: &AccessControl) -> Self {
+        self.0.add(AttrKind::AccessControl, access_control);
+        self
+    }
+
+    /// Set an attribute this crate doesn't wrap yet on this password (see
+    /// `Query::raw_attribute`).
+    pub fn raw_attribute(mut self, key: CFString, value: CFType) -> Self {
+        self.0.remove(key.as_concrete_TypeRef());
+        self.0.add(key.as_concrete_TypeRef(), &value);
+        self
+    }
+}
+
+impl From<GenericPasswordParams> for DictionaryBuilder {
+    fn from(params: GenericPasswordParams) -> DictionaryBuilder {
+        params.0
+    }
+}
+
+impl<'a> From<&'a GenericPasswordParams> for DictionaryBuilder {
+    fn from(params: &'a GenericPasswordParams) -> DictionaryBuilder {
+        params.0.clone()
+    }
+}
+
+/// Builder for the attributes which identify an Internet password, i.e.
+/// server, account, protocol, port, path, security domain and
+/// authentication type.
+///
+/// These are passed to `InternetPassword::create_with_params` and
+/// `InternetPassword::find_with_params`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InternetPasswordParams(DictionaryBuilder);
+
+impl InternetPasswordParams {
+    /// Create new Internet password parameters for the given server.
+    pub fn new(server: AttrServer) -> Self {
+        let mut attrs = DictionaryBuilder::new();
+        attrs.add_class(item::Class::InternetPassword);
+        attrs.add_attr(&server);
+        InternetPasswordParams(attrs)
+    }
+
+    /// Decompose a URL into Internet password parameters: the host becomes
+    /// the server, the scheme the protocol, and the username (if any) the
+    /// account. An explicit port and non-root path are also included.
+    ///
+    /// Returns an error with a kind of `ErrorKind::Param` if the URL has no
+    /// host or its scheme is not a supported `AttrProtocol`.
+    #[cfg(feature = "url")]
+    pub fn from_url(url: &Url) -> Result<Self, Error> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| Error::new(ErrorKind::Param, &format!("URL has no host: {}", url)))?;
+
+        let protocol = AttrProtocol::from_scheme(url.scheme()).ok_or_else(|| {
+            Error::new(
+                ErrorKind::Param,
+                &format!("unsupported URL scheme: {}", url.scheme()),
+            )
+        })?;
+
+        let mut params = Self::new(AttrServer::new(host)?).protocol(protocol);
+
+        if !url.username().is_empty() {
+            params = params.account(url.username());
+        }
+
+        if let Some(port) = url.port() {
+            params = params.port(port);
+        }
+
+        if url.path() != "/" && !url.path().is_empty() {
+            params = params.path(url.path());
+        }
+
+        Ok(params)
+    }
+
+    /// Set the account this password is associated with.
+    ///
+    /// Wrapper for the `kSecAttrAccount` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccount>
+    pub fn account(mut self, account: &str) -> Self {
+        self.0.add_string(AttrKind::Account, account);
+        self
+    }
+
+    /// Set the path (e.g. of a URL) this password is associated with.
+    ///
+    /// Wrapper for the `kSecAttrPath` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrpath>
+    pub fn path<P: Into<AttrPath>>(mut self, path: P) -> Self {
+        self.0.add_attr(&path.into());
+        self
+    }
+
+    /// Set the port this password is associated with.
+    ///
+    /// Wrapper for the `kSecAttrPort` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrport>
+    pub fn port<P: Into<AttrPort>>(mut self, port: P) -> Self {
+        self.0.add_attr(&port.into());
+        self
+    }
+
+    /// Set the protocol this password is associated with.
+    ///
+    /// Wrapper for the `kSecAttrProtocol` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrprotocol>
+    pub fn protocol(mut self, protocol: AttrProtocol) -> Self {
+        self.0.add_attr(&protocol);
+        self
+    }
+
+    /// Set the authentication scheme this password is used with.
+    ///
+    /// Wrapper for the `kSecAttrAuthenticationType` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrauthenticationtype>
+    pub fn authentication_type(mut self, auth_type: AttrAuthenticationType) -> Self {
+        self.0.add_attr(&auth_type);
+        self
+    }
+
+    /// Set the security domain (e.g. HTTP authentication realm) this
+    /// password is associated with.
+    ///
+    /// Wrapper for the `kSecAttrSecurityDomain` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrsecuritydomain>
+    pub fn security_domain<D: Into<AttrSecurityDomain>>(mut self, domain: D) -> Self {
+        self.0.add_attr(&domain.into());
+        self
+    }
+
+    /// Set a user-visible label on this password.
+    ///
+    /// Wrapper for the `kSecAttrLabel` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrlabel>
+    pub fn label<L: Into<AttrLabel>>(mut self, label: L) -> Self {
+        self.0.add_attr(&label.into());
+        self
+    }
+
+    /// Set a user-visible comment on this password.
+    ///
+    /// Wrapper for the `kSecAttrComment` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcomment>
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.0.remove(AttrKind::Comment);
+        self.0.add_string(AttrKind::Comment, comment);
+        self
+    }
+
+    /// Attach the given user-defined tags to this password (see
+    /// `ItemTags`). These are stored in the comment attribute, so this
+    /// replaces any comment set with `comment()`.
+    pub fn tags(mut self, tags: &ItemTags) -> Self {
+        self.0.remove(AttrKind::Comment);
+        self.0.add_string(AttrKind::Comment, tags.to_string());
+        self
+    }
+
+    /// Set the creator code of this password.
+    ///
+    /// Wrapper for the `kSecAttrCreator` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcreator>
+    pub fn creator<C: Into<AttrCreator>>(mut self, creator: C) -> Self {
+        self.0.add_attr(&creator.into());
+        self
+    }
+
+    /// Set a user-visible description of this password's kind (e.g.
+    /// "application password").
+    ///
+    /// Wrapper for the `kSecAttrDescription` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrdescription>
+    pub fn description(mut self, description: &str) -> Self {
+        self.0.add_string(AttrKind::Description, description);
+        self
+    }
+
+    /// Set whether this password is hidden from the Keychain Access
+    /// application.
+    ///
+    /// Wrapper for the `kSecAttrIsInvisible` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrisinvisible>
+    pub fn invisible(mut self, value: bool) -> Self {
+        self.0.add_boolean(AttrKind::Invisible, value);
+        self
+    }
+
+    /// Set the type code of this password.
+    ///
+    /// Wrapper for the `kSecAttrType` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrtype>
+    pub fn item_type<T: Into<AttrItemType>>(mut self, item_type: T) -> Self {
+        self.0.add_attr(&item_type.into());
+        self
+    }
+
+    /// Set whether this is a negative entry, i.e. one which records that
+    /// the user declined to store a password.
+    ///
+    /// Wrapper for the `kSecAttrIsNegative` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrisnegative>
+    pub fn negative(mut self, value: bool) -> Self {
+        self.0.add_boolean(AttrKind::Negative, value);
+        self
+    }
+
+    /// Set when this password is accessible (e.g. only while the device is
+    /// unlocked).
+    ///
+    /// Wrapper for the `kSecAttrAccessible` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccessible>
+    pub fn accessible(mut self, accessible: AttrAccessible) -> Self {
+        self.0.add_attr(&accessible);
+        self
+    }
+
+    /// Store this password in the given access group of the data protection
+    /// keychain, to share it with other applications and extensions from
+    /// the same team. The application must be entitled to use the access
+    /// group.
+    ///
+    /// Wrapper for the `kSecAttrAccessGroup` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccessgroup>
+    pub fn access_group(mut self, access_group: &str) -> Self {
+        self.0
+            .add_string(unsafe { kSecAttrAccessGroup }, access_group);
+        self.data_protection_keychain(true)
+    }
+
+    /// Store (or look up) this password in the data protection keychain
+    /// rather than a legacy file-based keychain (see
+    /// `Query::data_protection_keychain`).
+    ///
+    /// Wrapper for the `kSecUseDataProtectionKeychain` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecusedataprotectionkeychain>
+    pub fn data_protection_keychain(mut self, value: bool) -> Self {
+        self.0.remove(unsafe { kSecUseDataProtectionKeychain });
+        self.0
+            .add_boolean(unsafe { kSecUseDataProtectionKeychain }, value);
+        self
+    }
+
+    /// Set whether this password is synchronized with other devices via
+    /// iCloud Keychain (which limits its size, see `DataSizeLimits`).
+    ///
+    /// Wrapper for the `kSecAttrSynchronizable` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrsynchronizable>
+    pub fn synchronizable(mut self, value: bool) -> Self {
+        self.0.add_boolean(AttrKind::Synchronizable, value);
+        self
+    }
+
+    /// Set the access control policy (a.k.a. ACL) for this password, which
+    /// takes precedence over any `accessible()` setting.
+    ///
+    /// Wrapper for the `kSecAttrAccessControl` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccesscontrol>
+    pub fn access_control(mut self, access_control: &AccessControl) -> Self {
+        self.0.add(AttrKind::AccessControl, access_control);
+        self
+    }
+
+    /// Set an attribute this crate doesn't wrap yet on this password (see
+    /// `Query::raw_attribute`).
+    pub fn raw_attribute(mut self, key: CFString, value: CFType) -> Self {
+        self.0.remove(key.as_concrete_TypeRef());
+        self.0.add(key.as_concrete_TypeRef(), &value);
+        self
+    }
+}
+
+impl From<InternetPasswordParams> for DictionaryBuilder {
+    fn from(params: InternetPasswordParams) -> DictionaryBuilder {
+        params.0
+    }
+}
+
+impl<'a> From<&'a InternetPasswordParams> for DictionaryBuilder {
+    fn from(params: &'a InternetPasswordParams) -> DictionaryBuilder {
+        params.0.clone()
+    }
 }
 
 /// Wrapper around password data that ensures it is cleared from memory after
@@ -109,6 +955,11 @@ impl InternetPassword {
 pub struct PasswordData(Vec<u8>);
 
 impl PasswordData {
+    /// Wrap the given secret data
+    pub(crate) fn new(data: Vec<u8>) -> Self {
+        PasswordData(data)
+    }
+
     /// Borrow the password as a byte slice
     pub fn as_bytes(&self) -> &[u8] {
         self.0.as_ref()
@@ -133,6 +984,12 @@ impl AsRef<[u8]> for PasswordData {
     }
 }
 
+impl Debug for PasswordData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PasswordData([REDACTED])")
+    }
+}
+
 impl Drop for PasswordData {
     fn drop(&mut self) {
         self.0.zeroize();
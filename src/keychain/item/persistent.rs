@@ -0,0 +1,56 @@
+//! Persistent references to keychain items, i.e. opaque identifiers which
+//! remain valid across process launches and can be stored (e.g. in a
+//! database or configuration file) to look the item up again later.
+
+use core_foundation::data::CFData;
+use std::fmt::{self, Debug};
+
+/// Persistent reference to a keychain item.
+///
+/// Wrapper for the `kSecValuePersistentRef` attribute key. See:
+/// <https://developer.apple.com/documentation/security/ksecvaluepersistentref>
+#[derive(Clone, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PersistentItemRef(Vec<u8>);
+
+impl PersistentItemRef {
+    /// Restore a persistent reference from its serialized bytes (i.e. as
+    /// previously returned by `PersistentItemRef::as_bytes`)
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        PersistentItemRef(bytes.to_vec())
+    }
+
+    /// Borrow the serialized bytes of this persistent reference
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Get the `CFData` to use as the `kSecValuePersistentRef` of a query
+    pub(crate) fn as_CFData(&self) -> CFData {
+        CFData::from_buffer(&self.0)
+    }
+}
+
+impl AsRef<[u8]> for PersistentItemRef {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl From<Vec<u8>> for PersistentItemRef {
+    fn from(bytes: Vec<u8>) -> Self {
+        PersistentItemRef(bytes)
+    }
+}
+
+impl Debug for PersistentItemRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PersistentItemRef(")?;
+
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+
+        write!(f, ")")
+    }
+}
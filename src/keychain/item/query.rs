@@ -1,17 +1,27 @@
 //! Query the keychain, looking for particular items
 
-use crate::{attr::*, dictionary::DictionaryBuilder, ffi::*};
+use super::{
+    count_matching, delete_matching, delete_matching_everywhere, delete_matching_with_report,
+    update_matching, Class, DeletionReport, ItemAttributes, ItemChanges, ItemPages, PasswordData,
+};
+use crate::{
+    attr::*, date, dictionary::DictionaryBuilder, error::Error, ffi::*, keychain::Keychain,
+};
 use core_foundation::{
-    base::{CFType, TCFType},
+    array::CFArray,
+    base::{kCFNull, CFType, TCFType},
+    data::CFData,
     number::CFNumber,
     string::CFString,
 };
+use std::{slice, time::SystemTime};
 
 /// Limit the number of matched items to one or an unlimited number.
 ///
 /// Wrapper for the `kSecMatchLimit` attribute key. See:
 /// <https://developer.apple.com/documentation/security/ksecmatchlimit>
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MatchLimit {
     /// Match exactly one item.
     ///
@@ -53,7 +63,11 @@ impl MatchLimit {
 ///
 /// For more information, see "Search Attribute Keys and Values":
 /// <https://developer.apple.com/documentation/security/keychain_services/keychain_items/search_attribute_keys_and_values>
-#[derive(Default, Debug)]
+///
+/// Queries are cheap to clone, so a common base query can be built once and
+/// then specialized for individual lookups.
+#[derive(Clone, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Query(DictionaryBuilder);
 
 impl Query {
@@ -62,6 +76,63 @@ impl Query {
         Self::default()
     }
 
+    /// Query for items in the given access group of the data protection
+    /// keychain (which access groups only apply to on macOS).
+    ///
+    /// Wrapper for the `kSecAttrAccessGroup` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccessgroup>
+    pub fn access_group(mut self, access_group: &str) -> Self {
+        self.0
+            .add_string(unsafe { kSecAttrAccessGroup }, access_group);
+        self.data_protection_keychain(true)
+    }
+
+    /// Query the data protection keychain (i.e. the iOS-style keychain
+    /// available on macOS 10.15 and later) rather than the legacy
+    /// file-based keychains, which avoids their access control dialogs.
+    ///
+    /// Wrapper for the `kSecUseDataProtectionKeychain` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecusedataprotectionkeychain>
+    pub fn data_protection_keychain(mut self, value: bool) -> Self {
+        self.0.remove(unsafe { kSecUseDataProtectionKeychain });
+        self.0
+            .add_boolean(unsafe { kSecUseDataProtectionKeychain }, value);
+        self
+    }
+
+    /// Query for passwords associated with the given account.
+    ///
+    /// Wrapper for the `kSecAttrAccount` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccount>
+    pub fn account(mut self, account: &str) -> Self {
+        self.0.add_string(AttrKind::Account, account);
+        self
+    }
+
+    /// Search only the given keychain (rather than every keychain in the
+    /// search list).
+    ///
+    /// Wrapper for the `kSecMatchSearchList` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecmatchsearchlist>
+    pub fn keychain(self, keychain: &Keychain) -> Self {
+        self.keychains(slice::from_ref(keychain))
+    }
+
+    /// Search the given keychains (e.g. the login keychain along with a
+    /// custom one) in a single query, in the given order, rather than every
+    /// keychain in the default search list.
+    ///
+    /// Wrapper for the `kSecMatchSearchList` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecmatchsearchlist>
+    pub fn keychains(mut self, keychains: &[Keychain]) -> Self {
+        self.0.remove(unsafe { kSecMatchSearchList });
+        self.0.add(
+            unsafe { kSecMatchSearchList },
+            &CFArray::from_CFTypes(keychains),
+        );
+        self
+    }
+
     /// Query for keychain items with the provided `SecAttrApplicationLabel`
     /// (not to be confused with a `SecAttrLabel`), i.e. the hash/fingerprint
     /// of a public key in the keychain.
@@ -88,6 +159,157 @@ impl Query {
         self
     }
 
+    /// Query for generic passwords with the provided `AttrGeneric` data.
+    ///
+    /// Wrapper for the `kSecAttrGeneric` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrgeneric>
+    pub fn generic<G>(mut self, generic: G) -> Self
+    where
+        G: Into<AttrGeneric>,
+    {
+        self.0.add_attr(&generic.into());
+        self
+    }
+
+    /// Match string attributes (e.g. labels and accounts) regardless of case.
+    ///
+    /// Wrapper for the `kSecMatchCaseInsensitive` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecmatchcaseinsensitive>
+    pub fn case_insensitive(mut self, value: bool) -> Self {
+        self.0
+            .add_boolean(unsafe { kSecMatchCaseInsensitive }, value);
+        self
+    }
+
+    /// Query for keys which can or can't be used to derive other keys (e.g. by key agreement).
+    ///
+    /// Wrapper for the `kSecAttrCanDerive` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcanderive>
+    pub fn can_derive(mut self, value: bool) -> Self {
+        self.0.add_boolean(AttrKind::Derive, value);
+        self
+    }
+
+    /// Query for keys which can or can't be used to decrypt data.
+    ///
+    /// Wrapper for the `kSecAttrCanDecrypt` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcandecrypt>
+    pub fn can_decrypt(mut self, value: bool) -> Self {
+        self.0.add_boolean(AttrKind::Decrypt, value);
+        self
+    }
+
+    /// Query for keys which can or can't be used to encrypt data.
+    ///
+    /// Wrapper for the `kSecAttrCanEncrypt` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcanencrypt>
+    pub fn can_encrypt(mut self, value: bool) -> Self {
+        self.0.add_boolean(AttrKind::Encrypt, value);
+        self
+    }
+
+    /// Query for keys which can or can't be used to create signatures.
+    ///
+    /// Wrapper for the `kSecAttrCanSign` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcansign>
+    pub fn can_sign(mut self, value: bool) -> Self {
+        self.0.add_boolean(AttrKind::Sign, value);
+        self
+    }
+
+    /// Query for keys which can or can't be used to verify signatures.
+    ///
+    /// Wrapper for the `kSecAttrCanVerify` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcanverify>
+    pub fn can_verify(mut self, value: bool) -> Self {
+        self.0.add_boolean(AttrKind::Verify, value);
+        self
+    }
+
+    /// Query for keys which can or can't be used to wrap other keys.
+    ///
+    /// Wrapper for the `kSecAttrCanWrap` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcanwrap>
+    pub fn can_wrap(mut self, value: bool) -> Self {
+        self.0.add_boolean(AttrKind::Wrap, value);
+        self
+    }
+
+    /// Query for keys which can or can't be used to unwrap other keys.
+    ///
+    /// Wrapper for the `kSecAttrCanUnwrap` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcanunwrap>
+    pub fn can_unwrap(mut self, value: bool) -> Self {
+        self.0.add_boolean(AttrKind::Unwrap, value);
+        self
+    }
+
+    /// Query for certificates (or identities) with the given email address,
+    /// if they have one: certificates without any email address also match.
+    ///
+    /// Wrapper for the `kSecMatchEmailAddressIfPresent` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecmatchemailaddressifpresent>
+    pub fn email_address_if_present(mut self, email_address: &str) -> Self {
+        self.0
+            .add_string(unsafe { kSecMatchEmailAddressIfPresent }, email_address);
+        self
+    }
+
+    /// Query for certificates (or identities) issued by any of the given
+    /// issuers, i.e. DER-encoded distinguished names in normalized form
+    /// (see `Certificate::normalized_issuer`). Identities whose certificate
+    /// chains up to one of these issuers match too.
+    ///
+    /// Wrapper for the `kSecMatchIssuers` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecmatchissuers>
+    pub fn issuers<I: AsRef<[u8]>>(mut self, issuers: &[I]) -> Self {
+        let issuers: Vec<CFData> = issuers
+            .iter()
+            .map(|issuer| CFData::from_buffer(issuer.as_ref()))
+            .collect();
+
+        self.0.add(
+            unsafe { kSecMatchIssuers },
+            &CFArray::from_CFTypes(&issuers),
+        );
+        self
+    }
+
+    /// Query for keychain items created at the given time.
+    ///
+    /// Keychain Services only supports exact matches on dates. To select
+    /// items created within a `DateRange`, use
+    /// `ItemAttributes::find_created_within` instead.
+    ///
+    /// Wrapper for the `kSecAttrCreationDate` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcreationdate>
+    pub fn creation_date<T: Into<SystemTime>>(mut self, time: T) -> Self {
+        self.0.add(
+            AttrKind::CreationDate,
+            &date::CFDate_from_system_time(time.into()),
+        );
+        self
+    }
+
+    /// Query for keychain items with the given creator code.
+    ///
+    /// Wrapper for the `kSecAttrCreator` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcreator>
+    pub fn creator<C: Into<AttrCreator>>(mut self, creator: C) -> Self {
+        self.0.add_attr(&creator.into());
+        self
+    }
+
+    /// Query for keychain items with the given type code (e.g. `*b"note"`
+    /// for secure notes).
+    ///
+    /// Wrapper for the `kSecAttrType` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrtype>
+    pub fn item_type<T: Into<AttrItemType>>(mut self, item_type: T) -> Self {
+        self.0.add_attr(&item_type.into());
+        self
+    }
+
     /// Query for keys with the given `SecAttrKeyClass`.
     ///
     /// Wrapper for the `kSecAttrKeyClass` attribute key. See:
@@ -115,6 +337,37 @@ impl Query {
         self
     }
 
+    /// Limit the number of items the query matches. By default, lookups
+    /// return all matching items.
+    ///
+    /// Only applies to lookups: updates and deletions always act on every
+    /// matching item. To process a large number of items a batch at a time,
+    /// use `ItemAttributes::pages` instead.
+    ///
+    /// Wrapper for the `kSecMatchLimit` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecmatchlimit>
+    pub fn match_limit(mut self, limit: MatchLimit) -> Self {
+        self.0.remove(unsafe { kSecMatchLimit });
+        self.0.add(unsafe { kSecMatchLimit }, &limit.as_CFType());
+        self
+    }
+
+    /// Query for keychain items last modified at the given time.
+    ///
+    /// Keychain Services only supports exact matches on dates. To select
+    /// items modified within a `DateRange`, use
+    /// `ItemAttributes::find_modified_within` instead.
+    ///
+    /// Wrapper for the `kSecAttrModificationDate` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrmodificationdate>
+    pub fn modification_date<T: Into<SystemTime>>(mut self, time: T) -> Self {
+        self.0.add(
+            AttrKind::ModificationDate,
+            &date::CFDate_from_system_time(time.into()),
+        );
+        self
+    }
+
     /// Query for keys which are or not permanent members of the default keychain.
     ///
     /// Wrapper for the `kSecAttrIsPermanent` attribute key. See:
@@ -129,10 +382,27 @@ impl Query {
     /// Wrapper for the `kSecAttrSynchronizable` attribute key. See:
     /// <https://developer.apple.com/documentation/security/ksecattrsynchronizable>
     pub fn synchronizable(mut self, value: bool) -> Self {
+        self.0.remove(AttrKind::Synchronizable);
         self.0.add_boolean(AttrKind::Synchronizable, value);
         self
     }
 
+    /// Query for items regardless of whether they are synchronizable, i.e.
+    /// match both iCloud Keychain and device-local copies of an item (use
+    /// `ItemAttributes::is_synchronizable` to tell them apart).
+    ///
+    /// By default only device-local items are matched.
+    ///
+    /// Wrapper for the `kSecAttrSynchronizableAny` attribute value. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrsynchronizableany>
+    pub fn synchronizable_any(mut self) -> Self {
+        self.0.remove(AttrKind::Synchronizable);
+        self.0.add(AttrKind::Synchronizable, &unsafe {
+            CFString::wrap_under_get_rule(kSecAttrSynchronizableAny)
+        });
+        self
+    }
+
     /// Query for keys which are or are not sensitive.
     ///
     /// Wrapper for the `kSecAttrIsSensitive` attribute key. See:
@@ -142,13 +412,111 @@ impl Query {
         self
     }
 
+    /// Query for generic passwords associated with the given service.
+    ///
+    /// Wrapper for the `kSecAttrService` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrservice>
+    pub fn service(mut self, service: &str) -> Self {
+        self.0.add_string(AttrKind::Service, service);
+        self
+    }
+
+    /// Query for certificates (or identities) whose subject contains the
+    /// given string.
+    ///
+    /// Wrapper for the `kSecMatchSubjectContains` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecmatchsubjectcontains>
+    pub fn subject_contains(mut self, value: &str) -> Self {
+        self.0
+            .add_string(unsafe { kSecMatchSubjectContains }, value);
+        self
+    }
+
     /// Query for keys stored in an external token i.e. the
-    /// Secure Enclave Processor (SEP).
+    /// Secure Enclave Processor (SEP) or a CryptoTokenKit token.
     ///
     /// Wrapper for the `kSecAttrTokenID` attribute key. See:
     /// <https://developer.apple.com/documentation/security/ksecattrtokenid>
-    pub fn token_id(mut self, value: AttrTokenId) -> Self {
-        self.0.add_attr(&value);
+    pub fn token_id<T: Into<AttrTokenId>>(mut self, value: T) -> Self {
+        self.0.add_attr(&value.into());
+        self
+    }
+
+    /// Match string attributes regardless of diacritics, e.g. so that
+    /// `"resume"` matches `"résumé"`.
+    ///
+    /// Wrapper for the `kSecMatchDiacriticInsensitive` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecmatchdiacriticinsensitive>
+    pub fn diacritic_insensitive(mut self, value: bool) -> Self {
+        self.0
+            .add_boolean(unsafe { kSecMatchDiacriticInsensitive }, value);
+        self
+    }
+
+    /// Query for certificates (or identities) which are valid at the given
+    /// time, i.e. not yet expired and no longer pending.
+    ///
+    /// Wrapper for the `kSecMatchValidOnDate` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecmatchvalidondate>
+    pub fn valid_on_date<T: Into<SystemTime>>(mut self, time: T) -> Self {
+        self.0.remove(unsafe { kSecMatchValidOnDate });
+        self.0.add(
+            unsafe { kSecMatchValidOnDate },
+            &date::CFDate_from_system_time(time.into()),
+        );
+        self
+    }
+
+    /// Query for certificates (or identities) which are currently valid,
+    /// as of when the query is performed.
+    ///
+    /// Wrapper for the `kSecMatchValidOnDate` attribute key with a
+    /// `kCFNull` value. See:
+    /// <https://developer.apple.com/documentation/security/ksecmatchvalidondate>
+    pub fn valid_now(mut self) -> Self {
+        self.0.remove(unsafe { kSecMatchValidOnDate });
+        self.0.add(unsafe { kSecMatchValidOnDate }, &unsafe {
+            CFType::wrap_under_get_rule(kCFNull)
+        });
+        self
+    }
+
+    /// Match string attributes regardless of character width, e.g. so that
+    /// full-width `"ＡＢＣ"` matches `"ABC"`.
+    ///
+    /// Wrapper for the `kSecMatchWidthInsensitive` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecmatchwidthinsensitive>
+    pub fn width_insensitive(mut self, value: bool) -> Self {
+        self.0
+            .add_boolean(unsafe { kSecMatchWidthInsensitive }, value);
+        self
+    }
+
+    /// Restrict the query to the given items (e.g. `Item`s or `Key`s found
+    /// by an earlier query), rather than matching every item in the keychain.
+    ///
+    /// Any other attributes of the query must also match, so this can be
+    /// used to act on previously enumerated items without re-matching items
+    /// added since. An empty list matches no items.
+    ///
+    /// Wrapper for the `kSecUseItemList` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecuseitemlist>
+    pub fn item_list<T: TCFType>(mut self, items: &[T]) -> Self {
+        let items: Vec<CFType> = items.iter().map(TCFType::as_CFType).collect();
+        self.0
+            .add(unsafe { kSecUseItemList }, &CFArray::from_CFTypes(&items));
+        self
+    }
+
+    /// Restrict the query to the items with the given persistent references.
+    ///
+    /// Wrapper for the `kSecMatchItemList` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecmatchitemlist>
+    pub(crate) fn match_persistent_refs(mut self, persistent_refs: &[CFData]) -> Self {
+        self.0.add(
+            unsafe { kSecMatchItemList },
+            &CFArray::from_CFTypes(persistent_refs),
+        );
         self
     }
 
@@ -161,6 +529,18 @@ impl Query {
         self.0.add_string(unsafe { kSecUseOperationPrompt }, value);
         self
     }
+
+    /// Set an attribute (or other query parameter) this crate doesn't have
+    /// a wrapper for yet, e.g. one added in a newer release of the Security
+    /// framework, replacing any value the query already has for it.
+    ///
+    /// The key should be one of the `kSec*` constants, and the value is
+    /// passed to Keychain Services as-is, without being checked.
+    pub fn raw_attribute(mut self, key: CFString, value: CFType) -> Self {
+        self.0.remove(key.as_concrete_TypeRef());
+        self.0.add(key.as_concrete_TypeRef(), &value);
+        self
+    }
 }
 
 impl From<Query> for DictionaryBuilder {
@@ -168,3 +548,241 @@ impl From<Query> for DictionaryBuilder {
         params.0
     }
 }
+
+impl<'a> From<&'a Query> for DictionaryBuilder {
+    fn from(params: &'a Query) -> DictionaryBuilder {
+        params.0.clone()
+    }
+}
+
+/// Builder for a complete keychain lookup: the class of items to search,
+/// the `Query` they must match, and which results to return for each of
+/// them.
+///
+/// Lookups always return the attributes of the matching items (see
+/// `ItemAttributes`), optionally along with references to the items
+/// themselves and/or their persistent references.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ItemQuery {
+    class: Class,
+    query: Query,
+    return_refs: bool,
+    return_persistent_refs: bool,
+}
+
+impl ItemQuery {
+    /// Create a query for all items of the given class
+    pub fn new(class: Class) -> Self {
+        Self {
+            class,
+            query: Query::new(),
+            return_refs: false,
+            return_persistent_refs: false,
+        }
+    }
+
+    /// Get the class of items this query searches
+    pub fn class(&self) -> Class {
+        self.class
+    }
+
+    /// Borrow the `Query` the items must match
+    pub fn query(&self) -> &Query {
+        &self.query
+    }
+
+    /// Query for items with the given label (see `Query::label`)
+    pub fn label<L: Into<AttrLabel>>(mut self, label: L) -> Self {
+        self.query = self.query.label(label);
+        self
+    }
+
+    /// Query for passwords with the given account (see `Query::account`)
+    pub fn account(mut self, account: &str) -> Self {
+        self.query = self.query.account(account);
+        self
+    }
+
+    /// Query for generic passwords with the given service (see
+    /// `Query::service`)
+    pub fn service(mut self, service: &str) -> Self {
+        self.query = self.query.service(service);
+        self
+    }
+
+    /// Query for generic passwords with the given generic data (see
+    /// `Query::generic`)
+    pub fn generic<G: Into<AttrGeneric>>(mut self, generic: G) -> Self {
+        self.query = self.query.generic(generic);
+        self
+    }
+
+    /// Query for items in the given access group of the data protection
+    /// keychain (see `Query::access_group`)
+    pub fn access_group(mut self, access_group: &str) -> Self {
+        self.query = self.query.access_group(access_group);
+        self
+    }
+
+    /// Query the data protection keychain rather than the legacy file-based
+    /// keychains (see `Query::data_protection_keychain`)
+    pub fn data_protection_keychain(mut self, value: bool) -> Self {
+        self.query = self.query.data_protection_keychain(value);
+        self
+    }
+
+    /// Query for items which are or are not synchronizable (see
+    /// `Query::synchronizable`)
+    pub fn synchronizable(mut self, value: bool) -> Self {
+        self.query = self.query.synchronizable(value);
+        self
+    }
+
+    /// Query for items regardless of whether they are synchronizable (see
+    /// `Query::synchronizable_any`)
+    pub fn synchronizable_any(mut self) -> Self {
+        self.query = self.query.synchronizable_any();
+        self
+    }
+
+    /// Limit the number of items the query matches (see
+    /// `Query::match_limit`)
+    pub fn match_limit(mut self, limit: MatchLimit) -> Self {
+        self.query = self.query.match_limit(limit);
+        self
+    }
+
+    /// Refine the query with other match options, e.g.
+    /// `.matching(|query| query.key_class(AttrKeyClass::Private))`
+    pub fn matching<F>(mut self, refine: F) -> Self
+    where
+        F: FnOnce(Query) -> Query,
+    {
+        self.query = refine(self.query);
+        self
+    }
+
+    /// Return references to the matching items along with their attributes,
+    /// so they can be acted on (e.g. deleted).
+    ///
+    /// Wrapper for the `kSecReturnRef` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecreturnref>
+    pub fn return_refs(mut self, value: bool) -> Self {
+        self.return_refs = value;
+        self
+    }
+
+    /// Return persistent references to the matching items along with their
+    /// attributes (see `ItemAttributes::persistent_ref`).
+    ///
+    /// Wrapper for the `kSecReturnPersistentRef` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecreturnpersistentref>
+    pub fn return_persistent_refs(mut self, value: bool) -> Self {
+        self.return_persistent_refs = value;
+        self
+    }
+
+    /// Find the attributes (and any other requested results) of all
+    /// matching items, returning an empty `Vec` if nothing matches.
+    ///
+    /// Wrapper for the `SecItemCopyMatching` function. See:
+    /// <https://developer.apple.com/documentation/security/1398306-secitemcopymatching>
+    pub fn find(&self) -> Result<Vec<ItemAttributes>, Error> {
+        let mut return_keys = vec![];
+
+        if self.return_refs {
+            return_keys.push(unsafe { kSecReturnRef });
+        }
+
+        if self.return_persistent_refs {
+            return_keys.push(unsafe { kSecReturnPersistentRef });
+        }
+
+        ItemAttributes::find_returning(self.class, &self.query, &return_keys)
+    }
+
+    /// Find the single matching item, returning its attributes along with
+    /// its secret data (see `ItemAttributes::find_with_data`).
+    pub fn find_with_data(&self) -> Result<Option<(ItemAttributes, PasswordData)>, Error> {
+        ItemAttributes::find_with_data(self.class, &self.query)
+    }
+
+    /// Page through the matching items (see `ItemAttributes::pages`)
+    pub fn pages(&self, page_size: usize) -> Result<ItemPages, Error> {
+        ItemAttributes::pages(self.class, &self.query, page_size)
+    }
+
+    /// Count the matching items (see `count_matching`)
+    pub fn count(&self) -> Result<usize, Error> {
+        count_matching(self.class, &self.query)
+    }
+
+    /// Apply the given changes to all matching items (see
+    /// `update_matching`)
+    pub fn update(&self, changes: &ItemChanges) -> Result<(), Error> {
+        update_matching(self.class, &self.query, changes)
+    }
+
+    /// Delete all matching items (see `delete_matching`)
+    pub fn delete(&self) -> Result<(), Error> {
+        delete_matching(self.class, &self.query)
+    }
+
+    /// Delete all matching items, including their iCloud Keychain copies
+    /// (see `delete_matching_everywhere`)
+    pub fn delete_everywhere(&self) -> Result<(), Error> {
+        delete_matching_everywhere(self.class, &self.query)
+    }
+
+    /// Delete all matching items, reporting which were removed (see
+    /// `delete_matching_with_report`)
+    pub fn delete_with_report(&self) -> Result<DeletionReport, Error> {
+        delete_matching_with_report(self.class, &self.query)
+    }
+
+    /// Get the attributes of a new item matching this query, with the given
+    /// changes applied (see `Keychain::upsert_item`).
+    pub(crate) fn new_item_attrs(&self, changes: &ItemChanges) -> DictionaryBuilder {
+        let mut attrs = DictionaryBuilder::from(&self.query);
+
+        // Search parameters only apply to lookups
+        let search_keys = unsafe {
+            [
+                kSecMatchCaseInsensitive,
+                kSecMatchDiacriticInsensitive,
+                kSecMatchEmailAddressIfPresent,
+                kSecMatchIssuers,
+                kSecMatchItemList,
+                kSecMatchLimit,
+                kSecMatchSearchList,
+                kSecMatchSubjectContains,
+                kSecMatchValidOnDate,
+                kSecMatchWidthInsensitive,
+                kSecUseItemList,
+                kSecUseOperationPrompt,
+            ]
+        };
+
+        for &key in &search_keys {
+            attrs.remove(key);
+        }
+
+        attrs.add_class(self.class);
+        attrs.merge(changes.attrs());
+        attrs
+    }
+
+    /// Query for items with an attribute this crate doesn't wrap yet (see
+    /// `Query::raw_attribute`)
+    pub fn raw_attribute(mut self, key: CFString, value: CFType) -> Self {
+        self.query = self.query.raw_attribute(key, value);
+        self
+    }
+
+    /// Restrict this query to the given keychain (see `Query::keychain`)
+    pub(crate) fn in_keychain(mut self, keychain: &Keychain) -> Self {
+        self.query = self.query.keychain(keychain);
+        self
+    }
+}
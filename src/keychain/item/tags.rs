@@ -0,0 +1,170 @@
+//! User-defined tags (e.g. `env:prod`) attached to keychain items.
+//!
+//! Keychain Services has no notion of tags, so by convention they are
+//! stored in an item's comment (i.e. `kSecAttrComment`) attribute, encoded
+//! as `tags:` followed by a comma-separated list, e.g. `tags:env:prod,team:ops`.
+//! Items can then be located by tag with `ItemAttributes::find_by_tag`.
+
+use crate::error::{Error, ErrorKind};
+use std::{
+    collections::{btree_set, BTreeSet},
+    fmt::{self, Display},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Prefix identifying a comment which contains encoded tags
+const TAGS_PREFIX: &str = "tags:";
+
+/// Separator between tags in an encoded comment
+const TAGS_SEPARATOR: char = ',';
+
+/// Prefix of the tag recording when an item expires, followed by the
+/// number of seconds since the Unix epoch (e.g. `expires:1700000000`)
+const EXPIRY_TAG_PREFIX: &str = "expires:";
+
+/// Set of user-defined tags attached to a keychain item.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ItemTags(BTreeSet<String>);
+
+impl ItemTags {
+    /// Create an empty set of tags
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a tag to this set, returning an error with a kind of
+    /// `ErrorKind::Param` if it is empty or contains a comma or whitespace.
+    pub fn insert(&mut self, tag: &str) -> Result<(), Error> {
+        if tag.is_empty() || tag.contains(|c: char| c == TAGS_SEPARATOR || c.is_whitespace()) {
+            return Err(Error::new(
+                ErrorKind::Param,
+                &format!(
+                    "invalid tag (must be non-empty without commas/whitespace): {:?}",
+                    tag
+                ),
+            ));
+        }
+
+        self.0.insert(tag.to_owned());
+        Ok(())
+    }
+
+    /// Add a tag to this set, in builder style.
+    pub fn with(mut self, tag: &str) -> Result<Self, Error> {
+        self.insert(tag)?;
+        Ok(self)
+    }
+
+    /// Remove a tag from this set, returning whether it was present.
+    pub fn remove(&mut self, tag: &str) -> bool {
+        self.0.remove(tag)
+    }
+
+    /// Does this set contain the given tag?
+    pub fn contains(&self, tag: &str) -> bool {
+        self.0.contains(tag)
+    }
+
+    /// Is this set empty?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Get when the item these tags are attached to expires (if ever), as
+    /// recorded in an `expires:` tag. See `purge_expired`.
+    ///
+    /// Tags recording times which can't be represented are ignored.
+    pub fn expiry(&self) -> Option<SystemTime> {
+        self.0
+            .iter()
+            .filter(|tag| tag.starts_with(EXPIRY_TAG_PREFIX))
+            .filter_map(|tag| tag[EXPIRY_TAG_PREFIX.len()..].parse().ok())
+            .filter_map(|secs| UNIX_EPOCH.checked_add(Duration::from_secs(secs)))
+            .min()
+    }
+
+    /// Set when the item these tags are attached to expires, replacing any
+    /// previous expiry (or removing it if `None`). Expiry times have a
+    /// resolution of one second.
+    pub fn set_expiry(&mut self, expiry: Option<SystemTime>) {
+        self.0.retain(|tag| !tag.starts_with(EXPIRY_TAG_PREFIX));
+
+        if let Some(expiry) = expiry {
+            let secs = expiry
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs());
+
+            self.0.insert(format!("{}{}", EXPIRY_TAG_PREFIX, secs));
+        }
+    }
+
+    /// Set when the item these tags are attached to expires, in builder
+    /// style.
+    pub fn with_expiry(mut self, expiry: SystemTime) -> Self {
+        self.set_expiry(Some(expiry));
+        self
+    }
+
+    /// Iterate over the tags in this set (in sorted order)
+    pub fn iter(&self) -> btree_set::Iter<'_, String> {
+        self.0.iter()
+    }
+
+    /// Decode the tags stored in an item's comment, returning an empty set
+    /// if the comment doesn't contain encoded tags.
+    pub(crate) fn from_comment(comment: &str) -> Self {
+        comment.parse().unwrap_or_default()
+    }
+}
+
+/// Encodes tags in the form stored in an item's comment attribute.
+impl Display for ItemTags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", TAGS_PREFIX)?;
+
+        for (i, tag) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", TAGS_SEPARATOR)?;
+            }
+
+            write!(f, "{}", tag)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Decodes tags from the form stored in an item's comment attribute.
+impl FromStr for ItemTags {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        if !s.starts_with(TAGS_PREFIX) {
+            return Err(Error::new(
+                ErrorKind::Decode,
+                &format!("expected {:?} prefix on encoded tags: {:?}", TAGS_PREFIX, s),
+            ));
+        }
+
+        let mut tags = Self::new();
+
+        for tag in s[TAGS_PREFIX.len()..].split(TAGS_SEPARATOR) {
+            if !tag.is_empty() {
+                tags.insert(tag)?;
+            }
+        }
+
+        Ok(tags)
+    }
+}
+
+impl<'a> IntoIterator for &'a ItemTags {
+    type Item = &'a String;
+    type IntoIter = btree_set::Iter<'a, String>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,248 @@
+//! Soft deletion of keychain items, i.e. "tombstones".
+//!
+//! When the `DeletionMode` is set to `Tombstone`, deleting a key (or an
+//! item purged by e.g. `purge_expired` or `dedupe`) moves it into an archival
+//! namespace instead of destroying it, so accidental deletions can be undone
+//! with `Tombstone::restore` until they're purged for good with
+//! `Tombstone::purge` or `purge_tombstones`.
+//!
+//! Tombstoned items are renamed rather than moved: their label becomes
+//! `deleted:` followed by the deletion time in seconds since the Unix epoch,
+//! a colon, and the original label (e.g. `deleted:1700000000:My Key`). The
+//! attribute identifying the item is prefixed in the same way, so normal
+//! lookups no longer find it:
+//!
+//! - Generic passwords: the service
+//! - Internet passwords: the server
+//! - Keys: the application tag (keys without one remain findable by their
+//!   application label)
+//! - Certificates and identities: only the label is renamed
+
+use super::{Class, ItemAttributes, Query};
+use crate::{attr::AttrKind, dictionary::DictionaryBuilder, error::Error, keychain::key::Key};
+use core_foundation::data::CFData;
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Prefix of the labels (and identifying attributes) of tombstoned items
+pub const TOMBSTONE_PREFIX: &str = "deleted:";
+
+/// What happens to items when they're deleted.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DeletionMode {
+    /// Items are destroyed immediately (the default)
+    Destroy,
+
+    /// Items are moved to the tombstone namespace, from which they can be
+    /// restored until they're purged
+    Tombstone,
+}
+
+/// Set the `DeletionMode` used process-wide.
+pub fn set_deletion_mode(mode: DeletionMode) {
+    TOMBSTONE_MODE.store(mode == DeletionMode::Tombstone, Ordering::SeqCst);
+}
+
+/// Get the current `DeletionMode`.
+pub fn deletion_mode() -> DeletionMode {
+    if TOMBSTONE_MODE.load(Ordering::SeqCst) {
+        DeletionMode::Tombstone
+    } else {
+        DeletionMode::Destroy
+    }
+}
+
+/// Whether items are tombstoned rather than destroyed
+static TOMBSTONE_MODE: AtomicBool = AtomicBool::new(false);
+
+/// A soft-deleted item, which can be restored or purged.
+#[derive(Debug)]
+pub struct Tombstone {
+    item: ItemAttributes,
+    deleted_at: SystemTime,
+}
+
+impl Tombstone {
+    /// Find all tombstoned items of the given class.
+    pub fn find_all(class: Class) -> Result<Vec<Self>, Error> {
+        Ok(ItemAttributes::find(class, &Query::new(), true)?
+            .into_iter()
+            .filter_map(Self::from_item)
+            .collect())
+    }
+
+    /// Get the attributes of the tombstoned item (as renamed)
+    pub fn item(&self) -> &ItemAttributes {
+        &self.item
+    }
+
+    /// Get when the item was deleted (at a resolution of one second)
+    pub fn deleted_at(&self) -> SystemTime {
+        self.deleted_at
+    }
+
+    /// Get the label the item had before it was deleted
+    pub fn original_label(&self) -> Option<String> {
+        self.item
+            .label()
+            .and_then(|label| strip_prefix(label.as_bytes()).map(|(_, rest)| rest.to_vec()))
+            .map(|label| String::from_utf8_lossy(&label).into_owned())
+    }
+
+    /// Restore the item, moving it back out of the tombstone namespace.
+    ///
+    /// Fails with the error for a duplicate item if an item with the
+    /// original identifying attributes has been created in the meantime.
+    pub fn restore(self) -> Result<(), Error> {
+        let mut attrs = DictionaryBuilder::new();
+        attrs.add_string(AttrKind::Label, self.original_label().unwrap_or_default());
+
+        match identifier(&self.item) {
+            Some((kind, Identifier::String(value))) => {
+                let value = strip_prefix(value.as_bytes()).map_or(value.clone(), |(_, rest)| {
+                    String::from_utf8_lossy(rest).into_owned()
+                });
+                attrs.add_string(kind, value);
+            }
+            Some((kind, Identifier::Data(value))) => {
+                let value = strip_prefix(&value).map_or(&value[..], |(_, rest)| rest);
+                attrs.add(kind, &CFData::from_buffer(value));
+            }
+            None => (),
+        }
+
+        self.item.update(attrs)
+    }
+
+    /// Permanently destroy the item.
+    pub fn purge(self) -> Result<(), Error> {
+        self.item.destroy()
+    }
+
+    /// Get the tombstone for the given item, if it's been tombstoned
+    fn from_item(item: ItemAttributes) -> Option<Self> {
+        let deleted_at = deletion_time(&item)?;
+        Some(Tombstone { item, deleted_at })
+    }
+}
+
+/// Permanently destroy all tombstoned items of the given classes which were
+/// deleted longer ago than the given retention period, returning the
+/// tombstones which were purged.
+pub fn purge_tombstones(classes: &[Class], retention: Duration) -> Result<Vec<Tombstone>, Error> {
+    let now = SystemTime::now();
+    let mut purged = vec![];
+
+    for class in classes {
+        for tombstone in Tombstone::find_all(*class)? {
+            let age = now.duration_since(tombstone.deleted_at).unwrap_or_default();
+
+            if age > retention {
+                tombstone.item.destroy()?;
+                purged.push(tombstone);
+            }
+        }
+    }
+
+    Ok(purged)
+}
+
+/// Get when the given item was tombstoned (if it has been)
+pub(crate) fn deletion_time(item: &ItemAttributes) -> Option<SystemTime> {
+    let label = item.label()?;
+    let (secs, _) = strip_prefix(label.as_bytes())?;
+    UNIX_EPOCH.checked_add(Duration::from_secs(secs))
+}
+
+/// Move the item the given attributes belong to (which must have been found
+/// with `return_refs` set) into the tombstone namespace
+pub(crate) fn bury(item: &ItemAttributes) -> Result<(), Error> {
+    let prefix = tombstone_prefix();
+    let mut attrs = DictionaryBuilder::new();
+    attrs.add_string(
+        AttrKind::Label,
+        format!("{}{}", prefix, item.label().unwrap_or_default()),
+    );
+
+    match identifier(item) {
+        Some((kind, Identifier::String(value))) => {
+            attrs.add_string(kind, format!("{}{}", prefix, value))
+        }
+        Some((kind, Identifier::Data(value))) => {
+            let mut data = prefix.into_bytes();
+            data.extend_from_slice(&value);
+            attrs.add(kind, &CFData::from_buffer(&data));
+        }
+        None => (),
+    }
+
+    item.update(attrs)
+}
+
+/// Move the given key into the tombstone namespace
+pub(crate) fn bury_key(key: &Key) -> Result<(), Error> {
+    let prefix = tombstone_prefix();
+    let mut attrs = DictionaryBuilder::new();
+    let label = key
+        .label()
+        .map(|label| label.to_string())
+        .unwrap_or_default();
+    attrs.add_string(AttrKind::Label, format!("{}{}", prefix, label));
+
+    if let Some(tag) = key.application_tag() {
+        let mut data = prefix.into_bytes();
+        data.extend_from_slice(tag.as_ref());
+        attrs.add(AttrKind::ApplicationTag, &CFData::from_buffer(&data));
+    }
+
+    super::update(Class::Key, key, attrs)
+}
+
+/// Value of the attribute identifying an item
+enum Identifier {
+    String(String),
+    Data(Vec<u8>),
+}
+
+/// Get the attribute identifying the given item (if any) for its class
+fn identifier(item: &ItemAttributes) -> Option<(AttrKind, Identifier)> {
+    match item.class() {
+        Class::GenericPassword => item
+            .service()
+            .map(|service| (AttrKind::Service, Identifier::String(service))),
+        Class::InternetPassword => item
+            .server()
+            .map(|server| (AttrKind::Server, Identifier::String(server))),
+        Class::Key => item.application_tag().map(|tag| {
+            (
+                AttrKind::ApplicationTag,
+                Identifier::Data(tag.as_ref().to_vec()),
+            )
+        }),
+        Class::Certificate | Class::Identity => None,
+    }
+}
+
+/// Get the prefix to add to items tombstoned now
+fn tombstone_prefix() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs());
+
+    format!("{}{}:", TOMBSTONE_PREFIX, secs)
+}
+
+/// Split a tombstoned value into its deletion time and original value,
+/// returning `None` if it isn't in the tombstone namespace
+fn strip_prefix(value: &[u8]) -> Option<(u64, &[u8])> {
+    if !value.starts_with(TOMBSTONE_PREFIX.as_bytes()) {
+        return None;
+    }
+
+    let rest = &value[TOMBSTONE_PREFIX.len()..];
+    let colon = rest.iter().position(|&byte| byte == b':')?;
+    let secs = std::str::from_utf8(&rest[..colon]).ok()?.parse().ok()?;
+    Some((secs, &rest[colon + 1..]))
+}
@@ -0,0 +1,205 @@
+//! Compare-and-swap updates of keychain items, i.e. optimistic concurrency
+//! for items shared between several processes (e.g. via an access group).
+
+use super::{Class, ItemAttributes, ItemTags, Query};
+use crate::{
+    attr::{AttrAccessible, AttrCreator, AttrGeneric, AttrItemType, AttrKind, AttrLabel},
+    dictionary::DictionaryBuilder,
+    error::{Error, ErrorKind},
+    ffi::*,
+};
+use core_foundation::{
+    base::{CFType, TCFType},
+    data::CFData,
+    string::CFString,
+};
+use std::{
+    borrow::Borrow,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Builder for changes to apply to an existing keychain item with
+/// `Item::update`, `update_if_unchanged`, or `update_matching`.
+#[derive(Clone, Debug, Default)]
+pub struct ItemChanges(DictionaryBuilder);
+
+impl ItemChanges {
+    /// Create an empty set of changes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the attributes to update
+    pub(crate) fn attrs(&self) -> &DictionaryBuilder {
+        &self.0
+    }
+
+    /// Change the label of the item.
+    ///
+    /// Wrapper for the `kSecAttrLabel` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrlabel>
+    pub fn label<L: Into<AttrLabel>>(mut self, label: L) -> Self {
+        self.0.remove(AttrKind::Label);
+        self.0.add_attr(&label.into());
+        self
+    }
+
+    /// Change the comment on the item.
+    ///
+    /// Wrapper for the `kSecAttrComment` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcomment>
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.0.remove(AttrKind::Comment);
+        self.0.add_string(AttrKind::Comment, comment);
+        self
+    }
+
+    /// Change the description of the item's kind.
+    ///
+    /// Wrapper for the `kSecAttrDescription` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrdescription>
+    pub fn description(mut self, description: &str) -> Self {
+        self.0.remove(AttrKind::Description);
+        self.0.add_string(AttrKind::Description, description);
+        self
+    }
+
+    /// Change the creator code of the item.
+    ///
+    /// Wrapper for the `kSecAttrCreator` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcreator>
+    pub fn creator<C: Into<AttrCreator>>(mut self, creator: C) -> Self {
+        self.0.remove(AttrKind::Creator);
+        self.0.add_attr(&creator.into());
+        self
+    }
+
+    /// Change the type code of the item.
+    ///
+    /// Wrapper for the `kSecAttrType` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrtype>
+    pub fn item_type<T: Into<AttrItemType>>(mut self, item_type: T) -> Self {
+        self.0.remove(AttrKind::ItemType);
+        self.0.add_attr(&item_type.into());
+        self
+    }
+
+    /// Change the generic data attached to the item (generic passwords only).
+    ///
+    /// Wrapper for the `kSecAttrGeneric` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrgeneric>
+    pub fn generic<G: Into<AttrGeneric>>(mut self, generic: G) -> Self {
+        self.0.remove(AttrKind::Generic);
+        self.0.add_attr(&generic.into());
+        self
+    }
+
+    /// Change the protection class of the item, i.e. when it can be read
+    /// (see `Item::set_accessible`).
+    ///
+    /// Wrapper for the `kSecAttrAccessible` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccessible>
+    pub fn accessible(mut self, accessible: AttrAccessible) -> Self {
+        self.0.remove(AttrKind::Accessible);
+        self.0.add_attr(&accessible);
+        self
+    }
+
+    /// Replace the user-defined tags attached to the item (see `ItemTags`),
+    /// overwriting its comment (including one set with `comment()`).
+    pub fn tags(mut self, tags: &ItemTags) -> Self {
+        self.0.remove(AttrKind::Comment);
+        self.0.add_string(AttrKind::Comment, tags.to_string());
+        self
+    }
+
+    /// Replace the secret data of the item (e.g. a password).
+    ///
+    /// Wrapper for the `kSecValueData` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecvaluedata>
+    pub fn data(mut self, data: &[u8]) -> Self {
+        self.0.remove(unsafe { kSecValueData });
+        self.0
+            .add(unsafe { kSecValueData }, &CFData::from_buffer(data));
+        self
+    }
+
+    /// Change an attribute this crate doesn't wrap yet (see
+    /// `Query::raw_attribute`).
+    pub fn raw_attribute(mut self, key: CFString, value: CFType) -> Self {
+        self.0.remove(key.as_concrete_TypeRef());
+        self.0.add(key.as_concrete_TypeRef(), &value);
+        self
+    }
+}
+
+/// Whether `Keychain::upsert_item` added a new item or updated an existing
+/// one.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum UpsertOutcome {
+    /// No item matched, so a new one was added
+    Added,
+
+    /// The existing item was updated
+    Updated,
+}
+
+/// Apply the given changes to the single item of the given class matching
+/// the given `Query`, but only if it hasn't been modified since
+/// `expected_modification_date` (e.g. as returned by
+/// `Item::modification_date` when the item was read).
+///
+/// Returns an error with a kind of:
+///
+/// - `ErrorKind::Conflict` if the item has been modified since then
+/// - `ErrorKind::ItemNotFound` if no item matches the query
+/// - `ErrorKind::Param` if more than one item matches the query
+///
+/// Keychain modification dates have a resolution of one second, so they
+/// are compared to the nearest second. Keychain Services has no atomic
+/// compare-and-swap, so a concurrent modification between the check and
+/// the update can still go undetected: this narrows the window for lost
+/// updates rather than eliminating it.
+pub fn update_if_unchanged<Q: Borrow<Query>>(
+    class: Class,
+    query: Q,
+    expected_modification_date: SystemTime,
+    changes: &ItemChanges,
+) -> Result<(), Error> {
+    let mut items = ItemAttributes::find(class, query.borrow(), true)?;
+
+    let item = match items.len() {
+        0 => return Err(Error::new(ErrorKind::ItemNotFound, "no item matches query")),
+        1 => items.remove(0),
+        n => {
+            return Err(Error::new(
+                ErrorKind::Param,
+                &format!("expected query to match a single item (matched {})", n),
+            ))
+        }
+    };
+
+    let modification_date = item
+        .modification_date()
+        .ok_or_else(|| Error::new(ErrorKind::NoSuchAttr, "item has no modification date"))?;
+
+    if unix_seconds(modification_date) != unix_seconds(expected_modification_date) {
+        return Err(Error::new(
+            ErrorKind::Conflict,
+            &format!(
+                "item modified at {:?} (expected {:?})",
+                modification_date, expected_modification_date
+            ),
+        ));
+    }
+
+    item.update(changes.0.clone())
+}
+
+/// Truncate a `SystemTime` to whole seconds relative to the Unix epoch
+fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
+}
@@ -0,0 +1,121 @@
+//! Storing structured secrets (e.g. an API token along with its refresh
+//! token and expiry) as JSON in a single generic password.
+//!
+//! Values are wrapped in an envelope recording the version of the payload
+//! format, so a payload written in a format this release doesn't understand
+//! is rejected rather than misread:
+//!
+//! ```text
+//! { "version": 1, "value": { "token": "...", "expires": 1700000000 } }
+//! ```
+
+use super::{
+    item::{Class, GenericPassword, ItemChanges, ItemQuery},
+    Keychain,
+};
+use crate::error::{Error, ErrorKind};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use zeroize::Zeroize;
+
+/// Version of the payload format written by `Keychain::put_json`
+pub const JSON_PAYLOAD_VERSION: u32 = 1;
+
+/// Envelope values are written in
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    version: u32,
+    value: &'a T,
+}
+
+/// Version of a stored envelope, checked before its value is decoded
+#[derive(Deserialize)]
+struct EnvelopeVersion {
+    version: u32,
+}
+
+/// Envelope values are read from
+#[derive(Deserialize)]
+struct StoredEnvelope<T> {
+    value: T,
+}
+
+impl Keychain {
+    /// Serialize the given value as JSON and store it as the generic
+    /// password with the given service and account in this keychain,
+    /// adding the password or replacing an existing one.
+    pub fn put_json<T: Serialize>(
+        &self,
+        service: &str,
+        account: &str,
+        value: &T,
+    ) -> Result<(), Error> {
+        let envelope = Envelope {
+            version: JSON_PAYLOAD_VERSION,
+            value,
+        };
+
+        let mut payload = serde_json::to_vec(&envelope).map_err(|e| {
+            Error::new(
+                ErrorKind::Param,
+                &format!("couldn't serialize value as JSON: {}", e),
+            )
+        })?;
+
+        let query = ItemQuery::new(Class::GenericPassword)
+            .service(service)
+            .account(account);
+        let result = self.upsert_item(&query, &ItemChanges::new().data(&payload));
+
+        payload.zeroize();
+        result.map(|_| ())
+    }
+
+    /// Get the value stored as JSON (by `Keychain::put_json`) in the generic
+    /// password with the given service and account in this keychain,
+    /// returning `Ok(None)` if there is no such password.
+    ///
+    /// Returns an error with a kind of `ErrorKind::Decode` if the password
+    /// doesn't contain a value of the given type in a supported payload
+    /// format.
+    pub fn get_json<T: DeserializeOwned>(
+        &self,
+        service: &str,
+        account: &str,
+    ) -> Result<Option<T>, Error> {
+        let password = match GenericPassword::find(self, service, account)? {
+            Some(password) => password,
+            None => return Ok(None),
+        };
+
+        let payload = match password.password() {
+            Ok(payload) => payload,
+            Err(e) => {
+                return match e.kind() {
+                    // Deleted by another process since it was looked up
+                    ErrorKind::ItemNotFound => Ok(None),
+                    _ => Err(e),
+                };
+            }
+        };
+
+        let envelope: EnvelopeVersion =
+            serde_json::from_slice(payload.as_ref()).map_err(malformed_payload)?;
+
+        if envelope.version != JSON_PAYLOAD_VERSION {
+            return Err(Error::new(
+                ErrorKind::Decode,
+                &format!("unsupported JSON payload version: {}", envelope.version),
+            ));
+        }
+
+        let envelope: StoredEnvelope<T> =
+            serde_json::from_slice(payload.as_ref()).map_err(malformed_payload)?;
+
+        Ok(Some(envelope.value))
+    }
+}
+
+/// Convert a JSON decoding error into an `ErrorKind::Decode` error
+fn malformed_payload(e: serde_json::Error) -> Error {
+    Error::new(ErrorKind::Decode, &format!("malformed JSON payload: {}", e))
+}
@@ -1,64 +1,85 @@
-use crate::ffi::*;
-use core_foundation::{base::TCFType, string::CFString};
+use super::{Key, KeyOperation, KeyPairGenerateParams};
+use crate::{attr::AttrKeyType, dictionary::Dictionary, error::Error, ffi::*};
+use core_foundation::{base::TCFType, error::CFErrorRef, string::CFString};
+use std::ptr;
 
 /// Cryptographic algorithms for use with keys stored in the keychain.
 ///
+/// # ECIES variants
+///
+/// The `ECIESEncryption*` algorithms (listed in `KeyAlgorithm::ECIES`)
+/// differ in how the AES-GCM key and IV are derived from the ECDH shared
+/// secret, and ciphertexts can only be decrypted with the same variant:
+///
+/// - Fixed IV (e.g. `ECIESEncryptionStandardX963SHA256AESGCM`): the KDF only
+///   derives the key, and an all-zero IV is used. Available since macOS
+///   10.12 / iOS 10, and what third-party libraries which emulate Apple's
+///   format (and older Apple platforms) generally expect.
+/// - Variable IV (e.g. `ECIESEncryptionStandardVariableIVX963SHA256AESGCM`):
+///   the KDF derives both the key and IV. Requires macOS 10.13 / iOS 11.
+/// - Cofactor vs. standard ECDH: the NIST curves have a cofactor of 1, so
+///   these produce identical results, but peers must agree on the name.
+///
+/// Use `KeyAlgorithm::is_available` to check whether the running system
+/// supports a given algorithm.
+///
 /// Wrapper for `SecKeyAlgorithm`. See:
 /// <https://developer.apple.com/documentation/security/seckeyalgorithm>
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum KeyAlgorithm {
-    /// Elliptic Curve Encryption Standard X963
+    /// ECIES: standard ECDH, X9.63 KDF with SHA-1, AES-GCM with a fixed IV
     ECIESEncryptionStandardX963SHA1AESGCM,
 
-    /// Elliptic Curve Encryption Standard X963
+    /// ECIES: standard ECDH, X9.63 KDF with SHA-224, AES-GCM with a fixed IV
     ECIESEncryptionStandardX963SHA224AESGCM,
 
-    /// Elliptic Curve Encryption Standard X963
+    /// ECIES: standard ECDH, X9.63 KDF with SHA-256, AES-GCM with a fixed IV
     ECIESEncryptionStandardX963SHA256AESGCM,
 
-    /// Elliptic Curve Encryption Standard X963
+    /// ECIES: standard ECDH, X9.63 KDF with SHA-384, AES-GCM with a fixed IV
     ECIESEncryptionStandardX963SHA384AESGCM,
 
-    /// Elliptic Curve Encryption Standard X963
+    /// ECIES: standard ECDH, X9.63 KDF with SHA-512, AES-GCM with a fixed IV
     ECIESEncryptionStandardX963SHA512AESGCM,
 
-    /// Elliptic Curve Encryption Standard Variable IVX963
+    /// ECIES: standard ECDH, X9.63 KDF with SHA-224, AES-GCM with a variable IV
     ECIESEncryptionStandardVariableIVX963SHA224AESGCM,
 
-    /// Elliptic Curve Encryption Standard Variable IVX963
+    /// ECIES: standard ECDH, X9.63 KDF with SHA-256, AES-GCM with a variable IV
     ECIESEncryptionStandardVariableIVX963SHA256AESGCM,
 
-    /// Elliptic Curve Encryption Standard Variable IVX963
+    /// ECIES: standard ECDH, X9.63 KDF with SHA-384, AES-GCM with a variable IV
     ECIESEncryptionStandardVariableIVX963SHA384AESGCM,
 
-    /// Elliptic Curve Encryption Standard Variable IVX963
+    /// ECIES: standard ECDH, X9.63 KDF with SHA-512, AES-GCM with a variable IV
     ECIESEncryptionStandardVariableIVX963SHA512AESGCM,
 
-    /// Elliptic Curve Encryption Cofactor Variable IVX963
+    /// ECIES: cofactor ECDH, X9.63 KDF with SHA-224, AES-GCM with a variable IV
     ECIESEncryptionCofactorVariableIVX963SHA224AESGCM,
 
-    /// Elliptic Curve Encryption Cofactor Variable IVX963
+    /// ECIES: cofactor ECDH, X9.63 KDF with SHA-256, AES-GCM with a variable IV
     ECIESEncryptionCofactorVariableIVX963SHA256AESGCM,
 
-    /// Elliptic Curve Encryption Cofactor Variable IVX963
+    /// ECIES: cofactor ECDH, X9.63 KDF with SHA-384, AES-GCM with a variable IV
     ECIESEncryptionCofactorVariableIVX963SHA384AESGCM,
 
-    /// Elliptic Curve Encryption Cofactor Variable IVX963
+    /// ECIES: cofactor ECDH, X9.63 KDF with SHA-512, AES-GCM with a variable IV
     ECIESEncryptionCofactorVariableIVX963SHA512AESGCM,
 
-    /// Elliptic Curve Encryption Cofactor X963
+    /// ECIES: cofactor ECDH, X9.63 KDF with SHA-1, AES-GCM with a fixed IV
     ECIESEncryptionCofactorX963SHA1AESGCM,
 
-    /// Elliptic Curve Encryption Cofactor X963
+    /// ECIES: cofactor ECDH, X9.63 KDF with SHA-224, AES-GCM with a fixed IV
     ECIESEncryptionCofactorX963SHA224AESGCM,
 
-    /// Elliptic Curve Encryption Cofactor X963
+    /// ECIES: cofactor ECDH, X9.63 KDF with SHA-256, AES-GCM with a fixed IV
     ECIESEncryptionCofactorX963SHA256AESGCM,
 
-    /// Elliptic Curve Encryption Cofactor X963
+    /// ECIES: cofactor ECDH, X9.63 KDF with SHA-384, AES-GCM with a fixed IV
     ECIESEncryptionCofactorX963SHA384AESGCM,
 
-    /// Elliptic Curve Encryption Cofactor X963
+    /// ECIES: cofactor ECDH, X9.63 KDF with SHA-512, AES-GCM with a fixed IV
     ECIESEncryptionCofactorX963SHA512AESGCM,
 
     /// Elliptic Curve Signature RFC4754
@@ -236,6 +257,80 @@ pub enum KeyAlgorithm {
 }
 
 impl KeyAlgorithm {
+    /// All ECIES encryption algorithms
+    pub const ECIES: &'static [KeyAlgorithm] = &[
+        KeyAlgorithm::ECIESEncryptionStandardX963SHA1AESGCM,
+        KeyAlgorithm::ECIESEncryptionStandardX963SHA224AESGCM,
+        KeyAlgorithm::ECIESEncryptionStandardX963SHA256AESGCM,
+        KeyAlgorithm::ECIESEncryptionStandardX963SHA384AESGCM,
+        KeyAlgorithm::ECIESEncryptionStandardX963SHA512AESGCM,
+        KeyAlgorithm::ECIESEncryptionStandardVariableIVX963SHA224AESGCM,
+        KeyAlgorithm::ECIESEncryptionStandardVariableIVX963SHA256AESGCM,
+        KeyAlgorithm::ECIESEncryptionStandardVariableIVX963SHA384AESGCM,
+        KeyAlgorithm::ECIESEncryptionStandardVariableIVX963SHA512AESGCM,
+        KeyAlgorithm::ECIESEncryptionCofactorVariableIVX963SHA224AESGCM,
+        KeyAlgorithm::ECIESEncryptionCofactorVariableIVX963SHA256AESGCM,
+        KeyAlgorithm::ECIESEncryptionCofactorVariableIVX963SHA384AESGCM,
+        KeyAlgorithm::ECIESEncryptionCofactorVariableIVX963SHA512AESGCM,
+        KeyAlgorithm::ECIESEncryptionCofactorX963SHA1AESGCM,
+        KeyAlgorithm::ECIESEncryptionCofactorX963SHA224AESGCM,
+        KeyAlgorithm::ECIESEncryptionCofactorX963SHA256AESGCM,
+        KeyAlgorithm::ECIESEncryptionCofactorX963SHA384AESGCM,
+        KeyAlgorithm::ECIESEncryptionCofactorX963SHA512AESGCM,
+    ];
+
+    /// Is this one of the ECIES encryption algorithms?
+    pub fn is_ecies(self) -> bool {
+        Self::ECIES.contains(&self)
+    }
+
+    /// Is this algorithm supported on the running system?
+    ///
+    /// Probes for support by checking the algorithm against an ephemeral
+    /// (i.e. non-permanent) software key of the kind it operates on, which
+    /// for RSA algorithms means generating a 2048-bit RSA key.
+    ///
+    /// Wrapper for the `SecKeyIsAlgorithmSupported` function. See:
+    /// <https://developer.apple.com/documentation/security/1644057-seckeyisalgorithmsupported>
+    pub fn is_available(self) -> bool {
+        // Algorithm identifiers have the form e.g. `algid:sign:RSA:...`
+        let id = self.as_CFString().to_string();
+        let mut components = id.split(':').skip(1);
+
+        let operation = match components.next() {
+            Some("encrypt") => KeyOperation::Encrypt,
+            Some("sign") => KeyOperation::Sign,
+            Some("keyexchange") => KeyOperation::KeyExchange,
+            _ => return false,
+        };
+
+        let params = match components.next() {
+            Some("RSA") => KeyPairGenerateParams::new(AttrKeyType::Rsa, 2048),
+            _ => KeyPairGenerateParams::new(AttrKeyType::EcSecPrimeRandom, 256),
+        }
+        .permanent(false);
+
+        let mut error: CFErrorRef = ptr::null_mut();
+        let private_key_ref: KeyRef = unsafe {
+            SecKeyCreateRandomKey(Dictionary::from(params).as_concrete_TypeRef(), &mut error)
+        };
+
+        if private_key_ref.is_null() {
+            // Free the error
+            let _ = Error::from(error);
+            return false;
+        }
+
+        let private_key = unsafe { Key::wrap_under_create_rule(private_key_ref) };
+
+        match operation {
+            KeyOperation::Encrypt => private_key
+                .public()
+                .is_ok_and(|public_key| public_key.is_supported(operation, self)),
+            _ => private_key.is_supported(operation, self),
+        }
+    }
+
     /// Get `CFString` containing the `kSecKeyAlgorithm` dictionary value for
     /// a particular cryptographic algorithm.
     pub fn as_CFString(self) -> CFString {
@@ -328,6 +423,60 @@ impl KeyAlgorithm {
                 KeyAlgorithm::RSASignatureMessagePSSSHA512 => {
                     kSecKeyAlgorithmRSASignatureMessagePSSSHA512
                 }
+                KeyAlgorithm::ECIESEncryptionStandardX963SHA1AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionStandardX963SHA1AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionStandardX963SHA224AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionStandardX963SHA224AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionStandardX963SHA256AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionStandardX963SHA256AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionStandardX963SHA384AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionStandardX963SHA384AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionStandardX963SHA512AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionStandardX963SHA512AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionStandardVariableIVX963SHA224AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionStandardVariableIVX963SHA224AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionStandardVariableIVX963SHA256AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionStandardVariableIVX963SHA256AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionStandardVariableIVX963SHA384AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionStandardVariableIVX963SHA384AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionStandardVariableIVX963SHA512AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionStandardVariableIVX963SHA512AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionCofactorVariableIVX963SHA224AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionCofactorVariableIVX963SHA224AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionCofactorVariableIVX963SHA256AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionCofactorVariableIVX963SHA256AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionCofactorVariableIVX963SHA384AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionCofactorVariableIVX963SHA384AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionCofactorVariableIVX963SHA512AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionCofactorVariableIVX963SHA512AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionCofactorX963SHA1AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionCofactorX963SHA1AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionCofactorX963SHA224AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionCofactorX963SHA224AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionCofactorX963SHA256AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionCofactorX963SHA256AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionCofactorX963SHA384AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionCofactorX963SHA384AESGCM
+                }
+                KeyAlgorithm::ECIESEncryptionCofactorX963SHA512AESGCM => {
+                    kSecKeyAlgorithmECIESEncryptionCofactorX963SHA512AESGCM
+                }
                 // Use that RSA signature message for everything else
                 _ => {
                     kSecKeyAlgorithmRSASignatureMessagePSSSHA512
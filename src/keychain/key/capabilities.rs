@@ -0,0 +1,111 @@
+//! Capabilities of keys, i.e. which operations they may be used for.
+
+use super::*;
+use core_foundation::boolean::CFBoolean;
+
+/// Operations a key may be used for, as recorded in its `kSecAttrCan*`
+/// attributes.
+///
+/// Obtained with `Key::capabilities` and applied with
+/// `Key::set_capabilities`, so the capabilities of existing keys can be
+/// audited and tightened (e.g. disabling decryption with a signing key).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyCapabilities {
+    /// Can the key be used to sign data? (`kSecAttrCanSign`)
+    pub can_sign: bool,
+
+    /// Can the key be used to verify signatures? (`kSecAttrCanVerify`)
+    pub can_verify: bool,
+
+    /// Can the key be used to encrypt data? (`kSecAttrCanEncrypt`)
+    pub can_encrypt: bool,
+
+    /// Can the key be used to decrypt data? (`kSecAttrCanDecrypt`)
+    pub can_decrypt: bool,
+
+    /// Can the key be used to derive other keys? (`kSecAttrCanDerive`)
+    pub can_derive: bool,
+
+    /// Can the key be used to wrap other keys? (`kSecAttrCanWrap`)
+    pub can_wrap: bool,
+
+    /// Can the key be used to unwrap other keys? (`kSecAttrCanUnwrap`)
+    pub can_unwrap: bool,
+}
+
+impl KeyCapabilities {
+    /// Attributes corresponding to each capability
+    fn attrs(&self) -> [(AttrKind, bool); 7] {
+        [
+            (AttrKind::Sign, self.can_sign),
+            (AttrKind::Verify, self.can_verify),
+            (AttrKind::Encrypt, self.can_encrypt),
+            (AttrKind::Decrypt, self.can_decrypt),
+            (AttrKind::Derive, self.can_derive),
+            (AttrKind::Wrap, self.can_wrap),
+            (AttrKind::Unwrap, self.can_unwrap),
+        ]
+    }
+}
+
+impl Key {
+    /// Get the capabilities of this `Key`. Capabilities whose attributes
+    /// are missing are reported as `false`.
+    pub fn capabilities(&self) -> KeyCapabilities {
+        let attrs = self.attributes();
+
+        let capability = |attr_kind: AttrKind| {
+            attrs.find(attr_kind).is_some_and(|value| {
+                if let Some(boolean) = value.downcast::<CFBoolean>() {
+                    bool::from(boolean)
+                } else if let Some(number) = value.downcast::<CFNumber>() {
+                    number.to_i64().is_some_and(|n| n != 0)
+                } else {
+                    false
+                }
+            })
+        };
+
+        KeyCapabilities {
+            can_sign: capability(AttrKind::Sign),
+            can_verify: capability(AttrKind::Verify),
+            can_encrypt: capability(AttrKind::Encrypt),
+            can_decrypt: capability(AttrKind::Decrypt),
+            can_derive: capability(AttrKind::Derive),
+            can_wrap: capability(AttrKind::Wrap),
+            can_unwrap: capability(AttrKind::Unwrap),
+        }
+    }
+
+    /// Change the capabilities of this `Key` in place.
+    ///
+    /// The key must be stored in the keychain (i.e. permanent), and Keychain
+    /// Services may refuse some changes (e.g. for keys stored in the Secure
+    /// Enclave), in which case its error is returned.
+    ///
+    /// Wrapper for the `SecItemUpdate` function. See:
+    /// <https://developer.apple.com/documentation/security/1393617-secitemupdate>
+    pub fn set_capabilities(&self, capabilities: &KeyCapabilities) -> Result<(), Error> {
+        let mut attrs = DictionaryBuilder::new();
+
+        for &(attr_kind, value) in capabilities.attrs().iter() {
+            attrs.add_boolean(attr_kind, value);
+        }
+
+        item::update(item::Class::Key, self, attrs)
+    }
+}
+
+impl KeyPairGenerateParams {
+    /// Set all of the capabilities of the private key at once.
+    pub fn capabilities(self, capabilities: &KeyCapabilities) -> Self {
+        self.can_sign(capabilities.can_sign)
+            .can_verify(capabilities.can_verify)
+            .can_encrypt(capabilities.can_encrypt)
+            .can_decrypt(capabilities.can_decrypt)
+            .can_derive(capabilities.can_derive)
+            .can_wrap(capabilities.can_wrap)
+            .can_unwrap(capabilities.can_unwrap)
+    }
+}
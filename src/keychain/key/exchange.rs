@@ -0,0 +1,271 @@
+//! Elliptic curve Diffie-Hellman (ECDH) key agreement.
+
+use super::*;
+use core_foundation::number::CFNumber;
+use std::fmt::{self, Debug};
+#[cfg(feature = "kdf")]
+use zeroize::Zeroizing;
+use zeroize::{Zeroize, ZeroizeOnDrop};
+
+/// ECDH key exchange algorithms, i.e. the `ECDHKeyExchange*` subset of
+/// `KeyAlgorithm`.
+///
+/// The `X963*` variants pass the shared secret through the ANSI X9.63 KDF
+/// with the given hash function, producing a key of the size requested in
+/// `KeyExchangeParams`. `Standard` and `Cofactor` return the raw shared
+/// secret, which should be passed through a KDF before being used as a key.
+/// The NIST curves have a cofactor of 1, so cofactor and standard ECDH
+/// produce identical results, but peers must agree on the name.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyExchangeAlgorithm {
+    /// Standard ECDH, returning the raw shared secret
+    Standard,
+
+    /// Cofactor ECDH, returning the raw shared secret
+    Cofactor,
+
+    /// Standard ECDH, X9.63 KDF with SHA-1
+    StandardX963SHA1,
+
+    /// Standard ECDH, X9.63 KDF with SHA-224
+    StandardX963SHA224,
+
+    /// Standard ECDH, X9.63 KDF with SHA-256
+    StandardX963SHA256,
+
+    /// Standard ECDH, X9.63 KDF with SHA-384
+    StandardX963SHA384,
+
+    /// Standard ECDH, X9.63 KDF with SHA-512
+    StandardX963SHA512,
+
+    /// Cofactor ECDH, X9.63 KDF with SHA-1
+    CofactorX963SHA1,
+
+    /// Cofactor ECDH, X9.63 KDF with SHA-224
+    CofactorX963SHA224,
+
+    /// Cofactor ECDH, X9.63 KDF with SHA-256
+    CofactorX963SHA256,
+
+    /// Cofactor ECDH, X9.63 KDF with SHA-384
+    CofactorX963SHA384,
+
+    /// Cofactor ECDH, X9.63 KDF with SHA-512
+    CofactorX963SHA512,
+}
+
+impl KeyExchangeAlgorithm {
+    /// Does this algorithm derive a key with the X9.63 KDF (as opposed to
+    /// returning the raw shared secret)?
+    pub fn uses_kdf(self) -> bool {
+        !matches!(
+            self,
+            KeyExchangeAlgorithm::Standard | KeyExchangeAlgorithm::Cofactor
+        )
+    }
+
+    /// Get the `KeyAlgorithm` this algorithm corresponds to
+    pub fn as_key_algorithm(self) -> KeyAlgorithm {
+        match self {
+            KeyExchangeAlgorithm::Standard => KeyAlgorithm::ECDHKeyExchangeStandard,
+            KeyExchangeAlgorithm::Cofactor => KeyAlgorithm::ECDHKeyExchangeCofactor,
+            KeyExchangeAlgorithm::StandardX963SHA1 => KeyAlgorithm::ECDHKeyExchangeStandardX963SHA1,
+            KeyExchangeAlgorithm::StandardX963SHA224 => {
+                KeyAlgorithm::ECDHKeyExchangeStandardX963SHA224
+            }
+            KeyExchangeAlgorithm::StandardX963SHA256 => {
+                KeyAlgorithm::ECDHKeyExchangeStandardX963SHA256
+            }
+            KeyExchangeAlgorithm::StandardX963SHA384 => {
+                KeyAlgorithm::ECDHKeyExchangeStandardX963SHA384
+            }
+            KeyExchangeAlgorithm::StandardX963SHA512 => {
+                KeyAlgorithm::ECDHKeyExchangeStandardX963SHA512
+            }
+            KeyExchangeAlgorithm::CofactorX963SHA1 => KeyAlgorithm::ECDHKeyExchangeCofactorX963SHA1,
+            KeyExchangeAlgorithm::CofactorX963SHA224 => {
+                KeyAlgorithm::ECDHKeyExchangeCofactorX963SHA224
+            }
+            KeyExchangeAlgorithm::CofactorX963SHA256 => {
+                KeyAlgorithm::ECDHKeyExchangeCofactorX963SHA256
+            }
+            KeyExchangeAlgorithm::CofactorX963SHA384 => {
+                KeyAlgorithm::ECDHKeyExchangeCofactorX963SHA384
+            }
+            KeyExchangeAlgorithm::CofactorX963SHA512 => {
+                KeyAlgorithm::ECDHKeyExchangeCofactorX963SHA512
+            }
+        }
+    }
+}
+
+impl From<KeyExchangeAlgorithm> for KeyAlgorithm {
+    fn from(alg: KeyExchangeAlgorithm) -> KeyAlgorithm {
+        alg.as_key_algorithm()
+    }
+}
+
+/// Parameters of a key exchange (see `Key::exchange`).
+///
+/// Only used by the `X963*` algorithms: the raw `Standard` and `Cofactor`
+/// algorithms ignore them.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyExchangeParams {
+    requested_size: usize,
+    shared_info: Option<Vec<u8>>,
+}
+
+impl KeyExchangeParams {
+    /// Create parameters deriving a key of the given size in bytes
+    ///
+    /// Wrapper for the `kSecKeyKeyExchangeParameterRequestedSize` key. See:
+    /// <https://developer.apple.com/documentation/security/seckeykeyexchangeparameter/requestedsize>
+    pub fn new(requested_size: usize) -> Self {
+        Self {
+            requested_size,
+            shared_info: None,
+        }
+    }
+
+    /// Set the shared info (i.e. context) mixed into the derived key
+    ///
+    /// Wrapper for the `kSecKeyKeyExchangeParameterSharedInfo` key. See:
+    /// <https://developer.apple.com/documentation/security/seckeykeyexchangeparameter/sharedinfo>
+    pub fn shared_info(mut self, shared_info: &[u8]) -> Self {
+        self.shared_info = Some(shared_info.to_vec());
+        self
+    }
+
+    /// Get the size in bytes of the key to derive
+    pub fn requested_size(&self) -> usize {
+        self.requested_size
+    }
+}
+
+impl<'a> From<&'a KeyExchangeParams> for Dictionary {
+    fn from(params: &'a KeyExchangeParams) -> Dictionary {
+        let mut result = DictionaryBuilder::new();
+        result.add(
+            unsafe { kSecKeyKeyExchangeParameterRequestedSize },
+            &CFNumber::from(params.requested_size as i64),
+        );
+
+        if let Some(shared_info) = &params.shared_info {
+            result.add(
+                unsafe { kSecKeyKeyExchangeParameterSharedInfo },
+                &CFData::from_buffer(shared_info),
+            );
+        }
+
+        result.into()
+    }
+}
+
+impl Key {
+    /// Perform an ECDH key exchange between this private key and a peer's
+    /// public key, returning the derived key (or for the raw algorithms,
+    /// the shared secret) as a `SharedSecret`.
+    ///
+    /// Wrapper for the `SecKeyCopyKeyExchangeResult` function. See:
+    /// <https://developer.apple.com/documentation/security/seckeycopykeyexchangeresult(_:_:_:_:_:)>
+    pub fn exchange(
+        &self,
+        alg: KeyExchangeAlgorithm,
+        peer_public_key: &Key,
+        params: &KeyExchangeParams,
+    ) -> Result<SharedSecret, Error> {
+        hooks::run(OperationKind::KeyExchange, Some(item::Class::Key), || {
+            let mut error: CFErrorRef = ptr::null_mut();
+            let result = unsafe {
+                SecKeyCopyKeyExchangeResult(
+                    self.as_concrete_TypeRef(),
+                    alg.as_key_algorithm().as_CFString().as_CFTypeRef(),
+                    peer_public_key.as_concrete_TypeRef(),
+                    Dictionary::from(params).as_concrete_TypeRef(),
+                    &mut error,
+                )
+            };
+
+            if error.is_null() {
+                let result = unsafe { CFData::wrap_under_create_rule(result) };
+                Ok(SharedSecret(result.bytes().to_vec()))
+            } else {
+                Err(error.into())
+            }
+        })
+    }
+}
+
+/// Output of an ECDH key exchange, which is cleared from memory when
+/// dropped.
+pub struct SharedSecret(Vec<u8>);
+
+impl SharedSecret {
+    /// Borrow the shared secret as bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Get the length of the shared secret in bytes
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Is the shared secret empty?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Derive `len` bytes of keying material from the shared secret with
+    /// HKDF-SHA-256 (RFC 5869), using the given salt (which may be empty)
+    /// and info (i.e. context, such as a protocol name and the public keys
+    /// of both parties).
+    ///
+    /// Intended for the raw `KeyExchangeAlgorithm::Standard` and `Cofactor`
+    /// algorithms: the output of the `X963*` algorithms has already been
+    /// through a KDF. Returns an error with a kind of `ErrorKind::Param` if
+    /// `len` exceeds HKDF's limit of 8160 bytes.
+    #[cfg(feature = "kdf")]
+    pub fn expand_hkdf(
+        &self,
+        salt: &[u8],
+        info: &[u8],
+        len: usize,
+    ) -> Result<Zeroizing<Vec<u8>>, Error> {
+        let mut okm = Zeroizing::new(vec![0u8; len]);
+
+        hkdf::Hkdf::<sha2::Sha256>::new(Some(salt), &self.0)
+            .expand(info, &mut okm)
+            .map_err(|_| {
+                Error::new(
+                    ErrorKind::Param,
+                    &format!("HKDF output length too large: {}", len),
+                )
+            })?;
+
+        Ok(okm)
+    }
+}
+
+impl Debug for SharedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SharedSecret([REDACTED])")
+    }
+}
+
+impl Zeroize for SharedSecret {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for SharedSecret {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl ZeroizeOnDrop for SharedSecret {}
@@ -0,0 +1,150 @@
+//! Lifecycle management for long-lived keys: locating a key by its tag and
+//! (re)generating it when it is missing or unusable.
+
+use super::*;
+use crate::keychain::Keychain;
+use std::borrow::Borrow;
+
+/// Manages keys identified by an application tag, regenerating them from a
+/// declared specification (i.e. `KeyPairGenerateParams`) whenever they are
+/// missing or no longer match it.
+///
+/// Keys can disappear or become unusable for reasons outside of an
+/// application's control, e.g. keys protected by
+/// `AccessConstraint::BiometryCurrentSet` are invalidated when the enrolled
+/// biometrics change, and device-bound (e.g. Secure Enclave) keys are not
+/// restored from backups onto a new device. `KeyManager::ensure` centralizes
+/// the resulting recovery logic.
+#[derive(Copy, Clone, Debug)]
+pub struct KeyManager;
+
+impl KeyManager {
+    /// Ensure a usable private key with the given application tag exists,
+    /// generating a new permanent key pair from `spec` if it does not.
+    ///
+    /// An existing key is usable if its type and size match `spec` and its
+    /// public key can be derived. Otherwise it is deleted and replaced.
+    ///
+    /// Any application tag set on `spec` is replaced by `tag`. The returned
+    /// `EnsuredKey` reports whether an existing key was used or a new one was
+    /// generated, so applications can e.g. re-register the new public key
+    /// with a remote service.
+    pub fn ensure<T, P>(tag: T, spec: P) -> Result<EnsuredKey, Error>
+    where
+        T: Into<AttrApplicationTag>,
+        P: Borrow<KeyPairGenerateParams>,
+    {
+        ensure_key(None, tag.into(), spec.borrow())
+    }
+
+    /// Ensure a usable private key with the given application tag exists in
+    /// the given keychain, generating it there if it does not (see
+    /// `KeyManager::ensure`).
+    pub fn ensure_in<T, P>(keychain: &Keychain, tag: T, spec: P) -> Result<EnsuredKey, Error>
+    where
+        T: Into<AttrApplicationTag>,
+        P: Borrow<KeyPairGenerateParams>,
+    {
+        ensure_key(Some(keychain), tag.into(), spec.borrow())
+    }
+}
+
+/// Ensure a usable key exists, searching for (and generating) it in the
+/// given keychain, or the default one if `None`.
+fn ensure_key(
+    keychain: Option<&Keychain>,
+    tag: AttrApplicationTag,
+    spec: &KeyPairGenerateParams,
+) -> Result<EnsuredKey, Error> {
+    if spec.spec().is_none() {
+        return Err(Error::new(
+            ErrorKind::KeySizeNotAllowed,
+            "unsupported key pair specification",
+        ));
+    }
+
+    let mut query = item::Query::new()
+        .key_class(AttrKeyClass::Private)
+        .application_tag(tag.clone());
+
+    if let Some(keychain) = keychain {
+        query = query.keychain(keychain);
+    }
+
+    let outcome = match Key::find(&query)? {
+        Some(key) => {
+            if matches_spec(&key, spec) {
+                return Ok(EnsuredKey {
+                    key,
+                    outcome: KeyOutcome::Existing,
+                });
+            }
+
+            // Remove the unusable key so it isn't found in place of the
+            // regenerated one
+            if let Err(e) = key.delete() {
+                match e.kind() {
+                    // Already gone
+                    ErrorKind::ItemNotFound => (),
+                    _ => return Err(e),
+                }
+            }
+
+            KeyOutcome::Regenerated
+        }
+        None => KeyOutcome::Generated,
+    };
+
+    let mut params = spec.clone().application_tag(tag).permanent(true);
+
+    if let Some(keychain) = keychain {
+        params = params.keychain(keychain);
+    }
+
+    let key_pair = KeyPair::create(&params)?;
+
+    Ok(EnsuredKey {
+        key: key_pair.private_key,
+        outcome,
+    })
+}
+
+/// Private key returned from `KeyManager::ensure`, along with a report of
+/// what was needed to obtain it.
+#[derive(Debug)]
+pub struct EnsuredKey {
+    /// Private key which is present and valid in the keychain
+    pub key: Key,
+
+    /// How the key was obtained
+    pub outcome: KeyOutcome,
+}
+
+impl EnsuredKey {
+    /// Was a new key generated (i.e. any previously registered public key
+    /// is no longer usable)?
+    pub fn is_new(&self) -> bool {
+        self.outcome != KeyOutcome::Existing
+    }
+}
+
+/// What `KeyManager::ensure` had to do to obtain a usable key.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum KeyOutcome {
+    /// An existing, valid key was found.
+    Existing,
+
+    /// No key with the given tag existed, so one was generated. This is the
+    /// expected outcome on first use, or after a restore onto a new device.
+    Generated,
+
+    /// A key with the given tag was found but was unusable (e.g. its type or
+    /// size no longer matches the specification), so it was deleted and a
+    /// new one generated.
+    Regenerated,
+}
+
+/// Is the given existing key usable in place of one generated from `spec`?
+fn matches_spec(key: &Key, spec: &KeyPairGenerateParams) -> bool {
+    key.spec() == spec.spec() && key.public().is_ok()
+}
@@ -1,30 +1,58 @@
 //! Keys stored in macOS Keychain Services.
 
 mod algorithm;
+mod capabilities;
+mod exchange;
+mod manager;
 mod operation;
 mod pair;
+mod purpose;
+mod spec;
+#[cfg(feature = "legacy-keychain")]
+mod symmetric;
+mod usage;
+mod wrap;
 
-pub use self::{algorithm::*, operation::*, pair::*};
+#[cfg(feature = "legacy-keychain")]
+pub use self::symmetric::*;
+pub use self::{
+    algorithm::*, capabilities::*, exchange::*, manager::*, operation::*, pair::*, purpose::*,
+    spec::*, usage::*, wrap::*,
+};
 use crate::{
     attr::*,
     ciphertext::Ciphertext,
+    date,
     dictionary::{Dictionary, DictionaryBuilder},
-    error::Error,
+    error::{Error, ErrorKind},
     ffi::*,
-    keychain::item::{self, MatchLimit},
+    keychain::{
+        entitlements,
+        hooks::{self, OperationKind},
+        item::{self, MatchLimit},
+        policy::{self, Subject},
+        prompt::{self, PromptContext},
+    },
     signature::Signature,
 };
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
 use core_foundation::{
     base::{CFIndexConvertible, CFType, CFTypeRef, FromVoid, TCFType},
     data::{CFData, CFDataRef},
+    date::CFDate,
     error::CFErrorRef,
+    number::{CFNumber, CFNumberRef},
     string::{CFString, CFStringRef},
 };
 use std::{
+    borrow::Borrow,
     ffi::c_void,
-    fmt::{self, Debug},
+    fmt::{self, Debug, Display},
     ptr,
+    time::SystemTime,
 };
+use zeroize::Zeroize;
 
 declare_TCFType! {
     /// Object which represents a cryptographic key.
@@ -39,39 +67,134 @@ impl_TCFType!(Key, KeyRef, SecKeyGetTypeID);
 impl Key {
     /// Find a `Key` in the keyring using the given `ItemQuery`.
     ///
+    /// Returns `Ok(None)` if no key matches the query, reserving `Err` for
+    /// failures of the underlying keychain operation.
+    ///
+    /// The query can be passed either by value or by reference, allowing a
+    /// base query to be reused across several lookups.
+    ///
     /// Wrapper for `SecItemCopyMatching`. See:
     /// <https://developer.apple.com/documentation/security/1398306-secitemcopymatching>
-    pub fn find(query: item::Query) -> Result<Self, Error> {
-        let mut params = DictionaryBuilder::from(query);
-        params.add(unsafe { kSecClass }, &item::Class::Key.as_CFString());
-        params.add(unsafe { kSecMatchLimit }, &MatchLimit::One.as_CFType());
-        params.add_boolean(unsafe { kSecReturnRef }, true);
-
-        let mut result: KeyRef = ptr::null_mut();
-        let status = unsafe {
-            SecItemCopyMatching(
-                Dictionary::from(params).as_concrete_TypeRef(),
-                &mut result as &mut CFTypeRef,
-            )
-        };
+    pub fn find<Q: Borrow<item::Query>>(query: Q) -> Result<Option<Self>, Error> {
+        hooks::run(OperationKind::Find, Some(item::Class::Key), || {
+            let mut params = DictionaryBuilder::from(query.borrow());
+            params.add(unsafe { kSecClass }, &item::Class::Key.as_CFString());
+            // Override any limit in the caller's query, as anything but `One`
+            // returns an array rather than a single key
+            params.remove(unsafe { kSecMatchLimit });
+            params.add(unsafe { kSecMatchLimit }, &MatchLimit::One.as_CFType());
+            params.add_boolean(unsafe { kSecReturnRef }, true);
 
-        // Return an error if the status was unsuccessful
-        if let Some(e) = Error::maybe_from_OSStatus(status) {
-            return Err(e);
-        }
+            let mut result: KeyRef = ptr::null_mut();
+            let status = unsafe {
+                SecItemCopyMatching(
+                    Dictionary::from(params.clone()).as_concrete_TypeRef(),
+                    &mut result as &mut CFTypeRef,
+                )
+            };
 
-        Ok(unsafe { Key::wrap_under_create_rule(result) })
+            // Return an error if the status was unsuccessful
+            if let Some(e) = Error::maybe_from_OSStatus(status) {
+                return match e.kind() {
+                    ErrorKind::ItemNotFound => Ok(None),
+                    _ => Err(entitlements::diagnose(e, &params)),
+                };
+            }
+
+            Ok(Some(unsafe { Key::wrap_under_create_rule(result) }))
+        })
+    }
+
+    /// Find the private `Key` whose public key has the given hash.
+    ///
+    /// For asymmetric keys Keychain Services sets the application label
+    /// (i.e. `kSecAttrApplicationLabel`) to the SHA-1 hash of the public key,
+    /// so a hash obtained from e.g. a certificate or a remote key registry
+    /// can be used to locate the corresponding private key.
+    ///
+    /// Returns `Ok(None)` if no matching private key is in the keychain.
+    pub fn find_by_public_key_hash(hash: &[u8]) -> Result<Option<Self>, Error> {
+        Self::find(
+            item::Query::new()
+                .key_class(AttrKeyClass::Private)
+                .application_label(hash),
+        )
     }
 
     /// If self is a private key, derive the public key for it.
+    ///
+    /// Returns an error with a kind of `ErrorKind::NotAvailable` if the
+    /// public key can't be derived (e.g. for symmetric keys).
+    ///
+    /// Wrapper for the `SecKeyCopyPublicKey` function. See:
+    /// <https://developer.apple.com/documentation/security/seckeycopypublickey(_:)>
     pub fn public(&self) -> Result<Self, Error> {
         let result = unsafe { SecKeyCopyPublicKey(self.as_concrete_TypeRef()) };
-        assert!(!result.is_null());
+
+        if result.is_null() {
+            return Err(Error::new(
+                ErrorKind::NotAvailable,
+                "couldn't derive a public key from this key",
+            ));
+        }
 
         Ok(unsafe { Key::wrap_under_create_rule(result) })
     }
 
-    /// Get the `AttrApplicationLabel` for this `Key`.
+    /// Store the public half of this key (derived from it, if this is a
+    /// private key) as a separate permanent item in the keychain with the
+    /// given tag and label, returning the stored public key.
+    ///
+    /// Keys generated in the Secure Enclave only persist the private key,
+    /// so this allows the public key to be looked up later (e.g. with
+    /// `Key::find`) without needing a handle to the private key.
+    pub fn persist_public<T, L>(&self, tag: T, label: L) -> Result<Self, Error>
+    where
+        T: Into<AttrApplicationTag>,
+        L: Into<AttrLabel>,
+    {
+        let mut attrs = DictionaryBuilder::new();
+        attrs.add_attr(&tag.into());
+        attrs.add_attr(&label.into());
+        self.add_public(attrs)
+    }
+
+    /// Add the public half of this key to the keychain with the given
+    /// additional attributes.
+    ///
+    /// Wrapper for `SecItemAdd`. See:
+    /// <https://developer.apple.com/documentation/security/1401659-secitemadd>
+    pub(crate) fn add_public(&self, mut attrs: DictionaryBuilder) -> Result<Self, Error> {
+        hooks::run(OperationKind::Add, Some(item::Class::Key), || {
+            let public_key = match self.class() {
+                Some(AttrKeyClass::Public) => self.clone(),
+                _ => self.public()?,
+            };
+
+            attrs.add(unsafe { kSecClass }, &item::Class::Key.as_CFString());
+            attrs.add_attr(&AttrKeyClass::Public);
+            policy::enforce(&Subject::Item(Some(item::Class::Key)), &mut attrs)?;
+            attrs.add(unsafe { kSecValueRef }, &public_key);
+            attrs.add_boolean(unsafe { kSecReturnRef }, true);
+
+            let mut result: CFTypeRef = ptr::null_mut();
+            let status = unsafe {
+                SecItemAdd(
+                    Dictionary::from(attrs.clone()).as_concrete_TypeRef(),
+                    &mut result,
+                )
+            };
+
+            if let Some(e) = Error::maybe_from_OSStatus(status) {
+                return Err(entitlements::diagnose(e, &attrs));
+            }
+
+            Ok(unsafe { Key::wrap_under_create_rule(result as KeyRef) })
+        })
+    }
+
+    /// Get the `AttrApplicationLabel` for this `Key`, i.e. the hash of its
+    /// public key (for asymmetric keys).
     pub fn application_label(&self) -> Option<AttrApplicationLabel> {
         self.attributes()
             .find(AttrKind::ApplicationLabel)
@@ -82,6 +205,12 @@ impl Key {
             })
     }
 
+    /// Get the usage statistics recorded for this key, which are only
+    /// collected while usage tracking is enabled (see `set_usage_tracking`).
+    pub fn usage_stats(&self) -> Result<KeyUsageStats, Error> {
+        KeyUsageStats::find(self)
+    }
+
     /// Get the `AttrApplicationTag` for this `Key`.
     pub fn application_tag(&self) -> Option<AttrApplicationTag> {
         self.attributes().find(AttrKind::ApplicationTag).map(|tag| {
@@ -98,6 +227,54 @@ impl Key {
         })
     }
 
+    /// Change the `AttrLabel` of this `Key` in place.
+    ///
+    /// Unlike deleting and recreating the key, this preserves its access
+    /// control settings, so the user-visible name of e.g. a Secure Enclave
+    /// key can be changed without generating a new key.
+    ///
+    /// Wrapper for the `SecItemUpdate` function. See:
+    /// <https://developer.apple.com/documentation/security/1393617-secitemupdate>
+    pub fn set_label<L: Into<AttrLabel>>(&self, label: L) -> Result<(), Error> {
+        let mut attrs = DictionaryBuilder::new();
+        attrs.add_attr(&label.into());
+        item::update(item::Class::Key, self, attrs)
+    }
+
+    /// Bump the modification date of this `Key` to the current time without
+    /// changing it (see `Item::touch`).
+    pub fn touch(&self) -> Result<(), Error> {
+        item::update(item::Class::Key, self, item::touch_attrs())
+    }
+
+    /// Get the date this `Key` was created (if available).
+    ///
+    /// Wrapper for the `kSecAttrCreationDate` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrcreationdate>
+    pub fn creation_date(&self) -> Option<SystemTime> {
+        self.date_attribute(AttrKind::CreationDate)
+    }
+
+    /// Get the date this `Key` was last modified (if available).
+    ///
+    /// Wrapper for the `kSecAttrModificationDate` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrmodificationdate>
+    pub fn modification_date(&self) -> Option<SystemTime> {
+        self.date_attribute(AttrKind::ModificationDate)
+    }
+
+    /// Get the date this `Key` was created as a `chrono::DateTime`.
+    #[cfg(feature = "chrono")]
+    pub fn creation_datetime(&self) -> Option<DateTime<Utc>> {
+        self.creation_date().map(DateTime::from)
+    }
+
+    /// Get the date this `Key` was last modified as a `chrono::DateTime`.
+    #[cfg(feature = "chrono")]
+    pub fn modification_datetime(&self) -> Option<DateTime<Utc>> {
+        self.modification_date().map(DateTime::from)
+    }
+
     /// Get the `AttrKeyClass` for this `Key`.
     pub fn class(&self) -> Option<AttrKeyClass> {
         self.attributes()
@@ -112,6 +289,39 @@ impl Key {
             .map(|keytype| AttrKeyType::from(keytype.as_CFTypeRef() as CFStringRef))
     }
 
+    /// Get the size of this `Key` in bits (if available).
+    ///
+    /// Wrapper for the `kSecAttrKeySizeInBits` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrkeysizeinbits>
+    pub fn size_in_bits(&self) -> Option<usize> {
+        self.attributes()
+            .find(AttrKind::KeySizeInBits)
+            .and_then(|size| {
+                unsafe { CFNumber::wrap_under_get_rule(size.as_CFTypeRef() as CFNumberRef) }
+                    .to_i64()
+            })
+            .map(|size| size as usize)
+    }
+
+    /// Get the `AttrTokenId` of the token this `Key` is stored on (if it is
+    /// not stored in software), e.g. the Secure Enclave or a smart card.
+    ///
+    /// Wrapper for the `kSecAttrTokenID` attribute. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrtokenid>
+    pub fn token_id(&self) -> Option<AttrTokenId> {
+        self.attributes().find(AttrKind::TokenId).map(|token_id| {
+            AttrTokenId::from(&unsafe {
+                CFString::wrap_under_get_rule(token_id.as_CFTypeRef() as CFStringRef)
+            })
+        })
+    }
+
+    /// Is this `Key` stored on a hardware token (e.g. the Secure Enclave)
+    /// rather than in software?
+    pub fn is_hardware_backed(&self) -> bool {
+        self.token_id().is_some()
+    }
+
     /// Determine whether a key is suitable for an operation using a certain algorithm
     ///
     /// Wrapper for the `SecKeyIsAlgorithmSupported` function. See:
@@ -132,22 +342,42 @@ impl Key {
     /// Wrapper for the `SecKeyCreateSignature` function. See:
     /// <https://developer.apple.com/documentation/security/1643916-seckeycreatesignature>
     pub fn sign(&self, alg: KeyAlgorithm, data: &[u8]) -> Result<Signature, Error> {
-        let mut error: CFErrorRef = ptr::null_mut();
-        let signature = unsafe {
-            SecKeyCreateSignature(
-                self.as_concrete_TypeRef(),
-                alg.as_CFString().as_CFTypeRef(),
-                CFData::from_buffer(data).as_concrete_TypeRef(),
-                &mut error,
-            )
-        };
+        let signature = hooks::run(OperationKind::Sign, Some(item::Class::Key), || {
+            let mut error: CFErrorRef = ptr::null_mut();
+            let signature = unsafe {
+                SecKeyCreateSignature(
+                    self.as_concrete_TypeRef(),
+                    alg.as_CFString().as_CFTypeRef(),
+                    CFData::from_buffer(data).as_concrete_TypeRef(),
+                    &mut error,
+                )
+            };
 
-        if error.is_null() {
-            let bytes = unsafe { CFData::wrap_under_create_rule(signature) }.to_vec();
-            Ok(Signature::new(alg, bytes))
-        } else {
-            Err(error.into())
-        }
+            if error.is_null() {
+                let bytes = unsafe { CFData::wrap_under_create_rule(signature) }.to_vec();
+                Ok(Signature::new(alg, bytes))
+            } else {
+                Err(error.into())
+            }
+        })?;
+
+        record_usage(self, KeyOperation::Sign);
+        Ok(signature)
+    }
+
+    /// Create a cryptographic signature of the given data using this key,
+    /// prompting the user (if the key requires authentication) with text
+    /// rendered from the installed `PromptTemplate` and the given reason.
+    ///
+    /// The key is looked up again by its `AttrApplicationLabel` with the
+    /// rendered prompt, as the prompt can only be set when a key is found.
+    pub fn sign_with_reason(
+        &self,
+        alg: KeyAlgorithm,
+        data: &[u8],
+        reason: &str,
+    ) -> Result<Signature, Error> {
+        self.with_prompt(KeyOperation::Sign, reason)?.sign(alg, data)
     }
 
     /// Verifies the cryptographic signature of the given data using this key.
@@ -155,22 +385,24 @@ impl Key {
     /// Wrapper for the `SecKeyVerifySignature` function. See:
     /// <https://developer.apple.com/documentation/security/1643715-seckeyverifysignature>
     pub fn verify(&self, signed_data: &[u8], signature: &Signature) -> Result<bool, Error> {
-        let mut error: CFErrorRef = ptr::null_mut();
-        let result = unsafe {
-            SecKeyVerifySignature(
-                self.as_concrete_TypeRef(),
-                signature.algorithm().as_CFString().as_CFTypeRef(),
-                CFData::from_buffer(signed_data).as_concrete_TypeRef(),
-                CFData::from_buffer(signature.as_bytes()).as_concrete_TypeRef(),
-                &mut error,
-            )
-        };
+        hooks::run(OperationKind::Verify, Some(item::Class::Key), || {
+            let mut error: CFErrorRef = ptr::null_mut();
+            let result = unsafe {
+                SecKeyVerifySignature(
+                    self.as_concrete_TypeRef(),
+                    signature.algorithm().as_CFString().as_CFTypeRef(),
+                    CFData::from_buffer(signed_data).as_concrete_TypeRef(),
+                    CFData::from_buffer(signature.as_bytes()).as_concrete_TypeRef(),
+                    &mut error,
+                )
+            };
 
-        if error.is_null() {
-            Ok(result == 0x1)
-        } else {
-            Err(error.into())
-        }
+            if error.is_null() {
+                Ok(result == 0x1)
+            } else {
+                Err(error.into())
+            }
+        })
     }
 
     /// Encrypts a block of data using a public key and specified algorithm
@@ -178,22 +410,24 @@ impl Key {
     /// Wrapper for the `SecKeyCreateEncryptedData` function. See:
     /// <https://developer.apple.com/documentation/security/1643957-seckeycreateencrypteddata>
     pub fn encrypt(&self, alg: KeyAlgorithm, plaintext: &[u8]) -> Result<Ciphertext, Error> {
-        let mut error: CFErrorRef = ptr::null_mut();
-        let ciphertext = unsafe {
-            SecKeyCreateEncryptedData(
-                self.as_concrete_TypeRef(),
-                alg.as_CFString().as_CFTypeRef(),
-                CFData::from_buffer(plaintext).as_concrete_TypeRef(),
-                &mut error,
-            )
-        };
+        hooks::run(OperationKind::Encrypt, Some(item::Class::Key), || {
+            let mut error: CFErrorRef = ptr::null_mut();
+            let ciphertext = unsafe {
+                SecKeyCreateEncryptedData(
+                    self.as_concrete_TypeRef(),
+                    alg.as_CFString().as_CFTypeRef(),
+                    CFData::from_buffer(plaintext).as_concrete_TypeRef(),
+                    &mut error,
+                )
+            };
 
-        if error.is_null() {
-            let bytes = unsafe { CFData::wrap_under_create_rule(ciphertext) }.to_vec();
-            Ok(Ciphertext::new(alg, bytes))
-        } else {
-            Err(error.into())
-        }
+            if error.is_null() {
+                let bytes = unsafe { CFData::wrap_under_create_rule(ciphertext) }.to_vec();
+                Ok(Ciphertext::new(alg, bytes))
+            } else {
+                Err(error.into())
+            }
+        })
     }
 
     /// Decrypts a block of data using a private key and specified algorithm
@@ -201,52 +435,114 @@ impl Key {
     /// Wrapper for the `SecKeyCreateDecryptedData` function. See:
     /// <https://developer.apple.com/documentation/security/1644043-seckeycreatedecrypteddata>
     pub fn decrypt(&self, ciphertext: Ciphertext) -> Result<Vec<u8>, Error> {
-        let mut error: CFErrorRef = ptr::null_mut();
-        let plaintext = unsafe {
-            SecKeyCreateDecryptedData(
-                self.as_concrete_TypeRef(),
-                ciphertext.algorithm().as_CFString().as_CFTypeRef(),
-                CFData::from_buffer(ciphertext.as_ref()).as_concrete_TypeRef(),
-                &mut error,
-            )
-        };
+        let plaintext = hooks::run(OperationKind::Decrypt, Some(item::Class::Key), || {
+            let mut error: CFErrorRef = ptr::null_mut();
+            let plaintext = unsafe {
+                SecKeyCreateDecryptedData(
+                    self.as_concrete_TypeRef(),
+                    ciphertext.algorithm().as_CFString().as_CFTypeRef(),
+                    CFData::from_buffer(ciphertext.as_ref()).as_concrete_TypeRef(),
+                    &mut error,
+                )
+            };
 
-        if error.is_null() {
-            let bytes = unsafe { CFData::wrap_under_create_rule(plaintext) }.to_vec();
-            Ok(bytes)
-        } else {
-            Err(error.into())
-        }
+            if error.is_null() {
+                let bytes = unsafe { CFData::wrap_under_create_rule(plaintext) }.to_vec();
+                Ok(bytes)
+            } else {
+                Err(error.into())
+            }
+        })?;
+
+        record_usage(self, KeyOperation::Decrypt);
+        Ok(plaintext)
+    }
+
+    /// Decrypts a block of data using this private key, prompting the user
+    /// (if the key requires authentication) with text rendered from the
+    /// installed `PromptTemplate` and the given reason.
+    ///
+    /// See `Key::sign_with_reason` for how the prompt is applied.
+    pub fn decrypt_with_reason(
+        &self,
+        ciphertext: Ciphertext,
+        reason: &str,
+    ) -> Result<Vec<u8>, Error> {
+        self.with_prompt(KeyOperation::Decrypt, reason)?
+            .decrypt(ciphertext)
     }
 
     /// Delete this key from the keychain
     ///
+    /// If the `DeletionMode` is `Tombstone`, the key is moved into the
+    /// tombstone namespace instead, from which it can be restored (see
+    /// `Tombstone`).
+    ///
     /// Wrapper for `SecItemDelete` function. See:
     /// <https://developer.apple.com/documentation/security/1395547-secitemdelete>
     pub fn delete(self) -> Result<(), Error> {
-        let mut query = DictionaryBuilder::new();
-        let key_class = self.class().unwrap();
-        query.add(unsafe { kSecClass }, &item::Class::Key.as_CFString());
-        query.add(unsafe { kSecAttrKeyClass }, &key_class.as_CFString());
-        if key_class == AttrKeyClass::Public {
-            query.add(unsafe { kSecAttrKeyType }, &self.key_type().unwrap().as_CFString());
-            query.add(
-                unsafe { kSecAttrApplicationTag },
-                &self.application_tag().unwrap().as_CFType(),
-            );
-        } else if key_class == AttrKeyClass::Private {
-            query.add(
-                unsafe { kSecAttrApplicationLabel },
-                &self.application_label().unwrap().as_CFType(),
-            );
-            query.add_boolean(unsafe { kSecReturnRef }, true);
-        }
-        let status = unsafe { SecItemDelete(Dictionary::from(query).as_concrete_TypeRef()) };
-        if let Some(e) = Error::maybe_from_OSStatus(status) {
-            Err(e)
-        } else {
-            Ok(())
-        }
+        hooks::run(OperationKind::Delete, Some(item::Class::Key), || {
+            if item::deletion_mode() == item::DeletionMode::Tombstone {
+                return item::bury_key(&self);
+            }
+
+            let mut query = DictionaryBuilder::new();
+            let key_class = self.class().unwrap();
+            query.add(unsafe { kSecClass }, &item::Class::Key.as_CFString());
+            query.add(unsafe { kSecAttrKeyClass }, &key_class.as_CFString());
+            if key_class == AttrKeyClass::Public {
+                query.add(
+                    unsafe { kSecAttrKeyType },
+                    &self.key_type().unwrap().as_CFString(),
+                );
+                query.add(
+                    unsafe { kSecAttrApplicationTag },
+                    &self.application_tag().unwrap().as_CFType(),
+                );
+            } else if key_class == AttrKeyClass::Private {
+                query.add(
+                    unsafe { kSecAttrApplicationLabel },
+                    &self.application_label().unwrap().as_CFType(),
+                );
+                query.add_boolean(unsafe { kSecReturnRef }, true);
+            }
+            let status = unsafe { SecItemDelete(Dictionary::from(query).as_concrete_TypeRef()) };
+            if let Some(e) = Error::maybe_from_OSStatus(status) {
+                Err(e)
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    /// Find this private key again, using a prompt rendered for the given
+    /// operation and reason
+    fn with_prompt(&self, operation: KeyOperation, reason: &str) -> Result<Key, Error> {
+        let application_label = self.application_label().ok_or_else(|| {
+            Error::new(
+                ErrorKind::Param,
+                "key has no application label to find it by",
+            )
+        })?;
+
+        let label = self.label().map(|label| label.to_string());
+        let prompt = prompt::render_prompt(&PromptContext {
+            operation,
+            label: label.as_deref(),
+            reason: Some(reason),
+        });
+
+        let query = item::Query::new()
+            .key_class(AttrKeyClass::Private)
+            .application_label(application_label)
+            .use_operation_prompt(&prompt);
+
+        Self::find(query)?.ok_or_else(|| {
+            Error::new(
+                ErrorKind::ItemNotFound,
+                "private key is no longer in the keychain",
+            )
+        })
     }
 
     /// Export this key as an external representation.
@@ -283,7 +579,7 @@ impl Key {
     ///
     /// Wrapper for the `SecKeyCreateWithData` function. See:
     /// <https://developer.apple.com/documentation/security/1643701-seckeycreatewithdata>
-    pub fn from_external_representation(params: RestoreKeyParams) -> Result<Self, Error> {
+    pub fn from_external_representation(mut params: RestoreKeyParams) -> Result<Self, Error> {
         let mut error: CFErrorRef = ptr::null_mut();
         let data = unsafe {
             SecKeyCreateWithData(
@@ -293,6 +589,9 @@ impl Key {
             )
         };
 
+        // Private and symmetric key data is secret
+        params.key_data.zeroize();
+
         if error.is_null() {
             Ok(unsafe { Key::wrap_under_create_rule(data) })
         } else {
@@ -300,6 +599,73 @@ impl Key {
         }
     }
 
+    /// Is this (private) `Key` still present and usable in the keychain?
+    ///
+    /// Keys protected by `AccessConstraint::BiometryCurrentSet` are silently
+    /// invalidated when the enrolled biometrics change, and keys bound to a
+    /// device are not carried over when a backup is restored elsewhere. This
+    /// probes the keychain for the key (by its application label) without
+    /// prompting the user, returning `Ok(false)` if it is no longer there.
+    pub fn is_still_valid(&self) -> Result<bool, Error> {
+        hooks::run(OperationKind::Find, Some(item::Class::Key), || {
+            let application_label = match self.application_label() {
+                Some(label) => label,
+                None => return Ok(false),
+            };
+
+            let mut params = DictionaryBuilder::new();
+            params.add(unsafe { kSecClass }, &item::Class::Key.as_CFString());
+            params.add_attr(&application_label);
+            params.add(unsafe { kSecMatchLimit }, &MatchLimit::One.as_CFType());
+
+            // Fail rather than prompting if the key requires authentication
+            let auth_ui = unsafe { CFString::wrap_under_get_rule(kSecUseAuthenticationUIFail) };
+            params.add(unsafe { kSecUseAuthenticationUI }, &auth_ui);
+
+            if let Some(class) = self.class() {
+                params.add_attr(&class);
+            }
+
+            let status = unsafe {
+                SecItemCopyMatching(
+                    Dictionary::from(params).as_concrete_TypeRef(),
+                    ptr::null_mut(),
+                )
+            };
+
+            match Error::maybe_from_OSStatus(status) {
+                None => Ok(true),
+                Some(e) => match e.kind() {
+                    // The key exists, but using it requires user interaction
+                    ErrorKind::InteractionNotAllowed => Ok(true),
+                    ErrorKind::ItemNotFound => Ok(false),
+                    _ => Err(e),
+                },
+            }
+        })
+    }
+
+    /// Ensure this `Key` is still valid (see `Key::is_still_valid`),
+    /// returning an error of kind `ErrorKind::KeyInvalidated` if it is not.
+    pub fn ensure_valid(&self) -> Result<(), Error> {
+        if self.is_still_valid()? {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::KeyInvalidated,
+                &format!("{} is no longer present in the keychain", self),
+            ))
+        }
+    }
+
+    /// Get a `CFDate` attribute of this `Key` as a `SystemTime`.
+    fn date_attribute(&self, attr_kind: AttrKind) -> Option<SystemTime> {
+        self.attributes()
+            .find(attr_kind)
+            .and_then(|value| value.downcast::<CFDate>())
+            .map(|date| date::system_time_from_CFDate(&date))
+    }
+
     /// Fetch attributes for this `Key`.
     ///
     /// Wrapper for `SecKeyCopyAttributes`. See:
@@ -313,10 +679,63 @@ impl Debug for Key {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "SecKey {{ application_label: {:?}, application_tag: {:?}, label: {:?} }}",
+            "SecKey {{ class: {:?}, key_type: {:?}, size_in_bits: {:?}, hardware_backed: {:?}, \
+             application_label: {:?}, application_tag: {:?}, label: {:?} }}",
+            self.class(),
+            self.key_type(),
+            self.size_in_bits(),
+            self.is_hardware_backed(),
             self.application_label(),
             self.application_tag(),
             self.label()
         )
     }
 }
+
+/// Human-readable description of a key which never includes key material,
+/// e.g. `private EC P-256 key (tag: "my-key", hardware-backed)`.
+impl Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.class() {
+            Some(AttrKeyClass::Public) => write!(f, "public ")?,
+            Some(AttrKeyClass::Private) => write!(f, "private ")?,
+            Some(AttrKeyClass::Symmetric) => write!(f, "symmetric ")?,
+            None => (),
+        }
+
+        match (self.key_type(), self.size_in_bits()) {
+            (Some(AttrKeyType::EcSecPrimeRandom), Some(size)) => write!(f, "EC P-{} ", size)?,
+            (Some(AttrKeyType::EcSecPrimeRandom), None) => write!(f, "EC ")?,
+            (Some(AttrKeyType::Rsa), Some(size)) => write!(f, "RSA-{} ", size)?,
+            (Some(AttrKeyType::Rsa), None) => write!(f, "RSA ")?,
+            (Some(AttrKeyType::Aes), Some(size)) => write!(f, "AES-{} ", size)?,
+            (Some(AttrKeyType::Aes), None) => write!(f, "AES ")?,
+            (None, _) => (),
+        }
+
+        write!(f, "key")?;
+
+        let mut details = vec![];
+
+        if let Some(tag) = self.application_tag() {
+            details.push(match tag.as_str() {
+                Ok(tag) => format!("tag: {:?}", tag),
+                Err(_) => format!("tag: {:?}", tag.as_bytes()),
+            });
+        }
+
+        if let Some(label) = self.label() {
+            details.push(format!("label: {:?}", label.to_string()));
+        }
+
+        if self.is_hardware_backed() {
+            details.push("hardware-backed".to_owned());
+        }
+
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
@@ -6,6 +6,7 @@ use self::KeyOperation::*;
 /// Wrapper for `SecKeyOperationType`. See:
 /// <https://developer.apple.com/documentation/security/seckeyoperationtype>
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum KeyOperation {
     /// Decrypt operation
     Decrypt,
@@ -2,7 +2,7 @@ use super::*;
 use crate::{access::AccessControl, dictionary::*, error::Error, attr::*, keychain::Keychain};
 use core_foundation::base::TCFType;
 use core_foundation::data::CFData;
-use std::ptr;
+use std::{borrow::Borrow, ptr};
 
 /// Public key pairs (i.e. public and private key) stored in the keychain.
 #[derive(Debug)]
@@ -15,95 +15,147 @@ pub struct KeyPair {
 }
 
 impl KeyPair {
-    /// An asymmetric cryptographic key pair is composed of a public and a private key that are generated together.
-    /// The public key can be distributed freely, but keep the private key secret.
-    /// One or both may be stored in a keychain for safekeeping.
+    /// Is the Secure Enclave available on this device?
     ///
-    /// Wrapper for the `SecKeyCreateRandomKey` function see:
-    /// <https://developer.apple.com/documentation/security/1823694-seckeycreaterandomkey>
-    pub fn create(params: KeyPairGenerateParams) -> Result<KeyPair, Error> {
+    /// Probes for the Secure Enclave by generating an ephemeral (i.e.
+    /// non-permanent) P-256 key in it.
+    pub fn is_secure_enclave_available() -> bool {
+        let params = KeyPairGenerateParams::new(AttrKeyType::EcSecPrimeRandom, 256)
+            .token_id(AttrTokenId::SecureEnclave)
+            .permanent(false);
+
         let mut error: CFErrorRef = ptr::null_mut();
         let private_key_ref: KeyRef = unsafe {
-            SecKeyCreateRandomKey(Dictionary::from(params.clone()).as_concrete_TypeRef(), &mut error)
+            SecKeyCreateRandomKey(Dictionary::from(params).as_concrete_TypeRef(), &mut error)
         };
+
         if private_key_ref.is_null() {
-            Err(error.into())
+            // Free the error
+            let _ = Error::from(error);
+            false
         } else {
-            let public_key_ref = unsafe { SecKeyCopyPublicKey(private_key_ref) };
-            assert!(!public_key_ref.is_null());
-            assert!(!private_key_ref.is_null());
-
-            let public_key = unsafe { Key::wrap_under_create_rule(public_key_ref) };
+            let _ = unsafe { Key::wrap_under_create_rule(private_key_ref) };
+            true
+        }
+    }
 
-            /*
-            let public_key_base_ref = public_key.to_external_representation().unwrap();
-            let data = CFData::from_buffer(&public_key_base_ref);
+    /// An asymmetric cryptographic key pair is composed of a public and a private key that are generated together.
+    /// The public key can be distributed freely, but keep the private key secret.
+    /// One or both may be stored in a keychain for safekeeping.
+    ///
+    /// Wrapper for the `SecKeyCreateRandomKey` function see:
+    /// <https://developer.apple.com/documentation/security/1823694-seckeycreaterandomkey>
+    pub fn create<P>(params: P) -> Result<KeyPair, Error>
+    where
+        P: Borrow<KeyPairGenerateParams>,
+    {
+        hooks::run(OperationKind::GenerateKey, Some(item::Class::Key), || {
+            let params = &params.borrow().enforce_policy()?;
+            let mut error: CFErrorRef = ptr::null_mut();
+            let private_key_ref: KeyRef = unsafe {
+                SecKeyCreateRandomKey(Dictionary::from(params).as_concrete_TypeRef(), &mut error)
+            };
+            if private_key_ref.is_null() {
+                Err(params.diagnose_error(error.into()))
+            } else {
+                let private_key = unsafe { Key::wrap_under_create_rule(private_key_ref) };
+
+                let keypair = KeyPair {
+                    public_key: private_key.public()?,
+                    private_key,
+                };
+
+                keypair.persist_public_key(params)
+            }
+        })
+    }
 
-            let mut attrs = DictionaryBuilder::new();
-            attrs.add_class(item::Class::Key);
-            attrs.add_attr(&AttrKeyType::EcSecPrimeRandom);
-            attrs.add_attr(&AttrKeyClass::Public);
-            attrs.add(unsafe { kSecValueData }, &data);
+    /// Generate a public/private `KeyPair` using the given
+    /// `GeneratePairParams`.
+    ///
+    /// `SecKeyGeneratePair` is deprecated, and doesn't persist keys
+    /// generated in the Secure Enclave the same way, so those are generated
+    /// with `KeyPair::create` instead. New code should prefer
+    /// `KeyPair::create`, which returns the same `KeyPair`.
+    ///
+    /// Wrapper for the `SecKeyGeneratePair` function. See:
+    /// <https://developer.apple.com/documentation/security/1395339-seckeygeneratepair>
+    pub fn generate<P>(params: P) -> Result<KeyPair, Error>
+    where
+        P: Borrow<KeyPairGenerateParams>,
+    {
+        if params.borrow().is_secure_enclave() {
+            return KeyPair::create(params);
+        }
 
-            println!("got here.");
-            Keychain::default().add_item(attrs)?;
+        hooks::run(OperationKind::GenerateKey, Some(item::Class::Key), || {
+            let params = params.borrow().enforce_policy()?;
+            let mut public_key_ref: KeyRef = ptr::null_mut();
+            let mut private_key_ref: KeyRef = ptr::null_mut();
+
+            let status = unsafe {
+                SecKeyGeneratePair(
+                    Dictionary::from(&params).as_concrete_TypeRef(),
+                    &mut public_key_ref,
+                    &mut private_key_ref,
+                )
+            };
+
+            // Return an error if the status was unsuccessful
+            if let Some(e) = Error::maybe_from_OSStatus(status) {
+                return Err(e);
+            }
 
-            println!("finished...");
-            */
+            assert!(!public_key_ref.is_null());
+            assert!(!private_key_ref.is_null());
 
-            Ok(unsafe {
+            let keypair = unsafe {
                 KeyPair {
-                    public_key,
+                    public_key: Key::wrap_under_create_rule(public_key_ref),
                     private_key: Key::wrap_under_create_rule(private_key_ref),
                 }
-            })
-        }
+            };
+
+            keypair.persist_public_key(&params)
+        })
     }
 
-    /// Generate a public/private `KeyPair` using the given
-    /// `GeneratePairParams`.
-    ///
-    /// Wrapper for the `SecKeyGeneratePair` function. See:
-    /// <https://developer.apple.com/documentation/security/1395339-seckeygeneratepair>
-    pub fn generate(params: KeyPairGenerateParams) -> Result<KeyPair, Error> {
-        let mut public_key_ref: KeyRef = ptr::null_mut();
-        let mut private_key_ref: KeyRef = ptr::null_mut();
-
-        let status = unsafe {
-            SecKeyGeneratePair(
-                Dictionary::from(params).as_concrete_TypeRef(),
-                &mut public_key_ref,
-                &mut private_key_ref,
-            )
-        };
+    /// Store the public key of a newly generated pair as a separate item if
+    /// the parameters ask for it (see
+    /// `KeyPairGenerateParams::persist_public_key`), returning the pair with
+    /// the stored public key.
+    fn persist_public_key(mut self, params: &KeyPairGenerateParams) -> Result<Self, Error> {
+        if let Some(public_key_attrs) = &params.public_key_attrs {
+            let mut public_key_attrs = public_key_attrs.clone();
 
-        // Return an error if the status was unsuccessful
-        if let Some(e) = Error::maybe_from_OSStatus(status) {
-            return Err(e);
-        }
+            if let Some(access_group) = params.attrs.get(unsafe { kSecAttrAccessGroup }) {
+                public_key_attrs.add(unsafe { kSecAttrAccessGroup }, access_group);
+            }
 
-        assert!(!public_key_ref.is_null());
-        assert!(!private_key_ref.is_null());
+            self.public_key = self.private_key.add_public(public_key_attrs)?;
+        }
 
-        Ok(unsafe {
-            KeyPair {
-                public_key: Key::wrap_under_create_rule(public_key_ref),
-                private_key: Key::wrap_under_create_rule(private_key_ref),
-            }
-        })
+        Ok(self)
     }
 }
 
 /// Builder for key generation parameters (passed to the underlying
 /// `SecKeyGeneratePair` function)
 ///
+/// Parameters are `Clone`, and `KeyPair::create`/`KeyPair::generate` accept
+/// them by reference, so a base set of parameters can be specialized (e.g.
+/// with a different tag or label) for each key generated.
+///
 /// For more information on generating cryptographic keys in a keychain, see:
 /// <https://developer.apple.com/documentation/security/certificate_key_and_trust_services/keys/generating_new_cryptographic_keys>
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct KeyPairGenerateParams {
     key_type: AttrKeyType,
     key_size: usize,
     attrs: DictionaryBuilder,
+    #[cfg_attr(feature = "serde", serde(default))]
+    public_key_attrs: Option<DictionaryBuilder>,
 }
 
 impl KeyPairGenerateParams {
@@ -113,18 +165,65 @@ impl KeyPairGenerateParams {
             key_type,
             key_size,
             attrs: <_>::default(),
+            public_key_attrs: None,
         }
     }
 
-    /// Set the access control policy (a.k.a. ACL) for the `Key`.
+    /// Get the `KeySpec` of the keys these parameters generate, if they're
+    /// of a supported type and size
+    pub fn spec(&self) -> Option<KeySpec> {
+        KeySpec::new(self.key_type, self.key_size).ok()
+    }
+
+    /// Set the access control policy (a.k.a. ACL) for the `Key`, replacing
+    /// any previously set policy (e.g. by `KeyPairGenerateParams::for_purpose`).
     ///
     /// Wrapper for the `kSecAttrAccessControl` attribute key. See:
     /// <https://developer.apple.com/documentation/security/ksecattraccesscontrol>
     pub fn access_control(mut self, access_control: &AccessControl) -> Self {
+        self.attrs.remove(AttrKind::AccessControl);
         self.attrs.add(AttrKind::AccessControl, access_control);
         self
     }
 
+    /// Store the private key (and the public key, if it's persisted with
+    /// `persist_public_key`) in the given access group of the data
+    /// protection keychain, to share it with other applications and
+    /// extensions from the same team. The application must be entitled to
+    /// use the access group.
+    ///
+    /// Wrapper for the `kSecAttrAccessGroup` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattraccessgroup>
+    pub fn access_group(mut self, access_group: &str) -> Self {
+        self.attrs.remove(unsafe { kSecAttrAccessGroup });
+        self.attrs
+            .add_string(unsafe { kSecAttrAccessGroup }, access_group);
+        self
+    }
+
+    /// Store the key in the given (file-based) keychain, rather than the
+    /// default one.
+    ///
+    /// Wrapper for the `kSecUseKeychain` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecusekeychain>
+    pub fn keychain(mut self, keychain: &Keychain) -> Self {
+        self.attrs.remove(unsafe { kSecUseKeychain });
+        self.attrs.add(unsafe { kSecUseKeychain }, keychain);
+        self
+    }
+
+    /// Store the key in the data protection keychain rather than a legacy
+    /// file-based keychain (see `Query::data_protection_keychain`).
+    ///
+    /// Wrapper for the `kSecUseDataProtectionKeychain` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecusedataprotectionkeychain>
+    pub fn data_protection_keychain(mut self, value: bool) -> Self {
+        self.attrs.remove(unsafe { kSecUseDataProtectionKeychain });
+        self.attrs
+            .add_boolean(unsafe { kSecUseDataProtectionKeychain }, value);
+        self
+    }
+
     /// Set a tag (private, application-specific identifier) on this key.
     /// Tags are useful as the "primary key" for looking up keychain items.
     ///
@@ -228,6 +327,25 @@ impl KeyPairGenerateParams {
         self
     }
 
+    /// Also store the public key as a separate permanent item in the
+    /// keychain with the given tag and label when the pair is generated
+    /// with either `KeyPair::create` or `KeyPair::generate` (see
+    /// `Key::persist_public`).
+    ///
+    /// Only the private key is persisted when generating keys in the Secure
+    /// Enclave, so this is needed to look up the public key later.
+    pub fn persist_public_key<T, L>(mut self, tag: T, label: L) -> Self
+    where
+        T: Into<AttrApplicationTag>,
+        L: Into<AttrLabel>,
+    {
+        let mut public_key_attrs = DictionaryBuilder::new();
+        public_key_attrs.add_attr(&tag.into());
+        public_key_attrs.add_attr(&label.into());
+        self.public_key_attrs = Some(public_key_attrs);
+        self
+    }
+
     /// Set whether this key can be wrapped with NONE algorithm. True
     /// means it cannot be wrapped with NONE, false means it can.
     ///
@@ -262,20 +380,134 @@ impl KeyPairGenerateParams {
     ///
     /// Wrapper for the `kSecAttrTokenID` attribute key. See:
     /// <https://developer.apple.com/documentation/security/ksecattrtokenid>
-    pub fn token_id(mut self, value: AttrTokenId) -> Self {
-        self.attrs.add_attr(&value);
+    pub fn token_id<T: Into<AttrTokenId>>(mut self, value: T) -> Self {
+        self.attrs.add_attr(&value.into());
+        self
+    }
+
+    /// Set an attribute this crate doesn't wrap yet on the private key (see
+    /// `Query::raw_attribute`). Like the other attributes, it's passed in
+    /// `kSecPrivateKeyAttrs` rather than with the key type and size.
+    pub fn raw_attribute(mut self, key: CFString, value: CFType) -> Self {
+        self.attrs.remove(key.as_concrete_TypeRef());
+        self.attrs.add(key.as_concrete_TypeRef(), &value);
         self
     }
 }
 
+impl KeyPairGenerateParams {
+    /// Check these parameters against the installed `Policy`, returning
+    /// corrected parameters if it permits, and then against the supported
+    /// `KeySpec`s.
+    fn enforce_policy(&self) -> Result<Self, Error> {
+        let mut params = self.clone();
+        policy::enforce(
+            &Subject::KeyGeneration(self.key_type, self.key_size),
+            &mut params.attrs,
+        )?;
+        KeySpec::new(self.key_type, self.key_size)?;
+        Ok(params)
+    }
+
+    /// Is the key to be generated in the Secure Enclave?
+    fn is_secure_enclave(&self) -> bool {
+        self.attrs
+            .get(AttrKind::TokenId)
+            .is_some_and(|token_id| *token_id == AttrTokenId::SecureEnclave.as_CFType())
+    }
+
+    /// Refine an error returned when generating a key with these parameters
+    /// into a more specific `ErrorKind`, using the parameters and probes of
+    /// the environment to determine the likely cause.
+    ///
+    /// Errors are only reported as `ErrorKind::AccessControlUnusable` if the
+    /// key's `AccessControl` requires user authentication (e.g. a passcode
+    /// or biometrics) and the error indicates that authentication isn't
+    /// available. Other errors are returned unchanged.
+    pub(crate) fn diagnose_error(&self, error: Error) -> Error {
+        match error.kind() {
+            ErrorKind::MissingEntitlement => return entitlements::diagnose(error, &self.attrs),
+            ErrorKind::KeySizeNotAllowed | ErrorKind::UserCanceled => return error,
+            _ => (),
+        }
+
+        if self.is_secure_enclave() {
+            if self.key_type != AttrKeyType::EcSecPrimeRandom || self.key_size != 256 {
+                return Error::new(
+                    ErrorKind::KeySizeNotAllowed,
+                    &format!(
+                        "the Secure Enclave only supports 256-bit EC keys, not {}-bit {:?} ({})",
+                        self.key_size, self.key_type, error
+                    ),
+                );
+            }
+
+            if !KeyPair::is_secure_enclave_available() {
+                return Error::new(
+                    ErrorKind::SecureEnclaveUnavailable,
+                    &format!("this device has no usable Secure Enclave ({})", error),
+                );
+            }
+        }
+
+        match error.kind() {
+            ErrorKind::AuthFailed | ErrorKind::NotAvailable if self.requires_authentication() => {
+                Error::new(
+                    ErrorKind::AccessControlUnusable,
+                    &format!(
+                        "access control policy can't be satisfied, e.g. no passcode or \
+                         biometrics are configured ({})",
+                        error
+                    ),
+                )
+            }
+            _ => error,
+        }
+    }
+
+    /// Does the key's `AccessControl` policy (if any) require the user to
+    /// authenticate, e.g. with a passcode or biometrics?
+    fn requires_authentication(&self) -> bool {
+        match self.attrs.get(AttrKind::AccessControl) {
+            Some(access_control) => {
+                let access_control = unsafe {
+                    AccessControl::wrap_under_get_rule(
+                        access_control.as_CFTypeRef() as AccessControlRef
+                    )
+                };
+                !access_control.info().constraints.is_empty()
+            }
+            None => false,
+        }
+    }
+}
+
 impl From<KeyPairGenerateParams> for Dictionary {
     fn from(params: KeyPairGenerateParams) -> Dictionary {
+        Dictionary::from(&params)
+    }
+}
+
+impl<'a> From<&'a KeyPairGenerateParams> for Dictionary {
+    fn from(params: &'a KeyPairGenerateParams) -> Dictionary {
         let mut result = DictionaryBuilder::new();
         result.add_attr(&params.key_type);
         result.add_number(AttrKind::KeySizeInBits, params.key_size as i64);
+
+        if let Some(value) = params.attrs.get(unsafe { kSecUseDataProtectionKeychain }) {
+            result.add(unsafe { kSecUseDataProtectionKeychain }, value);
+        }
+
+        let mut private_key_attrs = params.attrs.clone();
+
+        if let Some(keychain) = params.attrs.get(unsafe { kSecUseKeychain }) {
+            result.add(unsafe { kSecUseKeychain }, keychain);
+            private_key_attrs.remove(unsafe { kSecUseKeychain });
+        }
+
         result.add(
             unsafe { kSecPrivateKeyAttrs },
-            &Dictionary::from(params.attrs),
+            &Dictionary::from(private_key_attrs),
         );
         result.into()
     }
@@ -322,3 +554,66 @@ impl RestoreKeyParams {
         self.key_data.as_slice()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        access::{AccessConstraint, AccessControlFlags},
+        attr::AttrAccessible,
+        error::ErrorKind,
+    };
+
+    #[test]
+    fn diagnose_key_generation_errors() {
+        let user_presence = AccessControl::create_with_flags(
+            AttrAccessible::WhenUnlocked,
+            AccessControlFlags::from(&[AccessConstraint::UserPresence][..]),
+        )
+        .unwrap();
+        let no_constraints =
+            AccessControl::create_with_flags(AttrAccessible::WhenUnlocked, Default::default())
+                .unwrap();
+
+        let diagnose = |params: &KeyPairGenerateParams, kind: ErrorKind| {
+            params
+                .diagnose_error(Error::new(kind, "key generation failed"))
+                .kind()
+                .clone()
+        };
+
+        let params = KeyPairGenerateParams::new(AttrKeyType::EcSecPrimeRandom, 256)
+            .access_control(&user_presence);
+
+        for error_kind in vec![ErrorKind::AuthFailed, ErrorKind::NotAvailable] {
+            match diagnose(&params, error_kind) {
+                ErrorKind::AccessControlUnusable => (),
+                other => panic!("unexpected error kind: {:?}", other),
+            }
+        }
+
+        // Generic errors aren't blamed on the access control policy
+        for error_kind in vec![ErrorKind::Param, ErrorKind::InteractionNotAllowed] {
+            match diagnose(&params, error_kind) {
+                ErrorKind::Param | ErrorKind::InteractionNotAllowed => (),
+                other => panic!("unexpected error kind: {:?}", other),
+            }
+        }
+
+        // Nor are errors when the policy doesn't require authentication
+        let params = KeyPairGenerateParams::new(AttrKeyType::EcSecPrimeRandom, 256)
+            .access_control(&no_constraints);
+
+        match diagnose(&params, ErrorKind::NotAvailable) {
+            ErrorKind::NotAvailable => (),
+            other => panic!("unexpected error kind: {:?}", other),
+        }
+
+        let params = KeyPairGenerateParams::new(AttrKeyType::EcSecPrimeRandom, 256);
+
+        match diagnose(&params, ErrorKind::AuthFailed) {
+            ErrorKind::AuthFailed => (),
+            other => panic!("unexpected error kind: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Presets for generating keys restricted to a single purpose.
+//!
+//! Keys generated with `KeyPairGenerateParams::new` can be used for any
+//! operation their type supports. `KeyPairGenerateParams::for_purpose`
+//! instead disables every capability the key's purpose doesn't need, and
+//! restricts it to this device while it's unlocked, so a signing key can't
+//! also be used to e.g. decrypt data.
+
+use super::*;
+use crate::access::{AccessControl, AccessControlFlags};
+
+/// Purpose a key is generated for.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyPurpose {
+    /// Signing data (and verifying signatures with the public key)
+    SigningOnly,
+
+    /// Decrypting data (encrypted with the public key)
+    DecryptionOnly,
+
+    /// Key agreement (i.e. ECDH), which requires an EC key
+    AgreementOnly,
+}
+
+impl KeyPurpose {
+    /// Get the capabilities of the private keys of key pairs generated for
+    /// this purpose.
+    pub fn capabilities(self) -> KeyCapabilities {
+        let mut capabilities = KeyCapabilities::default();
+
+        match self {
+            KeyPurpose::SigningOnly => capabilities.can_sign = true,
+            KeyPurpose::DecryptionOnly => capabilities.can_decrypt = true,
+            KeyPurpose::AgreementOnly => capabilities.can_derive = true,
+        }
+
+        capabilities
+    }
+}
+
+impl KeyPairGenerateParams {
+    /// Create new `KeyPairGenerateParams` for a key pair with the given
+    /// `KeySpec` which can only be used for the given `KeyPurpose`.
+    ///
+    /// The private key is only accessible on this device while it's
+    /// unlocked (i.e. `AttrAccessible::WhenUnlockedThisDeviceOnly`). Keys
+    /// stored in the Secure Enclave need an access control policy which
+    /// includes `AccessOption::PrivateKeyUsage`, which can be set with
+    /// `access_control()` to replace the default one.
+    ///
+    /// Returns an error with a kind of `ErrorKind::Param` for key agreement
+    /// with RSA keys, which don't support it.
+    pub fn for_purpose(spec: KeySpec, purpose: KeyPurpose) -> Result<Self, Error> {
+        if purpose == KeyPurpose::AgreementOnly && spec.key_type() != AttrKeyType::EcSecPrimeRandom
+        {
+            return Err(Error::new(
+                ErrorKind::Param,
+                &format!("{} keys don't support key agreement", spec),
+            ));
+        }
+
+        let access_control = AccessControl::create_with_flags(
+            AttrAccessible::WhenUnlockedThisDeviceOnly,
+            AccessControlFlags::new(),
+        )?;
+
+        Ok(Self::from_spec(spec)
+            .capabilities(&purpose.capabilities())
+            .access_control(&access_control))
+    }
+}
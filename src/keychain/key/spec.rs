@@ -0,0 +1,133 @@
+//! Supported combinations of key types and sizes.
+//!
+//! `KeyPairGenerateParams::new` accepts any `AttrKeyType` and size in bits,
+//! but Keychain Services only supports a handful of combinations and
+//! reports others with unhelpful errors (if at all). A `KeySpec` names a
+//! valid combination, e.g. `KeySpec::EcP256`, and parameters are checked
+//! against the supported specs before a key is generated.
+
+use super::*;
+
+/// Type and size of an asymmetric key pair.
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeySpec {
+    /// 1024-bit RSA (not recommended: use for legacy interop only)
+    Rsa1024,
+
+    /// 2048-bit RSA
+    Rsa2048,
+
+    /// 3072-bit RSA
+    Rsa3072,
+
+    /// 4096-bit RSA
+    Rsa4096,
+
+    /// NIST P-256 (a.k.a. secp256r1) elliptic curve, the only curve
+    /// supported by the Secure Enclave
+    EcP256,
+
+    /// NIST P-384 (a.k.a. secp384r1) elliptic curve
+    EcP384,
+
+    /// NIST P-521 (a.k.a. secp521r1) elliptic curve
+    EcP521,
+}
+
+impl KeySpec {
+    /// All supported key specs
+    pub const ALL: &'static [KeySpec] = &[
+        KeySpec::Rsa1024,
+        KeySpec::Rsa2048,
+        KeySpec::Rsa3072,
+        KeySpec::Rsa4096,
+        KeySpec::EcP256,
+        KeySpec::EcP384,
+        KeySpec::EcP521,
+    ];
+
+    /// Look up the spec for the given key type and size in bits.
+    ///
+    /// Returns an error with a kind of `ErrorKind::KeySizeNotAllowed` if the
+    /// combination isn't supported for key pairs.
+    pub fn new(key_type: AttrKeyType, size_in_bits: usize) -> Result<Self, Error> {
+        Self::ALL
+            .iter()
+            .find(|spec| spec.key_type() == key_type && spec.size_in_bits() == size_in_bits)
+            .cloned()
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::KeySizeNotAllowed,
+                    &format!(
+                        "unsupported key pair: {}-bit {:?} (supported: {})",
+                        size_in_bits,
+                        key_type,
+                        Self::ALL
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                )
+            })
+    }
+
+    /// Get the `kSecAttrKeyType` of keys with this spec
+    pub fn key_type(self) -> AttrKeyType {
+        match self {
+            KeySpec::Rsa1024 | KeySpec::Rsa2048 | KeySpec::Rsa3072 | KeySpec::Rsa4096 => {
+                AttrKeyType::Rsa
+            }
+            KeySpec::EcP256 | KeySpec::EcP384 | KeySpec::EcP521 => AttrKeyType::EcSecPrimeRandom,
+        }
+    }
+
+    /// Get the `kSecAttrKeySizeInBits` of keys with this spec
+    pub fn size_in_bits(self) -> usize {
+        match self {
+            KeySpec::Rsa1024 => 1024,
+            KeySpec::Rsa2048 => 2048,
+            KeySpec::Rsa3072 => 3072,
+            KeySpec::Rsa4096 => 4096,
+            KeySpec::EcP256 => 256,
+            KeySpec::EcP384 => 384,
+            KeySpec::EcP521 => 521,
+        }
+    }
+}
+
+/// Displays specs as e.g. `RSA-2048` or `EC P-256`
+impl Display for KeySpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.key_type() {
+            AttrKeyType::EcSecPrimeRandom => write!(f, "EC P-{}", self.size_in_bits()),
+            _ => write!(f, "RSA-{}", self.size_in_bits()),
+        }
+    }
+}
+
+impl Key {
+    /// Get the `KeySpec` of this key, if it's a key pair of a supported
+    /// type and size.
+    pub fn spec(&self) -> Option<KeySpec> {
+        KeySpec::new(self.key_type()?, self.size_in_bits()?).ok()
+    }
+}
+
+impl KeyPairGenerateParams {
+    /// Create new `KeyPairGenerateParams` for the given `KeySpec`.
+    pub fn from_spec(spec: KeySpec) -> Self {
+        Self::new(spec.key_type(), spec.size_in_bits())
+    }
+
+    /// Create new `KeyPairGenerateParams` for an RSA key pair with the given
+    /// modulus size in bits (1024, 2048, 3072, or 4096).
+    ///
+    /// The key's permanence, label, and tag are set with the usual builder
+    /// methods. Returns an error with a kind of `ErrorKind::KeySizeNotAllowed`
+    /// if the modulus size isn't supported.
+    pub fn rsa(modulus_size: usize) -> Result<Self, Error> {
+        KeySpec::new(AttrKeyType::Rsa, modulus_size).map(Self::from_spec)
+    }
+}
@@ -0,0 +1,144 @@
+//! Symmetric (AES) keys, e.g. a master key kept in the keychain rather
+//! than derived ad hoc.
+//!
+//! `SecKeyCreateRandomKey` only generates asymmetric keys, so symmetric keys
+//! are generated with the legacy `SecKeyGenerateSymmetric` function, which
+//! stores them in a file-based keychain.
+
+use super::*;
+use crate::keychain::Keychain;
+
+/// Sizes in bits of the AES keys `Key::generate_symmetric` can generate
+pub const AES_KEY_SIZES: &[usize] = &[128, 192, 256];
+
+/// Builder for symmetric key generation parameters (passed to the
+/// underlying `SecKeyGenerateSymmetric` function)
+#[derive(Clone, Debug)]
+pub struct SymmetricKeyGenerateParams {
+    key_size: usize,
+    attrs: DictionaryBuilder,
+}
+
+impl SymmetricKeyGenerateParams {
+    /// Create new parameters for an AES key of the given size in bits (see
+    /// `AES_KEY_SIZES`).
+    pub fn aes(key_size: usize) -> Self {
+        Self {
+            key_size,
+            attrs: DictionaryBuilder::new(),
+        }
+    }
+
+    /// Set a tag (private, application-specific identifier) on this key.
+    ///
+    /// Wrapper for the `kSecAttrApplicationTag` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrapplicationtag>
+    pub fn application_tag<T>(mut self, tag: T) -> Self
+    where
+        T: Into<AttrApplicationTag>,
+    {
+        self.attrs.remove(AttrKind::ApplicationTag);
+        self.attrs.add_attr(&tag.into());
+        self
+    }
+
+    /// Set a string label on this key, by which it can be found with
+    /// `Key::find_symmetric`.
+    ///
+    /// Wrapper for the `kSecAttrLabel` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrlabel>
+    pub fn label<L: Into<AttrLabel>>(mut self, label: L) -> Self {
+        self.attrs.remove(AttrKind::Label);
+        self.attrs.add_attr(&label.into());
+        self
+    }
+
+    /// Set whether this key is stored permanently in the keychain (default: false).
+    ///
+    /// Wrapper for the `kSecAttrIsPermanent` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecattrispermanent>
+    pub fn permanent(mut self, value: bool) -> Self {
+        self.attrs.remove(AttrKind::Permanent);
+        self.attrs.add_boolean(AttrKind::Permanent, value);
+        self
+    }
+
+    /// Store this key in the given keychain, rather than the default one.
+    ///
+    /// Wrapper for the `kSecUseKeychain` attribute key. See:
+    /// <https://developer.apple.com/documentation/security/ksecusekeychain>
+    pub fn keychain(mut self, keychain: &Keychain) -> Self {
+        self.attrs.remove(unsafe { kSecUseKeychain });
+        self.attrs.add(unsafe { kSecUseKeychain }, keychain);
+        self
+    }
+
+    /// Get the size in bits of the key to generate
+    pub fn key_size(&self) -> usize {
+        self.key_size
+    }
+}
+
+impl<'a> From<&'a SymmetricKeyGenerateParams> for Dictionary {
+    fn from(params: &'a SymmetricKeyGenerateParams) -> Dictionary {
+        let mut result = params.attrs.clone();
+        result.add_attr(&AttrKeyType::Aes);
+        result.add_number(AttrKind::KeySizeInBits, params.key_size as i64);
+        result.into()
+    }
+}
+
+impl Key {
+    /// Generate a symmetric (AES) key with the given parameters.
+    ///
+    /// Returns an error with a kind of `ErrorKind::KeySizeNotAllowed` if the
+    /// key size isn't one of `AES_KEY_SIZES`.
+    ///
+    /// Wrapper for the `SecKeyGenerateSymmetric` function. See:
+    /// <https://developer.apple.com/documentation/security/seckeygeneratesymmetric(_:_:)>
+    pub fn generate_symmetric<P>(params: P) -> Result<Key, Error>
+    where
+        P: Borrow<SymmetricKeyGenerateParams>,
+    {
+        hooks::run(OperationKind::GenerateKey, Some(item::Class::Key), || {
+            let mut params = params.borrow().clone();
+
+            if !AES_KEY_SIZES.contains(&params.key_size) {
+                return Err(Error::new(
+                    ErrorKind::KeySizeNotAllowed,
+                    &format!(
+                        "unsupported AES key size: {} bits (supported: 128, 192, 256)",
+                        params.key_size
+                    ),
+                ));
+            }
+
+            policy::enforce(
+                &Subject::KeyGeneration(AttrKeyType::Aes, params.key_size),
+                &mut params.attrs,
+            )?;
+
+            let mut error: CFErrorRef = ptr::null_mut();
+            let key_ref: KeyRef = unsafe {
+                SecKeyGenerateSymmetric(Dictionary::from(&params).as_concrete_TypeRef(), &mut error)
+            };
+
+            if key_ref.is_null() {
+                Err(error.into())
+            } else {
+                Ok(unsafe { Key::wrap_under_create_rule(key_ref) })
+            }
+        })
+    }
+
+    /// Find a symmetric key with the given label (see
+    /// `SymmetricKeyGenerateParams::label`), returning `Ok(None)` if there
+    /// is no such key.
+    pub fn find_symmetric<L: Into<AttrLabel>>(label: L) -> Result<Option<Self>, Error> {
+        Key::find(
+            item::Query::new()
+                .key_class(AttrKeyClass::Symmetric)
+                .label(label),
+        )
+    }
+}
@@ -0,0 +1,178 @@
+//! Opt-in tracking of how often keys are used, to support rotating keys
+//! based on their actual usage (e.g. after a number of signatures).
+//!
+//! When enabled with `set_usage_tracking`, successful signing and
+//! decryption operations are counted in a "sidecar" generic password item
+//! per key, stored in the default keychain with a service of
+//! `USAGE_STATS_SERVICE` and the hex-encoded `AttrApplicationLabel` (i.e.
+//! public key hash) of the key as its account. Keys without an application
+//! label (e.g. symmetric keys) aren't tracked.
+
+use super::*;
+use crate::keychain::{item::GenericPassword, Keychain};
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// Service of the generic password items key usage stats are stored in
+pub const USAGE_STATS_SERVICE: &str = "rs.keychain-services.key-usage";
+
+/// Usage statistics recorded for a key. See `Key::usage_stats`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct KeyUsageStats {
+    /// Number of signatures created with the key
+    pub sign_count: u64,
+
+    /// Number of decryptions performed with the key
+    pub decrypt_count: u64,
+
+    /// When the key was last used (at a resolution of one second)
+    pub last_used: Option<SystemTime>,
+}
+
+impl KeyUsageStats {
+    /// Find the usage stats recorded for the given key, returning empty
+    /// stats if none have been recorded
+    pub(crate) fn find(key: &Key) -> Result<Self, Error> {
+        let account = match sidecar_account(key) {
+            Some(account) => account,
+            None => return Ok(Self::default()),
+        };
+
+        match GenericPassword::find(&Keychain::find_default()?, USAGE_STATS_SERVICE, &account)? {
+            Some(sidecar) => sidecar.password()?.as_str().parse(),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+/// Encodes stats in the form stored in sidecar items, e.g.
+/// `sign=3,decrypt=1,last_used=1700000000`.
+impl Display for KeyUsageStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "sign={},decrypt={}", self.sign_count, self.decrypt_count)?;
+
+        if let Some(last_used) = self.last_used {
+            let secs = last_used
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_secs());
+
+            write!(f, ",last_used={}", secs)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Decodes stats from the form stored in sidecar items, ignoring unknown
+/// fields (and last used times which can't be represented).
+impl FromStr for KeyUsageStats {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let mut stats = Self::default();
+
+        for field in s.split(',').filter(|field| !field.is_empty()) {
+            let mut parts = field.splitn(2, '=');
+            let name = parts.next().unwrap();
+            let value: u64 = parts.next().and_then(|v| v.parse().ok()).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::Decode,
+                    &format!("malformed key usage stats field: {:?}", field),
+                )
+            })?;
+
+            match name {
+                "sign" => stats.sign_count = value,
+                "decrypt" => stats.decrypt_count = value,
+                "last_used" => stats.last_used = UNIX_EPOCH.checked_add(Duration::from_secs(value)),
+                _ => (),
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Enable or disable tracking of key usage (disabled by default).
+pub fn set_usage_tracking(enabled: bool) {
+    USAGE_TRACKING.store(enabled, Ordering::SeqCst);
+}
+
+/// Is key usage tracking enabled?
+pub fn is_usage_tracking_enabled() -> bool {
+    USAGE_TRACKING.load(Ordering::SeqCst)
+}
+
+/// Record a successful operation performed with the given key (if usage
+/// tracking is enabled).
+///
+/// Tracking is best-effort: failures to record usage don't fail the
+/// operation itself.
+pub(crate) fn record_usage(key: &Key, operation: KeyOperation) {
+    if is_usage_tracking_enabled() {
+        let _ = try_record(key, operation);
+    }
+}
+
+/// Whether key usage tracking is enabled
+static USAGE_TRACKING: AtomicBool = AtomicBool::new(false);
+
+/// Serializes updates to sidecar items within this process
+static SIDECAR_LOCK: Mutex<()> = Mutex::new(());
+
+/// Update the sidecar item of the given key to record an operation
+fn try_record(key: &Key, operation: KeyOperation) -> Result<(), Error> {
+    let account = match sidecar_account(key) {
+        Some(account) => account,
+        None => return Ok(()),
+    };
+
+    let _guard = SIDECAR_LOCK.lock().map_err(|_| {
+        Error::new(
+            ErrorKind::NotAvailable,
+            "key usage stats lock poisoned by a panic in another thread",
+        )
+    })?;
+    let keychain = Keychain::find_default()?;
+    let sidecar = GenericPassword::find(&keychain, USAGE_STATS_SERVICE, &account)?;
+
+    let mut stats = match &sidecar {
+        Some(sidecar) => sidecar.password()?.as_str().parse()?,
+        None => KeyUsageStats::default(),
+    };
+
+    match operation {
+        KeyOperation::Sign => stats.sign_count = stats.sign_count.saturating_add(1),
+        KeyOperation::Decrypt => stats.decrypt_count = stats.decrypt_count.saturating_add(1),
+        _ => (),
+    }
+
+    stats.last_used = Some(SystemTime::now());
+
+    match sidecar {
+        Some(sidecar) => sidecar.set_password(&stats.to_string()),
+        None => {
+            GenericPassword::create(&keychain, USAGE_STATS_SERVICE, &account, &stats.to_string())
+                .map(|_| ())
+        }
+    }
+}
+
+/// Get the account of the sidecar item for the given key (if it has an
+/// application label to identify it by)
+fn sidecar_account(key: &Key) -> Option<String> {
+    key.application_label().map(|label| {
+        label
+            .as_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    })
+}
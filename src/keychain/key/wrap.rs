@@ -0,0 +1,108 @@
+//! Wrapping keys (e.g. symmetric data keys) under a key encryption key
+//! (KEK), so they can be exported for backup or escrow and later restored.
+//!
+//! The wrapped key's external representation is encrypted to the KEK's
+//! public key with an ECIES or RSA-OAEP algorithm, so a KEK whose private
+//! key lives in the Secure Enclave can wrap keys on any machine holding
+//! its public key, but only unwrap them on the device itself.
+
+use super::*;
+
+/// A key encrypted under a key encryption key (see `Key::wrap`), along with
+/// the attributes needed to restore it.
+#[derive(Clone, Debug)]
+pub struct WrappedKey {
+    key_type: AttrKeyType,
+    key_class: AttrKeyClass,
+    ciphertext: Ciphertext,
+}
+
+impl WrappedKey {
+    /// Create a `WrappedKey` from its parts, e.g. when loading one which
+    /// was previously exported.
+    pub fn new(key_type: AttrKeyType, key_class: AttrKeyClass, ciphertext: Ciphertext) -> Self {
+        Self {
+            key_type,
+            key_class,
+            ciphertext,
+        }
+    }
+
+    /// Get the type of the wrapped key
+    pub fn key_type(&self) -> AttrKeyType {
+        self.key_type
+    }
+
+    /// Get the class of the wrapped key
+    pub fn key_class(&self) -> AttrKeyClass {
+        self.key_class
+    }
+
+    /// Borrow the encrypted key
+    pub fn ciphertext(&self) -> &Ciphertext {
+        &self.ciphertext
+    }
+}
+
+impl Key {
+    /// Wrap (i.e. encrypt) the given key with this public key, using the
+    /// given ECIES or RSA-OAEP algorithm.
+    ///
+    /// The key must be exportable (see `Key::to_external_representation`),
+    /// so keys generated inside the Secure Enclave can't be wrapped.
+    pub fn wrap(&self, key: &Key, alg: KeyAlgorithm) -> Result<WrappedKey, Error> {
+        if !is_wrapping_algorithm(alg) {
+            return Err(Error::new(
+                ErrorKind::Param,
+                &format!("not a key wrapping algorithm: {:?}", alg),
+            ));
+        }
+
+        let (key_type, key_class) = match (key.key_type(), key.class()) {
+            (Some(key_type), Some(key_class)) => (key_type, key_class),
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::Param,
+                    "key to wrap has no type or class attribute",
+                ))
+            }
+        };
+
+        let mut key_data = key.to_external_representation()?;
+        let result = self.encrypt(alg, &key_data);
+        key_data.zeroize();
+
+        Ok(WrappedKey::new(key_type, key_class, result?))
+    }
+
+    /// Unwrap (i.e. decrypt and restore) a key wrapped with the public key
+    /// corresponding to this private key.
+    ///
+    /// The restored key isn't stored in the keychain.
+    pub fn unwrap_key(&self, wrapped_key: WrappedKey) -> Result<Key, Error> {
+        let key_data = self.decrypt(wrapped_key.ciphertext)?;
+
+        Key::from_external_representation(RestoreKeyParams {
+            key_class: wrapped_key.key_class,
+            key_data,
+            key_type: wrapped_key.key_type,
+        })
+    }
+}
+
+/// Can the given algorithm be used to wrap keys?
+fn is_wrapping_algorithm(alg: KeyAlgorithm) -> bool {
+    match alg {
+        KeyAlgorithm::RSAEncryptionOAEPSHA1
+        | KeyAlgorithm::RSAEncryptionOAEPSHA224
+        | KeyAlgorithm::RSAEncryptionOAEPSHA256
+        | KeyAlgorithm::RSAEncryptionOAEPSHA384
+        | KeyAlgorithm::RSAEncryptionOAEPSHA512
+        | KeyAlgorithm::RSAEncryptionOAEPSHA1AESGCM
+        | KeyAlgorithm::RSAEncryptionOAEPSHA224AESGCM
+        | KeyAlgorithm::RSAEncryptionOAEPSHA256AESGCM
+        | KeyAlgorithm::RSAEncryptionOAEPSHA384AESGCM
+        | KeyAlgorithm::RSAEncryptionOAEPSHA512AESGCM => true,
+        _ => alg.is_ecies(),
+    }
+}
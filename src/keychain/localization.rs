@@ -0,0 +1,59 @@
+//! Pluggable localization of operation prompts and user-facing errors.
+//!
+//! Multilingual applications install a `Localizer` with `set_localizer`,
+//! which is then consulted whenever this crate produces text which may be
+//! shown to users: the prompts rendered from a `PromptTemplate` (see
+//! `keychain::prompt`) and the `Display` representation of `Error`s.
+//!
+//! Each method returns `None` to fall back to the crate's built-in (English)
+//! text. Messages which come from the operating system (e.g. the
+//! descriptions of `OSStatus` codes) are already localized by it.
+
+use super::prompt::PromptTemplate;
+use crate::{error::ErrorKind, key::KeyOperation};
+use std::sync::{Arc, RwLock};
+
+/// Provider of translations for user-facing text.
+pub trait Localizer: Send + Sync {
+    /// Get the prompt template to use for the given operation, in place of
+    /// the one installed with `set_prompt_template`
+    fn prompt_template(&self, _operation: KeyOperation) -> Option<PromptTemplate> {
+        None
+    }
+
+    /// Get the name of the given operation, as substituted for the
+    /// `{operation}` placeholder of prompt templates
+    fn operation_name(&self, _operation: KeyOperation) -> Option<String> {
+        None
+    }
+
+    /// Get the summary of the given kind of error, which is displayed after
+    /// an error's description
+    fn error_kind(&self, _kind: &ErrorKind) -> Option<String> {
+        None
+    }
+
+    /// Translate the description of an error of the given kind
+    fn error_description(&self, _kind: &ErrorKind, _description: &str) -> Option<String> {
+        None
+    }
+}
+
+/// Install the given `Localizer` process-wide (or remove the current one if
+/// `None`), returning the previously installed localizer (if any).
+pub fn set_localizer(localizer: Option<Arc<dyn Localizer>>) -> Option<Arc<dyn Localizer>> {
+    let mut installed = LOCALIZER.write().unwrap();
+
+    match localizer {
+        Some(localizer) => installed.replace(localizer),
+        None => installed.take(),
+    }
+}
+
+/// Get the currently installed `Localizer` (if any)
+pub fn localizer() -> Option<Arc<dyn Localizer>> {
+    LOCALIZER.read().unwrap().clone()
+}
+
+/// Currently installed localizer
+static LOCALIZER: RwLock<Option<Arc<dyn Localizer>>> = RwLock::new(None);
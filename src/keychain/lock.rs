@@ -0,0 +1,130 @@
+//! Observing keychains being locked and unlocked, so protected operations
+//! can be deferred until a keychain is unlocked instead of failing.
+//!
+//! Lock state changes are observed via the keychain callback API (see the
+//! `events` module), so as with other keychain events they are delivered
+//! on the run loop of the thread which made the first subscription.
+//!
+//! Only file-based keychains report lock state changes: Keychain Services
+//! has no equivalent for the data protection keychain, whose availability
+//! is instead signalled by `UIApplication`'s protected data notifications.
+
+use super::{
+    events::{self, EventSubscription, KeychainEventKind},
+    Keychain,
+};
+use crate::error::Error;
+use core_foundation::runloop::{kCFRunLoopDefaultMode, kCFRunLoopRunFinished, CFRunLoopRunInMode};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Interval at which `LockWatcher::wait_until_unlocked` checks for unlock
+const WAIT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Lock state of a keychain
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LockState {
+    /// Keychain is locked, so its items can't be accessed
+    Locked,
+
+    /// Keychain is unlocked
+    Unlocked,
+}
+
+/// Watches a keychain being locked and unlocked, until dropped.
+#[derive(Debug)]
+pub struct LockWatcher {
+    keychain: Keychain,
+    locked: Arc<AtomicBool>,
+    _subscription: EventSubscription,
+}
+
+impl LockWatcher {
+    /// Watch the given keychain, invoking `on_change` with its new state
+    /// whenever it is locked or unlocked.
+    pub fn new<F>(keychain: &Keychain, mut on_change: F) -> Result<Self, Error>
+    where
+        F: FnMut(LockState) + Send + 'static,
+    {
+        let path = keychain.path()?;
+        let locked = Arc::new(AtomicBool::new(keychain.is_locked()?));
+        let locked_by_events = locked.clone();
+
+        let subscription = events::subscribe(
+            &[KeychainEventKind::Lock, KeychainEventKind::Unlock],
+            move |event| {
+                let event_path = event.keychain.as_ref().and_then(|k| k.path().ok());
+
+                if event_path.as_ref() != Some(&path) {
+                    return;
+                }
+
+                let state = if event.kind == KeychainEventKind::Lock {
+                    LockState::Locked
+                } else {
+                    LockState::Unlocked
+                };
+
+                locked_by_events.store(state == LockState::Locked, Ordering::SeqCst);
+                on_change(state);
+            },
+        )?;
+
+        Ok(LockWatcher {
+            keychain: keychain.clone(),
+            locked,
+            _subscription: subscription,
+        })
+    }
+
+    /// Get the current lock state of the watched keychain
+    pub fn state(&self) -> LockState {
+        if self.locked.load(Ordering::SeqCst) {
+            LockState::Locked
+        } else {
+            LockState::Unlocked
+        }
+    }
+
+    /// Block until the watched keychain is unlocked or the given timeout
+    /// elapses, running the current thread's run loop so lock events can
+    /// be delivered. Returns whether the keychain is unlocked.
+    ///
+    /// The keychain's lock state is also polled, so this doesn't depend on
+    /// events being delivered to the current thread's run loop (and doesn't
+    /// spin if the run loop has nothing to run).
+    pub fn wait_until_unlocked(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if let Ok(locked) = self.keychain.is_locked() {
+                self.locked.store(locked, Ordering::SeqCst);
+            }
+
+            if self.state() == LockState::Unlocked {
+                return true;
+            }
+
+            let now = Instant::now();
+
+            if now >= deadline {
+                return false;
+            }
+
+            let slice = WAIT_INTERVAL.min(deadline - now);
+            let result =
+                unsafe { CFRunLoopRunInMode(kCFRunLoopDefaultMode, slice.as_secs_f64(), 1) };
+
+            // The run loop returns immediately if it has no sources
+            if result == kCFRunLoopRunFinished {
+                thread::sleep(slice);
+            }
+        }
+    }
+}
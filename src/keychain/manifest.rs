@@ -0,0 +1,642 @@
+//! Declarative manifests of the keychain items (passwords and keys) an
+//! application requires, which can be applied to reconcile the keychain
+//! with them (e.g. when provisioning a machine).
+//!
+//! Manifests can be loaded from property lists (XML or binary) with
+//! `Manifest::from_plist`, or (with the `serde` feature) deserialized from
+//! JSON or any other format. Both use the same structure:
+//!
+//! ```text
+//! {
+//!   "entries": [
+//!     {
+//!       "class": "generic_password",
+//!       "service": "com.example.db",
+//!       "account": "app",
+//!       "label": "Database password",
+//!       "accessible": "WhenUnlockedThisDeviceOnly",
+//!       "data": { "env": "DB_PASSWORD" }
+//!     },
+//!     {
+//!       "class": "key",
+//!       "tag": "com.example.signing",
+//!       "key_type": "EcSecPrimeRandom",
+//!       "key_size": 256,
+//!       "constraints": ["BiometryCurrentSet"],
+//!       "secure_enclave": true
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Password data is never stored in a manifest: it is either read from an
+//! environment variable (`env`) or obtained from the callback passed to
+//! `apply_manifest` by name (`callback`).
+
+use super::{
+    item::{
+        GenericPassword, GenericPasswordParams, InternetPassword, InternetPasswordParams,
+        PasswordData,
+    },
+    key::{KeyManager, KeyOutcome, KeyPairGenerateParams},
+    Keychain,
+};
+use crate::{
+    access::{AccessConstraint, AccessControl, AccessControlFlags, AccessOption},
+    attr::{AttrAccessible, AttrKeyType, AttrServer, AttrTokenId},
+    dictionary::Dictionary,
+    error::{Error, ErrorKind},
+};
+use core_foundation::{
+    array::CFArray,
+    base::{CFType, TCFType},
+    boolean::CFBoolean,
+    data::CFData,
+    dictionary::CFDictionary,
+    number::CFNumber,
+    propertylist::{self, kCFPropertyListImmutable, CFPropertyList},
+    string::CFString,
+};
+use std::env;
+
+/// Declarative description of the items an application requires.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Manifest {
+    /// Items which should be present in the keychain
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// Item required by a `Manifest`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "class", rename_all = "snake_case")
+)]
+pub enum ManifestEntry {
+    /// Generic password identified by service and account
+    GenericPassword {
+        /// Service the password is associated with
+        service: String,
+
+        /// Account the password is associated with
+        account: String,
+
+        /// User-visible label of the password
+        #[cfg_attr(feature = "serde", serde(default))]
+        label: Option<String>,
+
+        /// Comment on the password (only set when it is created)
+        #[cfg_attr(feature = "serde", serde(default))]
+        comment: Option<String>,
+
+        /// When the password is accessible (only set when it is created)
+        #[cfg_attr(feature = "serde", serde(default))]
+        accessible: Option<AttrAccessible>,
+
+        /// Source of the password data
+        data: ManifestData,
+    },
+
+    /// Internet password identified by server and account
+    InternetPassword {
+        /// Server the password is associated with
+        server: String,
+
+        /// Account the password is associated with
+        account: String,
+
+        /// User-visible label of the password
+        #[cfg_attr(feature = "serde", serde(default))]
+        label: Option<String>,
+
+        /// Comment on the password (only set when it is created)
+        #[cfg_attr(feature = "serde", serde(default))]
+        comment: Option<String>,
+
+        /// When the password is accessible (only set when it is created)
+        #[cfg_attr(feature = "serde", serde(default))]
+        accessible: Option<AttrAccessible>,
+
+        /// Source of the password data
+        data: ManifestData,
+    },
+
+    /// Private key identified by its application tag, which is generated
+    /// (see `KeyManager::ensure`) if missing or invalidated
+    Key {
+        /// Application tag of the key
+        tag: String,
+
+        /// Type of the key to generate
+        key_type: AttrKeyType,
+
+        /// Size of the key to generate in bits
+        key_size: usize,
+
+        /// User-visible label of the key
+        #[cfg_attr(feature = "serde", serde(default))]
+        label: Option<String>,
+
+        /// When the key is accessible (defaults to
+        /// `AttrAccessible::WhenUnlockedThisDeviceOnly` if `constraints` or
+        /// `secure_enclave` are given)
+        #[cfg_attr(feature = "serde", serde(default))]
+        accessible: Option<AttrAccessible>,
+
+        /// Constraints on the usage of the key (e.g. biometric auth)
+        #[cfg_attr(feature = "serde", serde(default))]
+        constraints: Vec<AccessConstraint>,
+
+        /// Generate the key in the Secure Enclave
+        #[cfg_attr(feature = "serde", serde(default))]
+        secure_enclave: bool,
+    },
+}
+
+/// Source of the secret data of a password in a `Manifest`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum ManifestData {
+    /// Read the data from the environment variable with the given name
+    Env(String),
+
+    /// Obtain the data from the callback passed to `apply_manifest`, which
+    /// is given this name
+    Callback(String),
+}
+
+/// What `apply_manifest` did to reconcile an entry.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ManifestOutcome {
+    /// The item was already present and up-to-date
+    Unchanged,
+
+    /// The item was missing (or, for keys, invalidated) and was created
+    Created,
+
+    /// The item was present but its data or label were updated
+    Updated,
+}
+
+/// Result of reconciling an individual `ManifestEntry`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AppliedEntry {
+    /// Description of the entry, e.g. `generic_password com.example.db/app`
+    pub description: String,
+
+    /// What was done to reconcile it
+    pub outcome: ManifestOutcome,
+}
+
+impl Manifest {
+    /// Parse a manifest from a property list in any format supported by
+    /// Core Foundation (i.e. XML or binary).
+    ///
+    /// Wrapper for the `CFPropertyListCreateWithData` function. See:
+    /// <https://developer.apple.com/documentation/corefoundation/1430022-cfpropertylistcreatewithdata>
+    pub fn from_plist(bytes: &[u8]) -> Result<Self, Error> {
+        let (plist, _) =
+            propertylist::create_with_data(CFData::from_buffer(bytes), kCFPropertyListImmutable)
+                .map_err(|e| Error::new(ErrorKind::from_cf_error(&e), &e.description()))?;
+
+        let root = unsafe { CFPropertyList::wrap_under_create_rule(plist) }.as_CFType();
+        let root = PlistDict::from_value(&root, "manifest")?;
+
+        let mut entries = vec![];
+
+        for entry in root.array("entries")?.iter() {
+            entries.push(PlistDict::from_value(&entry, "entry")?.entry()?);
+        }
+
+        Ok(Manifest { entries })
+    }
+}
+
+impl ManifestEntry {
+    /// Describe this entry, e.g. `generic_password com.example.db/app`
+    pub fn description(&self) -> String {
+        match self {
+            ManifestEntry::GenericPassword {
+                service, account, ..
+            } => format!("generic_password {}/{}", service, account),
+            ManifestEntry::InternetPassword {
+                server, account, ..
+            } => format!("internet_password {}/{}", server, account),
+            ManifestEntry::Key { tag, .. } => format!("key {}", tag),
+        }
+    }
+}
+
+/// Reconcile the given keychain with a `Manifest`, creating any items
+/// which are missing and updating the data and labels of those which
+/// differ from it. Keys are created in the same keychain, except for those
+/// generated in the Secure Enclave.
+///
+/// `callback` is invoked with the name given by `ManifestData::Callback`
+/// entries to obtain their data.
+///
+/// Items in the keychain which aren't in the manifest are left alone, as
+/// are the access control settings of existing items (which Keychain
+/// Services doesn't allow to be changed). Processing stops at the first
+/// error, leaving the preceding entries applied.
+pub fn apply_manifest<F>(
+    keychain: &Keychain,
+    manifest: &Manifest,
+    mut callback: F,
+) -> Result<Vec<AppliedEntry>, Error>
+where
+    F: FnMut(&str) -> Result<String, Error>,
+{
+    let mut applied = vec![];
+
+    for entry in &manifest.entries {
+        let outcome = match entry {
+            ManifestEntry::GenericPassword {
+                service,
+                account,
+                label,
+                comment,
+                accessible,
+                data,
+            } => {
+                let data = data.resolve(&mut callback)?;
+
+                match GenericPassword::find(keychain, service, account)? {
+                    Some(password) => reconcile_password(
+                        label,
+                        &data,
+                        || password.label(),
+                        || password.password(),
+                        |label| password.set_label(label),
+                        |data| password.set_password(data),
+                    )?,
+                    None => {
+                        let mut params = GenericPasswordParams::new(service, account);
+
+                        if let Some(label) = label {
+                            params = params.label(label.as_str());
+                        }
+
+                        if let Some(comment) = comment {
+                            params = params.comment(comment);
+                        }
+
+                        if let Some(accessible) = accessible {
+                            params = params.accessible(*accessible);
+                        }
+
+                        GenericPassword::create_with_params(keychain, &params, &data)?;
+                        ManifestOutcome::Created
+                    }
+                }
+            }
+            ManifestEntry::InternetPassword {
+                server,
+                account,
+                label,
+                comment,
+                accessible,
+                data,
+            } => {
+                let data = data.resolve(&mut callback)?;
+
+                match InternetPassword::find(keychain, server, account, None)? {
+                    Some(password) => reconcile_password(
+                        label,
+                        &data,
+                        || password.label(),
+                        || password.password(),
+                        |label| password.set_label(label),
+                        |data| password.set_password(data),
+                    )?,
+                    None => {
+                        let mut params =
+                            InternetPasswordParams::new(AttrServer::new(server)?).account(account);
+
+                        if let Some(label) = label {
+                            params = params.label(label.as_str());
+                        }
+
+                        if let Some(comment) = comment {
+                            params = params.comment(comment);
+                        }
+
+                        if let Some(accessible) = accessible {
+                            params = params.accessible(*accessible);
+                        }
+
+                        InternetPassword::create_with_params(keychain, &params, &data)?;
+                        ManifestOutcome::Created
+                    }
+                }
+            }
+            ManifestEntry::Key {
+                tag,
+                key_type,
+                key_size,
+                label,
+                accessible,
+                constraints,
+                secure_enclave,
+            } => {
+                let mut params = KeyPairGenerateParams::new(*key_type, *key_size);
+
+                if let Some(label) = label {
+                    params = params.label(label.as_str());
+                }
+
+                if !constraints.is_empty() || *secure_enclave || accessible.is_some() {
+                    let mut flags = AccessControlFlags::from(constraints.as_slice());
+
+                    if *secure_enclave {
+                        flags.add(AccessOption::PrivateKeyUsage);
+                    }
+
+                    let access_control = AccessControl::create_with_flags(
+                        accessible.unwrap_or(AttrAccessible::WhenUnlockedThisDeviceOnly),
+                        flags,
+                    )?;
+
+                    params = params.access_control(&access_control);
+                }
+
+                if *secure_enclave {
+                    params = params.token_id(AttrTokenId::SecureEnclave);
+                }
+
+                // Secure Enclave keys are kept in the data protection
+                // keychain rather than a file-based one
+                let ensured = if *secure_enclave {
+                    KeyManager::ensure(tag.as_str(), &params)?
+                } else {
+                    KeyManager::ensure_in(keychain, tag.as_str(), &params)?
+                };
+
+                match ensured.outcome {
+                    KeyOutcome::Existing => match label {
+                        Some(label)
+                            if ensured.key.label().map(|l| l.to_string()).as_ref()
+                                != Some(label) =>
+                        {
+                            ensured.key.set_label(label.as_str())?;
+                            ManifestOutcome::Updated
+                        }
+                        _ => ManifestOutcome::Unchanged,
+                    },
+                    KeyOutcome::Generated | KeyOutcome::Regenerated => ManifestOutcome::Created,
+                }
+            }
+        };
+
+        applied.push(AppliedEntry {
+            description: entry.description(),
+            outcome,
+        });
+    }
+
+    Ok(applied)
+}
+
+impl ManifestData {
+    /// Obtain the data from its source
+    fn resolve<F>(&self, callback: &mut F) -> Result<String, Error>
+    where
+        F: FnMut(&str) -> Result<String, Error>,
+    {
+        match self {
+            ManifestData::Env(name) => env::var(name).map_err(|e| {
+                Error::new(
+                    ErrorKind::Param,
+                    &format!("couldn't read password from ${}: {}", name, e),
+                )
+            }),
+            ManifestData::Callback(name) => callback(name),
+        }
+    }
+}
+
+/// Update the data and label of an existing password to match a manifest
+fn reconcile_password<L, D, SL, SD>(
+    label: &Option<String>,
+    data: &str,
+    current_label: L,
+    current_data: D,
+    set_label: SL,
+    set_data: SD,
+) -> Result<ManifestOutcome, Error>
+where
+    L: FnOnce() -> Result<String, Error>,
+    D: FnOnce() -> Result<PasswordData, Error>,
+    SL: FnOnce(&str) -> Result<(), Error>,
+    SD: FnOnce(&str) -> Result<(), Error>,
+{
+    let mut outcome = ManifestOutcome::Unchanged;
+
+    if current_data()?.as_bytes() != data.as_bytes() {
+        set_data(data)?;
+        outcome = ManifestOutcome::Updated;
+    }
+
+    if let Some(label) = label {
+        // Items created without a label have no label attribute
+        if current_label().ok().as_ref() != Some(label) {
+            set_label(label)?;
+            outcome = ManifestOutcome::Updated;
+        }
+    }
+
+    Ok(outcome)
+}
+
+/// Dictionary in a manifest property list
+struct PlistDict(Dictionary);
+
+impl PlistDict {
+    /// Get a dictionary from a property list value
+    fn from_value(value: &CFType, what: &str) -> Result<Self, Error> {
+        value
+            .downcast::<CFDictionary>()
+            .map(|dict| {
+                PlistDict(unsafe { Dictionary::wrap_under_get_rule(dict.as_concrete_TypeRef()) })
+            })
+            .ok_or_else(|| Error::new(ErrorKind::Decode, &format!("expected {} dictionary", what)))
+    }
+
+    /// Parse a `ManifestEntry` from this dictionary
+    fn entry(&self) -> Result<ManifestEntry, Error> {
+        let class = self.string("class")?;
+
+        match class.as_str() {
+            "generic_password" => Ok(ManifestEntry::GenericPassword {
+                service: self.string("service")?,
+                account: self.string("account")?,
+                label: self.optional_string("label")?,
+                comment: self.optional_string("comment")?,
+                accessible: self.accessible()?,
+                data: self.data()?,
+            }),
+            "internet_password" => Ok(ManifestEntry::InternetPassword {
+                server: self.string("server")?,
+                account: self.string("account")?,
+                label: self.optional_string("label")?,
+                comment: self.optional_string("comment")?,
+                accessible: self.accessible()?,
+                data: self.data()?,
+            }),
+            "key" => {
+                let key_type = match self.string("key_type")?.as_str() {
+                    "Aes" => AttrKeyType::Aes,
+                    "Rsa" => AttrKeyType::Rsa,
+                    "EcSecPrimeRandom" => AttrKeyType::EcSecPrimeRandom,
+                    other => return Err(unknown("key_type", other)),
+                };
+
+                let mut constraints = vec![];
+
+                if self.find("constraints").is_some() {
+                    for constraint in self.array("constraints")?.iter() {
+                        let constraint = constraint
+                            .downcast::<CFString>()
+                            .ok_or_else(|| wrong_type("constraints", "strings"))?
+                            .to_string();
+
+                        constraints.push(match constraint.as_str() {
+                            "UserPresence" => AccessConstraint::UserPresence,
+                            "BiometryAny" => AccessConstraint::BiometryAny,
+                            "BiometryCurrentSet" => AccessConstraint::BiometryCurrentSet,
+                            "DevicePasscode" => AccessConstraint::DevicePasscode,
+                            other => return Err(unknown("constraints", other)),
+                        });
+                    }
+                }
+
+                let secure_enclave = match self.find("secure_enclave") {
+                    Some(value) => value
+                        .downcast::<CFBoolean>()
+                        .map(bool::from)
+                        .ok_or_else(|| wrong_type("secure_enclave", "a boolean"))?,
+                    None => false,
+                };
+
+                Ok(ManifestEntry::Key {
+                    tag: self.string("tag")?,
+                    key_type,
+                    key_size: self.key_size()?,
+                    label: self.optional_string("label")?,
+                    accessible: self.accessible()?,
+                    constraints,
+                    secure_enclave,
+                })
+            }
+            other => Err(unknown("class", other)),
+        }
+    }
+
+    /// Parse the `accessible` key of this dictionary (if present)
+    fn accessible(&self) -> Result<Option<AttrAccessible>, Error> {
+        let accessible = match self.optional_string("accessible")? {
+            Some(accessible) => accessible,
+            None => return Ok(None),
+        };
+
+        Ok(Some(match accessible.as_str() {
+            "WhenPasscodeSetThisDeviceOnly" => AttrAccessible::WhenPasscodeSetThisDeviceOnly,
+            "WhenUnlockedThisDeviceOnly" => AttrAccessible::WhenUnlockedThisDeviceOnly,
+            "WhenUnlocked" => AttrAccessible::WhenUnlocked,
+            "AfterFirstUnlockThisDeviceOnly" => AttrAccessible::AfterFirstUnlockThisDeviceOnly,
+            "AfterFirstUnlock" => AttrAccessible::AfterFirstUnlock,
+            "AlwaysThisDeviceOnly" => AttrAccessible::AlwaysThisDeviceOnly,
+            "Always" => AttrAccessible::Always,
+            other => return Err(unknown("accessible", other)),
+        }))
+    }
+
+    /// Parse the `data` key of this dictionary
+    fn data(&self) -> Result<ManifestData, Error> {
+        let data = self
+            .find("data")
+            .ok_or_else(|| missing("data"))
+            .and_then(|value| PlistDict::from_value(&value, "data"))?;
+
+        if let Some(name) = data.optional_string("env")? {
+            Ok(ManifestData::Env(name))
+        } else if let Some(name) = data.optional_string("callback")? {
+            Ok(ManifestData::Callback(name))
+        } else {
+            Err(missing("data.env or data.callback"))
+        }
+    }
+
+    /// Parse the `key_size` key of this dictionary
+    fn key_size(&self) -> Result<usize, Error> {
+        self.find("key_size")
+            .ok_or_else(|| missing("key_size"))?
+            .downcast::<CFNumber>()
+            .and_then(|n| n.to_i64())
+            .filter(|&n| n > 0)
+            .map(|n| n as usize)
+            .ok_or_else(|| wrong_type("key_size", "a positive integer"))
+    }
+
+    /// Get a required array
+    fn array(&self, key: &str) -> Result<CFArray<CFType>, Error> {
+        self.find(key)
+            .ok_or_else(|| missing(key))?
+            .downcast::<CFArray>()
+            .map(|array| unsafe { CFArray::wrap_under_get_rule(array.as_concrete_TypeRef()) })
+            .ok_or_else(|| wrong_type(key, "an array"))
+    }
+
+    /// Get a required string
+    fn string(&self, key: &str) -> Result<String, Error> {
+        self.optional_string(key)?.ok_or_else(|| missing(key))
+    }
+
+    /// Get an optional string
+    fn optional_string(&self, key: &str) -> Result<Option<String>, Error> {
+        match self.find(key) {
+            Some(value) => value
+                .downcast::<CFString>()
+                .map(|s| Some(s.to_string()))
+                .ok_or_else(|| wrong_type(key, "a string")),
+            None => Ok(None),
+        }
+    }
+
+    /// Find the value of the given key
+    fn find(&self, key: &str) -> Option<CFType> {
+        self.0
+            .find(CFString::new(key).as_CFType())
+            .map(|value| value.clone())
+    }
+}
+
+/// Error for a missing manifest key
+fn missing(key: &str) -> Error {
+    Error::new(
+        ErrorKind::Decode,
+        &format!("manifest entry is missing {:?}", key),
+    )
+}
+
+/// Error for a manifest value of the wrong type
+fn wrong_type(key: &str, expected: &str) -> Error {
+    Error::new(
+        ErrorKind::Decode,
+        &format!("expected manifest {:?} to be {}", key, expected),
+    )
+}
+
+/// Error for an unrecognized manifest value
+fn unknown(key: &str, value: &str) -> Error {
+    Error::new(
+        ErrorKind::Decode,
+        &format!("unknown manifest {:?}: {:?}", key, value),
+    )
+}
@@ -1,15 +1,65 @@
 //! Keychains
 
+pub mod audit;
+pub mod certificate;
+#[cfg(feature = "cryptokit")]
+pub mod cryptokit;
+pub mod entitlements;
+#[cfg(feature = "legacy-keychain")]
+pub mod events;
+pub mod hooks;
+pub mod identity;
 pub mod item;
+#[cfg(feature = "json")]
+pub mod json;
 pub mod key;
+pub mod localization;
+#[cfg(feature = "legacy-keychain")]
+pub mod lock;
+pub mod manifest;
+pub mod passwords;
+pub mod piv;
+pub mod policy;
+pub mod prompt;
+pub mod token;
+pub mod watchdog;
 
-use self::item::MatchLimit;
-pub use self::{item::Item, key::Key};
+pub use self::{certificate::Certificate, identity::Identity, item::Item, key::Key, token::Token};
+use self::{hooks::OperationKind, item::MatchLimit, policy::Subject};
 use crate::dictionary::*;
-use crate::error::Error;
+use crate::error::{Error, ErrorKind};
 use crate::ffi::*;
-use core_foundation::base::{CFTypeRef, TCFType};
-use std::{ffi::CString, os::raw::c_char, os::unix::ffi::OsStrExt, path::Path, ptr};
+use core_foundation::{
+    base::{CFTypeRef, TCFType},
+    boolean::CFBoolean,
+};
+#[cfg(feature = "legacy-keychain")]
+use std::{
+    ffi::{CString, OsString},
+    os::raw::{c_char, c_void},
+    os::unix::ffi::{OsStrExt, OsStringExt},
+    path::{Path, PathBuf},
+};
+use std::{
+    fmt::{self, Debug},
+    ptr,
+};
+
+/// Number of times `Keychain::upsert_item` tries adding or updating an item
+/// before giving up on racing with other processes
+const MAX_UPSERT_ATTEMPTS: usize = 3;
+
+/// Size of the buffer keychain paths are read into (i.e. `PATH_MAX`)
+#[cfg(feature = "legacy-keychain")]
+const MAX_PATH_LENGTH: usize = 1024;
+
+/// `kSecUnlockStateStatus` bit of `SecKeychainStatus`
+#[cfg(feature = "legacy-keychain")]
+const UNLOCK_STATE_STATUS: u32 = 1;
+
+/// Path to the macOS System keychain
+#[cfg(feature = "legacy-keychain")]
+pub const SYSTEM_KEYCHAIN_PATH: &str = "/Library/Keychains/System.keychain";
 
 declare_TCFType! {
     /// Keychains which store cryptographic keys, passwords, and other secrets.
@@ -46,6 +96,7 @@ impl Keychain {
     ///
     /// Wrapper for the `SecKeychainCreate` function. See:
     /// <https://developer.apple.com/documentation/security/1401214-seckeychaincreate>
+    #[cfg(feature = "legacy-keychain")]
     pub fn create(path: &Path, password: Option<&str>) -> Result<Keychain, Error> {
         let path_cstring = CString::new(path.as_os_str().as_bytes()).unwrap();
         let mut result: KeychainRef = ptr::null_mut();
@@ -80,10 +131,120 @@ impl Keychain {
         }
     }
 
+    /// Open the keychain at the given path. The keychain is not unlocked,
+    /// and opening a nonexistent keychain succeeds: the resulting errors
+    /// are reported when it is first used.
+    ///
+    /// Wrapper for the `SecKeychainOpen` function. See:
+    /// <https://developer.apple.com/documentation/security/1396431-seckeychainopen>
+    #[cfg(feature = "legacy-keychain")]
+    pub fn open(path: &Path) -> Result<Keychain, Error> {
+        let path_cstring = CString::new(path.as_os_str().as_bytes()).unwrap();
+        let mut result: KeychainRef = ptr::null_mut();
+
+        let status = unsafe { SecKeychainOpen(path_cstring.as_ptr(), &mut result) };
+
+        if let Some(e) = Error::maybe_from_OSStatus(status) {
+            Err(e)
+        } else {
+            Ok(unsafe { Keychain::wrap_under_create_rule(result) })
+        }
+    }
+
+    /// Open the macOS System keychain (i.e. `SYSTEM_KEYCHAIN_PATH`), which
+    /// stores e.g. saved Wi-Fi passwords and system-wide certificates.
+    #[cfg(feature = "legacy-keychain")]
+    pub fn system() -> Result<Keychain, Error> {
+        Self::open(Path::new(SYSTEM_KEYCHAIN_PATH))
+    }
+
+    /// Get the path of this keychain's file.
+    ///
+    /// Wrapper for the `SecKeychainGetPath` function. See:
+    /// <https://developer.apple.com/documentation/security/1396130-seckeychaingetpath>
+    #[cfg(feature = "legacy-keychain")]
+    pub fn path(&self) -> Result<PathBuf, Error> {
+        let mut buffer = vec![0u8; MAX_PATH_LENGTH];
+        let mut length = buffer.len() as u32;
+
+        let status = unsafe {
+            SecKeychainGetPath(
+                self.as_concrete_TypeRef(),
+                &mut length,
+                buffer.as_mut_ptr() as *mut c_char,
+            )
+        };
+
+        if let Some(e) = Error::maybe_from_OSStatus(status) {
+            return Err(e);
+        }
+
+        buffer.truncate(length as usize);
+        Ok(PathBuf::from(OsString::from_vec(buffer)))
+    }
+
+    /// Lock this keychain.
+    ///
+    /// Wrapper for the `SecKeychainLock` function. See:
+    /// <https://developer.apple.com/documentation/security/1402223-seckeychainlock>
+    #[cfg(feature = "legacy-keychain")]
+    pub fn lock(&self) -> Result<(), Error> {
+        let status = unsafe { SecKeychainLock(self.as_concrete_TypeRef()) };
+
+        if let Some(e) = Error::maybe_from_OSStatus(status) {
+            Err(e)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Unlock this keychain with the given password. If no password is
+    /// given, the user will be prompted for it.
+    ///
+    /// Wrapper for the `SecKeychainUnlock` function. See:
+    /// <https://developer.apple.com/documentation/security/1400341-seckeychainunlock>
+    #[cfg(feature = "legacy-keychain")]
+    pub fn unlock(&self, password: Option<&str>) -> Result<(), Error> {
+        let status = match password {
+            Some(pw) => unsafe {
+                SecKeychainUnlock(
+                    self.as_concrete_TypeRef(),
+                    pw.len() as u32,
+                    pw.as_bytes().as_ptr() as *const c_void,
+                    true,
+                )
+            },
+            None => unsafe { SecKeychainUnlock(self.as_concrete_TypeRef(), 0, ptr::null(), false) },
+        };
+
+        if let Some(e) = Error::maybe_from_OSStatus(status) {
+            Err(e)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Is this keychain currently locked?
+    ///
+    /// Wrapper for the `SecKeychainGetStatus` function. See:
+    /// <https://developer.apple.com/documentation/security/1397950-seckeychaingetstatus>
+    #[cfg(feature = "legacy-keychain")]
+    pub fn is_locked(&self) -> Result<bool, Error> {
+        let mut status_bits = 0;
+        let status = unsafe { SecKeychainGetStatus(self.as_concrete_TypeRef(), &mut status_bits) };
+
+        if let Some(e) = Error::maybe_from_OSStatus(status) {
+            Err(e)
+        } else {
+            Ok(status_bits & UNLOCK_STATE_STATUS == 0)
+        }
+    }
+
     /// Delete this keychain.
     ///
     /// Wrapper for the `SecKeychainDelete` function. See:
     /// <https://developer.apple.com/documentation/security/1395206-seckeychaindelete>
+    #[cfg(feature = "legacy-keychain")]
     pub fn delete(self) -> Result<(), Error> {
         let status = unsafe { SecKeychainDelete(self.as_concrete_TypeRef()) };
 
@@ -94,31 +255,155 @@ impl Keychain {
         }
     }
 
-    /// Find an item in this keychain.
+    /// Iterate over the attributes of every item in this keychain of the
+    /// given classes, or of all classes if none are given.
+    ///
+    /// Items are fetched lazily, a batch at a time (see
+    /// `ItemAttributes::pages`), and include references to the items
+    /// themselves.
+    pub fn items(&self, classes: &[item::Class]) -> item::KeychainItems {
+        item::KeychainItems::new(classes, item::Query::new().keychain(self))
+    }
+
+    /// Find the items of the given classes in this keychain matching the
+    /// given `Query`, whatever their class (see `item::find_items`).
+    pub fn find_items(
+        &self,
+        classes: &[item::Class],
+        query: &item::Query,
+    ) -> Result<Vec<item::FoundItem>, Error> {
+        item::find_items(classes, query.clone().keychain(self))
+    }
+
+    /// Count the items in this keychain matching the given `ItemQuery`,
+    /// without fetching their attributes or secret data (e.g. to check how
+    /// many items a bulk operation would affect).
+    pub fn count_matching(&self, query: &item::ItemQuery) -> Result<usize, Error> {
+        query.clone().in_keychain(self).count()
+    }
+
+    /// Delete all items in this keychain matching the given `ItemQuery`.
+    ///
+    /// Returns an error with a kind of `ErrorKind::ItemNotFound` if no items
+    /// match the query. Use `Keychain::delete_matching_with_report` to find
+    /// out which items were deleted.
+    ///
+    /// Wrapper for the `SecItemDelete` function. See:
+    /// <https://developer.apple.com/documentation/security/1395547-secitemdelete>
+    pub fn delete_matching(&self, query: &item::ItemQuery) -> Result<(), Error> {
+        query.clone().in_keychain(self).delete()
+    }
+
+    /// Delete all items in this keychain matching the given `ItemQuery`,
+    /// fetching them first so the returned `DeletionReport` says exactly
+    /// which items were removed (and which couldn't be).
+    pub fn delete_matching_with_report(
+        &self,
+        query: &item::ItemQuery,
+    ) -> Result<item::DeletionReport, Error> {
+        query.clone().in_keychain(self).delete_with_report()
+    }
+
+    /// Add an item matching the given `ItemQuery` to this keychain with the
+    /// given changes applied, or if one already exists, apply the changes
+    /// to it instead.
+    ///
+    /// The query's attributes (e.g. service and account) should identify a
+    /// single item, and become the attributes of the new item along with
+    /// the changes. If the item is added or deleted by another process
+    /// between adding and updating it, the operation is retried rather than
+    /// failing with `ErrorKind::DuplicateItem` or `ErrorKind::ItemNotFound`.
+    ///
+    /// Wrapper for the `SecItemAdd` and `SecItemUpdate` functions. See:
+    /// <https://developer.apple.com/documentation/security/1401659-secitemadd>
+    /// <https://developer.apple.com/documentation/security/1393617-secitemupdate>
+    pub fn upsert_item(
+        &self,
+        query: &item::ItemQuery,
+        changes: &item::ItemChanges,
+    ) -> Result<item::UpsertOutcome, Error> {
+        let query = query.clone().in_keychain(self);
+        let attrs = query.new_item_attrs(changes);
+        let mut last_error = None;
+
+        for _ in 0..MAX_UPSERT_ATTEMPTS {
+            let e = match self.add_item(attrs.clone()) {
+                Ok(_) => return Ok(item::UpsertOutcome::Added),
+                Err(e) => e,
+            };
+
+            match e.kind() {
+                ErrorKind::DuplicateItem => (),
+                _ => return Err(e),
+            }
+
+            let e = match query.update(changes) {
+                Ok(()) => return Ok(item::UpsertOutcome::Updated),
+                Err(e) => e,
+            };
+
+            match e.kind() {
+                // Deleted since adding it failed
+                ErrorKind::ItemNotFound => last_error = Some(e),
+                _ => return Err(e),
+            }
+        }
+
+        Err(last_error.unwrap())
+    }
+
+    /// Look up the item with the given persistent reference (e.g. as
+    /// returned by `ItemAttributes::persistent_ref` and stored since),
+    /// returning `Ok(None)` if it no longer exists.
+    ///
+    /// Wrapper for `SecItemCopyMatching` with `kSecValuePersistentRef`. See:
+    /// <https://developer.apple.com/documentation/security/ksecvaluepersistentref>
+    pub fn item_from_persistent_ref(
+        &self,
+        persistent_ref: &item::PersistentItemRef,
+    ) -> Result<Option<Item>, Error> {
+        let mut attrs = DictionaryBuilder::new();
+        attrs.add(
+            unsafe { kSecValuePersistentRef },
+            &persistent_ref.as_CFData(),
+        );
+        self.find_item(attrs)
+    }
+
+    /// Find an item in this keychain, returning `Ok(None)` if no matching
+    /// item exists.
     ///
     /// This is a private method we wrap using builders for querying various
     /// keychain item types.
     ///
     /// Wrapper for `SecItemCopyMatching`. See:
     /// <https://developer.apple.com/documentation/security/1398306-secitemcopymatching>
-    fn find_item(&self, mut attrs: DictionaryBuilder) -> Result<Item, Error> {
-        attrs.add(unsafe { kSecMatchLimit }, &MatchLimit::One.as_CFType());
-        attrs.add_boolean(unsafe { kSecReturnRef }, true);
+    fn find_item(&self, mut attrs: DictionaryBuilder) -> Result<Option<Item>, Error> {
+        hooks::run(OperationKind::Find, attrs.class(), || {
+            // Any other limit would return an array of items
+            attrs.remove(unsafe { kSecMatchLimit });
+            attrs.add(unsafe { kSecMatchLimit }, &MatchLimit::One.as_CFType());
+            attrs.add_boolean(unsafe { kSecReturnRef }, true);
 
-        let mut result: ItemRef = ptr::null_mut();
-        let status = unsafe {
-            SecItemCopyMatching(
-                Dictionary::from(attrs).as_concrete_TypeRef(),
-                &mut result as &mut CFTypeRef,
-            )
-        };
+            let mut result: ItemRef = ptr::null_mut();
+            let status = unsafe {
+                SecItemCopyMatching(
+                    Dictionary::from(attrs.clone()).as_concrete_TypeRef(),
+                    &mut result as &mut CFTypeRef,
+                )
+            };
 
-        // Return an error if the status was unsuccessful
-        if let Some(e) = Error::maybe_from_OSStatus(status) {
-            return Err(e);
-        }
+            // Return an error if the status was unsuccessful
+            if let Some(e) = Error::maybe_from_OSStatus(status) {
+                let e = entitlements::diagnose(e, &attrs);
+                return match e.kind() {
+                    ErrorKind::ItemNotFound => Ok(None),
+                    _ => Err(e),
+                };
+            }
 
-        Ok(unsafe { Item::wrap_under_create_rule(result) })
+            Ok(Some(unsafe { Item::wrap_under_create_rule(result) }))
+        })
     }
 
     /// Add an item to this keychain.
@@ -129,20 +414,47 @@ impl Keychain {
     /// Wrapper for the `SecItemAdd` function. See:
     /// <https://developer.apple.com/documentation/security/1401659-secitemadd>
     fn add_item(&self, mut attrs: DictionaryBuilder) -> Result<Item, Error> {
-        attrs.add(unsafe { kSecUseKeychain }, self);
-        attrs.add_boolean(unsafe { kSecReturnRef }, true);
+        hooks::run(OperationKind::Add, attrs.class(), || {
+            policy::enforce(&Subject::Item(attrs.class()), &mut attrs)?;
+            item::validate_attrs(&attrs)?;
 
-        let mut result: ItemRef = ptr::null_mut();
-        let status =
-            unsafe { SecItemAdd(Dictionary::from(attrs).as_concrete_TypeRef(), &mut result) };
+            // Items in the data protection keychain don't belong to a keychain file
+            let data_protection = attrs
+                .get(unsafe { kSecUseDataProtectionKeychain })
+                .and_then(|value| value.downcast::<CFBoolean>())
+                .is_some_and(bool::from);
 
-        if let Some(e) = Error::maybe_from_OSStatus(status) {
-            println!("super duper {:?}.", e);
-            Err(e)
-        } else {
-            println!("fuck you.");
-            Ok(unsafe { Item::wrap_under_create_rule(result) })
-        }
+            if !data_protection {
+                attrs.add(unsafe { kSecUseKeychain }, self);
+            }
+
+            attrs.add_boolean(unsafe { kSecReturnRef }, true);
+
+            let mut result: ItemRef = ptr::null_mut();
+            let status = unsafe {
+                SecItemAdd(
+                    Dictionary::from(attrs.clone()).as_concrete_TypeRef(),
+                    &mut result,
+                )
+            };
+
+            match Error::maybe_from_OSStatus(status) {
+                Some(e) => Err(entitlements::diagnose(e, &attrs)),
+                None => Ok(unsafe { Item::wrap_under_create_rule(result) }),
+            }
+        })
+    }
+}
+
+impl Debug for Keychain {
+    #[cfg(feature = "legacy-keychain")]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecKeychain {{ path: {:?} }}", self.path().ok())
+    }
+
+    #[cfg(not(feature = "legacy-keychain"))]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecKeychain")
     }
 }
 
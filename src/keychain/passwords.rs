@@ -0,0 +1,58 @@
+//! Storing passwords in the default keychain by service and account, for
+//! applications which just need a place to keep a few secrets.
+//!
+//! Other processes may add, change, or delete the same password at any
+//! time: `set_password` retries when it loses a race with them (see
+//! `Keychain::upsert_item`), and the other functions treat a password
+//! deleted while they're running as not existing.
+
+use super::{
+    item::{delete_matching, Class, GenericPassword, ItemChanges, ItemQuery, PasswordData, Query},
+    Keychain,
+};
+use crate::error::{Error, ErrorKind};
+
+/// Store the given password for the given service and account, adding it to
+/// the default keychain or replacing an existing password.
+pub fn set_password(service: &str, account: &str, password: &str) -> Result<(), Error> {
+    let query = ItemQuery::new(Class::GenericPassword)
+        .service(service)
+        .account(account);
+    let changes = ItemChanges::new().data(password.as_bytes());
+
+    Keychain::find_default()?
+        .upsert_item(&query, &changes)
+        .map(|_| ())
+}
+
+/// Get the password stored for the given service and account, returning
+/// `Ok(None)` if there isn't one.
+pub fn get_password(service: &str, account: &str) -> Result<Option<PasswordData>, Error> {
+    let keychain = Keychain::find_default()?;
+
+    match GenericPassword::find(&keychain, service, account)? {
+        Some(password) => password.password().map(Some).or_else(|e| match e.kind() {
+            // Deleted by another process since it was looked up
+            ErrorKind::ItemNotFound => Ok(None),
+            _ => Err(e),
+        }),
+        None => Ok(None),
+    }
+}
+
+/// Delete the password stored for the given service and account, returning
+/// whether there was one to delete.
+pub fn delete_password(service: &str, account: &str) -> Result<bool, Error> {
+    let keychain = Keychain::find_default()?;
+    let query = Query::new()
+        .keychain(&keychain)
+        .service(service)
+        .account(account);
+
+    delete_matching(Class::GenericPassword, &query)
+        .map(|()| true)
+        .or_else(|e| match e.kind() {
+            ErrorKind::ItemNotFound => Ok(false),
+            _ => Err(e),
+        })
+}
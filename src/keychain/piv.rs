@@ -0,0 +1,160 @@
+//! Convenience layer for PIV (Personal Identity Verification, FIPS 201)
+//! smart cards, e.g. CAC and PIV cards used for enterprise authentication.
+//!
+//! These are exposed by the PIV token driver which ships with macOS as
+//! token-backed identities (see `keychain::token`). For more information on
+//! the card's key slots, see NIST SP 800-73-4:
+//! <https://csrc.nist.gov/publications/detail/sp/800-73/4/final>
+
+use crate::{
+    attr::{AttrKind, AttrTokenId},
+    error::Error,
+    ffi::*,
+    keychain::{
+        certificate::Certificate,
+        identity::Identity,
+        item::Class,
+        key::KeyAlgorithm,
+        token::{self, Token},
+    },
+    signature::Signature,
+};
+use core_foundation::{
+    base::{CFTypeRef, TCFType},
+    string::{CFString, CFStringRef},
+};
+
+/// Prefix of the token IDs assigned by the macOS PIV token driver.
+const PIV_TOKEN_ID_PREFIX: &str = "com.apple.pivtoken:";
+
+/// Key slots of a PIV card which hold an identity (i.e. a certificate and
+/// its private key).
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub enum PivSlot {
+    /// PIV Authentication key (slot `9A`), used to authenticate the
+    /// cardholder, e.g. for client TLS or SSH.
+    Authentication,
+
+    /// Digital Signature key (slot `9C`), used for signing documents and
+    /// email. Always requires the PIN to be entered before use.
+    DigitalSignature,
+
+    /// Key Management key (slot `9D`), used for encryption/key agreement.
+    KeyManagement,
+
+    /// Card Authentication key (slot `9E`), used to authenticate the card
+    /// itself (e.g. for physical access) without a PIN.
+    CardAuthentication,
+}
+
+impl PivSlot {
+    /// Get the key reference which identifies this slot on the card.
+    pub fn key_reference(self) -> u8 {
+        match self {
+            PivSlot::Authentication => 0x9A,
+            PivSlot::DigitalSignature => 0x9C,
+            PivSlot::KeyManagement => 0x9D,
+            PivSlot::CardAuthentication => 0x9E,
+        }
+    }
+
+    /// Get the label the macOS PIV token driver assigns to this slot's
+    /// identity (i.e. the `kSecAttrLabel` of the token's items).
+    pub fn label(self) -> &'static str {
+        match self {
+            PivSlot::Authentication => "Certificate For PIV Authentication",
+            PivSlot::DigitalSignature => "Certificate For Digital Signature",
+            PivSlot::KeyManagement => "Certificate For Key Management",
+            PivSlot::CardAuthentication => "Certificate For Card Authentication",
+        }
+    }
+}
+
+impl Token {
+    /// Is this token provided by the macOS PIV token driver?
+    pub fn is_piv(&self) -> bool {
+        match self.id() {
+            AttrTokenId::Token(id) => id.starts_with(PIV_TOKEN_ID_PREFIX),
+            AttrTokenId::SecureEnclave => false,
+        }
+    }
+}
+
+/// Identity stored in one of the slots of an inserted PIV card.
+#[derive(Debug)]
+pub struct PivIdentity {
+    slot: PivSlot,
+    token: Token,
+    identity: Identity,
+}
+
+impl PivIdentity {
+    /// Find the identity in the given slot of any inserted PIV card,
+    /// returning `Ok(None)` if no card (or no such identity) is present.
+    pub fn find(slot: PivSlot) -> Result<Option<Self>, Error> {
+        for token in Token::list()? {
+            if token.is_piv() {
+                if let Some(identity) = Self::find_on_token(&token, slot)? {
+                    return Ok(Some(identity));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Find the identity in the given slot of a particular PIV card.
+    pub fn find_on_token(token: &Token, slot: PivSlot) -> Result<Option<Self>, Error> {
+        let identity = token::find_all(Class::Identity, Some(token.id()))?
+            .iter()
+            .find(|item| {
+                item.find(AttrKind::Label).is_some_and(|label| {
+                    let label = unsafe {
+                        CFString::wrap_under_get_rule(label.as_CFTypeRef() as CFStringRef)
+                    };
+                    label.to_string() == slot.label()
+                })
+            })
+            .and_then(|item| {
+                item.find(unsafe { kSecValueRef } as CFTypeRef)
+                    .map(|identity| unsafe {
+                        Identity::wrap_under_get_rule(identity.as_CFTypeRef())
+                    })
+            });
+
+        Ok(identity.map(|identity| PivIdentity {
+            slot,
+            token: token.clone(),
+            identity,
+        }))
+    }
+
+    /// Get the slot this identity is stored in
+    pub fn slot(&self) -> PivSlot {
+        self.slot
+    }
+
+    /// Get the token (i.e. card) this identity is stored on
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+
+    /// Borrow the underlying `Identity`
+    pub fn identity(&self) -> &Identity {
+        &self.identity
+    }
+
+    /// Get the certificate stored in this slot
+    pub fn certificate(&self) -> Result<Certificate, Error> {
+        self.identity.certificate()
+    }
+
+    /// Sign the given data using the private key in this slot.
+    ///
+    /// The system prompts the user for the card's PIN if required. If the
+    /// user dismisses the prompt an error of kind `ErrorKind::UserCanceled`
+    /// is returned, and an incorrect PIN results in `ErrorKind::AuthFailed`.
+    pub fn sign(&self, alg: KeyAlgorithm, data: &[u8]) -> Result<Signature, Error> {
+        self.identity.private_key()?.sign(alg, data)
+    }
+}
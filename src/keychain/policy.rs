@@ -0,0 +1,290 @@
+//! Application-wide policy guardrails for creating keychain items and
+//! generating keys.
+//!
+//! A `Policy` declares rules (e.g. "all new items must be
+//! `ThisDeviceOnly`") which are checked whenever items are added to a
+//! keychain or key pairs are generated. Depending on its `Enforcement`,
+//! violating operations are either rejected with `ErrorKind::PolicyViolation`
+//! or their attributes are corrected to comply before they're performed.
+
+use crate::{
+    access::{AccessConstraint, AccessControl, AccessControlFlags, AccessOption},
+    attr::{AttrAccessible, AttrKeyType, AttrKind, AttrTokenId, TAttr},
+    dictionary::DictionaryBuilder,
+    error::{Error, ErrorKind},
+    keychain::item::Class,
+};
+use core_foundation::boolean::CFBoolean;
+use std::{
+    fmt::{self, Display},
+    sync::{Arc, RwLock},
+};
+
+/// Rules which can be enforced by a `Policy`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum PolicyRule {
+    /// Items must only be accessible on this device, i.e. they must have
+    /// a `*ThisDeviceOnly` accessibility and must not be synchronizable.
+    ///
+    /// Corrected by switching to the `ThisDeviceOnly` counterpart of the
+    /// accessibility (`WhenUnlockedThisDeviceOnly` if unset) and disabling
+    /// synchronization. Items protected by an `AccessControl` are only
+    /// checked for synchronization, as the accessibility an `AccessControl`
+    /// was created with can't be inspected.
+    ThisDeviceOnly,
+
+    /// Items must not be synchronized via iCloud Keychain.
+    ///
+    /// Corrected by disabling synchronization.
+    NotSynchronizable,
+
+    /// Keys stored in the Secure Enclave must be protected by an
+    /// `AccessControl`.
+    ///
+    /// Corrected by adding an `AccessControl` requiring the given constraint
+    /// (along with `AccessOption::PrivateKeyUsage`). As with
+    /// `ThisDeviceOnly`, the constraints of an existing `AccessControl` can't
+    /// be inspected, so any `AccessControl` satisfies this rule.
+    SecureEnclaveAccessControl(AccessConstraint),
+
+    /// Generated keys of the given type must be at least the given size
+    /// (in bits).
+    ///
+    /// Violations can't be corrected, and are always rejected.
+    MinimumKeySize(AttrKeyType, usize),
+}
+
+/// How violations of a `Policy` are handled.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Enforcement {
+    /// Reject operations which violate any rule
+    Reject,
+
+    /// Correct the attributes of operations which violate a rule so they
+    /// comply, rejecting them only when that isn't possible
+    Correct,
+}
+
+/// Set of rules enforced on all item creation and key generation.
+#[derive(Clone, Debug)]
+pub struct Policy {
+    enforcement: Enforcement,
+    rules: Vec<PolicyRule>,
+}
+
+impl Policy {
+    /// Create a new policy with the given `Enforcement` and no rules
+    pub fn new(enforcement: Enforcement) -> Self {
+        Self {
+            enforcement,
+            rules: vec![],
+        }
+    }
+
+    /// Add a rule to this policy. Rules are checked in the order added.
+    pub fn rule(mut self, rule: PolicyRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Get the `Enforcement` of this policy
+    pub fn enforcement(&self) -> Enforcement {
+        self.enforcement
+    }
+
+    /// Get the rules of this policy
+    pub fn rules(&self) -> &[PolicyRule] {
+        &self.rules
+    }
+
+    /// Check the given attributes of an item or key against this policy,
+    /// correcting them if permitted
+    fn enforce(&self, subject: &Subject, attrs: &mut DictionaryBuilder) -> Result<(), Error> {
+        for rule in &self.rules {
+            if let Some(violation) = check(*rule, subject, attrs) {
+                if self.enforcement == Enforcement::Reject || !correct(*rule, attrs)? {
+                    return Err(Error::new(
+                        ErrorKind::PolicyViolation,
+                        &format!("{} violates policy: {}", subject, violation),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Install the given `Policy` process-wide (or remove the current one if
+/// `None`), returning the previously installed policy (if any).
+pub fn set_policy(policy: Option<Policy>) -> Option<Policy> {
+    let previous = match policy {
+        Some(policy) => POLICY.write().unwrap().replace(Arc::new(policy)),
+        None => POLICY.write().unwrap().take(),
+    };
+
+    previous.map(|policy| (*policy).clone())
+}
+
+/// Get the currently installed `Policy` (if any)
+pub fn policy() -> Option<Policy> {
+    POLICY
+        .read()
+        .unwrap()
+        .as_ref()
+        .map(|policy| (**policy).clone())
+}
+
+/// Operations checked against the installed `Policy`
+pub(crate) enum Subject {
+    /// Adding an item of the given class to a keychain
+    Item(Option<Class>),
+
+    /// Generating a key pair of the given type and size
+    KeyGeneration(AttrKeyType, usize),
+}
+
+impl Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Subject::Item(Some(class)) => write!(f, "new {}", class),
+            Subject::Item(None) => write!(f, "new item"),
+            Subject::KeyGeneration(key_type, key_size) => {
+                write!(f, "generated {}-bit {:?} key", key_size, key_type)
+            }
+        }
+    }
+}
+
+/// Check the given attributes against the installed `Policy` (if any),
+/// correcting them in place if it permits.
+pub(crate) fn enforce(subject: &Subject, attrs: &mut DictionaryBuilder) -> Result<(), Error> {
+    // Release the lock before enforcing, as corrections may create objects
+    let policy = POLICY.read().unwrap().clone();
+
+    match policy {
+        Some(policy) => policy.enforce(subject, attrs),
+        None => Ok(()),
+    }
+}
+
+/// Currently installed policy
+static POLICY: RwLock<Option<Arc<Policy>>> = RwLock::new(None);
+
+/// Check a rule against the given attributes, returning a description of
+/// the violation (if any)
+fn check(rule: PolicyRule, subject: &Subject, attrs: &DictionaryBuilder) -> Option<String> {
+    match rule {
+        PolicyRule::ThisDeviceOnly => {
+            if is_synchronizable(attrs) {
+                return Some("must not be synchronizable".to_owned());
+            }
+
+            if attrs.get(AttrKind::AccessControl).is_some() {
+                return None;
+            }
+
+            match accessible(attrs) {
+                Some(accessible) if this_device_only(accessible) == accessible => None,
+                accessible => Some(format!(
+                    "must be accessible on this device only (accessibility: {:?})",
+                    accessible
+                )),
+            }
+        }
+        PolicyRule::NotSynchronizable => {
+            if is_synchronizable(attrs) {
+                Some("must not be synchronizable".to_owned())
+            } else {
+                None
+            }
+        }
+        PolicyRule::SecureEnclaveAccessControl(_) => {
+            let secure_enclave = attrs
+                .get(AttrKind::TokenId)
+                .is_some_and(|token_id| *token_id == AttrTokenId::SecureEnclave.as_CFType());
+
+            if secure_enclave && attrs.get(AttrKind::AccessControl).is_none() {
+                Some("Secure Enclave keys must have an access control policy".to_owned())
+            } else {
+                None
+            }
+        }
+        PolicyRule::MinimumKeySize(min_key_type, min_key_size) => match subject {
+            Subject::KeyGeneration(key_type, key_size)
+                if *key_type == min_key_type && *key_size < min_key_size =>
+            {
+                Some(format!(
+                    "{:?} keys must be at least {} bits",
+                    key_type, min_key_size
+                ))
+            }
+            _ => None,
+        },
+    }
+}
+
+/// Correct the given attributes to comply with a rule, returning `false`
+/// if that isn't possible
+fn correct(rule: PolicyRule, attrs: &mut DictionaryBuilder) -> Result<bool, Error> {
+    match rule {
+        PolicyRule::ThisDeviceOnly => {
+            disable_synchronization(attrs);
+
+            if attrs.get(AttrKind::AccessControl).is_none() {
+                let accessible = accessible(attrs).unwrap_or(AttrAccessible::WhenUnlocked);
+                attrs.remove(AttrKind::Accessible);
+                attrs.add_attr(&this_device_only(accessible));
+            }
+        }
+        PolicyRule::NotSynchronizable => disable_synchronization(attrs),
+        PolicyRule::SecureEnclaveAccessControl(constraint) => {
+            // An accessibility can't be specified alongside an access control
+            let protection = this_device_only(
+                accessible(attrs).unwrap_or(AttrAccessible::WhenUnlockedThisDeviceOnly),
+            );
+            attrs.remove(AttrKind::Accessible);
+
+            let mut flags = AccessControlFlags::new();
+            flags.add(constraint);
+            flags.add(AccessOption::PrivateKeyUsage);
+
+            let access_control = AccessControl::create_with_flags(protection, flags)?;
+            attrs.add(AttrKind::AccessControl, &access_control);
+        }
+        PolicyRule::MinimumKeySize(..) => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// Get the accessibility in the given attributes (if set)
+fn accessible(attrs: &DictionaryBuilder) -> Option<AttrAccessible> {
+    attrs
+        .get(AttrKind::Accessible)
+        .and_then(AttrAccessible::from_CFType)
+}
+
+/// Get the `ThisDeviceOnly` counterpart of an accessibility
+fn this_device_only(accessible: AttrAccessible) -> AttrAccessible {
+    match accessible {
+        AttrAccessible::WhenUnlocked => AttrAccessible::WhenUnlockedThisDeviceOnly,
+        AttrAccessible::AfterFirstUnlock => AttrAccessible::AfterFirstUnlockThisDeviceOnly,
+        AttrAccessible::Always => AttrAccessible::AlwaysThisDeviceOnly,
+        other => other,
+    }
+}
+
+/// Are the given attributes for a synchronizable item?
+fn is_synchronizable(attrs: &DictionaryBuilder) -> bool {
+    attrs
+        .get(AttrKind::Synchronizable)
+        .and_then(|value| value.downcast::<CFBoolean>())
+        .is_some_and(bool::from)
+}
+
+/// Disable synchronization in the given attributes
+fn disable_synchronization(attrs: &mut DictionaryBuilder) {
+    attrs.remove(AttrKind::Synchronizable);
+    attrs.add_boolean(AttrKind::Synchronizable, false);
+}
@@ -0,0 +1,135 @@
+//! Templates for the text of the prompts shown when using keys which require
+//! user authentication (e.g. Touch ID).
+//!
+//! Rather than assembling prompt text at each call site, applications
+//! install a `PromptTemplate` with `set_prompt_template` and pass only a
+//! reason to e.g. `Key::sign_with_reason`. Templates may contain the
+//! following placeholders, which are resolved when the key is used:
+//!
+//! - `{operation}`: the operation being performed, e.g. `sign`
+//! - `{label}`: the label of the key (or `key` if it has none)
+//! - `{reason}`: the reason supplied by the caller
+//!
+//! Literal braces can be included as `{{` and `}}`. Templates and operation
+//! names can be localized with a `Localizer` (see `keychain::localization`).
+
+use super::localization::localizer;
+use crate::key::KeyOperation;
+use std::sync::RwLock;
+
+/// Template used when none has been installed
+pub const DEFAULT_PROMPT_TEMPLATE: &str = "{reason}";
+
+/// Template for the text of operation prompts.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PromptTemplate(String);
+
+impl PromptTemplate {
+    /// Create a new prompt template
+    pub fn new(template: &str) -> Self {
+        PromptTemplate(template.to_owned())
+    }
+
+    /// Render this template with the given context. Unknown placeholders
+    /// are left as-is.
+    pub fn render(&self, context: &PromptContext) -> String {
+        let mut rendered = String::new();
+        let mut rest = self.0.as_str();
+
+        while let Some(start) = rest.find(['{', '}']) {
+            rendered.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                rendered.push_str(&rest[..1]);
+                rest = &rest[2..];
+                continue;
+            }
+
+            let end = match rest.find('}') {
+                Some(end) if rest.starts_with('{') => end,
+                _ => {
+                    rendered.push_str(&rest[..1]);
+                    rest = &rest[1..];
+                    continue;
+                }
+            };
+
+            match &rest[1..end] {
+                "operation" => rendered.push_str(&operation_name(context.operation)),
+                "label" => rendered.push_str(context.label.unwrap_or("key")),
+                "reason" => rendered.push_str(context.reason.unwrap_or("")),
+                _ => rendered.push_str(&rest[..=end]),
+            }
+
+            rest = &rest[end + 1..];
+        }
+
+        rendered.push_str(rest);
+        rendered
+    }
+}
+
+impl Default for PromptTemplate {
+    fn default() -> Self {
+        Self::new(DEFAULT_PROMPT_TEMPLATE)
+    }
+}
+
+/// Values for the placeholders in a `PromptTemplate`.
+#[derive(Copy, Clone, Debug)]
+pub struct PromptContext<'a> {
+    /// Operation being performed with the key
+    pub operation: KeyOperation,
+
+    /// Label of the key (if any)
+    pub label: Option<&'a str>,
+
+    /// Reason supplied by the caller (if any)
+    pub reason: Option<&'a str>,
+}
+
+/// Install the given `PromptTemplate` process-wide (or restore the default
+/// if `None`), returning the previously installed template (if any).
+pub fn set_prompt_template(template: Option<PromptTemplate>) -> Option<PromptTemplate> {
+    let mut installed = PROMPT_TEMPLATE.write().unwrap();
+
+    match template {
+        Some(template) => installed.replace(template),
+        None => installed.take(),
+    }
+}
+
+/// Get the currently installed `PromptTemplate` (or the default)
+pub fn prompt_template() -> PromptTemplate {
+    PROMPT_TEMPLATE.read().unwrap().clone().unwrap_or_default()
+}
+
+/// Currently installed prompt template
+static PROMPT_TEMPLATE: RwLock<Option<PromptTemplate>> = RwLock::new(None);
+
+/// Render the prompt for the given context, using the template provided by
+/// the installed `Localizer` (if any) or else the installed `PromptTemplate`.
+pub fn render_prompt(context: &PromptContext) -> String {
+    localizer()
+        .and_then(|localizer| localizer.prompt_template(context.operation))
+        .unwrap_or_else(prompt_template)
+        .render(context)
+}
+
+/// Get the name of an operation as used in prompts, as localized by the
+/// installed `Localizer` (if any)
+fn operation_name(operation: KeyOperation) -> String {
+    if let Some(name) = localizer().and_then(|localizer| localizer.operation_name(operation)) {
+        return name;
+    }
+
+    match operation {
+        KeyOperation::Decrypt => "decrypt",
+        KeyOperation::Encrypt => "encrypt",
+        KeyOperation::KeyExchange => "exchange keys",
+        KeyOperation::Sign => "sign",
+        KeyOperation::Verify => "verify",
+    }
+    .to_owned()
+}
@@ -0,0 +1,152 @@
+//! Keys and certificates provided by external tokens, i.e. smart cards and
+//! other CryptoTokenKit extensions.
+//!
+//! Token-backed items are exposed by Keychain Services in the special
+//! `kSecAttrAccessGroupToken` access group. See:
+//! <https://developer.apple.com/documentation/security/ksecattraccessgrouptoken>
+
+use crate::{
+    attr::{AttrKind, AttrTokenId},
+    dictionary::{Dictionary, DictionaryBuilder},
+    error::{Error, ErrorKind},
+    ffi::*,
+    keychain::{
+        certificate::Certificate,
+        hooks::{self, OperationKind},
+        identity::Identity,
+        item::{Class, MatchLimit},
+        key::Key,
+    },
+};
+use core_foundation::{
+    array::{CFArray, CFArrayRef},
+    base::{CFTypeRef, TCFType},
+    string::{CFString, CFStringRef},
+};
+use std::ptr;
+
+/// External token (e.g. an inserted smart card) which provides keys and/or
+/// certificates, identified by its `AttrTokenId`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Token {
+    id: AttrTokenId,
+}
+
+impl Token {
+    /// Create a handle to the token with the given ID.
+    pub fn new<T: Into<AttrTokenId>>(id: T) -> Self {
+        Self { id: id.into() }
+    }
+
+    /// List the tokens (e.g. inserted smart cards) which currently expose
+    /// keys or certificates.
+    pub fn list() -> Result<Vec<Self>, Error> {
+        let mut result: Vec<Self> = vec![];
+
+        for class in &[Class::Key, Class::Certificate] {
+            for item in find_all(*class, None)? {
+                if let Some(id) = token_id(&item) {
+                    if result.iter().all(|token| token.id != id) {
+                        result.push(Token { id });
+                    }
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Get the ID of this token. For CryptoTokenKit tokens this identifies
+    /// both the token driver and the instance (e.g. the card or reader).
+    pub fn id(&self) -> &AttrTokenId {
+        &self.id
+    }
+
+    /// Enumerate the keys provided by this token.
+    pub fn keys(&self) -> Result<Vec<Key>, Error> {
+        Ok(find_all(Class::Key, Some(&self.id))?
+            .iter()
+            .filter_map(|item| {
+                item.find(unsafe { kSecValueRef } as CFTypeRef)
+                    .map(|key| unsafe { Key::wrap_under_get_rule(key.as_CFTypeRef()) })
+            })
+            .collect())
+    }
+
+    /// Enumerate the certificates provided by this token.
+    pub fn certificates(&self) -> Result<Vec<Certificate>, Error> {
+        Ok(find_all(Class::Certificate, Some(&self.id))?
+            .iter()
+            .filter_map(|item| {
+                item.find(unsafe { kSecValueRef } as CFTypeRef)
+                    .map(|cert| unsafe { Certificate::wrap_under_get_rule(cert.as_CFTypeRef()) })
+            })
+            .collect())
+    }
+
+    /// Enumerate the identities (i.e. certificates with a corresponding
+    /// private key) provided by this token.
+    pub fn identities(&self) -> Result<Vec<Identity>, Error> {
+        Ok(find_all(Class::Identity, Some(&self.id))?
+            .iter()
+            .filter_map(|item| {
+                item.find(unsafe { kSecValueRef } as CFTypeRef)
+                    .map(|identity| unsafe {
+                        Identity::wrap_under_get_rule(identity.as_CFTypeRef())
+                    })
+            })
+            .collect())
+    }
+}
+
+/// Find the attributes and references of all token-backed items of the
+/// given class, optionally restricted to a particular token.
+///
+/// Wrapper for `SecItemCopyMatching`. See:
+/// <https://developer.apple.com/documentation/security/1398306-secitemcopymatching>
+pub(crate) fn find_all(
+    class: Class,
+    token_id: Option<&AttrTokenId>,
+) -> Result<Vec<Dictionary>, Error> {
+    hooks::run(OperationKind::Find, Some(class), || {
+        let mut query = DictionaryBuilder::new();
+        query.add_class(class);
+
+        let access_group = unsafe { CFString::wrap_under_get_rule(kSecAttrAccessGroupToken) };
+        query.add(unsafe { kSecAttrAccessGroup }, &access_group);
+        query.add(unsafe { kSecMatchLimit }, &MatchLimit::All.as_CFType());
+        query.add_boolean(unsafe { kSecReturnAttributes }, true);
+        query.add_boolean(unsafe { kSecReturnRef }, true);
+
+        if let Some(id) = token_id {
+            query.add_attr(id);
+        }
+
+        let mut result: CFTypeRef = ptr::null_mut();
+        let status = unsafe {
+            SecItemCopyMatching(Dictionary::from(query).as_concrete_TypeRef(), &mut result)
+        };
+
+        if let Some(e) = Error::maybe_from_OSStatus(status) {
+            return match e.kind() {
+                ErrorKind::ItemNotFound => Ok(vec![]),
+                _ => Err(e),
+            };
+        }
+
+        let items = unsafe { CFArray::<Dictionary>::wrap_under_create_rule(result as CFArrayRef) };
+        Ok(items
+            .iter()
+            .map(|item| unsafe { Dictionary::wrap_under_get_rule(item.as_concrete_TypeRef()) })
+            .collect())
+    })
+}
+
+/// Get the token ID from a dictionary of item attributes
+fn token_id(item: &Dictionary) -> Option<AttrTokenId> {
+    item.find(AttrKind::TokenId).map(|id| {
+        AttrTokenId::from(&unsafe {
+            CFString::wrap_under_get_rule(id.as_CFTypeRef() as CFStringRef)
+        })
+    })
+}
@@ -0,0 +1,223 @@
+//! Watchdog which flags keychain operations taking longer than expected.
+//!
+//! Security framework calls can block for a long time (or indefinitely),
+//! e.g. when waiting on a prompt the user never sees or on a locked keychain.
+//! A `Watchdog` is `Middleware` which reports such operations to a handler
+//! as `SlowOperation` warnings, both while they're still running and once
+//! they complete, so they can be forwarded to whatever logging or tracing
+//! the application uses.
+
+use super::hooks::{Middleware, OperationDescriptor};
+use crate::error::Error;
+use std::{
+    fmt::{self, Debug, Display},
+    sync::{Arc, Mutex, Weak},
+    thread::{self, ThreadId},
+    time::{Duration, Instant},
+};
+
+/// Shortest interval at which in-flight operations are checked
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Longest interval at which in-flight operations are checked
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Warning about a keychain operation which exceeded the threshold duration
+/// of a `Watchdog`.
+#[derive(Clone, Debug)]
+pub struct SlowOperation {
+    /// Operation which is (or was) slow
+    pub descriptor: OperationDescriptor,
+
+    /// Time the operation has been running for, or took if it completed
+    pub elapsed: Duration,
+
+    /// Threshold duration of the watchdog which flagged the operation
+    pub threshold: Duration,
+
+    /// Has the operation completed? If not, it may be hung.
+    pub completed: bool,
+
+    /// ID of the thread performing the operation
+    pub thread_id: ThreadId,
+
+    /// Name of the thread performing the operation (if it has one)
+    pub thread_name: Option<String>,
+}
+
+impl Display for SlowOperation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "slow keychain operation: {:?}", self.descriptor.kind)?;
+
+        if let Some(class) = self.descriptor.class {
+            write!(f, " ({:?})", class)?;
+        }
+
+        write!(
+            f,
+            " {} {:?} (threshold: {:?}) on thread {}",
+            if self.completed {
+                "took"
+            } else {
+                "still running after"
+            },
+            self.elapsed,
+            self.threshold,
+            self.thread_name
+                .as_ref()
+                .map_or("<unnamed>", String::as_str)
+        )
+    }
+}
+
+/// `Middleware` which reports keychain operations running for longer than
+/// a threshold duration to a handler.
+///
+/// Operations still in flight are checked from a background thread, which
+/// exits once the watchdog is dropped (i.e. its `MiddlewareRegistration`).
+/// Operations which exceed the threshold are reported once while running
+/// and once more when they complete.
+pub struct Watchdog {
+    state: Arc<State>,
+}
+
+impl Watchdog {
+    /// Create a watchdog which invokes the given handler for operations
+    /// running for longer than the given threshold. Register it with
+    /// `hooks::register_middleware` to start watching operations.
+    pub fn new<F>(threshold: Duration, handler: F) -> Self
+    where
+        F: Fn(&SlowOperation) + Send + Sync + 'static,
+    {
+        let state = Arc::new(State {
+            threshold,
+            handler: Box::new(handler),
+            in_flight: Mutex::new(vec![]),
+        });
+
+        let poll_interval = (threshold / 4)
+            .max(MIN_POLL_INTERVAL)
+            .min(MAX_POLL_INTERVAL);
+        let weak_state = Arc::downgrade(&state);
+
+        thread::Builder::new()
+            .name("keychain-watchdog".to_owned())
+            .spawn(move || monitor(&weak_state, poll_interval))
+            .expect("couldn't spawn keychain watchdog thread");
+
+        Self { state }
+    }
+
+    /// Get the threshold duration of this watchdog
+    pub fn threshold(&self) -> Duration {
+        self.state.threshold
+    }
+}
+
+impl Debug for Watchdog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Watchdog {{ threshold: {:?} }}", self.state.threshold)
+    }
+}
+
+impl Middleware for Watchdog {
+    fn before(&self, descriptor: &OperationDescriptor) -> Result<(), Error> {
+        let thread = thread::current();
+
+        self.state.in_flight.lock().unwrap().push(InFlight {
+            descriptor: *descriptor,
+            started: Instant::now(),
+            thread_id: thread.id(),
+            thread_name: thread.name().map(str::to_owned),
+            reported: false,
+        });
+
+        Ok(())
+    }
+
+    fn after(&self, _descriptor: &OperationDescriptor, _error: Option<&Error>) {
+        let thread_id = thread::current().id();
+
+        // Operations nest, so the innermost one on this thread just finished
+        let operation = {
+            let mut in_flight = self.state.in_flight.lock().unwrap();
+
+            match in_flight.iter().rposition(|op| op.thread_id == thread_id) {
+                Some(index) => in_flight.remove(index),
+                None => return,
+            }
+        };
+
+        let elapsed = operation.started.elapsed();
+
+        if elapsed > self.state.threshold {
+            (self.state.handler)(&operation.warning(elapsed, self.state.threshold, true));
+        }
+    }
+}
+
+/// State shared between a `Watchdog` and its monitor thread
+struct State {
+    threshold: Duration,
+    handler: Box<dyn Fn(&SlowOperation) + Send + Sync>,
+    in_flight: Mutex<Vec<InFlight>>,
+}
+
+/// Operation which is currently being performed
+struct InFlight {
+    descriptor: OperationDescriptor,
+    started: Instant,
+    thread_id: ThreadId,
+    thread_name: Option<String>,
+    reported: bool,
+}
+
+impl InFlight {
+    fn warning(&self, elapsed: Duration, threshold: Duration, completed: bool) -> SlowOperation {
+        SlowOperation {
+            descriptor: self.descriptor,
+            elapsed,
+            threshold,
+            completed,
+            thread_id: self.thread_id,
+            thread_name: self.thread_name.clone(),
+        }
+    }
+}
+
+/// Periodically report in-flight operations which have exceeded the
+/// threshold, until the watchdog is dropped
+fn monitor(state: &Weak<State>, poll_interval: Duration) {
+    loop {
+        thread::sleep(poll_interval);
+
+        let state = match state.upgrade() {
+            Some(state) => state,
+            None => return,
+        };
+
+        // Collect the warnings first so the handler runs without the lock held
+        let warnings: Vec<SlowOperation> = {
+            let mut in_flight = state.in_flight.lock().unwrap();
+
+            in_flight
+                .iter_mut()
+                .filter(|op| !op.reported)
+                .filter_map(|op| {
+                    let elapsed = op.started.elapsed();
+
+                    if elapsed > state.threshold {
+                        op.reported = true;
+                        Some(op.warning(elapsed, state.threshold, false))
+                    } else {
+                        None
+                    }
+                })
+                .collect()
+        };
+
+        for warning in &warnings {
+            (state.handler)(warning);
+        }
+    }
+}
@@ -13,7 +13,9 @@
 //!
 //! The Keychain Service API requires signed code to access much of its
 //! functionality. Accessing many APIs from an unsigned app will return
-//! an error with a kind of `ErrorKind::MissingEntitlement`.
+//! an error with a kind of `ErrorKind::MissingEntitlement`, or of
+//! `ErrorKind::MissingKeychainEntitlement` when the entitlements of the
+//! process show which access group or data protection requirement is unmet.
 //!
 //! Follow the instructions here to create a self-signed code signing certificate:
 //! <https://developer.apple.com/library/archive/documentation/Security/Conceptual/CodeSigningGuide/Procedures/Procedures.html>
@@ -49,6 +51,7 @@ extern crate core_foundation;
 mod access;
 mod attr;
 mod ciphertext;
+mod date;
 mod dictionary;
 mod error;
 mod ffi;
@@ -58,6 +61,7 @@ mod signature;
 pub use crate::access::*;
 pub use crate::attr::*;
 pub use crate::ciphertext::*;
+pub use crate::date::DateRange;
 pub use crate::error::*;
 pub use crate::key::*;
 pub use crate::keychain::*;
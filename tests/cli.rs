@@ -0,0 +1,90 @@
+#![cfg(feature = "cli")]
+
+//! Tests for the command line parsing of `keychain-cli`.
+//!
+//! These only exercise invalid command lines, which are rejected before
+//! the keychain is accessed, so they're safe to run in CI.
+
+use std::process::{Command, Output};
+
+/// Run `keychain-cli` with the given arguments
+fn keychain_cli(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_keychain-cli"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+/// Assert the given command line is rejected as invalid with the given
+/// error message (followed by usage information)
+fn assert_usage_error(args: &[&str], message: &str) {
+    let output = keychain_cli(args);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(2), "stderr: {}", stderr);
+    assert!(
+        stderr.starts_with(&format!("error: {}\n", message)),
+        "stderr: {}",
+        stderr
+    );
+    assert!(stderr.contains("usage: keychain-cli <command> [options]"));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn missing_command() {
+    assert_usage_error(&[], "no command given");
+}
+
+#[test]
+fn unknown_command() {
+    assert_usage_error(&["frobnicate"], "unknown command: frobnicate");
+}
+
+#[test]
+fn unexpected_argument() {
+    assert_usage_error(
+        &["list", "generic_password"],
+        "unexpected argument: generic_password",
+    );
+}
+
+#[test]
+fn missing_option_value() {
+    assert_usage_error(&["list", "--class"], "missing value for --class");
+}
+
+#[test]
+fn missing_required_option() {
+    assert_usage_error(&["export", "--private"], "missing required option --tag");
+    assert_usage_error(&["sign"], "missing required option --tag");
+}
+
+#[test]
+fn flags_take_no_value() {
+    // `--secure-enclave` is a flag, so `--tag` is parsed as the next option
+    assert_usage_error(
+        &["generate", "--secure-enclave", "--tag"],
+        "missing value for --tag",
+    );
+}
+
+#[test]
+fn unknown_item_class() {
+    assert_usage_error(
+        &["list", "--class", "key", "--class", "secret"],
+        "unknown item class: secret",
+    );
+}
+
+#[test]
+fn invalid_key_parameters() {
+    assert_usage_error(
+        &["generate", "--tag", "example", "--type", "dsa"],
+        "unknown key type: dsa",
+    );
+    assert_usage_error(
+        &["generate", "--tag", "example", "--size", "large"],
+        "invalid key size: large",
+    );
+}
@@ -174,3 +174,1364 @@ fn encrypt_and_decrypt_rsa_keys() {
     assert!(res.is_err());
 }
 
+#[test]
+fn date_range_bounds() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let start = UNIX_EPOCH + Duration::from_secs(1_000);
+    let end = UNIX_EPOCH + Duration::from_secs(2_000);
+    let range = DateRange::between(start, end);
+
+    assert!(range.contains(start));
+    assert!(range.contains(end));
+    assert!(!range.contains(start - Duration::from_secs(1)));
+    assert!(!range.contains(end + Duration::from_secs(1)));
+    assert!(DateRange::new().contains(UNIX_EPOCH));
+    assert!(DateRange::new()
+        .older_than(Duration::from_secs(60))
+        .contains(start));
+}
+
+#[test]
+fn server_attr_validation() {
+    assert!(AttrServer::new("example.com").is_ok());
+    assert!(AttrServer::new("192.168.0.1").is_ok());
+    assert!(AttrServer::new("[::1]").is_ok());
+    assert!(AttrServer::new("").is_err());
+    assert!(AttrServer::new("https://example.com").is_err());
+    assert!(AttrServer::new("bad host").is_err());
+    assert!(AttrServer::new("-example.com").is_err());
+
+    assert_eq!(
+        AttrProtocol::from_scheme("HTTPS"),
+        Some(AttrProtocol::HTTPS)
+    );
+    assert_eq!(AttrProtocol::from_scheme("gopher"), None);
+}
+
+#[test]
+fn item_tags_encoding() {
+    use keychain_services::keychain::item::ItemTags;
+
+    let tags = ItemTags::new()
+        .with("env:prod")
+        .unwrap()
+        .with("team:ops")
+        .unwrap();
+
+    assert_eq!(tags.to_string(), "tags:env:prod,team:ops");
+    assert_eq!("tags:env:prod,team:ops".parse::<ItemTags>().unwrap(), tags);
+    assert!(tags.contains("env:prod"));
+    assert!(!tags.contains("env:dev"));
+    assert!("just a comment".parse::<ItemTags>().is_err());
+    assert!(ItemTags::new().with("bad tag").is_err());
+    assert!(ItemTags::new().with("a,b").is_err());
+}
+
+#[test]
+fn item_tags_replace_comment() {
+    use keychain_services::keychain::item::{
+        GenericPasswordParams, InternetPasswordParams, ItemTags,
+    };
+
+    let tags = ItemTags::new().with("env:prod").unwrap();
+
+    let debug = format!(
+        "{:?}",
+        GenericPasswordParams::new("example.com", "example")
+            .comment("old comment")
+            .tags(&tags)
+    );
+    assert!(debug.contains("tags:env:prod"));
+    assert!(!debug.contains("old comment"));
+
+    let debug = format!(
+        "{:?}",
+        InternetPasswordParams::new(AttrServer::new("example.com").unwrap())
+            .tags(&tags)
+            .comment("new comment")
+    );
+    assert!(debug.contains("new comment"));
+    assert!(!debug.contains("tags:env:prod"));
+}
+
+#[test]
+fn item_changes_replace_values() {
+    use keychain_services::keychain::item::{ItemChanges, ItemTags};
+
+    let tags = ItemTags::new().with("env:prod").unwrap();
+    let debug = format!(
+        "{:?}",
+        ItemChanges::new()
+            .label("old label")
+            .comment("old comment")
+            .label("new label")
+            .tags(&tags)
+    );
+
+    assert!(debug.contains("new label"));
+    assert!(!debug.contains("old label"));
+    assert!(debug.contains("tags:env:prod"));
+    assert!(!debug.contains("old comment"));
+}
+
+#[test]
+fn item_tags_expiry() {
+    use keychain_services::keychain::item::ItemTags;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let expiry = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let mut tags = ItemTags::new()
+        .with("env:prod")
+        .unwrap()
+        .with_expiry(expiry);
+
+    assert_eq!(tags.to_string(), "tags:env:prod,expires:1700000000");
+    assert_eq!(
+        tags.to_string().parse::<ItemTags>().unwrap().expiry(),
+        Some(expiry)
+    );
+
+    tags.set_expiry(Some(expiry + Duration::from_secs(60)));
+    assert_eq!(tags.expiry(), Some(expiry + Duration::from_secs(60)));
+
+    tags.set_expiry(None);
+    assert_eq!(tags.expiry(), None);
+    assert!(tags.contains("env:prod"));
+
+    // Out of range expiry times are ignored
+    let tags = "tags:expires:18446744073709551615"
+        .parse::<ItemTags>()
+        .unwrap();
+    assert_eq!(tags.expiry(), None);
+}
+
+#[test]
+fn key_usage_stats_encoding() {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let stats = KeyUsageStats {
+        sign_count: 3,
+        decrypt_count: 1,
+        last_used: Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000)),
+    };
+
+    assert_eq!(stats.to_string(), "sign=3,decrypt=1,last_used=1700000000");
+    assert_eq!(stats.to_string().parse::<KeyUsageStats>().unwrap(), stats);
+    assert_eq!(
+        "sign=2,future=7"
+            .parse::<KeyUsageStats>()
+            .unwrap()
+            .sign_count,
+        2
+    );
+    assert!("sign=lots".parse::<KeyUsageStats>().is_err());
+    assert_eq!(
+        "last_used=18446744073709551615"
+            .parse::<KeyUsageStats>()
+            .unwrap()
+            .last_used,
+        None
+    );
+}
+
+#[test]
+fn prompt_template_rendering() {
+    use keychain_services::keychain::prompt::{PromptContext, PromptTemplate};
+
+    let template = PromptTemplate::new("{reason}: {operation} with \"{label}\" {{{unknown}}}");
+
+    let context = PromptContext {
+        operation: KeyOperation::Sign,
+        label: Some("Deploy Key"),
+        reason: Some("Approve release"),
+    };
+
+    assert_eq!(
+        template.render(&context),
+        "Approve release: sign with \"Deploy Key\" {{unknown}}"
+    );
+
+    let context = PromptContext {
+        operation: KeyOperation::Decrypt,
+        label: None,
+        reason: None,
+    };
+
+    assert_eq!(
+        template.render(&context),
+        ": decrypt with \"key\" {{unknown}}"
+    );
+}
+
+#[test]
+fn localizer_translates_prompts_and_errors() {
+    use keychain_services::keychain::{
+        localization::{set_localizer, Localizer},
+        prompt::{render_prompt, PromptContext, PromptTemplate},
+    };
+    use std::sync::Arc;
+
+    // Only translates text no other test produces, as localizers are global
+    struct German;
+
+    impl Localizer for German {
+        fn prompt_template(&self, operation: KeyOperation) -> Option<PromptTemplate> {
+            match operation {
+                KeyOperation::KeyExchange => Some(PromptTemplate::new("{reason}: {operation}")),
+                _ => None,
+            }
+        }
+
+        fn operation_name(&self, operation: KeyOperation) -> Option<String> {
+            match operation {
+                KeyOperation::KeyExchange => Some("Schlüsselaustausch".to_owned()),
+                _ => None,
+            }
+        }
+
+        fn error_kind(&self, kind: &ErrorKind) -> Option<String> {
+            match kind {
+                ErrorKind::UserCanceled => Some("vom Benutzer abgebrochen".to_owned()),
+                _ => None,
+            }
+        }
+    }
+
+    let context = PromptContext {
+        operation: KeyOperation::KeyExchange,
+        label: None,
+        reason: Some("Verbindung herstellen"),
+    };
+
+    assert!(set_localizer(Some(Arc::new(German))).is_none());
+
+    let prompt = render_prompt(&context);
+    let error = Error::new(ErrorKind::UserCanceled, "Touch ID").to_string();
+
+    assert!(set_localizer(None).is_some());
+
+    assert_eq!(prompt, "Verbindung herstellen: Schlüsselaustausch");
+    assert_eq!(error, "Touch ID (vom Benutzer abgebrochen)");
+}
+
+#[test]
+fn inventory_json() {
+    use keychain_services::keychain::item::{Inventory, InventoryEntry};
+
+    let inventory = Inventory {
+        schema_version: 1,
+        items: vec![InventoryEntry {
+            class: "generic_password".to_owned(),
+            service: Some("example.com".to_owned()),
+            comment: Some("say \"hi\"".to_owned()),
+            created_at: Some(1_000),
+            ..Default::default()
+        }],
+    };
+
+    assert_eq!(
+        inventory.to_json(),
+        "{\"schema_version\":1,\"items\":[{\"class\":\"generic_password\",\
+         \"service\":\"example.com\",\"comment\":\"say \\\"hi\\\"\",\"created_at\":1000}]}"
+    );
+}
+
+#[test]
+fn audit_report_json() {
+    use keychain_services::{
+        audit::{AuditEntry, AuditReport},
+        keychain::item::InventoryEntry,
+    };
+
+    let report = AuditReport {
+        schema_version: 1,
+        generated_at: 2_000,
+        items: vec![AuditEntry {
+            item: InventoryEntry {
+                class: "key".to_owned(),
+                key_class: Some("private".to_owned()),
+                ..Default::default()
+            },
+            accessible: Some("when_unlocked_this_device_only".to_owned()),
+            token_id: Some("secure_enclave".to_owned()),
+            extractable: Some(false),
+            ..Default::default()
+        }],
+    };
+
+    assert_eq!(
+        report.to_json(),
+        "{\"schema_version\":1,\"generated_at\":2000,\"items\":[{\"class\":\"key\",\
+         \"key_class\":\"private\",\"accessible\":\"when_unlocked_this_device_only\",\
+         \"token_id\":\"secure_enclave\",\"extractable\":false,\"synchronizable\":false}]}"
+    );
+}
+
+#[test]
+fn access_control_info_from_description() {
+    let info = AccessControlInfo::from_description(
+        "<SecAccessControlRef: aku;od(cbio(pbioc(00)pbioh(00)));odel(true);oe(true);osgn(true)>",
+    );
+
+    assert_eq!(
+        info.protection,
+        Some(AttrAccessible::WhenUnlockedThisDeviceOnly)
+    );
+    assert_eq!(info.constraints, &[AccessConstraint::BiometryCurrentSet]);
+    assert_eq!(info.options, &[AccessOption::PrivateKeyUsage]);
+
+    let info = AccessControlInfo::from_description(
+        "<SecAccessControlRef: ak;od(cpo(DeviceOwnerAuthentication));odel(true);oe(true)>",
+    );
+
+    assert_eq!(info.protection, Some(AttrAccessible::WhenUnlocked));
+    assert!(info.requires(AccessConstraint::UserPresence));
+    assert!(info.options.is_empty());
+
+    let acl = AccessControl::create_with_flags(
+        AttrAccessible::AfterFirstUnlockThisDeviceOnly,
+        AccessControlFlags::new(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        acl.info().protection,
+        Some(AttrAccessible::AfterFirstUnlockThisDeviceOnly)
+    );
+}
+
+#[test]
+fn diagnose_secure_enclave_key_size() {
+    let params =
+        KeyPairGenerateParams::new(AttrKeyType::Rsa, 2048).token_id(AttrTokenId::SecureEnclave);
+
+    match KeyPair::create(params).unwrap_err().kind() {
+        ErrorKind::KeySizeNotAllowed => (),
+        other => panic!("unexpected error kind: {:?}", other),
+    }
+}
+
+#[test]
+fn move_to_access_group_rejects_keys() {
+    use keychain_services::keychain::item::{move_to_access_group, Class, Query};
+
+    let result = move_to_access_group(
+        Class::Key,
+        Query::new(),
+        "com.example.old",
+        "com.example.new",
+    );
+
+    match result.unwrap_err().kind() {
+        ErrorKind::Param => (),
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn key_spec_validation() {
+    let spec = KeySpec::new(AttrKeyType::EcSecPrimeRandom, 384).unwrap();
+    assert_eq!(spec, KeySpec::EcP384);
+    assert_eq!(spec.to_string(), "EC P-384");
+    assert_eq!(KeySpec::Rsa2048.to_string(), "RSA-2048");
+
+    for &(key_type, size) in &[
+        (AttrKeyType::EcSecPrimeRandom, 2048),
+        (AttrKeyType::Rsa, 256),
+        (AttrKeyType::Aes, 256),
+    ] {
+        match KeySpec::new(key_type, size).unwrap_err().kind() {
+            ErrorKind::KeySizeNotAllowed => (),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    let keypair = KeyPair::create(KeyPairGenerateParams::from_spec(KeySpec::EcP384)).unwrap();
+    assert_eq!(keypair.private_key.spec(), Some(KeySpec::EcP384));
+
+    match KeyPairGenerateParams::rsa(1000).unwrap_err().kind() {
+        ErrorKind::KeySizeNotAllowed => (),
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    let invalid = KeyPairGenerateParams::new(AttrKeyType::EcSecPrimeRandom, 1024);
+
+    match KeyPair::create(invalid).unwrap_err().kind() {
+        ErrorKind::KeySizeNotAllowed => (),
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn ecies_variants_encrypt_and_decrypt() {
+    assert_eq!(KeyAlgorithm::ECIES.len(), 18);
+    assert!(!KeyAlgorithm::ECDSASignatureMessageX962SHA256.is_ecies());
+
+    let keypair = KeyPair::create(KeyPairGenerateParams::from_spec(KeySpec::EcP256)).unwrap();
+
+    for &alg in KeyAlgorithm::ECIES {
+        assert!(alg.is_ecies());
+
+        if !alg.is_available() {
+            continue;
+        }
+
+        let ciphertext = keypair.public_key.encrypt(alg, TEST_MESSAGE).unwrap();
+        let plaintext = keypair.private_key.decrypt(ciphertext).unwrap();
+        assert_eq!(plaintext, TEST_MESSAGE);
+    }
+
+    assert!(KeyAlgorithm::ECIESEncryptionStandardX963SHA256AESGCM.is_available());
+}
+
+#[test]
+fn key_capabilities() {
+    let params = KeyPairGenerateParams::from_spec(KeySpec::EcP256).can_decrypt(false);
+    let keypair = KeyPair::create(params).unwrap();
+
+    let capabilities = keypair.private_key.capabilities();
+    assert!(capabilities.can_sign);
+    assert!(!capabilities.can_decrypt);
+    assert!(keypair.public_key.capabilities().can_verify);
+}
+
+#[test]
+fn ecdh_key_exchange() {
+    let alice = KeyPair::create(KeyPairGenerateParams::from_spec(KeySpec::EcP256)).unwrap();
+    let bob = KeyPair::create(KeyPairGenerateParams::from_spec(KeySpec::EcP256)).unwrap();
+
+    let params = KeyExchangeParams::new(32).shared_info(b"keychain-services test");
+    let alg = KeyExchangeAlgorithm::StandardX963SHA256;
+    assert!(alg.uses_kdf());
+
+    let alice_secret = alice
+        .private_key
+        .exchange(alg, &bob.public_key, &params)
+        .unwrap();
+    let bob_secret = bob
+        .private_key
+        .exchange(alg, &alice.public_key, &params)
+        .unwrap();
+
+    assert_eq!(alice_secret.len(), 32);
+    assert_eq!(alice_secret.as_bytes(), bob_secret.as_bytes());
+    assert_eq!(format!("{:?}", alice_secret), "SharedSecret([REDACTED])");
+
+    let other_info = KeyExchangeParams::new(32).shared_info(b"other");
+    let other_secret = alice
+        .private_key
+        .exchange(alg, &bob.public_key, &other_info)
+        .unwrap();
+    assert_ne!(alice_secret.as_bytes(), other_secret.as_bytes());
+
+    let mut other_secret = other_secret;
+    zeroize::Zeroize::zeroize(&mut other_secret);
+    assert!(other_secret.is_empty());
+}
+
+#[test]
+fn create_derives_public_key() {
+    let keypair = KeyPair::create(KeyPairGenerateParams::from_spec(KeySpec::EcP256)).unwrap();
+    let public_key = keypair.private_key.public().unwrap();
+
+    assert_eq!(public_key.class(), Some(AttrKeyClass::Public));
+    assert_eq!(
+        public_key.to_external_representation().unwrap(),
+        keypair.public_key.to_external_representation().unwrap()
+    );
+}
+
+#[cfg(feature = "legacy-keychain")]
+#[test]
+fn symmetric_key_params_replace_values() {
+    let debug = format!(
+        "{:?}",
+        SymmetricKeyGenerateParams::aes(256)
+            .label("old label")
+            .permanent(true)
+            .label("new label")
+            .permanent(false)
+    );
+
+    assert!(debug.contains("new label"));
+    assert!(!debug.contains("old label"));
+    assert_eq!(debug.matches("\"perm\"").count(), 1);
+}
+
+#[cfg(feature = "legacy-keychain")]
+#[test]
+fn symmetric_key_size_validation() {
+    let params = SymmetricKeyGenerateParams::aes(512).permanent(false);
+
+    match Key::generate_symmetric(params).unwrap_err().kind() {
+        ErrorKind::KeySizeNotAllowed => (),
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn wrap_and_unwrap_keys() {
+    let kek = KeyPair::create(KeyPairGenerateParams::from_spec(KeySpec::EcP256)).unwrap();
+    let data_key = KeyPair::create(KeyPairGenerateParams::from_spec(KeySpec::EcP256)).unwrap();
+
+    let wrapped = kek
+        .public_key
+        .wrap(
+            &data_key.private_key,
+            KeyAlgorithm::ECIESEncryptionStandardVariableIVX963SHA256AESGCM,
+        )
+        .unwrap();
+    assert_eq!(wrapped.key_type(), AttrKeyType::EcSecPrimeRandom);
+    assert_eq!(wrapped.key_class(), AttrKeyClass::Private);
+
+    let unwrapped = kek.private_key.unwrap_key(wrapped).unwrap();
+    assert_eq!(
+        unwrapped.to_external_representation().unwrap(),
+        data_key.private_key.to_external_representation().unwrap()
+    );
+
+    let result = kek.public_key.wrap(
+        &data_key.private_key,
+        KeyAlgorithm::ECDSASignatureMessageX962SHA256,
+    );
+
+    match result.unwrap_err().kind() {
+        ErrorKind::Param => (),
+        other => panic!("expected param error, got {:?}", other),
+    }
+}
+
+#[cfg(feature = "kdf")]
+#[test]
+fn ecdh_hkdf_expansion() {
+    let alice = KeyPair::create(KeyPairGenerateParams::from_spec(KeySpec::EcP256)).unwrap();
+    let bob = KeyPair::create(KeyPairGenerateParams::from_spec(KeySpec::EcP256)).unwrap();
+
+    let params = KeyExchangeParams::new(0);
+    let alg = KeyExchangeAlgorithm::Standard;
+
+    let alice_secret: SharedSecret = alice
+        .private_key
+        .exchange(alg, &bob.public_key, &params)
+        .unwrap();
+    let bob_secret = bob
+        .private_key
+        .exchange(alg, &alice.public_key, &params)
+        .unwrap();
+
+    let alice_key = alice_secret.expand_hkdf(b"salt", b"test", 32).unwrap();
+    let bob_key = bob_secret.expand_hkdf(b"salt", b"test", 32).unwrap();
+    assert_eq!(alice_key.len(), 32);
+    assert_eq!(*alice_key, *bob_key);
+    assert_ne!(
+        *alice_key,
+        *alice_secret.expand_hkdf(b"salt", b"other", 32).unwrap()
+    );
+
+    let err = alice_secret
+        .expand_hkdf(&[], &[], 255 * 32 + 1)
+        .unwrap_err();
+    match err.kind() {
+        ErrorKind::Param => (),
+        other => panic!("expected param error, got {:?}", other),
+    }
+}
+
+#[test]
+fn purpose_restricted_key_generation() {
+    let params = KeyPairGenerateParams::for_purpose(KeySpec::EcP256, KeyPurpose::SigningOnly)
+        .unwrap()
+        .permanent(false);
+
+    let keypair = KeyPair::create(params).unwrap();
+    assert_eq!(
+        keypair.private_key.capabilities(),
+        KeyPurpose::SigningOnly.capabilities()
+    );
+
+    let signature = keypair
+        .private_key
+        .sign(KeyAlgorithm::ECDSASignatureMessageX962SHA256, TEST_MESSAGE)
+        .unwrap();
+    assert!(keypair.public_key.verify(TEST_MESSAGE, &signature).unwrap());
+
+    let result = KeyPairGenerateParams::for_purpose(KeySpec::Rsa2048, KeyPurpose::AgreementOnly);
+
+    match result.unwrap_err().kind() {
+        ErrorKind::Param => (),
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn missing_entitlement_requirements() {
+    use keychain_services::entitlements::*;
+
+    let unsigned = Entitlements::default();
+    assert_eq!(
+        unsigned.missing_requirement(None, true),
+        Some(EntitlementRequirement::CodeSignature)
+    );
+
+    let entitlements = Entitlements {
+        application_identifier: Some("ABCDE12345.com.example.app".to_owned()),
+        keychain_access_groups: vec!["ABCDE12345.com.example.shared.*".to_owned()],
+        application_groups: vec![],
+    };
+
+    assert!(entitlements.allows_access_group("ABCDE12345.com.example.app"));
+    assert!(entitlements.allows_access_group("ABCDE12345.com.example.shared.tokens"));
+    assert_eq!(entitlements.missing_requirement(None, true), None);
+    assert_eq!(
+        entitlements.missing_requirement(Some("ABCDE12345.com.example.shared.tokens"), true),
+        None
+    );
+
+    let requirement = entitlements
+        .missing_requirement(Some("ZYXWV98765.com.other.app"), false)
+        .unwrap();
+    assert_eq!(
+        requirement,
+        EntitlementRequirement::AccessGroup("ZYXWV98765.com.other.app".to_owned())
+    );
+
+    let groups_only = Entitlements {
+        application_groups: vec!["group.com.example".to_owned()],
+        ..Default::default()
+    };
+    assert_eq!(
+        groups_only.missing_requirement(Some("group.com.example"), true),
+        Some(EntitlementRequirement::DataProtectionKeychain)
+    );
+
+    let kind = ErrorKind::MissingKeychainEntitlement { requirement };
+    assert!(kind.to_string().contains("ZYXWV98765.com.other.app"));
+}
+
+#[test]
+fn empty_item_list_matches_nothing() {
+    use keychain_services::keychain::item::*;
+
+    let query = Query::new().item_list::<Key>(&[]);
+
+    for result in &[
+        delete_matching(Class::Key, &query),
+        update_matching(Class::Key, &query, &ItemChanges::new().label("renamed")),
+    ] {
+        match result.as_ref().unwrap_err().kind() {
+            ErrorKind::ItemNotFound => (),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    // Reported deletion distinguishes "nothing matched" from failures
+    let report = delete_matching_with_report(Class::Key, &query).unwrap();
+    assert_eq!(report.matched(), 0);
+
+    let query = Query::new().application_tag("rs.keychain-services.test.core.nonexistent");
+    let report = delete_matching_with_report(Class::Key, query).unwrap();
+    assert_eq!(report.matched(), 0);
+    assert!(report.is_complete());
+}
+
+#[test]
+fn item_query_builder() {
+    use keychain_services::keychain::item::*;
+
+    let query = ItemQuery::new(Class::GenericPassword)
+        .service("rs.keychain-services.test.core.nonexistent")
+        .account("nobody")
+        .synchronizable_any()
+        .match_limit(MatchLimit::Number(2))
+        .return_persistent_refs(true);
+
+    assert_eq!(query.class(), Class::GenericPassword);
+    assert!(query.find().unwrap().is_empty());
+    assert!(query.find_with_data().unwrap().is_none());
+}
+
+#[test]
+fn debug_output_redacts_secrets() {
+    use keychain_services::keychain::item::*;
+
+    let changes = ItemChanges::new().label("visible label").data(b"hunter2");
+    let debug = format!("{:?}", changes);
+
+    assert!(debug.contains("visible label"));
+    assert!(debug.contains("[REDACTED]"));
+    assert!(!debug.contains("hunter2"));
+
+    let query = Query::new()
+        .service("visible service")
+        .use_operation_prompt("Unlock the vault of alice@example.com");
+    let debug = format!("{:?}", query);
+
+    assert!(debug.contains("visible service"));
+    assert!(!debug.contains("alice@example.com"));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn serialized_queries_round_trip() {
+    use core_foundation::{base::TCFType, number::CFNumber, string::CFString};
+    use keychain_services::keychain::item::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let query = Query::new()
+        .service("example.com")
+        .synchronizable(true)
+        .match_limit(MatchLimit::Number(5))
+        .creation_date(UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+        .raw_attribute(
+            CFString::new("example-float"),
+            CFNumber::from(1.5f64).as_CFType(),
+        );
+
+    let json = serde_json::to_string(&query).unwrap();
+    assert!(json.contains("{\"$date\":1600000000.0}"));
+    assert!(json.contains("1.5"));
+
+    let decoded: Query = serde_json::from_str(&json).unwrap();
+    assert_eq!(serde_json::to_string(&decoded).unwrap(), json);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn unsupported_values_fail_to_serialize() {
+    let acl =
+        AccessControl::create_with_flags(AttrAccessible::WhenUnlocked, Default::default()).unwrap();
+    let params = KeyPairGenerateParams::from_spec(KeySpec::EcP256).access_control(&acl);
+
+    let err = serde_json::to_string(&params).unwrap_err();
+    assert!(err.to_string().contains("can't be serialized"));
+}
+
+#[test]
+fn specialized_queries_override_base_values() {
+    use keychain_services::keychain::item::*;
+
+    let base = Query::new()
+        .service("base service")
+        .account("base account")
+        .label("base label");
+    let specialized = base
+        .clone()
+        .account("override account")
+        .label("override label");
+
+    let debug = format!("{:?}", specialized);
+    assert!(debug.contains("base service"));
+    assert!(debug.contains("override account"));
+    assert!(!debug.contains("base account"));
+    assert!(debug.contains("override label"));
+    assert!(!debug.contains("base label"));
+
+    // The base query is unaffected
+    assert!(format!("{:?}", base).contains("base account"));
+}
+
+#[test]
+fn raw_attributes_replace_wrapped_values() {
+    use core_foundation::{base::TCFType, string::CFString};
+    use keychain_services::keychain::item::*;
+
+    // `kSecAttrLabel`
+    let query = Query::new().label("wrapped label").raw_attribute(
+        CFString::new("labl"),
+        CFString::new("raw label").as_CFType(),
+    );
+    let debug = format!("{:?}", query);
+
+    assert!(debug.contains("raw label"));
+    assert!(!debug.contains("wrapped label"));
+}
+
+#[test]
+fn import_rejects_non_archives() {
+    use keychain_services::keychain::item::{import_archive, ItemDestination};
+
+    let err = import_archive(
+        b"not an archive",
+        "passphrase",
+        ItemDestination::AccessGroup("com.example.archive"),
+    )
+    .unwrap_err();
+
+    match err.kind() {
+        ErrorKind::Decode => (),
+        other => panic!("expected decode error, got {:?}", other),
+    }
+}
+
+#[test]
+fn import_rejects_unbounded_pbkdf2_rounds() {
+    use keychain_services::keychain::item::{import_archive, ItemDestination};
+
+    for &rounds in &[0u32, 1, u32::max_value()] {
+        // Magic, version, rounds, salt, IV, and a (bogus) MAC
+        let mut archive = b"KSARCHIV\x01".to_vec();
+        archive.extend_from_slice(&rounds.to_be_bytes());
+        archive.extend_from_slice(&[0u8; 16 + 16 + 32]);
+
+        let err = import_archive(
+            &archive,
+            "passphrase",
+            ItemDestination::AccessGroup("com.example.archive"),
+        )
+        .unwrap_err();
+
+        match err.kind() {
+            ErrorKind::Decode => (),
+            other => panic!("expected decode error, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn certificates_valid_on_date() {
+    use keychain_services::keychain::item::*;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let label = "rs.keychain-services.test.core.nonexistent";
+
+    // Nothing has this label, but both forms of the date must be accepted
+    for query in &[
+        Query::new().label(label).valid_now(),
+        Query::new()
+            .label(label)
+            .valid_on_date(UNIX_EPOCH + Duration::from_secs(1)),
+    ] {
+        assert!(ItemAttributes::find_all(Class::Certificate, query)
+            .unwrap()
+            .is_empty());
+    }
+}
+
+#[test]
+fn certificate_match_options() {
+    use keychain_services::keychain::item::*;
+
+    let query = Query::new()
+        .label("rs.keychain-services.test.core.nonexistent")
+        .issuers(&[b"not a distinguished name"])
+        .email_address_if_present("nobody@example.com")
+        .subject_contains("nobody");
+
+    assert!(ItemAttributes::find_all(Class::Certificate, &query)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn paging_through_query_results() {
+    use keychain_services::keychain::item::*;
+
+    let err = ItemAttributes::pages(Class::Key, Query::new(), 0).unwrap_err();
+    match err.kind() {
+        ErrorKind::Param => (),
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    let query = Query::new()
+        .application_tag("rs.keychain-services.test.core.nonexistent")
+        .match_limit(MatchLimit::Number(10));
+    let mut pages = ItemAttributes::pages(Class::Key, query, 5).unwrap();
+    assert_eq!(pages.remaining(), 0);
+    assert!(pages.next().is_none());
+}
+
+#[test]
+fn generic_attribute_bytes() {
+    let generic = AttrGeneric::from("com.example.app");
+    assert_eq!(generic.as_bytes(), b"com.example.app");
+    assert_eq!(generic, AttrGeneric::new(b"com.example.app"));
+    assert_eq!(
+        AttrGeneric::from(&[0xffu8, 0x00][..]).as_ref(),
+        &[0xff, 0x00]
+    );
+}
+
+#[test]
+fn token_id_round_trip() {
+    assert!(AttrTokenId::new("com.apple.setoken").is_secure_enclave());
+    assert_eq!(AttrTokenId::SecureEnclave.to_string(), "com.apple.setoken");
+
+    let token_id = AttrTokenId::from("com.apple.pivtoken:1234");
+    assert_eq!(
+        token_id,
+        AttrTokenId::Token("com.apple.pivtoken:1234".to_owned())
+    );
+    assert_eq!(token_id.to_string(), "com.apple.pivtoken:1234");
+}
+
+#[test]
+fn token_handles() {
+    let token = Token::new("com.example.token:does-not-exist");
+    assert_eq!(
+        token.id(),
+        &AttrTokenId::Token("com.example.token:does-not-exist".to_owned())
+    );
+    assert_eq!(
+        token,
+        Token::new(AttrTokenId::from("com.example.token:does-not-exist"))
+    );
+
+    // Tokens which aren't present expose no items
+    assert!(token.keys().unwrap().is_empty());
+    assert!(token.certificates().unwrap().is_empty());
+    assert!(token.identities().unwrap().is_empty());
+    assert!(!Token::list().unwrap().contains(&token));
+}
+
+#[test]
+fn certificate_der_round_trip() {
+    let der = include_bytes!("test-certificate.der");
+    let cert = Certificate::from_der(der).unwrap();
+
+    assert_eq!(cert.to_der(), der.to_vec());
+    assert_eq!(
+        cert.subject_summary(),
+        Some("keychain-services test".to_owned())
+    );
+    assert_eq!(
+        format!("{:?}", cert),
+        "SecCertificate { subject_summary: Some(\"keychain-services test\") }"
+    );
+
+    match Certificate::from_der(b"not a certificate") {
+        Err(e) => match e.kind() {
+            ErrorKind::Decode => (),
+            other => panic!("unexpected error kind: {:?}", other),
+        },
+        Ok(_) => panic!("parsed an invalid certificate"),
+    }
+}
+
+#[test]
+fn piv_slot_mapping() {
+    use keychain_services::keychain::piv::PivSlot;
+
+    assert_eq!(PivSlot::Authentication.key_reference(), 0x9A);
+    assert_eq!(PivSlot::DigitalSignature.key_reference(), 0x9C);
+    assert_eq!(PivSlot::KeyManagement.key_reference(), 0x9D);
+    assert_eq!(PivSlot::CardAuthentication.key_reference(), 0x9E);
+
+    assert_eq!(
+        PivSlot::Authentication.label(),
+        "Certificate For PIV Authentication"
+    );
+    assert_eq!(
+        PivSlot::DigitalSignature.label(),
+        "Certificate For Digital Signature"
+    );
+    assert_eq!(
+        PivSlot::KeyManagement.label(),
+        "Certificate For Key Management"
+    );
+    assert_eq!(
+        PivSlot::CardAuthentication.label(),
+        "Certificate For Card Authentication"
+    );
+
+    assert!(Token::new("com.apple.pivtoken:0123456789ABCDEF").is_piv());
+    assert!(!Token::new("com.example.token:0123456789ABCDEF").is_piv());
+    assert!(!Token::new(AttrTokenId::SecureEnclave).is_piv());
+}
+
+#[test]
+fn authentication_type_codes() {
+    for &auth_type in AttrAuthenticationType::ALL {
+        let string = auth_type.as_CFString().to_string();
+        let mut code = [0u8; 4];
+        code.copy_from_slice(string.as_bytes());
+        assert_eq!(AttrAuthenticationType::from_code(&code), Some(auth_type));
+    }
+
+    assert_eq!(
+        AttrAuthenticationType::from_code(b"form"),
+        Some(AttrAuthenticationType::HTMLForm)
+    );
+    assert_eq!(AttrAuthenticationType::from_code(b"zzzz"), None);
+}
+
+#[test]
+fn legacy_attribute_tags() {
+    use keychain_services::keychain::item::AttrTag;
+
+    for &tag in AttrTag::ALL {
+        assert_eq!(AttrTag::from_code(&tag.code()), tag);
+    }
+
+    assert_eq!(AttrTag::from(*b"acct"), AttrTag::Account);
+    assert_eq!(AttrTag::Service.to_string(), "svce");
+    assert_eq!(AttrTag::from(*b"zzzz"), AttrTag::Other(*b"zzzz"));
+    assert_eq!(<[u8; 4]>::from(AttrTag::Other(*b"zzzz")), *b"zzzz");
+}
+
+#[test]
+fn legacy_attribute_decoding() {
+    use keychain_services::keychain::item::AttrValue;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let code = |code: &[u8; 4]| u32::from_be_bytes(*code).to_ne_bytes();
+
+    assert_eq!(
+        AttrValue::decode(b"acct", b"alice"),
+        AttrValue::String("alice".to_owned())
+    );
+    assert_eq!(
+        AttrValue::decode(b"cdat", b"20181106000000Z\0"),
+        AttrValue::Date(UNIX_EPOCH + Duration::from_secs(1_541_462_400))
+    );
+    assert_eq!(
+        AttrValue::decode(b"ptcl", &code(b"htps")),
+        AttrValue::Protocol(AttrProtocol::HTTPS)
+    );
+    assert_eq!(
+        AttrValue::decode(b"atyp", &code(b"httd")),
+        AttrValue::AuthenticationType(AttrAuthenticationType::HTTPDigest)
+    );
+    assert_eq!(
+        AttrValue::decode(b"port", &443u32.to_ne_bytes()),
+        AttrValue::Port(AttrPort(443))
+    );
+    assert_eq!(
+        AttrValue::decode(b"crtr", &code(b"aapl")),
+        AttrValue::Code(*b"aapl")
+    );
+    assert_eq!(AttrValue::decode(b"invi", &[1]), AttrValue::Boolean(true));
+    assert_eq!(
+        AttrValue::decode(b"ctyp", &3u32.to_ne_bytes()),
+        AttrValue::Number(3)
+    );
+    assert_eq!(AttrValue::decode(b"cusi", &[0]), AttrValue::Boolean(false));
+
+    // Malformed data and unknown attributes are returned as-is
+    assert_eq!(
+        AttrValue::decode(b"port", &[1, 2]),
+        AttrValue::Data(vec![1, 2])
+    );
+    assert_eq!(
+        AttrValue::decode(b"zzzz", b"data"),
+        AttrValue::Data(b"data".to_vec())
+    );
+}
+
+#[test]
+fn legacy_attribute_encoding() {
+    use keychain_services::keychain::item::AttrValue;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    let date = AttrValue::Date(UNIX_EPOCH + Duration::from_secs(1_541_462_400));
+    assert_eq!(date.encode(), b"20181106000000Z\0".to_vec());
+
+    let values = [
+        (b"acct", AttrValue::String("alice".to_owned())),
+        (b"cdat", date),
+        (b"ptcl", AttrValue::Protocol(AttrProtocol::HTTPS)),
+        (
+            b"atyp",
+            AttrValue::AuthenticationType(AttrAuthenticationType::HTTPDigest),
+        ),
+        (b"port", AttrValue::Port(AttrPort(443))),
+        (b"crtr", AttrValue::Code(*b"aapl")),
+        (b"invi", AttrValue::Boolean(true)),
+        (b"ctyp", AttrValue::Number(3)),
+        (b"zzzz", AttrValue::Data(b"data".to_vec())),
+    ];
+
+    for (tag, value) in &values {
+        assert_eq!(&AttrValue::decode(tag, &value.encode()), value);
+    }
+}
+
+#[test]
+fn manifest_from_plist() {
+    use keychain_services::manifest::*;
+
+    let plist = br#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>entries</key>
+    <array>
+        <dict>
+            <key>class</key><string>generic_password</string>
+            <key>service</key><string>com.example.db</string>
+            <key>account</key><string>app</string>
+            <key>accessible</key><string>WhenUnlocked</string>
+            <key>data</key><dict><key>env</key><string>DB_PASSWORD</string></dict>
+        </dict>
+        <dict>
+            <key>class</key><string>key</string>
+            <key>tag</key><string>com.example.signing</string>
+            <key>key_type</key><string>EcSecPrimeRandom</string>
+            <key>key_size</key><integer>256</integer>
+            <key>constraints</key><array><string>UserPresence</string></array>
+        </dict>
+    </array>
+</dict>
+</plist>"#;
+
+    let manifest = Manifest::from_plist(plist).unwrap();
+
+    assert_eq!(
+        manifest.entries,
+        vec![
+            ManifestEntry::GenericPassword {
+                service: "com.example.db".to_owned(),
+                account: "app".to_owned(),
+                label: None,
+                comment: None,
+                accessible: Some(AttrAccessible::WhenUnlocked),
+                data: ManifestData::Env("DB_PASSWORD".to_owned()),
+            },
+            ManifestEntry::Key {
+                tag: "com.example.signing".to_owned(),
+                key_type: AttrKeyType::EcSecPrimeRandom,
+                key_size: 256,
+                label: None,
+                accessible: None,
+                constraints: vec![AccessConstraint::UserPresence],
+                secure_enclave: false,
+            },
+        ]
+    );
+
+    let invalid = String::from_utf8(plist.to_vec())
+        .unwrap()
+        .replace("EcSecPrimeRandom", "Dsa");
+    match Manifest::from_plist(invalid.as_bytes()).unwrap_err().kind() {
+        ErrorKind::Decode => (),
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn item_data_size_limits() {
+    use keychain_services::keychain::item::*;
+
+    assert_eq!(data_size_limits(), DataSizeLimits::default());
+    assert!(validate_data(&vec![0u8; DEFAULT_MAX_SYNCHRONIZABLE_DATA_SIZE], true).is_ok());
+    assert!(validate_data(&vec![0u8; DEFAULT_MAX_DATA_SIZE], false).is_ok());
+
+    for &(size, synchronizable) in &[
+        (DEFAULT_MAX_SYNCHRONIZABLE_DATA_SIZE + 1, true),
+        (DEFAULT_MAX_DATA_SIZE + 1, false),
+    ] {
+        match validate_data(&vec![0u8; size], synchronizable)
+            .unwrap_err()
+            .kind()
+        {
+            ErrorKind::DataTooLarge => (),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    // Limits can be changed or disabled
+    let limits = DataSizeLimits {
+        max_data_size: None,
+        max_synchronizable_data_size: Some(16),
+    };
+    set_data_size_limits(limits);
+    assert_eq!(data_size_limits(), limits);
+    assert!(validate_data(&vec![0u8; DEFAULT_MAX_DATA_SIZE + 1], false).is_ok());
+    assert!(validate_data(&[0u8; 17], true).is_err());
+
+    set_data_size_limits(DataSizeLimits::unlimited());
+    assert!(validate_data(&vec![0u8; DEFAULT_MAX_DATA_SIZE + 1], true).is_ok());
+
+    set_data_size_limits(DataSizeLimits::default());
+}
+
+#[test]
+fn middleware_rejects_operations() {
+    use keychain_services::{hooks::*, keychain::item::Query};
+    use std::{
+        cell::Cell,
+        sync::{Arc, Mutex},
+    };
+
+    thread_local! {
+        // Only observe this thread, as other tests perform operations too
+        static OBSERVED: Cell<bool> = Cell::new(false);
+    }
+
+    struct TestMiddleware {
+        deny_lookups: bool,
+        log: Arc<Mutex<Vec<(OperationDescriptor, bool)>>>,
+    }
+
+    impl Middleware for TestMiddleware {
+        fn before(&self, descriptor: &OperationDescriptor) -> Result<(), Error> {
+            if self.deny_lookups
+                && OBSERVED.with(Cell::get)
+                && descriptor.kind == OperationKind::Find
+            {
+                Err(Error::new(
+                    ErrorKind::InteractionNotAllowed,
+                    &"key lookups denied by policy",
+                ))
+            } else {
+                Ok(())
+            }
+        }
+
+        fn after(&self, descriptor: &OperationDescriptor, error: Option<&Error>) {
+            if OBSERVED.with(Cell::get) {
+                self.log
+                    .lock()
+                    .unwrap()
+                    .push((*descriptor, error.is_some()));
+            }
+        }
+    }
+
+    let recorder_log = Arc::new(Mutex::new(vec![]));
+    let policy_log = Arc::new(Mutex::new(vec![]));
+
+    let recorder = register_middleware(TestMiddleware {
+        deny_lookups: false,
+        log: recorder_log.clone(),
+    });
+
+    let policy = register_middleware(TestMiddleware {
+        deny_lookups: true,
+        log: policy_log.clone(),
+    });
+
+    OBSERVED.with(|observed| observed.set(true));
+    let result = Key::find(Query::new());
+    OBSERVED.with(|observed| observed.set(false));
+    drop(policy);
+    drop(recorder);
+
+    match result.unwrap_err().kind() {
+        ErrorKind::InteractionNotAllowed => (),
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    // Middleware registered before the rejecting one observes the rejection,
+    // whereas the rejecting middleware's own `after` hook isn't invoked
+    let expected = OperationDescriptor {
+        kind: OperationKind::Find,
+        class: Some(keychain::item::Class::Key),
+    };
+    assert_eq!(*recorder_log.lock().unwrap(), vec![(expected, true)]);
+    assert!(policy_log.lock().unwrap().is_empty());
+}
+
+#[test]
+fn watchdog_flags_slow_operations() {
+    use keychain_services::{hooks::*, keychain::item::Query, watchdog::*};
+    use std::{
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    struct Stall(thread::ThreadId);
+
+    impl Middleware for Stall {
+        fn before(&self, _descriptor: &OperationDescriptor) -> Result<(), Error> {
+            if thread::current().id() == self.0 {
+                thread::sleep(Duration::from_millis(300));
+            }
+
+            Ok(())
+        }
+    }
+
+    // Only observe this thread, as other tests perform operations too
+    let thread_id = thread::current().id();
+    let warnings = Arc::new(Mutex::new(vec![]));
+    let recorded = warnings.clone();
+
+    let watchdog = register_middleware(Watchdog::new(
+        Duration::from_millis(20),
+        move |warning: &SlowOperation| {
+            if warning.thread_id == thread_id {
+                recorded.lock().unwrap().push(warning.clone());
+            }
+        },
+    ));
+    let stall = register_middleware(Stall(thread_id));
+
+    let _ = Key::find(Query::new());
+    drop(stall);
+    drop(watchdog);
+
+    let warnings = warnings.lock().unwrap();
+    assert_eq!(warnings.len(), 2);
+    assert!(!warnings[0].completed);
+    assert!(warnings[1].completed);
+
+    for warning in warnings.iter() {
+        assert_eq!(warning.descriptor.kind, OperationKind::Find);
+        assert!(warning.elapsed > warning.threshold);
+    }
+
+    assert!(warnings[1].elapsed >= Duration::from_millis(300));
+    assert!(warnings[1].to_string().contains("took"));
+}
+
+#[test]
+fn policy_rejects_key_generation() {
+    use keychain_services::policy::*;
+
+    // Only AES keys are covered, as other tests generate keys concurrently
+    let rule = PolicyRule::MinimumKeySize(AttrKeyType::Aes, 256);
+    let params = KeyPairGenerateParams::new(AttrKeyType::Aes, 128);
+
+    for &enforcement in &[Enforcement::Reject, Enforcement::Correct] {
+        set_policy(Some(Policy::new(enforcement).rule(rule)));
+        let result = KeyPair::create(&params);
+        let installed = set_policy(None).unwrap();
+
+        assert_eq!(installed.enforcement(), enforcement);
+        assert_eq!(installed.rules(), &[rule]);
+
+        // Key sizes can't be corrected, so both modes reject the key
+        match result.unwrap_err().kind() {
+            ErrorKind::PolicyViolation => (),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    assert!(policy().is_none());
+}
+
+#[cfg(feature = "cryptokit")]
+#[test]
+fn cryptokit_ed25519_sign_and_verify() {
+    use keychain_services::cryptokit::*;
+
+    let private_key = Ed25519PrivateKey::generate();
+    let public_key = private_key.public_key();
+    let signature = private_key.sign(TEST_MESSAGE).unwrap();
+
+    assert!(public_key.verify(TEST_MESSAGE, &signature).unwrap());
+    assert!(!public_key.verify(&[0u8, 0u8], &signature).unwrap());
+}
+
+#[cfg(feature = "cryptokit")]
+#[test]
+fn cryptokit_x25519_key_agreement() {
+    use keychain_services::cryptokit::*;
+
+    let alice = X25519PrivateKey::generate();
+    let bob = X25519PrivateKey::generate();
+
+    let mut alice_secret = alice.diffie_hellman(&bob.public_key()).unwrap();
+    let bob_secret = bob.diffie_hellman(&alice.public_key()).unwrap();
+
+    assert_eq!(alice_secret.as_bytes(), bob_secret.as_bytes());
+    assert_eq!(
+        format!("{:?}", alice_secret),
+        "X25519SharedSecret([REDACTED])"
+    );
+
+    zeroize::Zeroize::zeroize(&mut alice_secret);
+    assert!(alice_secret.as_bytes().iter().all(|&byte| byte == 0));
+}
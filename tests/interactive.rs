@@ -13,6 +13,7 @@
 // TODO: these tests presently fail (possibly due to a codesigning issue?)
 
 use keychain_services::*;
+use std::time::Duration;
 use tempfile::TempDir;
 
 const TEST_PASSWORD: &str = "test password. do not really use";
@@ -59,12 +60,399 @@ fn key_query() {
         .key_type(AttrKeyType::EcSecPrimeRandom)
         .application_label(keypair.public_key.application_label().unwrap());
 
-    let private_key = Key::find(private_key_query).unwrap();
+    let private_key = Key::find(&private_key_query).unwrap().unwrap();
 
     assert_eq!(
         keypair.private_key.application_label(),
         private_key.application_label()
     );
+
+    // The query's match limit is overridden, so a single key is returned
+    let limited_key = Key::find(private_key_query.match_limit(keychain::item::MatchLimit::All))
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(
+        keypair.private_key.application_label(),
+        limited_key.application_label()
+    );
+
+    assert!(private_key.is_still_valid().unwrap());
+}
+
+/// Generating permanent RSA keys with a label and tag
+#[test]
+fn generate_permanent_rsa_key() {
+    let tag = "rs.keychain-services.test.integration.rsa";
+    let label = "keychain-services.rs integration test RSA key";
+
+    let generate_params = KeyPairGenerateParams::rsa(3072)
+        .unwrap()
+        .application_tag(tag)
+        .label(label)
+        .permanent(true);
+
+    let keypair = KeyPair::generate(generate_params).unwrap();
+
+    let query = keychain::item::Query::new()
+        .key_class(AttrKeyClass::Private)
+        .application_tag(tag)
+        .label(label);
+
+    let private_key = Key::find(query).unwrap().unwrap();
+    assert_eq!(private_key.spec(), Some(KeySpec::Rsa3072));
+    assert_eq!(
+        private_key.application_label(),
+        keypair.private_key.application_label()
+    );
+
+    private_key.delete().unwrap();
+}
+
+/// Generating permanent AES keys and finding them by label
+#[test]
+fn generate_symmetric_key() {
+    let label = "keychain-services.rs integration test AES key";
+
+    let generate_params = SymmetricKeyGenerateParams::aes(256)
+        .application_tag("rs.keychain-services.test.integration.aes")
+        .label(label)
+        .permanent(true);
+
+    Key::generate_symmetric(generate_params).unwrap();
+
+    let key = Key::find_symmetric(label).unwrap().unwrap();
+    assert_eq!(key.class(), Some(AttrKeyClass::Symmetric));
+    assert_eq!(key.key_type(), Some(AttrKeyType::Aes));
+    assert_eq!(key.size_in_bits(), Some(256));
+
+    key.delete().unwrap();
+    assert!(Key::find_symmetric(label).unwrap().is_none());
+}
+
+/// Looking up private keys by public key hash
+#[test]
+fn key_find_by_public_key_hash() {
+    let keypair = generate_keypair(
+        "rs.keychain-services.test.integration.hash",
+        "keychain-services.rs integration test hash key",
+    );
+
+    let hash = keypair.public_key.application_label().unwrap();
+    let private_key = Key::find_by_public_key_hash(hash.as_bytes())
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(private_key.application_label(), Some(hash));
+}
+
+/// Finding recently created keys by date range
+#[test]
+fn key_find_created_within() {
+    let tag = "rs.keychain-services.test.integration.date-range";
+    generate_keypair(tag, "keychain-services.rs integration test date range key");
+
+    let query = keychain::item::Query::new().application_tag(tag);
+    let recent = DateRange::new().newer_than(Duration::from_secs(3600));
+    let items = keychain::item::ItemAttributes::find_created_within(
+        keychain::item::Class::Key,
+        &query,
+        &recent,
+    )
+    .unwrap();
+
+    assert!(!items.is_empty());
+    assert!(items.iter().all(|item| item.creation_date().is_some()));
+}
+
+/// Queries spanning synchronizable and device-local items
+#[test]
+fn key_find_synchronizable_any() {
+    let tag = "rs.keychain-services.test.integration.sync-any";
+    generate_keypair(tag, "keychain-services.rs integration test sync any key");
+
+    let query = keychain::item::Query::new()
+        .application_tag(tag)
+        .synchronizable_any();
+    let items =
+        keychain::item::ItemAttributes::find_all(keychain::item::Class::Key, &query).unwrap();
+
+    assert!(!items.is_empty());
+    assert!(items.iter().all(|item| !item.is_synchronizable()));
+}
+
+/// Tracking key usage in sidecar items
+#[test]
+fn key_usage_stats() {
+    let keypair = generate_keypair(
+        "rs.keychain-services.test.integration.usage",
+        "keychain-services.rs integration test usage key",
+    );
+
+    set_usage_tracking(true);
+
+    for _ in 0..2 {
+        keypair
+            .private_key
+            .sign(
+                KeyAlgorithm::ECDSASignatureMessageX962SHA256,
+                TEST_PASSWORD.as_bytes(),
+            )
+            .unwrap();
+    }
+
+    set_usage_tracking(false);
+
+    let stats = keypair.private_key.usage_stats().unwrap();
+    assert_eq!(stats.sign_count, 2);
+    assert_eq!(stats.decrypt_count, 0);
+    assert!(stats.last_used.is_some());
+}
+
+/// Relabeling keys in place
+#[test]
+fn key_set_label() {
+    let keypair = generate_keypair(
+        "rs.keychain-services.test.integration.relabel",
+        "keychain-services.rs integration test relabel key",
+    );
+
+    let new_label = "keychain-services.rs integration test relabeled key";
+    keypair.private_key.set_label(new_label).unwrap();
+
+    let hash = keypair.public_key.application_label().unwrap();
+    let private_key = Key::find_by_public_key_hash(hash.as_bytes())
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(private_key.label().unwrap().to_string(), new_label);
+}
+
+/// Queries filtering keys by class and capabilities
+#[test]
+fn key_query_by_capability() {
+    let keypair = generate_keypair(
+        "rs.keychain-services.test.integration.capability",
+        "keychain-services.rs integration test capability key",
+    );
+    let application_label = keypair.public_key.application_label().unwrap();
+
+    let signing_key_query = keychain::item::Query::new()
+        .key_class(AttrKeyClass::Private)
+        .can_sign(true)
+        .application_label(application_label.clone());
+
+    let signing_key = Key::find(&signing_key_query).unwrap().unwrap();
+    assert_eq!(
+        signing_key.application_label().unwrap(),
+        keypair.private_key.application_label().unwrap()
+    );
+
+    // Public keys only verify signatures
+    let public_signing_key_query = keychain::item::Query::new()
+        .key_class(AttrKeyClass::Public)
+        .can_sign(true)
+        .application_label(application_label);
+
+    assert!(Key::find(&public_signing_key_query).unwrap().is_none());
+
+    keypair.private_key.delete().unwrap();
+}
+
+/// Updates guarded by modification date
+#[test]
+fn key_update_if_unchanged() {
+    use keychain_services::keychain::item::{
+        update_if_unchanged, Class, ItemAttributes, ItemChanges,
+    };
+
+    let keypair = generate_keypair(
+        "rs.keychain-services.test.integration.cas",
+        "keychain-services.rs integration test cas key",
+    );
+
+    let query = keychain::item::Query::new()
+        .key_class(AttrKeyClass::Private)
+        .application_label(keypair.public_key.application_label().unwrap());
+
+    let modified = ItemAttributes::find_all(Class::Key, &query).unwrap()[0]
+        .modification_date()
+        .unwrap();
+
+    let stale = modified - Duration::from_secs(3600);
+    let changes = ItemChanges::new().label("keychain-services.rs integration test stale key");
+
+    let err = update_if_unchanged(Class::Key, &query, stale, &changes).unwrap_err();
+    match err.kind() {
+        ErrorKind::Conflict => (),
+        other => panic!("expected conflict, got {:?}", other),
+    }
+
+    let new_label = "keychain-services.rs integration test swapped key";
+    let changes = ItemChanges::new().label(new_label);
+    update_if_unchanged(Class::Key, &query, modified, &changes).unwrap();
+
+    let private_key = Key::find(&query).unwrap().unwrap();
+    assert_eq!(private_key.label().unwrap().to_string(), new_label);
+    private_key.delete().unwrap();
+}
+
+/// Polling for changes to keys
+#[test]
+fn key_change_detector() {
+    use keychain_services::keychain::item::{ChangeDetector, Class, ItemChange};
+
+    let tag = "rs.keychain-services.test.integration.changes";
+    let query = keychain::item::Query::new()
+        .key_class(AttrKeyClass::Private)
+        .application_tag(tag);
+    let mut detector = ChangeDetector::new(Class::Key, query).unwrap();
+
+    let keypair = generate_keypair(tag, "keychain-services.rs integration test changes key");
+    let changes = detector.poll().unwrap();
+    assert!(changes.iter().any(|change| match change {
+        ItemChange::Added(_) => true,
+        _ => false,
+    }));
+
+    // Modification dates have a resolution of one second
+    std::thread::sleep(Duration::from_secs(1));
+    keypair
+        .private_key
+        .set_label("keychain-services.rs integration test changed key")
+        .unwrap();
+    let changes = detector.poll().unwrap();
+    assert!(changes.iter().any(|change| match change {
+        ItemChange::Updated(_) => true,
+        _ => false,
+    }));
+
+    keypair.private_key.delete().unwrap();
+    let changes = detector.poll().unwrap();
+    assert!(changes.iter().any(|change| match change {
+        ItemChange::Removed(_) => true,
+        _ => false,
+    }));
+}
+
+/// Detecting and removing duplicate keys
+#[test]
+fn key_dedupe() {
+    use keychain_services::keychain::item::{dedupe, Class, DedupeAction};
+
+    let tag = "rs.keychain-services.test.integration.dedupe";
+    let label = "keychain-services.rs integration test dedupe key";
+    generate_keypair(tag, label);
+    generate_keypair(tag, label);
+
+    let query = keychain::item::Query::new().application_tag(tag);
+    let groups = dedupe(Class::Key, &query, DedupeAction::Report).unwrap();
+    assert!(groups.iter().any(|group| !group.duplicates().is_empty()));
+
+    dedupe(Class::Key, &query, DedupeAction::DeleteOlder).unwrap();
+    assert!(dedupe(Class::Key, &query, DedupeAction::Report)
+        .unwrap()
+        .is_empty());
+}
+
+/// Key descriptions
+#[test]
+fn key_display() {
+    let keypair = generate_keypair(
+        "rs.keychain-services.test.integration.display",
+        "keychain-services.rs integration test display key",
+    );
+
+    assert!(keypair.private_key.to_string().starts_with(
+        "private EC P-256 key (tag: \"rs.keychain-services.test.integration.display\""
+    ));
+}
+
+/// Ensuring a key exists generates it once and reuses it afterwards
+#[test]
+fn key_manager_ensure() {
+    let tag = "rs.keychain-services.test.integration.ensure";
+    let spec = KeyPairGenerateParams::new(AttrKeyType::EcSecPrimeRandom, 256)
+        .label("keychain-services.rs integration test ensure key");
+
+    let first = KeyManager::ensure(tag, &spec).unwrap();
+    let second = KeyManager::ensure(tag, &spec).unwrap();
+
+    assert_eq!(second.outcome, KeyOutcome::Existing);
+    assert_eq!(
+        first.key.application_label(),
+        second.key.application_label()
+    );
+    first.key.delete().unwrap();
+}
+
+/// Ensuring a key exists replaces a key which no longer matches the spec
+#[test]
+fn key_manager_regenerates_mismatched_keys() {
+    let tag = "rs.keychain-services.test.integration.ensure-mismatch";
+    let first = KeyManager::ensure(tag, KeyPairGenerateParams::from_spec(KeySpec::EcP256)).unwrap();
+    let second =
+        KeyManager::ensure(tag, KeyPairGenerateParams::from_spec(KeySpec::EcP384)).unwrap();
+
+    assert_eq!(first.outcome, KeyOutcome::Generated);
+    assert_eq!(second.outcome, KeyOutcome::Regenerated);
+    assert_eq!(second.key.spec(), Some(KeySpec::EcP384));
+    second.key.delete().unwrap();
+}
+
+/// Managed keys in a particular keychain
+#[test]
+fn key_manager_uses_given_keychain() {
+    let tmp = temp_keychain();
+    let tag = "rs.keychain-services.test.integration.ensure-in";
+    let spec = KeyPairGenerateParams::from_spec(KeySpec::EcP256);
+
+    let first = KeyManager::ensure_in(&tmp.keychain, tag, &spec).unwrap();
+    assert_eq!(first.outcome, KeyOutcome::Generated);
+
+    // The key is only found in the keychain it was created in
+    let query = keychain::item::Query::new()
+        .key_class(AttrKeyClass::Private)
+        .application_tag(tag);
+    assert!(Key::find(query.clone().keychain(&tmp.keychain))
+        .unwrap()
+        .is_some());
+    assert!(
+        Key::find(query.keychain(&Keychain::find_default().unwrap()))
+            .unwrap()
+            .is_none()
+    );
+
+    let second = KeyManager::ensure_in(&tmp.keychain, tag, &spec).unwrap();
+    assert_eq!(second.outcome, KeyOutcome::Existing);
+}
+
+/// Persisting the public half of a key pair as its own item
+#[test]
+fn key_persist_public() {
+    let tag = "rs.keychain-services.test.integration.persist-public";
+    let generate_params = KeyPairGenerateParams::new(AttrKeyType::EcSecPrimeRandom, 256)
+        .persist_public_key(tag, "keychain-services.rs integration test public key");
+
+    // Both `create` and the legacy `generate` path honor the setting
+    let generators: [fn(&KeyPairGenerateParams) -> Result<KeyPair, Error>; 2] =
+        [KeyPair::create, KeyPair::generate];
+
+    for generate in &generators {
+        let keypair = generate(&generate_params).unwrap();
+
+        let public_key_query = keychain::item::Query::new()
+            .key_class(AttrKeyClass::Public)
+            .application_tag(tag);
+
+        let public_key = Key::find(public_key_query).unwrap().unwrap();
+
+        assert_eq!(
+            keypair.public_key.to_external_representation().unwrap(),
+            public_key.to_external_representation().unwrap()
+        );
+        public_key.delete().unwrap();
+    }
 }
 
 /// Passwords
@@ -81,6 +469,1041 @@ fn store_and_retrieve_passwords() {
     assert_eq!(keychain_item.service().unwrap(), service);
     assert_eq!(keychain_item.account().unwrap(), account);
     assert_eq!(keychain_item.password().unwrap().as_str(), TEST_PASSWORD);
+    assert!(!keychain_item.to_string().contains(TEST_PASSWORD));
+    assert!(!format!("{:?}", keychain_item).contains(TEST_PASSWORD));
+}
+
+/// Formatting deleted items
+#[test]
+fn format_deleted_password() {
+    let tmp = temp_keychain();
+    let service = "example.com";
+    let account = "example";
+
+    let password =
+        keychain::item::GenericPassword::create(&tmp.keychain, service, account, TEST_PASSWORD)
+            .unwrap();
+    let stale = keychain::item::GenericPassword::find(&tmp.keychain, service, account)
+        .unwrap()
+        .unwrap();
+
+    password.delete().unwrap();
+
+    assert!(format!("{:?}", stale).contains("<invalid>"));
+    assert_eq!(stale.to_string(), "invalid item");
+}
+
+/// Updating an item which has been deleted fails rather than panicking
+#[test]
+fn update_deleted_password() {
+    let tmp = temp_keychain();
+    let service = "example.com";
+    let account = "example";
+
+    let password =
+        keychain::item::GenericPassword::create(&tmp.keychain, service, account, TEST_PASSWORD)
+            .unwrap();
+    let stale = keychain::item::GenericPassword::find(&tmp.keychain, service, account)
+        .unwrap()
+        .unwrap();
+
+    password.delete().unwrap();
+
+    assert!(stale.set_label("new label").is_err());
+    assert!(stale
+        .update(&keychain::item::ItemChanges::new().comment("new comment"))
+        .is_err());
+}
+
+/// Password metadata attributes
+#[test]
+fn store_password_metadata() {
+    let tmp = temp_keychain();
+    let params = keychain::item::GenericPasswordParams::new("example.com", "example")
+        .comment("test comment")
+        .description("application password")
+        .creator(*b"kcrs")
+        .item_type(*b"note")
+        .invisible(true);
+
+    let keychain_item =
+        keychain::item::GenericPassword::create_with_params(&tmp.keychain, &params, TEST_PASSWORD)
+            .unwrap();
+
+    assert_eq!(keychain_item.comment().unwrap(), "test comment");
+    assert_eq!(keychain_item.description().unwrap(), "application password");
+    assert_eq!(keychain_item.creator().unwrap(), AttrCreator(*b"kcrs"));
+    assert_eq!(keychain_item.item_type().unwrap(), AttrItemType(*b"note"));
+    assert!(keychain_item.is_invisible().unwrap());
+    assert!(!keychain_item.is_negative().unwrap());
+}
+
+/// Purging expired passwords
+#[test]
+fn purge_expired_passwords() {
+    use keychain_services::keychain::item::{
+        purge_expired, Class, GenericPassword, GenericPasswordParams, ItemTags, Query,
+    };
+    use std::time::{Duration, SystemTime};
+
+    let keychain = Keychain::find_default().unwrap();
+    let label = "keychain-services.rs integration test expired password";
+    let tags = ItemTags::new().with_expiry(SystemTime::now() - Duration::from_secs(60));
+    let params = GenericPasswordParams::new("rs.keychain-services.test.expiry", "example")
+        .label(label)
+        .tags(&tags);
+
+    GenericPassword::create_with_params(&keychain, &params, TEST_PASSWORD).unwrap();
+
+    let purged = purge_expired(&[Class::GenericPassword], Query::new().label(label)).unwrap();
+    assert_eq!(purged.len(), 1);
+    assert!(purged[0].is_expired());
+
+    assert!(
+        GenericPassword::find(&keychain, "rs.keychain-services.test.expiry", "example")
+            .unwrap()
+            .is_none()
+    );
+}
+
+/// Soft-deleting a password and restoring it from its tombstone
+#[test]
+fn tombstone_and_restore_password() {
+    use keychain_services::keychain::item::{
+        purge_expired, set_deletion_mode, Class, DeletionMode, GenericPassword,
+        GenericPasswordParams, ItemTags, Query, Tombstone,
+    };
+    use std::time::{Duration, SystemTime};
+
+    let keychain = Keychain::find_default().unwrap();
+    let service = "rs.keychain-services.test.tombstone";
+    let label = "keychain-services.rs integration test tombstoned password";
+    let tags = ItemTags::new().with_expiry(SystemTime::now() - Duration::from_secs(60));
+    let params = GenericPasswordParams::new(service, "example")
+        .label(label)
+        .tags(&tags);
+
+    GenericPassword::create_with_params(&keychain, &params, TEST_PASSWORD).unwrap();
+
+    set_deletion_mode(DeletionMode::Tombstone);
+    let purged = purge_expired(&[Class::GenericPassword], Query::new().label(label));
+    set_deletion_mode(DeletionMode::Destroy);
+    assert_eq!(purged.unwrap().len(), 1);
+
+    assert!(GenericPassword::find(&keychain, service, "example")
+        .unwrap()
+        .is_none());
+
+    let tombstone = Tombstone::find_all(Class::GenericPassword)
+        .unwrap()
+        .into_iter()
+        .find(|tombstone| tombstone.original_label().as_ref().map(String::as_str) == Some(label))
+        .unwrap();
+
+    assert!(tombstone.item().is_tombstone());
+    tombstone.restore().unwrap();
+
+    assert!(GenericPassword::find(&keychain, service, "example")
+        .unwrap()
+        .is_some());
+
+    purge_expired(&[Class::GenericPassword], Query::new().label(label)).unwrap();
+}
+
+/// Copying a password into another keychain
+#[test]
+fn copy_password_to_keychain() {
+    use keychain_services::keychain::item::{
+        GenericPassword, GenericPasswordParams, ItemDestination,
+    };
+
+    let source = temp_keychain();
+    let destination = temp_keychain();
+    let service = "rs.keychain-services.test.copy";
+    let params = GenericPasswordParams::new(service, "example").comment("copied");
+
+    let password =
+        GenericPassword::create_with_params(&source.keychain, &params, TEST_PASSWORD).unwrap();
+
+    password
+        .copy_to(ItemDestination::Keychain(&destination.keychain))
+        .unwrap();
+
+    let copy = GenericPassword::find(&destination.keychain, service, "example")
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(copy.password().unwrap().as_str(), TEST_PASSWORD);
+    assert_eq!(copy.comment().unwrap(), "copied");
+
+    // The original is left in place
+    assert!(GenericPassword::find(&source.keychain, service, "example")
+        .unwrap()
+        .is_some());
+}
+
+/// Backing up passwords to an encrypted archive and restoring them
+#[test]
+fn export_and_import_archive() {
+    use keychain_services::keychain::item::{
+        export_archive, import_archive, Class, GenericPassword, ItemDestination, Query,
+    };
+
+    let source = temp_keychain();
+    let destination = temp_keychain();
+    let service = "rs.keychain-services.test.archive";
+    let passphrase = "correct horse battery staple";
+
+    GenericPassword::create(&source.keychain, service, "example", TEST_PASSWORD).unwrap();
+
+    let query = Query::new().keychain(&source.keychain).service(service);
+    let archive = export_archive(&[Class::GenericPassword], &query, passphrase).unwrap();
+
+    let err = import_archive(
+        &archive,
+        "wrong passphrase",
+        ItemDestination::Keychain(&destination.keychain),
+    )
+    .unwrap_err();
+    match err.kind() {
+        ErrorKind::AuthFailed => (),
+        other => panic!("expected authentication failure, got {:?}", other),
+    }
+
+    let import = import_archive(
+        &archive,
+        passphrase,
+        ItemDestination::Keychain(&destination.keychain),
+    )
+    .unwrap();
+    assert_eq!(import.imported(), 1);
+
+    let restored = GenericPassword::find(&destination.keychain, service, "example")
+        .unwrap()
+        .unwrap();
+    assert_eq!(restored.password().unwrap().as_str(), TEST_PASSWORD);
+
+    // Importing again leaves the restored password alone
+    let import = import_archive(
+        &archive,
+        passphrase,
+        ItemDestination::Keychain(&destination.keychain),
+    )
+    .unwrap();
+    assert_eq!(import.skipped(), 1);
+}
+
+/// Tightening the capabilities of an existing key
+#[test]
+fn restrict_key_capabilities() {
+    let keypair = generate_keypair(
+        "rs.keychain-services.test.integration.capabilities",
+        "keychain-services.rs integration test capabilities key",
+    );
+
+    let mut capabilities = keypair.private_key.capabilities();
+    assert!(capabilities.can_decrypt);
+
+    capabilities.can_decrypt = false;
+    keypair.private_key.set_capabilities(&capabilities).unwrap();
+    assert_eq!(keypair.private_key.capabilities(), capabilities);
+
+    keypair.private_key.delete().unwrap();
+}
+
+/// Receiving keychain events via the callback API
+#[test]
+fn delete_listed_items_only() {
+    use keychain_services::keychain::item::{delete_matching, Class, Query};
+
+    let tag = "rs.keychain-services.test.integration.item-list";
+    let doomed = generate_keypair(tag, "keychain-services.rs item list test key");
+    let kept = generate_keypair(tag, "keychain-services.rs item list test key");
+
+    // Both keys match the tag, but only the listed one is deleted
+    delete_matching(
+        Class::Key,
+        Query::new()
+            .application_tag(tag)
+            .item_list(&[doomed.private_key]),
+    )
+    .unwrap();
+
+    let remaining = Key::find(Query::new().application_tag(tag))
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        remaining.application_label(),
+        kept.private_key.application_label()
+    );
+
+    kept.private_key.delete().unwrap();
+}
+
+#[test]
+fn delete_with_report() {
+    use keychain_services::keychain::item::{delete_matching_with_report, Class, Query};
+
+    let tag = "rs.keychain-services.test.integration.deletion-report";
+    generate_keypair(tag, "keychain-services.rs deletion report test key 1");
+    generate_keypair(tag, "keychain-services.rs deletion report test key 2");
+
+    let query = Query::new()
+        .application_tag(tag)
+        .key_class(AttrKeyClass::Private);
+    let report = delete_matching_with_report(Class::Key, &query).unwrap();
+
+    assert!(report.is_complete());
+    assert_eq!(report.matched(), 2);
+
+    let mut labels: Vec<_> = report
+        .deleted()
+        .iter()
+        .map(|item| item.label().unwrap())
+        .collect();
+    labels.sort();
+    assert_eq!(
+        labels,
+        [
+            "keychain-services.rs deletion report test key 1",
+            "keychain-services.rs deletion report test key 2"
+        ]
+    );
+
+    // Nothing is left to delete, which isn't an error
+    let report = delete_matching_with_report(Class::Key, &query).unwrap();
+    assert_eq!(report.matched(), 0);
+}
+
+#[test]
+fn touch_password() {
+    use keychain_services::keychain::item::GenericPassword;
+
+    let keychain = temp_keychain();
+    let service = "rs.keychain-services.test.touch";
+    let password =
+        GenericPassword::create(&keychain.keychain, service, "example", TEST_PASSWORD).unwrap();
+    let created = password.modification_date().unwrap();
+
+    // Modification dates have a resolution of one second
+    std::thread::sleep(Duration::from_millis(1100));
+    password.touch().unwrap();
+
+    assert!(password.modification_date().unwrap() > created);
+    assert_eq!(password.password().unwrap().as_str(), TEST_PASSWORD);
+}
+
+#[test]
+fn find_password_attributes() {
+    use keychain_services::keychain::item::{
+        delete_matching, Class, GenericPassword, ItemAttributes, Query,
+    };
+    use std::time::SystemTime;
+
+    let keychain = Keychain::find_default().unwrap();
+    let service = "rs.keychain-services.test.attributes";
+    GenericPassword::create(&keychain, service, "example", TEST_PASSWORD).unwrap();
+
+    let query = Query::new().service(service).account("example");
+    let attrs = ItemAttributes::find_all(Class::GenericPassword, &query).unwrap();
+
+    assert_eq!(attrs.len(), 1);
+    assert_eq!(attrs[0].service().unwrap(), service);
+    assert_eq!(attrs[0].account().unwrap(), "example");
+    let created = attrs[0].creation_date().unwrap();
+    let modified = attrs[0].modification_date().unwrap();
+    assert!(created <= modified);
+    assert!(modified <= SystemTime::now());
+
+    delete_matching(Class::GenericPassword, &query).unwrap();
+}
+
+#[test]
+fn generic_password_generic_attribute() {
+    use keychain_services::keychain::item::{
+        delete_matching, Class, GenericPassword, GenericPasswordParams, ItemAttributes, Query,
+    };
+
+    let keychain = Keychain::find_default().unwrap();
+    let service = "rs.keychain-services.test.generic";
+    let params = GenericPasswordParams::new(service, "example").generic(&b"\x00\x01\x02"[..]);
+    let password = GenericPassword::create_with_params(&keychain, &params, TEST_PASSWORD).unwrap();
+    assert_eq!(password.generic().unwrap().as_bytes(), b"\x00\x01\x02");
+
+    let query = Query::new().service(service).generic(&b"\x00\x01\x02"[..]);
+    let attrs = ItemAttributes::find_all(Class::GenericPassword, &query).unwrap();
+
+    assert_eq!(attrs.len(), 1);
+    assert_eq!(attrs[0].generic(), Some(AttrGeneric::new(b"\x00\x01\x02")));
+
+    let mismatched = Query::new().service(service).generic("other");
+    assert!(
+        ItemAttributes::find_all(Class::GenericPassword, &mismatched)
+            .unwrap()
+            .is_empty()
+    );
+
+    delete_matching(Class::GenericPassword, &query).unwrap();
+}
+
+#[test]
+fn descriptive_item_attributes() {
+    use keychain_services::keychain::item::{
+        delete_matching, update_matching, Class, GenericPassword, GenericPasswordParams,
+        ItemAttributes, ItemChanges, Query,
+    };
+
+    let keychain = Keychain::find_default().unwrap();
+    let service = "rs.keychain-services.test.descriptive";
+    let params = GenericPasswordParams::new(service, "example")
+        .description("secure note")
+        .creator(*b"rsks")
+        .item_type(*b"note");
+    GenericPassword::create_with_params(&keychain, &params, TEST_PASSWORD).unwrap();
+
+    let query = Query::new().service(service).item_type(*b"note");
+    let attrs = ItemAttributes::find_all(Class::GenericPassword, &query).unwrap();
+
+    assert_eq!(attrs.len(), 1);
+    assert_eq!(attrs[0].description().unwrap(), "secure note");
+    assert_eq!(attrs[0].creator(), Some(AttrCreator(*b"rsks")));
+    assert_eq!(attrs[0].item_type(), Some(AttrItemType(*b"note")));
+
+    let changes = ItemChanges::new()
+        .description("application password")
+        .item_type(*b"appl");
+    update_matching(Class::GenericPassword, &query, &changes).unwrap();
+
+    let query = Query::new().service(service).creator(*b"rsks");
+    let attrs = ItemAttributes::find_all(Class::GenericPassword, &query).unwrap();
+
+    assert_eq!(attrs[0].description().unwrap(), "application password");
+    assert_eq!(attrs[0].item_type(), Some(AttrItemType(*b"appl")));
+
+    delete_matching(Class::GenericPassword, &query).unwrap();
+}
+
+#[test]
+fn keyring_style_passwords() {
+    use keychain_services::keychain::passwords::*;
+
+    let service = "rs.keychain-services.test.passwords";
+    assert!(get_password(service, "example").unwrap().is_none());
+
+    set_password(service, "example", TEST_PASSWORD).unwrap();
+    assert_eq!(
+        get_password(service, "example").unwrap().unwrap().as_str(),
+        TEST_PASSWORD
+    );
+
+    set_password(service, "example", "replaced password").unwrap();
+    assert_eq!(
+        get_password(service, "example").unwrap().unwrap().as_str(),
+        "replaced password"
+    );
+
+    assert!(delete_password(service, "example").unwrap());
+    assert!(!delete_password(service, "example").unwrap());
+    assert!(get_password(service, "example").unwrap().is_none());
+}
+
+/// Structured secrets stored as JSON
+#[cfg(feature = "json")]
+#[test]
+fn json_password_payloads() {
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Credentials {
+        api_token: String,
+        refresh_token: String,
+        expires: u64,
+    }
+
+    let tmp = temp_keychain();
+    let service = "rs.keychain-services.test.json";
+    let credentials = Credentials {
+        api_token: "api token".to_owned(),
+        refresh_token: "refresh token".to_owned(),
+        expires: 1_700_000_000,
+    };
+
+    let stored: Option<Credentials> = tmp.keychain.get_json(service, "example").unwrap();
+    assert!(stored.is_none());
+
+    tmp.keychain
+        .put_json(service, "example", &credentials)
+        .unwrap();
+    let stored: Option<Credentials> = tmp.keychain.get_json(service, "example").unwrap();
+    assert_eq!(stored.unwrap(), credentials);
+
+    // Not a JSON payload
+    keychain::item::GenericPassword::create(&tmp.keychain, service, "plain", TEST_PASSWORD)
+        .unwrap();
+    let err = tmp
+        .keychain
+        .get_json::<Credentials>(service, "plain")
+        .unwrap_err();
+    match err.kind() {
+        ErrorKind::Decode => (),
+        other => panic!("expected decode error, got {:?}", other),
+    }
+}
+
+#[test]
+fn delete_synchronizable_items_everywhere() {
+    use keychain_services::keychain::item::{Class, GenericPassword, ItemQuery};
+
+    let keychain = Keychain::find_default().unwrap();
+    let service = "rs.keychain-services.test.delete-everywhere";
+    GenericPassword::create(&keychain, service, "example", TEST_PASSWORD).unwrap();
+
+    // Later settings replace earlier ones rather than being combined
+    let query = ItemQuery::new(Class::GenericPassword)
+        .service(service)
+        .synchronizable(true)
+        .synchronizable_any();
+    assert_eq!(query.count().unwrap(), 1);
+
+    query.delete_everywhere().unwrap();
+    assert_eq!(query.count().unwrap(), 0);
+}
+
+#[test]
+fn find_password_with_data() {
+    use keychain_services::keychain::item::{
+        delete_matching, Class, GenericPassword, ItemAttributes, Query,
+    };
+
+    let keychain = Keychain::find_default().unwrap();
+    let service = "rs.keychain-services.test.return-data";
+    GenericPassword::create(&keychain, service, "example", TEST_PASSWORD).unwrap();
+
+    let query = Query::new().service(service).account("example");
+    let (attrs, data) = ItemAttributes::find_with_data(Class::GenericPassword, &query)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(attrs.account().unwrap(), "example");
+    assert_eq!(data.as_str(), TEST_PASSWORD);
+
+    delete_matching(Class::GenericPassword, &query).unwrap();
+    assert!(
+        ItemAttributes::find_with_data(Class::GenericPassword, &query)
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn resolve_persistent_ref() {
+    use keychain_services::keychain::item::{
+        delete_matching, Class, GenericPassword, ItemAttributes, PersistentItemRef, Query,
+    };
+
+    let keychain = Keychain::find_default().unwrap();
+    let service = "rs.keychain-services.test.persistent-ref";
+    GenericPassword::create(&keychain, service, "example", TEST_PASSWORD).unwrap();
+
+    let query = Query::new().service(service).account("example");
+    let persistent_ref = ItemAttributes::find_with_persistent_refs(Class::GenericPassword, &query)
+        .unwrap()[0]
+        .persistent_ref()
+        .unwrap();
+
+    // Simulate storing the reference and restoring it after a restart
+    let restored = PersistentItemRef::from_bytes(persistent_ref.as_bytes());
+    let item = keychain
+        .item_from_persistent_ref(&restored)
+        .unwrap()
+        .unwrap();
+    assert_eq!(item.class(), Class::GenericPassword);
+
+    delete_matching(Class::GenericPassword, &query).unwrap();
+    assert!(keychain
+        .item_from_persistent_ref(&restored)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn modify_legacy_item_content() {
+    use keychain_services::keychain::item::{
+        AttrValue, Class, GenericPassword, ItemAttributes, LegacyAttribute, Query,
+    };
+
+    let keychain = temp_keychain();
+    let password =
+        GenericPassword::create(&keychain.keychain, "example.com", "example", TEST_PASSWORD)
+            .unwrap();
+
+    let query = Query::new().keychain(&keychain.keychain);
+    let persistent_ref = ItemAttributes::find_with_persistent_refs(Class::GenericPassword, &query)
+        .unwrap()[0]
+        .persistent_ref()
+        .unwrap();
+    let item = keychain
+        .keychain
+        .item_from_persistent_ref(&persistent_ref)
+        .unwrap()
+        .unwrap();
+
+    let attrs = [
+        LegacyAttribute {
+            tag: *b"labl",
+            value: AttrValue::String("edited label".to_owned()),
+        },
+        LegacyAttribute {
+            tag: *b"crtr",
+            value: AttrValue::Code(*b"rsks"),
+        },
+    ];
+    item.set_legacy_content(&attrs, Some(b"edited password"))
+        .unwrap();
+
+    assert_eq!(password.label().unwrap(), "edited label");
+    assert_eq!(password.creator().unwrap().as_bytes(), b"rsks");
+    assert_eq!(password.password().unwrap().as_str(), "edited password");
+}
+
+#[test]
+fn create_legacy_item_from_content() {
+    use keychain_services::keychain::item::{
+        AttrValue, Class, GenericPassword, Item, LegacyAttribute,
+    };
+
+    let keychain = temp_keychain();
+    let attrs = [
+        LegacyAttribute {
+            tag: *b"svce",
+            value: AttrValue::String("example.com".to_owned()),
+        },
+        LegacyAttribute {
+            tag: *b"acct",
+            value: AttrValue::String("example".to_owned()),
+        },
+        LegacyAttribute {
+            tag: *b"crtr",
+            value: AttrValue::Code(*b"rsks"),
+        },
+    ];
+
+    let item = Item::create_from_legacy_content(
+        &keychain.keychain,
+        Class::GenericPassword,
+        &attrs,
+        TEST_PASSWORD.as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(item.class(), Class::GenericPassword);
+
+    let password = GenericPassword::find(&keychain.keychain, "example.com", "example")
+        .unwrap()
+        .unwrap();
+    assert_eq!(password.creator().unwrap().as_bytes(), b"rsks");
+    assert_eq!(password.password().unwrap().as_str(), TEST_PASSWORD);
+}
+
+#[test]
+fn find_legacy_passwords() {
+    use keychain_services::keychain::item::{
+        GenericPassword, InternetPassword, LegacyInternetPasswordQuery,
+    };
+
+    let keychain = temp_keychain();
+    GenericPassword::create(&keychain.keychain, "example.com", "generic", TEST_PASSWORD).unwrap();
+    InternetPassword::create(&keychain.keychain, "example.com", "internet", TEST_PASSWORD).unwrap();
+
+    let keychains = [keychain.keychain.clone()];
+
+    let (password, data) =
+        GenericPassword::find_legacy(&keychains, Some("example.com"), Some("generic"))
+            .unwrap()
+            .unwrap();
+    assert_eq!(password.account().unwrap(), "generic");
+    assert_eq!(data.as_str(), TEST_PASSWORD);
+
+    let query = LegacyInternetPasswordQuery::new().server("example.com");
+    let (password, data) = InternetPassword::find_legacy(&keychains, &query)
+        .unwrap()
+        .unwrap();
+    assert_eq!(password.account().unwrap(), "internet");
+    assert_eq!(data.as_str(), TEST_PASSWORD);
+
+    assert!(
+        GenericPassword::find_legacy(&keychains, None, Some("nobody"))
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn enumerate_legacy_attributes() {
+    use keychain_services::keychain::item::{
+        AttrValue, Class, GenericPassword, GenericPasswordParams, ItemAttributes, Query,
+    };
+
+    let keychain = temp_keychain();
+    let params = GenericPasswordParams::new("example.com", "example").comment("test comment");
+    GenericPassword::create_with_params(&keychain.keychain, &params, TEST_PASSWORD).unwrap();
+
+    let query = Query::new().keychain(&keychain.keychain);
+    let persistent_ref = ItemAttributes::find_with_persistent_refs(Class::GenericPassword, &query)
+        .unwrap()[0]
+        .persistent_ref()
+        .unwrap();
+    let item = keychain
+        .keychain
+        .item_from_persistent_ref(&persistent_ref)
+        .unwrap()
+        .unwrap();
+
+    let info = item.legacy_attribute_info().unwrap();
+    assert!(info.iter().any(|attr| &attr.tag == b"acct"));
+    assert!(info.iter().any(|attr| &attr.tag == b"svce"));
+
+    let attrs = item.legacy_attributes().unwrap();
+    assert!(attrs
+        .iter()
+        .any(|attr| &attr.tag == b"icmt"
+            && attr.value == AttrValue::String("test comment".to_owned())));
+}
+
+#[test]
+fn delete_legacy_item() {
+    use keychain_services::keychain::item::GenericPassword;
+
+    let keychain = temp_keychain();
+    let password =
+        GenericPassword::create(&keychain.keychain, "example.com", "example", TEST_PASSWORD)
+            .unwrap();
+    password.delete().unwrap();
+
+    assert!(
+        GenericPassword::find(&keychain.keychain, "example.com", "example")
+            .unwrap()
+            .is_none()
+    );
+}
+
+#[test]
+fn find_owning_keychain() {
+    use keychain_services::keychain::item::GenericPassword;
+
+    let keychain = temp_keychain();
+    let password =
+        GenericPassword::create(&keychain.keychain, "example.com", "example", TEST_PASSWORD)
+            .unwrap();
+
+    assert_eq!(
+        password.keychain().unwrap().path().unwrap(),
+        keychain.keychain.path().unwrap()
+    );
+}
+
+#[test]
+fn page_through_passwords() {
+    use keychain_services::keychain::item::{
+        delete_matching, Class, GenericPassword, ItemAttributes, Query,
+    };
+
+    let keychain = Keychain::find_default().unwrap();
+    let service = "rs.keychain-services.test.pages";
+
+    for account in &["first", "second", "third"] {
+        GenericPassword::create(&keychain, service, account, TEST_PASSWORD).unwrap();
+    }
+
+    let query = Query::new().service(service);
+    let pages: Vec<_> = ItemAttributes::pages(Class::GenericPassword, &query, 2)
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[0].len(), 2);
+    assert_eq!(pages[1].len(), 1);
+
+    delete_matching(Class::GenericPassword, &query).unwrap();
+}
+
+#[test]
+fn enumerate_keychain_items() {
+    use keychain_services::keychain::item::{Class, GenericPassword};
+
+    let keychain = temp_keychain();
+    let service = "rs.keychain-services.test.items";
+
+    for account in &["first", "second"] {
+        GenericPassword::create(&keychain.keychain, service, account, TEST_PASSWORD).unwrap();
+    }
+
+    let items: Vec<_> = keychain
+        .keychain
+        .items(&[])
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(items.len(), 2);
+    assert!(items
+        .iter()
+        .all(|item| item.class() == Class::GenericPassword
+            && item.service().as_ref().map(String::as_str) == Some(service)));
+}
+
+#[test]
+fn find_items_of_any_class() {
+    use keychain_services::keychain::item::{
+        Class, FoundItem, GenericPassword, InternetPassword, Query,
+    };
+
+    let keychain = temp_keychain();
+    GenericPassword::create(&keychain.keychain, "example.com", "generic", TEST_PASSWORD).unwrap();
+    InternetPassword::create(&keychain.keychain, "example.com", "internet", TEST_PASSWORD).unwrap();
+
+    let items = keychain.keychain.find_items(&[], &Query::new()).unwrap();
+    assert_eq!(items.len(), 2);
+
+    for item in &items {
+        match item {
+            FoundItem::GenericPassword(password) => {
+                assert_eq!(password.account().unwrap(), "generic")
+            }
+            FoundItem::InternetPassword(password) => {
+                assert_eq!(password.account().unwrap(), "internet")
+            }
+            other => panic!("unexpected {} item", other.class()),
+        }
+    }
+
+    let items = keychain
+        .keychain
+        .find_items(&[Class::InternetPassword], &Query::new())
+        .unwrap();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].class(), Class::InternetPassword);
+}
+
+#[test]
+fn keychain_delete_matching_with_report() {
+    use keychain_services::keychain::item::{Class, GenericPassword, ItemQuery};
+
+    let keychain = temp_keychain();
+    let service = "rs.keychain-services.test.keychain-delete";
+
+    for account in &["first", "second"] {
+        GenericPassword::create(&keychain.keychain, service, account, TEST_PASSWORD).unwrap();
+    }
+
+    let keychain = &keychain.keychain;
+    let query = ItemQuery::new(Class::GenericPassword).service(service);
+    assert_eq!(keychain.count_matching(&query).unwrap(), 2);
+
+    let report = keychain.delete_matching_with_report(&query).unwrap();
+
+    assert_eq!(report.deleted().len(), 2);
+    assert!(report.is_complete());
+    assert_eq!(keychain.count_matching(&query).unwrap(), 0);
+
+    let err = keychain.delete_matching(&query).unwrap_err();
+    match err.kind() {
+        ErrorKind::ItemNotFound => (),
+        other => panic!("expected item not found, got {:?}", other),
+    }
+}
+
+#[test]
+fn keychain_upsert_item() {
+    use keychain_services::keychain::item::{
+        Class, GenericPassword, ItemChanges, ItemQuery, UpsertOutcome,
+    };
+
+    let keychain = temp_keychain();
+    let keychain = &keychain.keychain;
+    let service = "rs.keychain-services.test.upsert";
+    let query = ItemQuery::new(Class::GenericPassword)
+        .service(service)
+        .account("example");
+
+    let changes = ItemChanges::new().label("first").data(b"first password");
+    assert_eq!(
+        keychain.upsert_item(&query, &changes).unwrap(),
+        UpsertOutcome::Added
+    );
+
+    let changes = ItemChanges::new().label("second").data(b"second password");
+    assert_eq!(
+        keychain.upsert_item(&query, &changes).unwrap(),
+        UpsertOutcome::Updated
+    );
+
+    let password = GenericPassword::find(keychain, service, "example")
+        .unwrap()
+        .unwrap();
+    assert_eq!(password.label().unwrap(), "second");
+    assert_eq!(password.password().unwrap().as_str(), "second password");
+    assert_eq!(query.find().unwrap().len(), 1);
+}
+
+#[test]
+fn search_multiple_keychains() {
+    use keychain_services::keychain::item::{Class, GenericPassword, ItemAttributes, Query};
+
+    let first = temp_keychain();
+    let second = temp_keychain();
+    let service = "rs.keychain-services.test.search-list";
+
+    GenericPassword::create(&first.keychain, service, "first", TEST_PASSWORD).unwrap();
+    GenericPassword::create(&second.keychain, service, "second", TEST_PASSWORD).unwrap();
+
+    let query = Query::new().service(service);
+    let keychains = [first.keychain.clone(), second.keychain.clone()];
+
+    let both =
+        ItemAttributes::find_all(Class::GenericPassword, query.clone().keychains(&keychains));
+    assert_eq!(both.unwrap().len(), 2);
+
+    let one = ItemAttributes::find_all(Class::GenericPassword, query.keychain(&second.keychain));
+    assert_eq!(one.unwrap()[0].account().unwrap(), "second");
+}
+
+#[test]
+fn update_password_in_place() {
+    use keychain_services::keychain::item::{GenericPassword, ItemChanges};
+
+    let keychain = temp_keychain();
+    let service = "rs.keychain-services.test.update";
+    let password =
+        GenericPassword::create(&keychain.keychain, service, "example", TEST_PASSWORD).unwrap();
+
+    let changes = ItemChanges::new()
+        .label("updated label")
+        .data(b"updated password");
+    password.update(&changes).unwrap();
+
+    assert_eq!(password.label().unwrap(), "updated label");
+    assert_eq!(password.password().unwrap().as_str(), "updated password");
+}
+
+#[test]
+fn migrate_password_protection_class() {
+    use keychain_services::keychain::item::{
+        delete_matching, Class, GenericPassword, GenericPasswordParams, ItemAttributes, Query,
+    };
+
+    let keychain = Keychain::find_default().unwrap();
+    let service = "rs.keychain-services.test.accessible";
+    let params =
+        GenericPasswordParams::new(service, "example").accessible(AttrAccessible::AfterFirstUnlock);
+    let password = GenericPassword::create_with_params(&keychain, &params, TEST_PASSWORD).unwrap();
+
+    password
+        .set_accessible(AttrAccessible::WhenUnlockedThisDeviceOnly)
+        .unwrap();
+
+    let query = Query::new().service(service);
+    let attrs = ItemAttributes::find_all(Class::GenericPassword, &query).unwrap();
+
+    assert_eq!(attrs.len(), 1);
+    assert_eq!(
+        attrs[0].accessible(),
+        Some(AttrAccessible::WhenUnlockedThisDeviceOnly)
+    );
+    assert_eq!(password.password().unwrap().as_str(), TEST_PASSWORD);
+
+    delete_matching(Class::GenericPassword, &query).unwrap();
+}
+
+#[test]
+fn internet_password_attributes() {
+    use keychain_services::keychain::item::{InternetPassword, InternetPasswordParams};
+
+    let keychain = temp_keychain();
+    let params = InternetPasswordParams::new(AttrServer::new("example.com").unwrap())
+        .account("example")
+        .protocol(AttrProtocol::HTTPS)
+        .port(8443)
+        .path("/login")
+        .security_domain("Example Realm")
+        .authentication_type(AttrAuthenticationType::HTTPBasic);
+
+    InternetPassword::create_with_params(&keychain.keychain, &params, TEST_PASSWORD).unwrap();
+    let password = InternetPassword::find_with_params(&keychain.keychain, &params)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(password.port().unwrap(), AttrPort(8443));
+    assert_eq!(password.path().unwrap(), "/login");
+    assert_eq!(password.security_domain().unwrap(), "Example Realm");
+    assert_eq!(
+        password.authentication_type().unwrap(),
+        AttrAuthenticationType::HTTPBasic
+    );
+}
+
+#[test]
+fn keychain_events() {
+    use core_foundation::runloop::{kCFRunLoopDefaultMode, CFRunLoopRunInMode};
+    use keychain_services::keychain::events::*;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    let added = Arc::new(AtomicBool::new(false));
+    let added_clone = added.clone();
+    let _subscription = subscribe(&[KeychainEventKind::Add], move |event| {
+        assert_eq!(event.kind, KeychainEventKind::Add);
+        added_clone.store(true, Ordering::SeqCst);
+    })
+    .unwrap();
+
+    let tmp = temp_keychain();
+    keychain::item::GenericPassword::create(&tmp.keychain, "example.com", "events", TEST_PASSWORD)
+        .unwrap();
+
+    // Events are delivered via the run loop
+    unsafe { CFRunLoopRunInMode(kCFRunLoopDefaultMode, 1.0, 0) };
+    assert!(added.load(Ordering::SeqCst));
+}
+
+/// Watching keychains being locked and unlocked
+#[test]
+fn keychain_lock_watcher() {
+    use keychain_services::keychain::lock::*;
+
+    let tmp = temp_keychain();
+    assert!(!tmp.keychain.is_locked().unwrap());
+
+    let watcher = LockWatcher::new(&tmp.keychain, |_| ()).unwrap();
+    assert_eq!(watcher.state(), LockState::Unlocked);
+
+    tmp.keychain.lock().unwrap();
+    assert!(tmp.keychain.is_locked().unwrap());
+    assert!(!watcher.wait_until_unlocked(Duration::from_millis(500)));
+    assert_eq!(watcher.state(), LockState::Locked);
+
+    tmp.keychain.unlock(Some(TEST_PASSWORD)).unwrap();
+    assert!(watcher.wait_until_unlocked(Duration::from_secs(1)));
+}
+
+/// Looking up a nonexistent password is not an error
+#[test]
+fn find_missing_password() {
+    let tmp = temp_keychain();
+
+    let result =
+        keychain::item::GenericPassword::find(&tmp.keychain, "example.com", "nobody").unwrap();
+
+    assert!(result.is_none());
+}
+
+/// Looking up the password of an unknown Wi-Fi network is not an error
+#[test]
+fn find_missing_wifi_password() {
+    let result =
+        keychain::item::GenericPassword::wifi_password("keychain-services.rs test network")
+            .unwrap();
+
+    assert!(result.is_none());
 }
 
 ///